@@ -6,6 +6,8 @@ use rmvm_grpc::{
 use rmvm_proto::{ExecuteRequest, ExecuteResponse};
 use tonic::transport::Channel;
 
+pub mod pipe;
+
 #[derive(Debug, Clone)]
 pub struct RmvmAdapter {
     endpoint: String,
@@ -66,6 +68,9 @@ impl RmvmAdapter {
     }
 
     async fn client(&self) -> Result<RmvmExecutorClient<Channel>> {
+        if let Some(name) = pipe::pipe_name(&self.endpoint) {
+            return Ok(RmvmExecutorClient::new(pipe::channel(name).await?));
+        }
         RmvmExecutorClient::connect(self.endpoint.clone())
             .await
             .with_context(|| format!("failed to connect to RMVM endpoint {}", self.endpoint))
@@ -73,7 +78,9 @@ impl RmvmAdapter {
 }
 
 fn normalize_endpoint(input: &str) -> String {
-    if let Some(rest) = input.strip_prefix("grpc://") {
+    if input.starts_with(pipe::SCHEME) {
+        input.to_string()
+    } else if let Some(rest) = input.strip_prefix("grpc://") {
         format!("http://{rest}")
     } else if input.starts_with("http://") || input.starts_with("https://") {
         input.to_string()
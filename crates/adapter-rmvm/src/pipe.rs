@@ -0,0 +1,186 @@
+//! Windows named pipe transport for a local RMVM, used instead of a localhost port. Endpoints
+//! are written `pipe://<name>` and map to `\\.\pipe\<name>`; other platforms reject them.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use tonic::transport::Channel;
+use tonic::transport::server::Router;
+
+pub const SCHEME: &str = "pipe://";
+
+/// How long a client waits for a free pipe instance before giving up.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The pipe name of a `pipe://<name>` endpoint or address.
+pub fn pipe_name(endpoint: &str) -> Option<&str> {
+    endpoint
+        .strip_prefix(SCHEME)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+pub fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\{name}")
+}
+
+#[cfg(windows)]
+pub async fn channel(name: &str) -> Result<Channel> {
+    use anyhow::Context;
+    use hyper_util::rt::TokioIo;
+    use tokio::net::windows::named_pipe::ClientOptions;
+    use tonic::transport::{Endpoint, Uri};
+    use tower::service_fn;
+
+    /// Every instance is taken; the server opens the next one as soon as it accepts.
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    let path = pipe_path(name);
+    let connect_path = path.clone();
+    // tonic needs a URI for the channel, but the connector never dials it.
+    Endpoint::from_static("http://rmvm.pipe")
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let path = connect_path.clone();
+            async move {
+                let deadline = tokio::time::Instant::now() + CONNECT_TIMEOUT;
+                loop {
+                    match ClientOptions::new().open(&path) {
+                        Ok(client) => return Ok::<_, std::io::Error>(TokioIo::new(client)),
+                        Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                        Err(e) => return Err(e),
+                    }
+                    if tokio::time::Instant::now() >= deadline {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            format!("all instances of {path} stayed busy for {CONNECT_TIMEOUT:?}"),
+                        ));
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            }
+        }))
+        .await
+        .with_context(|| format!("failed to connect to RMVM pipe {path}"))
+}
+
+#[cfg(not(windows))]
+pub async fn channel(name: &str) -> Result<Channel> {
+    anyhow::bail!("RMVM endpoint {SCHEME}{name} needs Windows; use grpc://host:port")
+}
+
+/// Serves `router` on `\\.\pipe\<name>` until the process exits.
+#[cfg(windows)]
+pub async fn serve(router: Router, name: &str) -> Result<()> {
+    router
+        .serve_with_incoming(server::incoming(&pipe_path(name))?)
+        .await?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub async fn serve(_router: Router, name: &str) -> Result<()> {
+    anyhow::bail!("RMVM address {SCHEME}{name} needs Windows; use host:port")
+}
+
+#[cfg(windows)]
+mod server {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+    use tonic::transport::server::Connected;
+
+    pub struct PipeConnection(NamedPipeServer);
+
+    impl Connected for PipeConnection {
+        type ConnectInfo = ();
+
+        fn connect_info(&self) -> Self::ConnectInfo {}
+    }
+
+    impl AsyncRead for PipeConnection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for PipeConnection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+        }
+    }
+
+    /// Accepted connections on `path`. The first instance is created up front so a second
+    /// server on the same name fails here instead of silently sharing it.
+    pub fn incoming(path: &str) -> io::Result<ReceiverStream<io::Result<PipeConnection>>> {
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(path)?;
+        let path = path.to_string();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let accepted = server.connect().await;
+                // Open the next instance before handing this one off, so clients rarely see
+                // ERROR_PIPE_BUSY.
+                let next = ServerOptions::new().create(&path);
+                let connection = accepted.map(|()| PipeConnection(server));
+                if tx.send(connection).await.is_err() {
+                    return;
+                }
+                server = match next {
+                    Ok(next) => next,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                };
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pipe_endpoints() {
+        assert_eq!(
+            pipe_name("pipe://cortex-rmvm-50051"),
+            Some("cortex-rmvm-50051")
+        );
+        assert_eq!(pipe_name("pipe:// cortex-rmvm "), Some("cortex-rmvm"));
+        assert_eq!(pipe_name("pipe://"), None);
+        assert_eq!(pipe_name("grpc://127.0.0.1:50051"), None);
+        assert_eq!(pipe_path("cortex-rmvm"), r"\\.\pipe\cortex-rmvm");
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn other_platforms_reject_pipe_endpoints() {
+        let err = channel("cortex-rmvm").await.unwrap_err();
+        assert!(err.to_string().contains("needs Windows"), "{err}");
+    }
+}
@@ -0,0 +1,427 @@
+//! `cortex.brain.v1.BrainService`: the brain store over gRPC, for processes that should not
+//! link `brain-store` or parse CLI output.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::bail;
+use brain_store::{AttachmentGrant, BrainStore, BrainSummary, MemoryObject};
+use tonic::service::Interceptor;
+use tonic::service::interceptor::InterceptedService;
+use tonic::{Request, Response, Status};
+
+pub mod pb {
+    tonic::include_proto!("cortex.brain.v1");
+}
+
+pub use pb::brain_service_client::BrainServiceClient;
+pub use pb::brain_service_server::{BrainService, BrainServiceServer};
+
+use pb::mutate_request::Op;
+
+const DEFAULT_SCOPE: &str = "SCOPE_GLOBAL";
+const DEFAULT_FORGET_REASON: &str = "forgotten via brain service";
+
+/// Serves the store at `brain_home` (default `CORTEX_HOME`, then `~/.cortex`). Passphrases are
+/// read from the server's environment, as for the CLI.
+#[derive(Debug, Clone, Default)]
+pub struct BrainGrpcService {
+    brain_home: Option<PathBuf>,
+}
+
+impl BrainGrpcService {
+    pub fn new(brain_home: Option<PathBuf>) -> Self {
+        Self { brain_home }
+    }
+
+    fn store(&self) -> Result<BrainStore, Status> {
+        BrainStore::new(self.brain_home.clone()).map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn resolve(&self, store: &BrainStore, brain: &str) -> Result<BrainSummary, Status> {
+        let brain = (!brain.trim().is_empty()).then_some(brain.trim());
+        store
+            .resolve_brain_or_active(brain)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+}
+
+/// Rejects calls without `authorization: Bearer <token>`. The service reads and rewrites every
+/// brain the server can decrypt, so it is never served without one.
+#[derive(Clone)]
+pub struct RequireToken(Arc<str>);
+
+impl RequireToken {
+    pub fn new(token: &str) -> Self {
+        Self(token.into())
+    }
+}
+
+impl Interceptor for RequireToken {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let presented = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim);
+        match presented {
+            Some(token) if tokens_match(token, &self.0) => Ok(request),
+            Some(_) => Err(Status::unauthenticated("brain service token is not valid")),
+            None => Err(Status::unauthenticated(
+                "brain service requires a bearer token",
+            )),
+        }
+    }
+}
+
+/// Compares without exiting at the first differing byte, so timing does not leak the token.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The brain service for a server bound to `addr`, or to a local named pipe when `None`. Like
+/// the HTTP brain API it only listens on loopback, and it requires `token`.
+pub fn local_server(
+    addr: Option<SocketAddr>,
+    token: Option<&str>,
+) -> anyhow::Result<InterceptedService<BrainServiceServer<BrainGrpcService>, RequireToken>> {
+    if let Some(addr) = addr
+        && !addr.ip().is_loopback()
+    {
+        bail!("the brain service only listens on localhost; {addr} is not a loopback address");
+    }
+    let Some(token) = token.map(str::trim).filter(|t| !t.is_empty()) else {
+        bail!("the brain service requires RMVM_BRAIN_SERVICE_TOKEN");
+    };
+    Ok(BrainServiceServer::with_interceptor(
+        BrainGrpcService::default(),
+        RequireToken::new(token),
+    ))
+}
+
+fn failed(e: anyhow::Error) -> Status {
+    Status::failed_precondition(format!("{e:#}"))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+impl From<BrainSummary> for pb::Brain {
+    fn from(b: BrainSummary) -> Self {
+        Self {
+            brain_id: b.brain_id,
+            name: b.name,
+            tenant_id: b.tenant_id,
+            updated_at: b.updated_at,
+            active_branch: b.active_branch,
+        }
+    }
+}
+
+impl TryFrom<pb::Memory> for MemoryObject {
+    type Error = Status;
+
+    fn try_from(m: pb::Memory) -> Result<Self, Status> {
+        let value = if m.value_json.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_str(&m.value_json).map_err(|e| {
+                Status::invalid_argument(format!("memory {} has invalid value_json: {e}", m.id))
+            })?
+        };
+        Ok(Self {
+            id: m.id,
+            subject: m.subject,
+            predicate: m.predicate,
+            value,
+            memory_type: m.memory_type,
+            suppressed: m.suppressed,
+            recorded_at: non_empty(m.recorded_at),
+            superseded_by: None,
+        })
+    }
+}
+
+impl From<pb::Attachment> for AttachmentGrant {
+    fn from(a: pb::Attachment) -> Self {
+        Self {
+            agent_id: a.agent_id,
+            model_id: a.model_id,
+            read_classes: a.read_classes,
+            write_classes: a.write_classes,
+            sinks: a.sinks,
+            expires_at: non_empty(a.expires_at),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl BrainService for BrainGrpcService {
+    async fn create_brain(
+        &self,
+        request: Request<pb::CreateBrainRequest>,
+    ) -> Result<Response<pb::Brain>, Status> {
+        let req = request.into_inner();
+        if req.name.trim().is_empty() {
+            return Err(Status::invalid_argument("brain name is required"));
+        }
+        let brain = self
+            .store()?
+            .create_brain(brain_store::CreateBrainRequest {
+                name: req.name,
+                tenant_id: non_empty(req.tenant_id).unwrap_or_else(|| "local".to_string()),
+                passphrase_env: non_empty(req.passphrase_env),
+            })
+            .map_err(failed)?;
+        Ok(Response::new(brain.into()))
+    }
+
+    async fn list_brains(
+        &self,
+        _request: Request<pb::ListBrainsRequest>,
+    ) -> Result<Response<pb::ListBrainsResponse>, Status> {
+        let store = self.store()?;
+        let brains = store.list_brains().map_err(failed)?;
+        let active = store.active_brain_id().map_err(failed)?;
+        Ok(Response::new(pb::ListBrainsResponse {
+            brains: brains.into_iter().map(Into::into).collect(),
+            active_brain_id: active.unwrap_or_default(),
+        }))
+    }
+
+    async fn export_brain(
+        &self,
+        request: Request<pb::ExportBrainRequest>,
+    ) -> Result<Response<pb::ExportBrainResponse>, Status> {
+        let store = self.store()?;
+        let brain = self.resolve(&store, &request.into_inner().brain)?;
+        let mut package = Vec::new();
+        store
+            .export_brain_to(&brain.brain_id, &mut package)
+            .map_err(failed)?;
+        Ok(Response::new(pb::ExportBrainResponse { package }))
+    }
+
+    async fn import_brain(
+        &self,
+        request: Request<pb::ImportBrainRequest>,
+    ) -> Result<Response<pb::ImportBrainResponse>, Status> {
+        let req = request.into_inner();
+        let brain = self
+            .store()?
+            .import_brain_from(
+                req.package.as_slice(),
+                non_empty(req.name_override),
+                req.verify_only,
+            )
+            .map_err(|e| Status::invalid_argument(format!("{e:#}")))?;
+        Ok(Response::new(pb::ImportBrainResponse {
+            brain: brain.map(Into::into),
+        }))
+    }
+
+    async fn mutate(
+        &self,
+        request: Request<pb::MutateRequest>,
+    ) -> Result<Response<pb::MutateResponse>, Status> {
+        let req = request.into_inner();
+        let op = req
+            .op
+            .ok_or_else(|| Status::invalid_argument("mutate request has no op"))?;
+        let store = self.store()?;
+        let brain_id = self.resolve(&store, &req.brain)?.brain_id;
+        let affected = match op {
+            Op::AddMemories(add) => {
+                let memories = add
+                    .memories
+                    .into_iter()
+                    .map(MemoryObject::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                store.add_memory_objects(&brain_id, memories)
+            }
+            Op::Forget(forget) => store.forget_suppress(
+                &brain_id,
+                &forget.subject,
+                &forget.predicate,
+                &non_empty(forget.scope).unwrap_or_else(|| DEFAULT_SCOPE.to_string()),
+                &non_empty(forget.reason).unwrap_or_else(|| DEFAULT_FORGET_REASON.to_string()),
+            ),
+            Op::Attach(grant) => store.attach(&brain_id, grant.into()).map(|()| 1),
+            Op::Detach(detach) => store.detach(
+                &brain_id,
+                &detach.agent_id,
+                non_empty(detach.model_id).as_deref(),
+            ),
+            Op::SetActive(_) => store.set_active_brain(&brain_id).map(|_| 1),
+        }
+        .map_err(failed)?;
+        Ok(Response::new(pb::MutateResponse {
+            brain_id,
+            affected: affected as u64,
+        }))
+    }
+
+    async fn audit(
+        &self,
+        request: Request<pb::AuditRequest>,
+    ) -> Result<Response<pb::AuditResponse>, Status> {
+        let store = self.store()?;
+        let brain_id = self.resolve(&store, &request.into_inner().brain)?.brain_id;
+        let entries = store
+            .audit_trace(&brain_id)
+            .map_err(failed)?
+            .into_iter()
+            .map(|e| pb::AuditEntry {
+                id: e.id,
+                ts: e.ts,
+                actor: e.actor,
+                action: e.action,
+                details_json: e.details.to_string(),
+            })
+            .collect();
+        Ok(Response::new(pb::AuditResponse { brain_id, entries }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn creates_mutates_and_round_trips_a_brain() {
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_GRPC", "test-secret-grpc");
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let service = BrainGrpcService::new(Some(temp.path().to_path_buf()));
+
+        let brain = service
+            .create_brain(Request::new(pb::CreateBrainRequest {
+                name: "grpc-test".to_string(),
+                tenant_id: String::new(),
+                passphrase_env: "TEST_BRAIN_SECRET_GRPC".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(brain.tenant_id, "local");
+
+        let mutate = |op: Op| {
+            service.mutate(Request::new(pb::MutateRequest {
+                brain: "grpc-test".to_string(),
+                op: Some(op),
+            }))
+        };
+        let added = mutate(Op::AddMemories(pb::AddMemories {
+            memories: vec![pb::Memory {
+                id: "m1".to_string(),
+                subject: "user:local".to_string(),
+                predicate: "prefers_beverage".to_string(),
+                value_json: "\"tea\"".to_string(),
+                memory_type: "normative.preference".to_string(),
+                ..Default::default()
+            }],
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(added.affected, 1);
+        let forgotten = mutate(Op::Forget(pb::Forget {
+            subject: "user:local".to_string(),
+            predicate: "prefers_beverage".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(forgotten.affected, 1);
+        let bad = mutate(Op::AddMemories(pb::AddMemories {
+            memories: vec![pb::Memory {
+                value_json: "{".to_string(),
+                ..Default::default()
+            }],
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(bad.code(), tonic::Code::InvalidArgument);
+
+        let audit = service
+            .audit(Request::new(pb::AuditRequest {
+                brain: brain.brain_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!audit.entries.is_empty());
+
+        let package = service
+            .export_brain(Request::new(pb::ExportBrainRequest {
+                brain: "grpc-test".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .package;
+        let imported = service
+            .import_brain(Request::new(pb::ImportBrainRequest {
+                package,
+                name_override: "grpc-copy".to_string(),
+                verify_only: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .brain
+            .unwrap();
+        assert_eq!(imported.name, "grpc-copy");
+
+        let listed = service
+            .list_brains(Request::new(pb::ListBrainsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.brains.len(), 2);
+
+        let missing = service
+            .audit(Request::new(pb::AuditRequest {
+                brain: "nope".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn brain_service_needs_a_token_and_loopback() {
+        let mut check = RequireToken::new("brain-token");
+        let call = |value: Option<&'static str>| {
+            let mut request = Request::new(());
+            if let Some(value) = value {
+                request
+                    .metadata_mut()
+                    .insert("authorization", value.parse().unwrap());
+            }
+            request
+        };
+        assert!(check.call(call(Some("Bearer brain-token"))).is_ok());
+        for rejected in [None, Some("Bearer brain-tokex"), Some("brain-token")] {
+            let status = check.call(call(rejected)).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unauthenticated, "{rejected:?}");
+        }
+
+        let loopback = "127.0.0.1:50051".parse().ok();
+        assert!(local_server(loopback, Some("brain-token")).is_ok());
+        assert!(local_server(None, Some("brain-token")).is_ok());
+        assert!(local_server(loopback, None).is_err());
+        assert!(local_server("0.0.0.0:50051".parse().ok(), Some("brain-token")).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::env;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Result, anyhow};
+
+/// Where a [`crate::BrainStore`] keeps its files. Keys are `/`-separated paths relative to the
+/// store home, e.g. `brains/<brain_id>/brain.json`.
+pub trait StorageBackend: Send + Sync {
+    /// `None` when the key does not exist.
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()>;
+    /// Names of the directories directly under `prefix`.
+    fn list_dirs(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// Brain passphrases by the variable name recorded in each brain's manifest.
+pub trait SecretSource: Send + Sync {
+    fn secret(&self, name: &str) -> Option<String>;
+}
+
+/// Files under a home directory.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct FsBackend {
+    root: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl FsBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        key.split('/')
+            .fold(self.root.clone(), |p, part| p.join(part))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl StorageBackend for FsBackend {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn list_dirs(&self, prefix: &str) -> Result<Vec<String>> {
+        let dir = self.path(prefix);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut out = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                out.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Passphrases from environment variables, as the CLI uses.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvSecrets;
+
+#[cfg(feature = "fs")]
+impl SecretSource for EnvSecrets {
+    fn secret(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+}
+
+/// Files held in memory, e.g. a brain package imported in a browser.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    files: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let files = self
+            .files
+            .lock()
+            .map_err(|_| anyhow!("memory backend poisoned"))?;
+        Ok(files.get(key).cloned())
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let mut files = self
+            .files
+            .lock()
+            .map_err(|_| anyhow!("memory backend poisoned"))?;
+        files.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn list_dirs(&self, prefix: &str) -> Result<Vec<String>> {
+        let files = self
+            .files
+            .lock()
+            .map_err(|_| anyhow!("memory backend poisoned"))?;
+        let prefix = format!("{}/", prefix.trim_end_matches('/'));
+        let mut dirs = files
+            .keys()
+            .filter_map(|key| key.strip_prefix(&prefix)?.split_once('/'))
+            .map(|(dir, _)| dir.to_string())
+            .collect::<Vec<_>>();
+        dirs.dedup();
+        Ok(dirs)
+    }
+}
+
+/// Passphrases handed over by the caller instead of read from the environment.
+#[derive(Debug, Clone, Default)]
+pub struct StaticSecrets(BTreeMap<String, String>);
+
+impl StaticSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.0.insert(name.into(), secret.into());
+        self
+    }
+}
+
+impl SecretSource for StaticSecrets {
+    fn secret(&self, name: &str) -> Option<String> {
+        self.0.get(name).cloned()
+    }
+}
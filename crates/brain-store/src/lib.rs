@@ -1,7 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+#[cfg(feature = "fs")]
 use std::env;
+#[cfg(feature = "fs")]
 use std::fs;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 
 use anyhow::{Context, Result, anyhow, bail};
 use argon2::Argon2;
@@ -9,7 +13,7 @@ use base64::Engine as _;
 use base64::engine::general_purpose::STANDARD as B64;
 use chacha20poly1305::aead::{Aead, KeyInit, Payload};
 use chacha20poly1305::{XChaCha20Poly1305, XNonce};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use rand::RngCore;
 use rand::rngs::OsRng;
@@ -17,9 +21,22 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+mod backend;
+
+#[cfg(feature = "fs")]
+pub use backend::{EnvSecrets, FsBackend};
+pub use backend::{MemoryBackend, SecretSource, StaticSecrets, StorageBackend};
+
 const FORMAT_VERSION: &str = "brain/v1";
 const RMVM_PROTO_VERSION: &str = "cortex_rmvm_v3_1";
 const DEFAULT_SECRET_ENV: &str = "CORTEX_BRAIN_SECRET";
+// Backend keys, relative to the store home.
+const CONFIG_KEY: &str = "config.json";
+const API_KEYS_KEY: &str = "auth/api_keys.json";
+const BRAINS_DIR: &str = "brains";
+const MANIFEST_FILE: &str = "brain.json";
+const STATE_FILE: &str = "state.enc";
+const SIGNING_KEY_FILE: &str = "keys/signing_key.enc";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BrainManifest {
@@ -62,6 +79,8 @@ pub struct BranchState {
     pub rules: Vec<RuleEntry>,
     pub ledger: Vec<LedgerEvent>,
     pub suppressions: Vec<SuppressionRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retention: Vec<RetentionPolicy>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +91,18 @@ pub struct MemoryObject {
     pub value: serde_json::Value,
     pub memory_type: String,
     pub suppressed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_at: Option<String>,
+    /// Id of the object that replaced this one during consolidation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+}
+
+impl MemoryObject {
+    /// Neither forgotten nor superseded.
+    pub fn is_live(&self) -> bool {
+        !self.suppressed && self.superseded_by.is_none()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,6 +110,52 @@ pub struct RuleEntry {
     pub id: String,
     pub description: String,
     pub allowed_sinks: Vec<String>,
+    /// Memory class (handle type) the rule covers; `*` covers every class.
+    #[serde(default = "any_memory_class")]
+    pub memory_class: String,
+    #[serde(default)]
+    pub action: RuleAction,
+}
+
+impl RuleEntry {
+    pub fn covers(&self, memory_class: &str) -> bool {
+        self.memory_class == "*" || self.memory_class == memory_class
+    }
+
+    pub fn allows(&self, sink: &str) -> bool {
+        self.allowed_sinks.iter().any(|s| s == "*" || s == sink)
+    }
+}
+
+/// How long memory of one class is kept before the retention sweep suppresses it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Memory class (handle type); `*` is the fallback for classes without their own policy.
+    pub memory_class: String,
+    /// `None` keeps the class forever, overriding a `*` policy.
+    pub max_age_days: Option<u32>,
+}
+
+/// Retention window for `memory_class`: its own policy first, then `*`.
+pub fn retention_days(policies: &[RetentionPolicy], memory_class: &str) -> Option<u32> {
+    policies
+        .iter()
+        .find(|p| p.memory_class == memory_class)
+        .or_else(|| policies.iter().find(|p| p.memory_class == "*"))
+        .and_then(|p| p.max_age_days)
+}
+
+fn any_memory_class() -> String {
+    "*".to_string()
+}
+
+/// What happens to output that reaches a sink a rule does not allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Redact,
+    Block,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +166,29 @@ pub struct LedgerEvent {
     pub payload: serde_json::Value,
 }
 
+/// Ledger events and audit entries for one brain, applied by [`BrainStore::apply_writes`] in a
+/// single update instead of one re-encryption per record.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    ledger: Vec<(String, serde_json::Value)>,
+    audit: Vec<(String, String, serde_json::Value)>,
+}
+
+impl WriteBatch {
+    pub fn ledger(&mut self, operation: &str, payload: serde_json::Value) {
+        self.ledger.push((operation.to_string(), payload));
+    }
+
+    pub fn audit(&mut self, actor: &str, action: &str, details: serde_json::Value) {
+        self.audit
+            .push((actor.to_string(), action.to_string(), details));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ledger.is_empty() && self.audit.is_empty()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuppressionRecord {
     pub id: String,
@@ -98,6 +198,16 @@ pub struct SuppressionRecord {
     pub scope: String,
     pub reason: String,
     pub suppressed_count: usize,
+    /// Set when only these objects were suppressed rather than the whole subject and predicate;
+    /// such records are not sent to the kernel.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_ids: Vec<String>,
+}
+
+impl SuppressionRecord {
+    pub fn is_partial(&self) -> bool {
+        !self.object_ids.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,6 +229,50 @@ pub struct AuditEntry {
     pub details: serde_json::Value,
 }
 
+/// Receives each audit entry once it is saved, e.g. to forward it to a SIEM. `brain_id` is
+/// `None` for events that belong to no brain, such as a rejected proxy request.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, brain_id: Option<&str>, entry: &AuditEntry);
+}
+
+static AUDIT_SINK: OnceLock<Arc<dyn AuditSink>> = OnceLock::new();
+
+/// Installs the process-wide audit sink for every store; returns `false` if one was already set.
+pub fn set_audit_sink(sink: Arc<dyn AuditSink>) -> bool {
+    AUDIT_SINK.set(sink).is_ok()
+}
+
+/// Sends an event that is not stored in any brain to the audit sink, if one is installed.
+pub fn forward_audit(actor: &str, action: &str, details: serde_json::Value) {
+    if let Some(sink) = AUDIT_SINK.get() {
+        sink.record(None, &audit_entry(actor, action, details));
+    }
+}
+
+/// One lock per brain shared by every store in the process. A mutation reads, re-encrypts and
+/// rewrites the whole state, so two at once would lose one of the changes.
+static WRITE_LOCKS: OnceLock<Mutex<BrainLocks>> = OnceLock::new();
+
+type BrainLocks = HashMap<(PathBuf, String), Arc<Mutex<()>>>;
+
+fn write_lock(home_dir: &Path, brain_id: &str) -> Arc<Mutex<()>> {
+    WRITE_LOCKS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry((home_dir.to_path_buf(), brain_id.to_string()))
+        .or_default()
+        .clone()
+}
+
+fn notify_audit(brain_id: &str, entries: &[AuditEntry]) {
+    if let Some(sink) = AUDIT_SINK.get() {
+        for entry in entries {
+            sink.record(Some(brain_id), entry);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CreateBrainRequest {
     pub name: String,
@@ -139,6 +293,22 @@ pub struct MergeReport {
     pub conflicts: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct ConsolidateOptions {
+    /// Episodic objects sharing a subject and predicate are summarized once this many are live.
+    pub episodic_run: usize,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsolidationReport {
+    pub live_before: usize,
+    pub live_after: usize,
+    pub duplicates: usize,
+    pub superseded: usize,
+    pub episodic_summaries: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BrainPackage {
     package_version: String,
@@ -164,6 +334,43 @@ pub struct ApiKeyMapping {
     pub tenant_id: String,
     pub brain_id: String,
     pub subject: String,
+    #[serde(default, skip_serializing_if = "KeyQuota::is_unlimited")]
+    pub quota: KeyQuota,
+    /// Agent whose attachment grant governs every request made with this key. Keys without one
+    /// are owner keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// RFC 3339 time after which the key stops resolving, e.g. the grace window of a rotated key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+impl ApiKeyMapping {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at <= now)
+    }
+}
+
+/// Per-key limits enforced by the proxy; `None` means unlimited. Days and months are UTC.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct KeyQuota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_day: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_month: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub planner_tokens_per_day: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub planner_tokens_per_month: Option<u64>,
+}
+
+impl KeyQuota {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -171,12 +378,24 @@ struct ApiKeyMappings {
     mappings: Vec<ApiKeyMapping>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BrainStore {
     home_dir: PathBuf,
+    backend: Arc<dyn StorageBackend>,
+    secrets: Arc<dyn SecretSource>,
+}
+
+impl std::fmt::Debug for BrainStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BrainStore")
+            .field("home_dir", &self.home_dir)
+            .finish_non_exhaustive()
+    }
 }
 
 impl BrainStore {
+    /// Store under `home_override`, `CORTEX_HOME` or `~/.cortex`, with passphrases from env.
+    #[cfg(feature = "fs")]
     pub fn new(home_override: Option<PathBuf>) -> Result<Self> {
         let home_dir = if let Some(path) = home_override {
             path
@@ -191,25 +410,47 @@ impl BrainStore {
         fs::create_dir_all(home_dir.join("brains"))?;
         fs::create_dir_all(home_dir.join("auth"))?;
 
-        Ok(Self { home_dir })
+        Ok(Self {
+            backend: Arc::new(FsBackend::new(home_dir.clone())),
+            secrets: Arc::new(EnvSecrets),
+            home_dir,
+        })
+    }
+
+    /// Store over any backend, e.g. [`MemoryBackend`] with [`StaticSecrets`] on wasm32.
+    /// `home_dir` is empty for such stores.
+    pub fn with_backend(backend: Arc<dyn StorageBackend>, secrets: Arc<dyn SecretSource>) -> Self {
+        Self {
+            home_dir: PathBuf::new(),
+            backend,
+            secrets,
+        }
     }
 
     pub fn home_dir(&self) -> &Path {
         &self.home_dir
     }
 
+    /// File behind the API key mappings; it is rewritten on every mapping change.
+    pub fn api_keys_path(&self) -> PathBuf {
+        self.home_dir.join(API_KEYS_KEY)
+    }
+
+    /// Encrypted state of a brain; it is rewritten on every change to the brain.
+    pub fn brain_state_path(&self, brain_id: &str) -> PathBuf {
+        self.home_dir.join(brain_key(brain_id, STATE_FILE))
+    }
+
     pub fn create_brain(&self, req: CreateBrainRequest) -> Result<BrainSummary> {
         let secret_env = req
             .passphrase_env
             .unwrap_or_else(|| DEFAULT_SECRET_ENV.to_string());
-        let secret = env::var(&secret_env).with_context(|| {
+        let secret = self.secrets.secret(&secret_env).with_context(|| {
             format!("missing passphrase env var {secret_env}; set it before creating brain")
         })?;
 
         let slug = slugify(&req.name);
         let brain_id = format!("{}-{}", slug, &Uuid::new_v4().to_string()[..8]);
-        let brain_dir = self.brains_dir().join(&brain_id);
-        fs::create_dir_all(brain_dir.join("keys"))?;
 
         let mut salt = [0u8; 16];
         OsRng.fill_bytes(&mut salt);
@@ -253,12 +494,10 @@ impl BrainStore {
         };
         manifest.signature_b64 = sign_manifest(&manifest, &signing_key)?;
 
-        write_json(brain_dir.join("brain.json"), &manifest)?;
-        write_json(brain_dir.join("state.enc"), &state_enc)?;
-        write_json(
-            brain_dir.join("keys").join("signing_key.enc"),
-            &signing_key_enc,
-        )?;
+        self.write_json(&brain_key(&brain_id, MANIFEST_FILE), &manifest)?;
+        self.write_json(&brain_key(&brain_id, STATE_FILE), &state_enc)?;
+        self.write_json(&brain_key(&brain_id, SIGNING_KEY_FILE), &signing_key_enc)?;
+        notify_audit(&brain_id, &state.audit);
 
         Ok(BrainSummary {
             brain_id: manifest.brain_id,
@@ -271,16 +510,12 @@ impl BrainStore {
 
     pub fn list_brains(&self) -> Result<Vec<BrainSummary>> {
         let mut out = Vec::new();
-        for entry in fs::read_dir(self.brains_dir())? {
-            let entry = entry?;
-            if !entry.file_type()?.is_dir() {
+        for brain_id in self.backend.list_dirs(BRAINS_DIR)? {
+            let Some(manifest) =
+                self.read_json_opt::<BrainManifest>(&brain_key(&brain_id, MANIFEST_FILE))?
+            else {
                 continue;
-            }
-            let manifest_path = entry.path().join("brain.json");
-            if !manifest_path.exists() {
-                continue;
-            }
-            let manifest: BrainManifest = read_json(&manifest_path)?;
+            };
             out.push(BrainSummary {
                 brain_id: manifest.brain_id,
                 name: manifest.name,
@@ -297,7 +532,7 @@ impl BrainStore {
         let summary = self.resolve_brain(brain_ref)?;
         let mut cfg = self.read_config()?;
         cfg.active_brain = Some(summary.brain_id.clone());
-        write_json(self.config_path(), &cfg)?;
+        self.write_json(CONFIG_KEY, &cfg)?;
         Ok(summary)
     }
 
@@ -305,31 +540,61 @@ impl BrainStore {
         Ok(self.read_config()?.active_brain)
     }
 
+    #[cfg(feature = "fs")]
     pub fn export_brain(&self, brain_ref: &str, out_file: &Path) -> Result<()> {
-        let summary = self.resolve_brain(brain_ref)?;
-        let dir = self.brains_dir().join(&summary.brain_id);
-        let manifest: BrainManifest = read_json(dir.join("brain.json"))?;
-        let state: EncryptedBlob = read_json(dir.join("state.enc"))?;
-        let signing_key: EncryptedBlob = read_json(dir.join("keys").join("signing_key.enc"))?;
+        write_json(out_file, &self.package(brain_ref)?)
+    }
+
+    /// Streams the package to `out`, e.g. stdout for `cortex brain export --out -`.
+    pub fn export_brain_to(&self, brain_ref: &str, mut out: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut out, &self.package(brain_ref)?)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn package(&self, brain_ref: &str) -> Result<BrainPackage> {
+        let id = self.resolve_brain(brain_ref)?.brain_id;
+        let manifest: BrainManifest = self.read_json(&brain_key(&id, MANIFEST_FILE))?;
+        let state: EncryptedBlob = self.read_json(&brain_key(&id, STATE_FILE))?;
+        let signing_key: EncryptedBlob = self.read_json(&brain_key(&id, SIGNING_KEY_FILE))?;
 
         verify_manifest_signature(&manifest)?;
 
-        let package = BrainPackage {
+        Ok(BrainPackage {
             package_version: FORMAT_VERSION.to_string(),
             manifest,
             state,
             signing_key,
-        };
-        write_json(out_file, &package)
+        })
     }
 
+    #[cfg(feature = "fs")]
     pub fn import_brain(
         &self,
         in_file: &Path,
         name_override: Option<String>,
         verify_only: bool,
     ) -> Result<Option<BrainSummary>> {
-        let package: BrainPackage = read_json(in_file)?;
+        self.import_package(read_json(in_file)?, name_override, verify_only)
+    }
+
+    /// Reads a package from `input`, e.g. stdin for `cortex brain import --in -`.
+    pub fn import_brain_from(
+        &self,
+        input: impl Read,
+        name_override: Option<String>,
+        verify_only: bool,
+    ) -> Result<Option<BrainSummary>> {
+        let package = serde_json::from_reader(input).context("invalid brain package")?;
+        self.import_package(package, name_override, verify_only)
+    }
+
+    fn import_package(
+        &self,
+        package: BrainPackage,
+        name_override: Option<String>,
+        verify_only: bool,
+    ) -> Result<Option<BrainSummary>> {
         verify_manifest_signature(&package.manifest)?;
         let computed_state_hash = sha256_hex(&serde_json::to_vec(&package.state)?);
         if computed_state_hash != package.manifest.state_sha256 {
@@ -346,18 +611,19 @@ impl BrainStore {
         }
 
         let mut brain_id = manifest.brain_id.clone();
-        let mut target = self.brains_dir().join(&brain_id);
-        if target.exists() {
+        if self
+            .backend
+            .read(&brain_key(&brain_id, MANIFEST_FILE))?
+            .is_some()
+        {
             brain_id = format!("{}-{}", brain_id, &Uuid::new_v4().to_string()[..6]);
-            target = self.brains_dir().join(&brain_id);
         }
-        fs::create_dir_all(target.join("keys"))?;
-        manifest.brain_id = brain_id;
+        manifest.brain_id = brain_id.clone();
 
-        write_json(target.join("brain.json"), &manifest)?;
-        write_json(target.join("state.enc"), &package.state)?;
-        write_json(
-            target.join("keys").join("signing_key.enc"),
+        self.write_json(&brain_key(&brain_id, MANIFEST_FILE), &manifest)?;
+        self.write_json(&brain_key(&brain_id, STATE_FILE), &package.state)?;
+        self.write_json(
+            &brain_key(&brain_id, SIGNING_KEY_FILE),
             &package.signing_key,
         )?;
 
@@ -480,6 +746,7 @@ impl BrainStore {
                 scope: scope.to_string(),
                 reason: reason.to_string(),
                 suppressed_count: suppressed,
+                object_ids: Vec::new(),
             });
             state.audit.push(audit_entry(
                 "user",
@@ -526,6 +793,353 @@ impl BrainStore {
         Ok(removed)
     }
 
+    /// Adds a rule to the active branch, replacing any rule with the same id.
+    pub fn add_rule(&self, brain_ref: &str, rule: RuleEntry) -> Result<()> {
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            branch.rules.retain(|r| r.id != rule.id);
+            branch.rules.push(rule.clone());
+            state.audit.push(audit_entry(
+                "user",
+                "brain.rule_add",
+                serde_json::json!({
+                    "rule": rule.id,
+                    "memory_class": rule.memory_class,
+                    "allowed_sinks": rule.allowed_sinks,
+                    "action": rule.action,
+                }),
+            ));
+            Ok(())
+        })
+    }
+
+    pub fn remove_rule(&self, brain_ref: &str, rule_id: &str) -> Result<bool> {
+        let mut removed = false;
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            let before = branch.rules.len();
+            branch.rules.retain(|r| r.id != rule_id);
+            removed = branch.rules.len() != before;
+            state.audit.push(audit_entry(
+                "user",
+                "brain.rule_remove",
+                serde_json::json!({"rule": rule_id, "removed": removed}),
+            ));
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    pub fn rules(&self, brain_ref: &str) -> Result<Vec<RuleEntry>> {
+        let (manifest, state, _) = self.load_brain_with_secret(brain_ref)?;
+        Ok(state
+            .branches
+            .get(&manifest.active_branch)
+            .map(|b| b.rules.clone())
+            .unwrap_or_default())
+    }
+
+    /// Sets the retention window of a memory class on the active branch.
+    pub fn set_retention(&self, brain_ref: &str, policy: RetentionPolicy) -> Result<()> {
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            branch
+                .retention
+                .retain(|p| p.memory_class != policy.memory_class);
+            branch.retention.push(policy.clone());
+            state.audit.push(audit_entry(
+                "user",
+                "brain.retention_set",
+                serde_json::json!({
+                    "memory_class": policy.memory_class,
+                    "max_age_days": policy.max_age_days,
+                }),
+            ));
+            Ok(())
+        })
+    }
+
+    pub fn remove_retention(&self, brain_ref: &str, memory_class: &str) -> Result<bool> {
+        let mut removed = false;
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            let before = branch.retention.len();
+            branch.retention.retain(|p| p.memory_class != memory_class);
+            removed = branch.retention.len() != before;
+            state.audit.push(audit_entry(
+                "user",
+                "brain.retention_remove",
+                serde_json::json!({"memory_class": memory_class, "removed": removed}),
+            ));
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    pub fn retention(&self, brain_ref: &str) -> Result<Vec<RetentionPolicy>> {
+        Ok(self.active_branch_state(brain_ref)?.retention)
+    }
+
+    /// Suppresses memory older than its class's retention window. Returns how many objects
+    /// expired; nothing is written when none did.
+    pub fn sweep_retention(&self, brain_ref: &str, now: DateTime<Utc>) -> Result<usize> {
+        let mut preview = self.active_branch_state(brain_ref)?;
+        if expire_branch(&mut preview, now) == 0 {
+            return Ok(0);
+        }
+        let mut expired = 0usize;
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            expired = expire_branch(branch, now);
+            state.audit.push(audit_entry(
+                "maintenance",
+                "brain.retention_sweep",
+                serde_json::json!({"expired": expired}),
+            ));
+            Ok(())
+        })?;
+        Ok(expired)
+    }
+
+    pub fn list_attachments(&self, brain_ref: &str) -> Result<Vec<AttachmentGrant>> {
+        let (_, state, _) = self.load_brain_with_secret(brain_ref)?;
+        Ok(state.attachments)
+    }
+
+    pub fn append_ledger_event(
+        &self,
+        brain_ref: &str,
+        operation: &str,
+        payload: serde_json::Value,
+    ) -> Result<LedgerEvent> {
+        let event = LedgerEvent {
+            id: Uuid::new_v4().to_string(),
+            ts: Utc::now().to_rfc3339(),
+            operation: operation.to_string(),
+            payload,
+        };
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            branch.ledger.push(event.clone());
+            Ok(())
+        })?;
+        Ok(event)
+    }
+
+    pub fn apply_writes(&self, brain_ref: &str, batch: WriteBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let ts = Utc::now().to_rfc3339();
+        self.mutate_brain(brain_ref, |manifest, state| {
+            if !batch.ledger.is_empty() {
+                let branch = state
+                    .branches
+                    .get_mut(&manifest.active_branch)
+                    .ok_or_else(|| anyhow!("active branch missing"))?;
+                branch
+                    .ledger
+                    .extend(
+                        batch
+                            .ledger
+                            .into_iter()
+                            .map(|(operation, payload)| LedgerEvent {
+                                id: Uuid::new_v4().to_string(),
+                                ts: ts.clone(),
+                                operation,
+                                payload,
+                            }),
+                    );
+            }
+            state.audit.extend(
+                batch
+                    .audit
+                    .into_iter()
+                    .map(|(actor, action, details)| audit_entry(&actor, &action, details)),
+            );
+            Ok(())
+        })
+    }
+
+    pub fn ledger(&self, brain_ref: &str) -> Result<Vec<LedgerEvent>> {
+        let (manifest, state, _) = self.load_brain_with_secret(brain_ref)?;
+        Ok(state
+            .branches
+            .get(&manifest.active_branch)
+            .map(|b| b.ledger.clone())
+            .unwrap_or_default())
+    }
+
+    /// Adds memory objects to the active branch, keeping any object that already has the same
+    /// id. Returns how many were new.
+    pub fn add_memory_objects(&self, brain_ref: &str, objects: Vec<MemoryObject>) -> Result<usize> {
+        let mut added = 0usize;
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            let now = Utc::now().to_rfc3339();
+            for mut obj in objects {
+                if !branch.memory_objects.contains_key(&obj.id) {
+                    obj.recorded_at.get_or_insert_with(|| now.clone());
+                    branch.memory_objects.insert(obj.id.clone(), obj);
+                    added += 1;
+                }
+            }
+            state.audit.push(audit_entry(
+                "kernel",
+                "brain.memory_add",
+                serde_json::json!({"added": added}),
+            ));
+            Ok(())
+        })?;
+        Ok(added)
+    }
+
+    /// Supersedes duplicate memory objects and older conflicting values with the newest one, and
+    /// folds long episodic runs into a summary object. Superseded objects are kept but no longer
+    /// live.
+    pub fn consolidate(
+        &self,
+        brain_ref: &str,
+        options: ConsolidateOptions,
+    ) -> Result<ConsolidationReport> {
+        if options.dry_run {
+            let mut branch = self.active_branch_state(brain_ref)?;
+            return Ok(consolidate_branch(&mut branch, options.episodic_run));
+        }
+        let mut report = ConsolidationReport::default();
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            report = consolidate_branch(branch, options.episodic_run);
+            state.audit.push(audit_entry(
+                "user",
+                "brain.consolidate",
+                serde_json::to_value(&report)?,
+            ));
+            Ok(())
+        })?;
+        Ok(report)
+    }
+
+    /// Lets `compact` drop ledger events of the active branch that are no longer needed.
+    /// `compact` returns how many it removed; nothing is written when that is zero.
+    pub fn compact_ledger(
+        &self,
+        brain_ref: &str,
+        compact: impl FnOnce(&mut Vec<LedgerEvent>) -> usize,
+    ) -> Result<usize> {
+        let ledger = self.active_branch_state(brain_ref)?.ledger;
+        let mut compacted = ledger.clone();
+        if compact(&mut compacted) == 0 {
+            return Ok(0);
+        }
+        // Match by id so events appended meanwhile are kept.
+        let kept = compacted.iter().map(|e| &e.id).collect::<BTreeSet<_>>();
+        let dropped = ledger
+            .iter()
+            .filter(|e| !kept.contains(&e.id))
+            .map(|e| e.id.clone())
+            .collect::<BTreeSet<_>>();
+        let mut removed = 0usize;
+        self.mutate_brain(brain_ref, |manifest, state| {
+            let branch = state
+                .branches
+                .get_mut(&manifest.active_branch)
+                .ok_or_else(|| anyhow!("active branch missing"))?;
+            let before = branch.ledger.len();
+            branch.ledger.retain(|e| !dropped.contains(&e.id));
+            removed = before - branch.ledger.len();
+            state.audit.push(audit_entry(
+                "maintenance",
+                "brain.ledger_compact",
+                serde_json::json!({"removed": removed}),
+            ));
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    /// Removes attachment grants whose `expires_at` is before `now`.
+    pub fn sweep_expired_attachments(&self, brain_ref: &str, now: DateTime<Utc>) -> Result<usize> {
+        let expired = |grant: &AttachmentGrant| {
+            grant
+                .expires_at
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .is_some_and(|expires| expires <= now)
+        };
+        if !self.list_attachments(brain_ref)?.iter().any(expired) {
+            return Ok(0);
+        }
+        let mut removed = Vec::new();
+        self.mutate_brain(brain_ref, |_, state| {
+            state.attachments.retain(|grant| {
+                if expired(grant) {
+                    removed.push(format!("{}/{}", grant.agent_id, grant.model_id));
+                }
+                !expired(grant)
+            });
+            state.audit.push(audit_entry(
+                "maintenance",
+                "brain.attachment_expire",
+                serde_json::json!({"expired": removed}),
+            ));
+            Ok(())
+        })?;
+        Ok(removed.len())
+    }
+
+    /// Snapshot of the active branch (memory objects, ledger, suppressions, rules).
+    pub fn active_branch_state(&self, brain_ref: &str) -> Result<BranchState> {
+        let (manifest, mut state, _) = self.load_brain_with_secret(brain_ref)?;
+        state
+            .branches
+            .remove(&manifest.active_branch)
+            .ok_or_else(|| anyhow!("active branch missing"))
+    }
+
+    pub fn record_audit(
+        &self,
+        brain_ref: &str,
+        actor: &str,
+        action: &str,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        self.mutate_brain(brain_ref, |_, state| {
+            state.audit.push(audit_entry(actor, action, details));
+            Ok(())
+        })
+    }
+
+    /// Every branch, attachment and audit entry, decrypted; for local reports, not for export.
+    pub fn decrypted_state(&self, brain_ref: &str) -> Result<BrainState> {
+        let (_, state, _) = self.load_brain_with_secret(brain_ref)?;
+        Ok(state)
+    }
+
     pub fn audit_trace(&self, brain_ref: &str) -> Result<Vec<AuditEntry>> {
         let (_, state, _) = self.load_brain_with_secret(brain_ref)?;
         Ok(state.audit)
@@ -540,20 +1154,87 @@ impl BrainStore {
     ) -> Result<()> {
         let mut mappings = self.read_api_mappings()?;
         let hash = sha256_hex(api_key_plain.as_bytes());
-        mappings.mappings.retain(|m| m.key_hash != hash);
+        // Remapping a key keeps its quota and agent binding.
+        let (quota, agent_id) = mappings
+            .mappings
+            .iter()
+            .find(|m| m.key_hash == hash)
+            .map(|m| (m.quota, m.agent_id.clone()))
+            .unwrap_or_default();
+        let now = Utc::now();
+        mappings
+            .mappings
+            .retain(|m| m.key_hash != hash && !m.is_expired(now));
         mappings.mappings.push(ApiKeyMapping {
             key_hash: hash,
             tenant_id: tenant_id.to_string(),
             brain_id: brain_id.to_string(),
             subject: subject.to_string(),
+            quota,
+            agent_id,
+            expires_at: None,
         });
-        write_json(self.api_mapping_path(), &mappings)
+        self.write_json(API_KEYS_KEY, &mappings)
+    }
+
+    /// Keeps a key working until `at`; returns `false` if the key is not mapped.
+    pub fn expire_api_key(&self, api_key_plain: &str, at: DateTime<Utc>) -> Result<bool> {
+        let mut mappings = self.read_api_mappings()?;
+        let hash = sha256_hex(api_key_plain.as_bytes());
+        let Some(mapping) = mappings.mappings.iter_mut().find(|m| m.key_hash == hash) else {
+            return Ok(false);
+        };
+        mapping.expires_at = Some(at.to_rfc3339());
+        self.write_json(API_KEYS_KEY, &mappings)?;
+        Ok(true)
+    }
+
+    pub fn set_api_key_quota(&self, api_key_plain: &str, quota: KeyQuota) -> Result<ApiKeyMapping> {
+        self.update_api_key(api_key_plain, |mapping| mapping.quota = quota)
+    }
+
+    /// Binds a key to an agent, so the agent's grant applies whether or not the caller says who
+    /// it is; `None` makes it an owner key again.
+    pub fn set_api_key_agent(
+        &self,
+        api_key_plain: &str,
+        agent_id: Option<&str>,
+    ) -> Result<ApiKeyMapping> {
+        self.update_api_key(api_key_plain, |mapping| {
+            mapping.agent_id = agent_id.map(str::to_string);
+        })
+    }
+
+    fn update_api_key(
+        &self,
+        api_key_plain: &str,
+        update: impl FnOnce(&mut ApiKeyMapping),
+    ) -> Result<ApiKeyMapping> {
+        let mut mappings = self.read_api_mappings()?;
+        let hash = sha256_hex(api_key_plain.as_bytes());
+        let mapping = mappings
+            .mappings
+            .iter_mut()
+            .find(|m| m.key_hash == hash)
+            .ok_or_else(|| anyhow!("API key is not mapped"))?;
+        update(mapping);
+        let updated = mapping.clone();
+        self.write_json(API_KEYS_KEY, &mappings)?;
+        Ok(updated)
     }
 
     pub fn resolve_api_key(&self, api_key_plain: &str) -> Result<Option<ApiKeyMapping>> {
         let hash = sha256_hex(api_key_plain.as_bytes());
         let mappings = self.read_api_mappings()?;
-        Ok(mappings.mappings.into_iter().find(|m| m.key_hash == hash))
+        let now = Utc::now();
+        Ok(mappings
+            .mappings
+            .into_iter()
+            .find(|m| m.key_hash == hash && !m.is_expired(now)))
+    }
+
+    pub fn list_api_keys(&self) -> Result<Vec<ApiKeyMapping>> {
+        Ok(self.read_api_mappings()?.mappings)
     }
 
     pub fn resolve_brain(&self, brain_ref: &str) -> Result<BrainSummary> {
@@ -563,10 +1244,30 @@ impl BrainStore {
             .ok_or_else(|| anyhow!("brain not found: {brain_ref}"))
     }
 
+    pub fn state_sha256(&self, brain_ref: &str) -> Result<String> {
+        let summary = self.resolve_brain(brain_ref)?;
+        let manifest: BrainManifest =
+            self.read_json(&brain_key(&summary.brain_id, MANIFEST_FILE))?;
+        Ok(manifest.state_sha256)
+    }
+
+    /// Checks that `secret` decrypts the brain without reading it from the brain's env var.
+    pub fn verify_secret(&self, brain_ref: &str, secret: &str) -> Result<()> {
+        let id = self.resolve_brain(brain_ref)?.brain_id;
+        let manifest: BrainManifest = self.read_json(&brain_key(&id, MANIFEST_FILE))?;
+        verify_manifest_signature(&manifest)?;
+        let key = derive_key(secret.as_bytes(), &B64.decode(&manifest.kdf_salt_b64)?)?;
+        let state_enc: EncryptedBlob = self.read_json(&brain_key(&id, STATE_FILE))?;
+        decrypt_json::<BrainState>(&key, manifest.brain_id.as_bytes(), &state_enc)
+            .with_context(|| format!("secret does not unlock brain {}", manifest.brain_id))?;
+        Ok(())
+    }
+
     pub fn resolve_brain_or_active(&self, brain_ref: Option<&str>) -> Result<BrainSummary> {
         if let Some(brain_ref) = brain_ref {
             return self.resolve_brain(brain_ref);
         }
+        #[cfg(feature = "fs")]
         if let Ok(v) = env::var("CORTEX_BRAIN") {
             return self.resolve_brain(v.trim());
         }
@@ -580,22 +1281,33 @@ impl BrainStore {
     where
         F: FnOnce(&mut BrainManifest, &mut BrainState) -> Result<()>,
     {
-        let summary = self.resolve_brain(brain_ref)?;
-        let dir = self.brains_dir().join(&summary.brain_id);
-        let (mut manifest, mut state, signing_key) = self.load_by_dir(&dir)?;
+        let id = self.resolve_brain(brain_ref)?.brain_id;
+        let lock = write_lock(&self.home_dir, &id);
+        let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let (mut manifest, mut state, signing_key, key) = self.load_with_key(&id)?;
+        let (salt, secret_env_var) = (
+            manifest.kdf_salt_b64.clone(),
+            manifest.secret_env_var.clone(),
+        );
+        let audited = state.audit.len();
 
         f(&mut manifest, &mut state)?;
 
         manifest.updated_at = Utc::now().to_rfc3339();
-        let secret = env::var(&manifest.secret_env_var)
-            .with_context(|| format!("missing secret env var {}", manifest.secret_env_var))?;
-        let key = derive_key(secret.as_bytes(), &B64.decode(&manifest.kdf_salt_b64)?)?;
+        // The key derivation is the slow part; it is only repeated if the change rekeyed.
+        let key = if manifest.kdf_salt_b64 == salt && manifest.secret_env_var == secret_env_var {
+            key
+        } else {
+            let secret = self.manifest_secret(&manifest)?;
+            derive_key(secret.as_bytes(), &B64.decode(&manifest.kdf_salt_b64)?)?
+        };
         let state_enc = encrypt_json(&key, manifest.brain_id.as_bytes(), &state)?;
         manifest.state_sha256 = sha256_hex(&serde_json::to_vec(&state_enc)?);
         manifest.signature_b64 = sign_manifest(&manifest, &signing_key)?;
 
-        write_json(dir.join("brain.json"), &manifest)?;
-        write_json(dir.join("state.enc"), &state_enc)?;
+        self.write_json(&brain_key(&id, MANIFEST_FILE), &manifest)?;
+        self.write_json(&brain_key(&id, STATE_FILE), &state_enc)?;
+        notify_audit(&id, state.audit.get(audited..).unwrap_or_default());
         Ok(())
     }
 
@@ -604,25 +1316,32 @@ impl BrainStore {
         brain_ref: &str,
     ) -> Result<(BrainManifest, BrainState, SigningKey)> {
         let summary = self.resolve_brain(brain_ref)?;
-        self.load_by_dir(&self.brains_dir().join(summary.brain_id))
+        self.load_by_id(&summary.brain_id)
     }
 
-    fn load_by_dir(&self, brain_dir: &Path) -> Result<(BrainManifest, BrainState, SigningKey)> {
-        let manifest: BrainManifest = read_json(brain_dir.join("brain.json"))?;
+    fn load_by_id(&self, brain_id: &str) -> Result<(BrainManifest, BrainState, SigningKey)> {
+        let (manifest, state, signing_key, _) = self.load_with_key(brain_id)?;
+        Ok((manifest, state, signing_key))
+    }
+
+    fn load_with_key(
+        &self,
+        brain_id: &str,
+    ) -> Result<(BrainManifest, BrainState, SigningKey, [u8; 32])> {
+        let manifest: BrainManifest = self.read_json(&brain_key(brain_id, MANIFEST_FILE))?;
         verify_manifest_signature(&manifest)?;
 
-        let secret = env::var(&manifest.secret_env_var)
-            .with_context(|| format!("missing secret env var {}", manifest.secret_env_var))?;
+        let secret = self.manifest_secret(&manifest)?;
         let key = derive_key(secret.as_bytes(), &B64.decode(&manifest.kdf_salt_b64)?)?;
 
-        let state_enc: EncryptedBlob = read_json(brain_dir.join("state.enc"))?;
+        let state_enc: EncryptedBlob = self.read_json(&brain_key(brain_id, STATE_FILE))?;
         if sha256_hex(&serde_json::to_vec(&state_enc)?) != manifest.state_sha256 {
             bail!("state checksum mismatch for brain {}", manifest.brain_id);
         }
         let state: BrainState = decrypt_json(&key, manifest.brain_id.as_bytes(), &state_enc)?;
 
         let signing_key_enc: EncryptedBlob =
-            read_json(brain_dir.join("keys").join("signing_key.enc"))?;
+            self.read_json(&brain_key(brain_id, SIGNING_KEY_FILE))?;
         let signing_bytes = decrypt_bytes(&key, manifest.brain_id.as_bytes(), &signing_key_enc)?;
         let signing_key = SigningKey::from_bytes(
             &signing_bytes
@@ -631,34 +1350,192 @@ impl BrainStore {
                 .map_err(|_| anyhow!("invalid signing key bytes"))?,
         );
 
-        Ok((manifest, state, signing_key))
+        Ok((manifest, state, signing_key, key))
+    }
+
+    fn manifest_secret(&self, manifest: &BrainManifest) -> Result<String> {
+        self.secrets
+            .secret(&manifest.secret_env_var)
+            .ok_or_else(|| anyhow!("missing secret env var {}", manifest.secret_env_var))
     }
 
     fn read_config(&self) -> Result<AppConfig> {
-        if !self.config_path().exists() {
-            return Ok(AppConfig { active_brain: None });
-        }
-        read_json(self.config_path())
+        Ok(self
+            .read_json_opt(CONFIG_KEY)?
+            .unwrap_or(AppConfig { active_brain: None }))
     }
 
     fn read_api_mappings(&self) -> Result<ApiKeyMappings> {
-        if !self.api_mapping_path().exists() {
-            return Ok(ApiKeyMappings::default());
+        Ok(self.read_json_opt(API_KEYS_KEY)?.unwrap_or_default())
+    }
+
+    fn read_json<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T> {
+        self.read_json_opt(key)?
+            .ok_or_else(|| anyhow!("{key} not found in brain store"))
+    }
+
+    fn read_json_opt<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>> {
+        match self.backend.read(key)? {
+            Some(bytes) => Ok(Some(
+                serde_json::from_slice(&bytes).with_context(|| format!("invalid {key}"))?,
+            )),
+            None => Ok(None),
         }
-        read_json(self.api_mapping_path())
     }
 
-    fn brains_dir(&self) -> PathBuf {
-        self.home_dir.join("brains")
+    fn write_json<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        self.backend.write(key, &serde_json::to_vec_pretty(value)?)
     }
+}
+
+fn brain_key(brain_id: &str, file: &str) -> String {
+    format!("{BRAINS_DIR}/{brain_id}/{file}")
+}
 
-    fn config_path(&self) -> PathBuf {
-        self.home_dir.join("config.json")
+/// Suppresses objects recorded before their class's retention window, with one suppression
+/// record per subject and predicate. The record covers the whole pair when nothing live is
+/// left under it, and only the expired objects otherwise. Objects without `recorded_at` are
+/// kept.
+fn expire_branch(branch: &mut BranchState, now: DateTime<Utc>) -> usize {
+    let mut groups: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for obj in branch.memory_objects.values().filter(|o| !o.suppressed) {
+        let Some(days) = retention_days(&branch.retention, &obj.memory_type) else {
+            continue;
+        };
+        let recorded = obj
+            .recorded_at
+            .as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+        if recorded.is_some_and(|at| now.signed_duration_since(at).num_days() >= i64::from(days)) {
+            groups
+                .entry((obj.subject.clone(), obj.predicate.clone()))
+                .or_default()
+                .push(obj.id.clone());
+        }
     }
+    let mut expired = 0;
+    let ts = now.to_rfc3339();
+    for ((subject, predicate), ids) in groups {
+        for id in &ids {
+            if let Some(obj) = branch.memory_objects.get_mut(id) {
+                obj.suppressed = true;
+            }
+        }
+        let whole = !branch
+            .memory_objects
+            .values()
+            .any(|o| o.subject == subject && o.predicate == predicate && o.is_live());
+        expired += ids.len();
+        branch.suppressions.push(SuppressionRecord {
+            id: Uuid::new_v4().to_string(),
+            ts: ts.clone(),
+            subject,
+            predicate,
+            scope: "SCOPE_GLOBAL".to_string(),
+            reason: "retention".to_string(),
+            suppressed_count: ids.len(),
+            object_ids: if whole { Vec::new() } else { ids },
+        });
+    }
+    expired
+}
 
-    fn api_mapping_path(&self) -> PathBuf {
-        self.home_dir.join("auth").join("api_keys.json")
+fn consolidate_branch(branch: &mut BranchState, episodic_run: usize) -> ConsolidationReport {
+    let objects = &mut branch.memory_objects;
+    let mut report = ConsolidationReport {
+        live_before: objects.values().filter(|o| o.is_live()).count(),
+        ..Default::default()
+    };
+    let mut groups: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for obj in objects.values().filter(|o| o.is_live()) {
+        groups
+            .entry((obj.subject.clone(), obj.predicate.clone()))
+            .or_default()
+            .push(obj.id.clone());
     }
+    let now = Utc::now().to_rfc3339();
+    for ((subject, predicate), mut ids) in groups {
+        // Oldest first; objects without a timestamp predate everything recorded.
+        ids.sort_by_key(|id| {
+            (
+                objects[id].recorded_at.clone().unwrap_or_default(),
+                id.clone(),
+            )
+        });
+
+        // Exact duplicates carry nothing new: the newest copy supersedes them. They stay in the
+        // branch so a sync does not bring them back.
+        let mut kept: Vec<String> = Vec::new();
+        for id in ids.into_iter().rev() {
+            let original = kept
+                .iter()
+                .find(|k| {
+                    objects[*k].memory_type == objects[&id].memory_type
+                        && objects[*k].value == objects[&id].value
+                })
+                .cloned();
+            if let Some(original) = original {
+                if let Some(obj) = objects.get_mut(&id) {
+                    obj.superseded_by = Some(original);
+                }
+                report.duplicates += 1;
+            } else {
+                kept.push(id);
+            }
+        }
+        kept.reverse();
+        let Some(newest) = kept.last().cloned() else {
+            continue;
+        };
+
+        let episodic = objects[&newest].memory_type.starts_with("episodic");
+        let winner = if episodic {
+            if kept.len() < episodic_run.max(2) {
+                continue;
+            }
+            // Earlier summaries are folded in, so counts keep adding up across runs.
+            let count: u64 = kept
+                .iter()
+                .map(|id| objects[id].value["summary_of"].as_u64().unwrap_or(1))
+                .sum();
+            let values = kept
+                .iter()
+                .filter(|id| objects[*id].value.get("summary_of").is_none())
+                .map(|id| objects[id].value.clone())
+                .collect::<Vec<_>>();
+            let recent = &values[values.len().saturating_sub(10)..];
+            let summary = MemoryObject {
+                id: format!("summary-{}", Uuid::new_v4()),
+                subject,
+                predicate,
+                value: serde_json::json!({
+                    "summary_of": count,
+                    "first_recorded_at": objects[&kept[0]].recorded_at,
+                    "last_recorded_at": objects[&newest].recorded_at,
+                    "recent": recent,
+                }),
+                memory_type: objects[&newest].memory_type.clone(),
+                suppressed: false,
+                recorded_at: Some(now.clone()),
+                superseded_by: None,
+            };
+            let id = summary.id.clone();
+            objects.insert(id.clone(), summary);
+            report.episodic_summaries += 1;
+            kept.push(id.clone());
+            id
+        } else {
+            newest
+        };
+        for id in kept.iter().filter(|id| **id != winner) {
+            if let Some(obj) = objects.get_mut(id) {
+                obj.superseded_by = Some(winner.clone());
+                report.superseded += 1;
+            }
+        }
+    }
+    report.live_after = objects.values().filter(|o| o.is_live()).count();
+    report
 }
 
 fn audit_entry(actor: &str, action: &str, details: serde_json::Value) -> AuditEntry {
@@ -776,6 +1653,7 @@ fn manifest_signing_payload(manifest: &BrainManifest) -> Result<Vec<u8>> {
     Ok(serde_json::to_vec(&copy)?)
 }
 
+#[cfg(feature = "fs")]
 fn write_json<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<()> {
     if let Some(parent) = path.as_ref().parent() {
         fs::create_dir_all(parent)?;
@@ -784,6 +1662,7 @@ fn write_json<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "fs")]
 fn read_json<P: AsRef<Path>, T: for<'de> Deserialize<'de>>(path: P) -> Result<T> {
     let bytes = fs::read(path)?;
     Ok(serde_json::from_slice(&bytes)?)
@@ -813,6 +1692,8 @@ mod tests {
             passphrase_env: Some("TEST_BRAIN_SECRET".to_string()),
         })?;
         store.set_active_brain(&created.brain_id)?;
+        store.verify_secret("demo", "test-secret")?;
+        assert!(store.verify_secret("demo", "other-secret").is_err());
 
         let out = temp.path().join("demo.cbrain");
         store.export_brain(&created.brain_id, &out)?;
@@ -828,6 +1709,113 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn streamed_packages_match_files_and_are_verified() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        unsafe {
+            env::set_var("TEST_BRAIN_SECRET_STREAM", "test-secret-stream");
+        }
+        let store = BrainStore::new(Some(temp.path().to_path_buf()))?;
+        let created = store.create_brain(CreateBrainRequest {
+            name: "piped".to_string(),
+            tenant_id: "tenant-a".to_string(),
+            passphrase_env: Some("TEST_BRAIN_SECRET_STREAM".to_string()),
+        })?;
+        let out = temp.path().join("piped.cbrain");
+        store.export_brain(&created.brain_id, &out)?;
+
+        let mut piped = Vec::new();
+        store.export_brain_to(&created.brain_id, &mut piped)?;
+        assert_eq!(piped, fs::read(&out)?);
+        assert!(
+            store
+                .import_brain_from(piped.as_slice(), None, true)?
+                .is_none()
+        );
+        let streamed =
+            store.import_brain_from(piped.as_slice(), Some("piped-copy".to_string()), false)?;
+        assert_eq!(streamed.map(|b| b.name).as_deref(), Some("piped-copy"));
+
+        let mut tampered: serde_json::Value = serde_json::from_slice(&piped)?;
+        tampered["manifest"]["name"] = "someone-else".into();
+        let tampered = serde_json::to_vec(&tampered)?;
+        assert!(
+            store
+                .import_brain_from(tampered.as_slice(), None, true)
+                .is_err()
+        );
+        assert!(
+            store
+                .import_brain_from(&b"not a package"[..], None, true)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_write_batches_keep_every_record() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        unsafe {
+            env::set_var("TEST_BRAIN_SECRET_BATCH", "test-secret-batch");
+        }
+        let store = BrainStore::new(Some(temp.path().to_path_buf()))?;
+        let brain = store.create_brain(CreateBrainRequest {
+            name: "batch".to_string(),
+            tenant_id: "local".to_string(),
+            passphrase_env: Some("TEST_BRAIN_SECRET_BATCH".to_string()),
+        })?;
+
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let store = BrainStore::new(Some(temp.path().to_path_buf())).unwrap();
+                let brain_id = brain.brain_id.clone();
+                scope.spawn(move || {
+                    let mut batch = WriteBatch::default();
+                    batch.ledger("test.proof", serde_json::json!({ "writer": writer }));
+                    batch.ledger("test.append", serde_json::json!({ "writer": writer }));
+                    batch.audit("test", "test.hit", serde_json::json!({ "writer": writer }));
+                    store.apply_writes(&brain_id, batch).unwrap();
+                });
+            }
+        });
+
+        assert_eq!(store.ledger("batch")?.len(), 8);
+        let hits = store
+            .audit_trace("batch")?
+            .into_iter()
+            .filter(|entry| entry.action == "test.hit")
+            .count();
+        assert_eq!(hits, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn expired_api_keys_stop_resolving() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let store = BrainStore::new(Some(temp.path().to_path_buf()))?;
+        store.map_api_key("ctx_old", "tenant-a", "brain-1", "user:local")?;
+        store.map_api_key("ctx_new", "tenant-a", "brain-1", "user:local")?;
+
+        assert!(store.expire_api_key("ctx_old", Utc::now() + chrono::Duration::hours(1))?);
+        assert!(store.resolve_api_key("ctx_old")?.is_some());
+        store.expire_api_key("ctx_old", Utc::now() - chrono::Duration::seconds(1))?;
+        assert!(store.resolve_api_key("ctx_old")?.is_none());
+        assert!(store.resolve_api_key("ctx_new")?.is_some());
+        assert!(!store.expire_api_key("ctx_unknown", Utc::now())?);
+
+        // Expired mappings are dropped the next time a key is mapped.
+        store.map_api_key("ctx_third", "tenant-a", "brain-1", "user:local")?;
+        assert_eq!(store.list_api_keys()?.len(), 2);
+
+        // An agent binding survives remapping the key.
+        store.set_api_key_agent("ctx_third", Some("agent-a"))?;
+        store.map_api_key("ctx_third", "tenant-a", "brain-2", "user:local")?;
+        let mapping = store.resolve_api_key("ctx_third")?.unwrap();
+        assert_eq!(mapping.agent_id.as_deref(), Some("agent-a"));
+        assert_eq!(mapping.brain_id, "brain-2");
+        Ok(())
+    }
+
     #[test]
     fn branch_attach_forget_merge_audit() -> Result<()> {
         let temp = tempfile::tempdir()?;
@@ -854,6 +1842,24 @@ mod tests {
                 expires_at: None,
             },
         )?;
+        assert_eq!(store.list_attachments(&created.brain_id)?.len(), 1);
+
+        store.add_rule(
+            &created.brain_id,
+            RuleEntry {
+                id: "no-export".to_string(),
+                description: "preferences stay in chat".to_string(),
+                allowed_sinks: vec!["chat".to_string()],
+                memory_class: "normative.preference".to_string(),
+                action: RuleAction::Block,
+            },
+        )?;
+        let rules = store.rules(&created.brain_id)?;
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].covers("normative.preference") && !rules[0].covers("other"));
+        assert!(rules[0].allows("chat") && !rules[0].allows("export"));
+        assert!(store.remove_rule(&created.brain_id, "no-export")?);
+        assert!(!store.remove_rule(&created.brain_id, "no-export")?);
 
         let suppressed = store.forget_suppress(
             &created.brain_id,
@@ -863,12 +1869,202 @@ mod tests {
             "test",
         )?;
         assert_eq!(suppressed, 0);
+        let object = MemoryObject {
+            id: "kernel-1".to_string(),
+            subject: "user:x".to_string(),
+            predicate: "prefers_beverage".to_string(),
+            value: serde_json::json!("tea"),
+            memory_type: "normative.preference".to_string(),
+            suppressed: false,
+            recorded_at: None,
+            superseded_by: None,
+        };
+        let added =
+            store.add_memory_objects(&created.brain_id, vec![object.clone(), object.clone()])?;
+        assert_eq!(added, 1);
+        let branch = store.active_branch_state(&created.brain_id)?;
+        assert_eq!(branch.suppressions.len(), 1);
+        assert!(branch.memory_objects.contains_key("kernel-1"));
 
         let report = store.merge(&created.brain_id, "exp-a", "main", MergeStrategy::Ours)?;
         assert!(report.conflicts.is_empty());
 
         let audit = store.audit_trace(&created.brain_id)?;
         assert!(!audit.is_empty());
+
+        store.append_ledger_event(
+            &created.brain_id,
+            "rmvm.execute.proof",
+            serde_json::json!({"request_id": "req-1"}),
+        )?;
+        let ledger = store.ledger(&created.brain_id)?;
+        assert_eq!(
+            ledger.last().map(|e| e.operation.as_str()),
+            Some("rmvm.execute.proof")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn consolidate_supersedes_duplicates_conflicts_and_episodic_runs() {
+        let object =
+            |id: &str, predicate: &str, value: &str, memory_type: &str, at: &str| MemoryObject {
+                id: id.to_string(),
+                subject: "user:x".to_string(),
+                predicate: predicate.to_string(),
+                value: serde_json::json!(value),
+                memory_type: memory_type.to_string(),
+                suppressed: false,
+                recorded_at: Some(format!("2026-01-0{at}T00:00:00Z")),
+                superseded_by: None,
+            };
+        let mut branch = BranchState::default();
+        for obj in [
+            object("a1", "prefers_beverage", "tea", "normative.preference", "1"),
+            object("a2", "prefers_beverage", "tea", "normative.preference", "2"),
+            object(
+                "a3",
+                "prefers_beverage",
+                "coffee",
+                "normative.preference",
+                "3",
+            ),
+            object("e1", "visited", "paris", "episodic.event", "1"),
+            object("e2", "visited", "rome", "episodic.event", "2"),
+            object("e3", "visited", "oslo", "episodic.event", "3"),
+        ] {
+            branch.memory_objects.insert(obj.id.clone(), obj);
+        }
+
+        let report = consolidate_branch(&mut branch, 3);
+        assert_eq!(report.live_before, 6);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.superseded, 4);
+        assert_eq!(report.episodic_summaries, 1);
+        assert_eq!(report.live_after, 2);
+        let objects = &branch.memory_objects;
+        assert_eq!(objects["a1"].superseded_by.as_deref(), Some("a2"));
+        assert_eq!(objects["a2"].superseded_by.as_deref(), Some("a3"));
+        assert!(objects["a3"].is_live());
+        let summary = objects
+            .values()
+            .find(|o| o.id.starts_with("summary-"))
+            .unwrap();
+        assert_eq!(summary.value["summary_of"], 3);
+        assert_eq!(summary.value["recent"][2], "oslo");
+        assert_eq!(objects["e1"].superseded_by.as_ref(), Some(&summary.id));
+
+        let again = consolidate_branch(&mut branch, 3);
+        assert_eq!(
+            again.superseded + again.duplicates + again.episodic_summaries,
+            0
+        );
+    }
+
+    #[test]
+    fn retention_sweep_suppresses_expired_classes() {
+        let object = |id: &str, predicate: &str, memory_type: &str, at: &str| MemoryObject {
+            id: id.to_string(),
+            subject: "user:x".to_string(),
+            predicate: predicate.to_string(),
+            value: serde_json::json!(id),
+            memory_type: memory_type.to_string(),
+            suppressed: false,
+            recorded_at: Some(at.to_string()),
+            superseded_by: None,
+        };
+        let mut branch = BranchState {
+            retention: vec![
+                RetentionPolicy {
+                    memory_class: "*".to_string(),
+                    max_age_days: Some(30),
+                },
+                RetentionPolicy {
+                    memory_class: "normative.preference".to_string(),
+                    max_age_days: None,
+                },
+            ],
+            ..Default::default()
+        };
+        for obj in [
+            object("e1", "visited", "episodic.event", "2026-01-01T00:00:00Z"),
+            object("e2", "visited", "episodic.event", "2026-03-01T00:00:00Z"),
+            object("w1", "worked_at", "episodic.event", "2026-01-01T00:00:00Z"),
+            object(
+                "p1",
+                "prefers",
+                "normative.preference",
+                "2025-01-01T00:00:00Z",
+            ),
+        ] {
+            branch.memory_objects.insert(obj.id.clone(), obj);
+        }
+        let now = DateTime::parse_from_rfc3339("2026-03-10T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(expire_branch(&mut branch, now), 2);
+        let objects = &branch.memory_objects;
+        assert!(objects["e1"].suppressed && objects["w1"].suppressed);
+        assert!(objects["e2"].is_live() && objects["p1"].is_live());
+        assert_eq!(branch.suppressions.len(), 2);
+        let visited = &branch.suppressions[0];
+        assert_eq!(visited.predicate, "visited");
+        assert_eq!(visited.object_ids, vec!["e1".to_string()]);
+        assert!(!branch.suppressions[1].is_partial());
+        assert_eq!(expire_branch(&mut branch, now), 0);
+    }
+
+    #[test]
+    fn opens_an_exported_brain_in_memory_with_passed_in_secrets() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        unsafe {
+            env::set_var("TEST_BRAIN_SECRET_MEM", "test-secret-mem");
+        }
+        let store = BrainStore::new(Some(temp.path().to_path_buf()))?;
+        let created = store.create_brain(CreateBrainRequest {
+            name: "portable".to_string(),
+            tenant_id: "local".to_string(),
+            passphrase_env: Some("TEST_BRAIN_SECRET_MEM".to_string()),
+        })?;
+        let mut package = Vec::new();
+        store.export_brain_to(&created.brain_id, &mut package)?;
+
+        let locked = BrainStore::with_backend(
+            Arc::new(MemoryBackend::new()),
+            Arc::new(StaticSecrets::new()),
+        );
+        locked.import_brain_from(package.as_slice(), None, false)?;
+        assert_eq!(locked.list_brains()?.len(), 1);
+        assert!(locked.audit_trace("portable").is_err());
+
+        let memory = BrainStore::with_backend(
+            Arc::new(MemoryBackend::new()),
+            Arc::new(StaticSecrets::new().with("TEST_BRAIN_SECRET_MEM", "test-secret-mem")),
+        );
+        let imported = memory
+            .import_brain_from(package.as_slice(), None, false)?
+            .unwrap();
+        assert_eq!(imported.brain_id, created.brain_id);
+        memory.set_active_brain("portable")?;
+        memory.forget_suppress(
+            "portable",
+            "user:local",
+            "prefers_beverage",
+            "SCOPE_GLOBAL",
+            "test",
+        )?;
+        let actions = memory
+            .audit_trace(&imported.brain_id)?
+            .into_iter()
+            .map(|e| e.action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions.first().map(String::as_str), Some("brain.create"));
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            memory.resolve_brain_or_active(None)?.brain_id,
+            imported.brain_id
+        );
         Ok(())
     }
 }
@@ -0,0 +1,248 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use brain_store::{AuditEntry, AuditSink, set_audit_sink};
+use serde_json::json;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::webhooks::{WebhookConfig, WebhookDispatcher};
+
+pub const AUDIT_FILE_ENV: &str = "CORTEX_AUDIT_FILE";
+pub const AUDIT_SYSLOG_ENV: &str = "CORTEX_AUDIT_SYSLOG";
+pub const AUDIT_WEBHOOK_URLS_ENV: &str = "CORTEX_AUDIT_WEBHOOK_URLS";
+pub const AUDIT_WEBHOOK_SECRET_ENV: &str = "CORTEX_AUDIT_WEBHOOK_SECRET";
+
+const WEBHOOK_EVENT: &str = "audit.entry";
+/// RFC 5424 priority for facility local0 (16), severity notice (5).
+const SYSLOG_PRI: u8 = 16 * 8 + 5;
+
+static FORWARDER: OnceLock<Arc<AuditForwarder>> = OnceLock::new();
+
+/// Where audit entries are copied besides the brain itself. Empty means forwarding is off.
+#[derive(Debug, Clone, Default)]
+pub struct AuditSinkConfig {
+    /// JSON lines file, appended to.
+    pub file: Option<PathBuf>,
+    /// `udp://host:port`, or a unix datagram socket path such as `/dev/log`.
+    pub syslog: Option<String>,
+    pub webhooks: WebhookConfig,
+}
+
+impl AuditSinkConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            file: var(AUDIT_FILE_ENV).map(PathBuf::from),
+            syslog: var(AUDIT_SYSLOG_ENV),
+            webhooks: WebhookConfig {
+                urls: var(AUDIT_WEBHOOK_URLS_ENV)
+                    .map(|v| v.split(',').map(|u| u.trim().to_string()).collect())
+                    .unwrap_or_default(),
+                secret: var(AUDIT_WEBHOOK_SECRET_ENV),
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.file.is_none() && self.syslog.is_none() && self.webhooks.urls.is_empty()
+    }
+}
+
+enum Syslog {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+}
+
+impl Syslog {
+    fn connect(target: &str) -> Result<Self> {
+        if let Some(addr) = target.strip_prefix("udp://") {
+            let socket = UdpSocket::bind("0.0.0.0:0")?;
+            socket
+                .connect(addr)
+                .with_context(|| format!("failed to resolve syslog address {addr}"))?;
+            return Ok(Self::Udp(socket));
+        }
+        #[cfg(unix)]
+        {
+            let socket = std::os::unix::net::UnixDatagram::unbound()?;
+            socket
+                .connect(target)
+                .with_context(|| format!("failed to connect to syslog socket {target}"))?;
+            Ok(Self::Unix(socket))
+        }
+        #[cfg(not(unix))]
+        Err(anyhow::anyhow!(
+            "syslog target must be udp://host:port, got {target}"
+        ))
+    }
+
+    fn send(&self, message: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Udp(socket) => socket.send(message),
+            #[cfg(unix)]
+            Self::Unix(socket) => socket.send(message),
+        }
+    }
+}
+
+/// Copies every audit entry to the configured sinks. File and syslog writes happen inline;
+/// webhooks are delivered in the background and can be awaited with [`flush`].
+pub struct AuditForwarder {
+    file: Option<Mutex<File>>,
+    syslog: Option<Syslog>,
+    webhooks: Option<WebhookDispatcher>,
+    deliveries: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl AuditForwarder {
+    pub fn new(config: AuditSinkConfig) -> Result<Self> {
+        let file = config
+            .file
+            .map(|path| -> Result<_> {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .with_context(|| format!("failed to open audit file {}", path.display()))?;
+                Ok(Mutex::new(file))
+            })
+            .transpose()?;
+        let syslog = config.syslog.as_deref().map(Syslog::connect).transpose()?;
+        Ok(Self {
+            file,
+            syslog,
+            webhooks: WebhookDispatcher::new(config.webhooks),
+            deliveries: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl AuditSink for AuditForwarder {
+    fn record(&self, brain_id: Option<&str>, entry: &AuditEntry) {
+        let event = json!({
+            "brain_id": brain_id,
+            "id": entry.id,
+            "ts": entry.ts,
+            "actor": entry.actor,
+            "action": entry.action,
+            "details": entry.details,
+        });
+        if let Some(file) = self.file.as_ref() {
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Err(err) = writeln!(file, "{event}") {
+                warn!("failed to write audit entry {}: {err}", entry.id);
+            }
+        }
+        if let Some(syslog) = self.syslog.as_ref() {
+            let message = format!(
+                "<{SYSLOG_PRI}>1 {} - cortex {} {} - {event}",
+                entry.ts,
+                std::process::id(),
+                entry.action
+            );
+            if let Err(err) = syslog.send(message.as_bytes()) {
+                warn!("failed to send audit entry {} to syslog: {err}", entry.id);
+            }
+        }
+        if let Some(webhooks) = self.webhooks.as_ref() {
+            if tokio::runtime::Handle::try_current().is_err() {
+                warn!("no runtime to deliver audit entry {} by webhook", entry.id);
+                return;
+            }
+            let mut deliveries = self
+                .deliveries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            deliveries.retain(|d| !d.is_finished());
+            deliveries.extend(webhooks.fire(WEBHOOK_EVENT, event));
+        }
+    }
+}
+
+/// Installs forwarding from `CORTEX_AUDIT_*` for every brain store in this process.
+pub fn init_from_env() -> Result<()> {
+    let config = AuditSinkConfig::from_env();
+    if config.is_empty() {
+        return Ok(());
+    }
+    let forwarder = Arc::new(AuditForwarder::new(config)?);
+    if set_audit_sink(forwarder.clone()) {
+        let _ = FORWARDER.set(forwarder);
+    }
+    Ok(())
+}
+
+/// Waits up to `timeout` for webhook deliveries still in flight, so a CLI command does not exit
+/// before its audit entries leave the process.
+pub async fn flush(timeout: Duration) {
+    let Some(forwarder) = FORWARDER.get() else {
+        return;
+    };
+    let deliveries = std::mem::take(
+        &mut *forwarder
+            .deliveries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+    if tokio::time::timeout(timeout, join_all(deliveries))
+        .await
+        .is_err()
+    {
+        warn!("gave up waiting for audit webhook deliveries");
+    }
+}
+
+async fn join_all(deliveries: Vec<JoinHandle<()>>) {
+    for delivery in deliveries {
+        let _ = delivery.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwards_entries_to_file_and_udp_syslog() {
+        let temp = tempfile::tempdir().unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let forwarder = AuditForwarder::new(AuditSinkConfig {
+            file: Some(temp.path().join("audit/cortex.jsonl")),
+            syslog: Some(format!("udp://{}", receiver.local_addr().unwrap())),
+            webhooks: WebhookConfig::default(),
+        })
+        .unwrap();
+        let entry = AuditEntry {
+            id: "a1".to_string(),
+            ts: "2026-01-01T00:00:00Z".to_string(),
+            actor: "cli".to_string(),
+            action: "brain.forget".to_string(),
+            details: json!({"subject": "user:local"}),
+        };
+        forwarder.record(Some("b1"), &entry);
+
+        let line = fs::read_to_string(temp.path().join("audit/cortex.jsonl")).unwrap();
+        let logged: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(logged["brain_id"], "b1");
+        assert_eq!(logged["action"], "brain.forget");
+
+        let mut buf = [0u8; 2048];
+        let n = receiver.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("<133>1 2026-01-01T00:00:00Z - cortex "));
+        assert!(message.contains(" brain.forget - {"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use brain_store::{ApiKeyMapping, AttachmentGrant, BrainStore, RuleEntry};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+/// Modification time and length of a file, or `None` when it does not exist. The length
+/// catches a rewrite within the filesystem's timestamp resolution.
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> FileStamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// API key mappings, attachment grants and brain rules, reused until the file they came from
+/// changes, so steady-state requests skip re-reading `api_keys.json` and deriving the brain key
+/// to read grants and rules. Expiry is still checked on every lookup.
+#[derive(Debug)]
+pub struct AuthCache {
+    store: BrainStore,
+    keys: Mutex<Option<(FileStamp, HashMap<String, ApiKeyMapping>)>>,
+    grants: Mutex<HashMap<String, (FileStamp, Vec<AttachmentGrant>)>>,
+    rules: Mutex<HashMap<String, (FileStamp, Vec<RuleEntry>)>>,
+}
+
+impl AuthCache {
+    pub fn new(store: BrainStore) -> Self {
+        Self {
+            store,
+            keys: Mutex::new(None),
+            grants: Mutex::new(HashMap::new()),
+            rules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn store(&self) -> &BrainStore {
+        &self.store
+    }
+
+    /// Same answer as [`BrainStore::resolve_api_key`].
+    pub fn resolve_api_key(&self, api_key: &str) -> Result<Option<ApiKeyMapping>> {
+        let current = stamp(&self.store.api_keys_path());
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let fresh = keys.as_ref().is_some_and(|(cached, _)| *cached == current);
+        if !fresh {
+            let mappings = self
+                .store
+                .list_api_keys()?
+                .into_iter()
+                .map(|m| (m.key_hash.clone(), m))
+                .collect();
+            *keys = Some((current, mappings));
+        }
+        let hash = format!("{:x}", Sha256::digest(api_key.as_bytes()));
+        let now = Utc::now();
+        Ok(keys
+            .as_ref()
+            .and_then(|(_, mappings)| mappings.get(&hash))
+            .filter(|m| !m.is_expired(now))
+            .cloned())
+    }
+
+    /// Same answer as [`BrainStore::list_attachments`] for a brain id.
+    pub fn attachments(&self, brain_id: &str) -> Result<Vec<AttachmentGrant>> {
+        let current = stamp(&self.store.brain_state_path(brain_id));
+        if let Some((cached, grants)) = self
+            .grants
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(brain_id)
+            && current.is_some()
+            && *cached == current
+        {
+            return Ok(grants.clone());
+        }
+        // Decrypting takes a key derivation, so other brains are not held up meanwhile.
+        let grants = self.store.list_attachments(brain_id)?;
+        self.grants
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(brain_id.to_string(), (current, grants.clone()));
+        Ok(grants)
+    }
+
+    /// Same answer as [`BrainStore::rules`] for a brain id.
+    pub fn rules(&self, brain_id: &str) -> Result<Vec<RuleEntry>> {
+        let current = stamp(&self.store.brain_state_path(brain_id));
+        if let Some((cached, rules)) = self
+            .rules
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(brain_id)
+            && current.is_some()
+            && *cached == current
+        {
+            return Ok(rules.clone());
+        }
+        let rules = self.store.rules(brain_id)?;
+        self.rules
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(brain_id.to_string(), (current, rules.clone()));
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brain_store::{CreateBrainRequest, RuleAction};
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn key_changes_on_disk_are_picked_up() {
+        let temp = tempfile::tempdir().unwrap();
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_AUTH_CACHE", "test-secret-auth-cache");
+        }
+        let store = BrainStore::new(Some(temp.path().to_path_buf())).unwrap();
+        let brain = store
+            .create_brain(CreateBrainRequest {
+                name: "auth-cache".to_string(),
+                tenant_id: "local".to_string(),
+                passphrase_env: Some("TEST_BRAIN_SECRET_AUTH_CACHE".to_string()),
+            })
+            .unwrap();
+        store
+            .map_api_key("ctx_one", "local", &brain.brain_id, "user:a")
+            .unwrap();
+        let cache = AuthCache::new(BrainStore::new(Some(temp.path().to_path_buf())).unwrap());
+
+        let mapping = cache.resolve_api_key("ctx_one").unwrap().unwrap();
+        assert_eq!(mapping.subject, "user:a");
+        assert!(cache.resolve_api_key("ctx_two").unwrap().is_none());
+
+        store
+            .map_api_key("ctx_one", "local", &brain.brain_id, "user:someone")
+            .unwrap();
+        let mapping = cache.resolve_api_key("ctx_one").unwrap().unwrap();
+        assert_eq!(mapping.subject, "user:someone");
+
+        store
+            .expire_api_key("ctx_one", Utc::now() - Duration::seconds(1))
+            .unwrap();
+        assert!(cache.resolve_api_key("ctx_one").unwrap().is_none());
+    }
+
+    #[test]
+    fn rule_changes_on_disk_are_picked_up() {
+        let temp = tempfile::tempdir().unwrap();
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_AUTH_CACHE_RULES", "test-secret-rules");
+        }
+        let store = BrainStore::new(Some(temp.path().to_path_buf())).unwrap();
+        let brain = store
+            .create_brain(CreateBrainRequest {
+                name: "auth-cache-rules".to_string(),
+                tenant_id: "local".to_string(),
+                passphrase_env: Some("TEST_BRAIN_SECRET_AUTH_CACHE_RULES".to_string()),
+            })
+            .unwrap();
+        let cache = AuthCache::new(BrainStore::new(Some(temp.path().to_path_buf())).unwrap());
+        assert!(cache.rules(&brain.brain_id).unwrap().is_empty());
+        assert!(cache.rules(&brain.brain_id).unwrap().is_empty());
+
+        store
+            .add_rule(
+                &brain.brain_id,
+                RuleEntry {
+                    id: "no-export".to_string(),
+                    description: "preferences stay in chat".to_string(),
+                    allowed_sinks: vec!["chat".to_string()],
+                    memory_class: "*".to_string(),
+                    action: RuleAction::Block,
+                },
+            )
+            .unwrap();
+        let rules = cache.rules(&brain.brain_id).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].id, "no-export");
+
+        store.remove_rule(&brain.brain_id, "no-export").unwrap();
+        assert!(cache.rules(&brain.brain_id).unwrap().is_empty());
+    }
+}
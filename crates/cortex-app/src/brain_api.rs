@@ -0,0 +1,434 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use axum::extract::{Path, Query, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use brain_store::{AttachmentGrant, BrainStore, BrainSummary, MemoryObject};
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use tokio::net::TcpListener;
+use tracing::info;
+
+/// Brain ref that stands for the store's active brain in API paths.
+const ACTIVE_BRAIN: &str = "active";
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct BrainApiConfig {
+    pub bind_addr: SocketAddr,
+    pub brain_home: Option<PathBuf>,
+    pub token: String,
+}
+
+struct ApiState {
+    brain_home: Option<PathBuf>,
+    token: String,
+}
+
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({"error": {"code": self.code, "message": self.message}});
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MemoriesQuery {
+    subject: Option<String>,
+    predicate: Option<String>,
+    #[serde(default)]
+    include_forgotten: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgetBody {
+    subject: String,
+    predicate: String,
+    #[serde(default = "default_scope")]
+    scope: String,
+    #[serde(default = "default_reason")]
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DetachQuery {
+    agent: String,
+    model: Option<String>,
+}
+
+fn default_scope() -> String {
+    "SCOPE_GLOBAL".to_string()
+}
+
+fn default_reason() -> String {
+    "forgotten via brain API".to_string()
+}
+
+/// Serves the brain API until Ctrl-C. Only loopback addresses are accepted.
+pub async fn serve_brain_api(config: BrainApiConfig) -> Result<()> {
+    if !config.bind_addr.ip().is_loopback() {
+        bail!(
+            "brain API only listens on localhost; {} is not a loopback address",
+            config.bind_addr
+        );
+    }
+    let listener = TcpListener::bind(config.bind_addr)
+        .await
+        .with_context(|| format!("failed to bind {}", config.bind_addr))?;
+    serve_on_listener(listener, config, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+async fn serve_on_listener(
+    listener: TcpListener,
+    config: BrainApiConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    info!(
+        "cortex brain API listening on http://{}",
+        listener.local_addr()?
+    );
+    let state = Arc::new(ApiState {
+        brain_home: config.brain_home,
+        token: config.token,
+    });
+    axum::serve(listener, routes().with_state(state))
+        .with_graceful_shutdown(shutdown)
+        .await
+        .context("brain API server failed")
+}
+
+fn routes() -> Router<Arc<ApiState>> {
+    Router::new()
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/v1/brains", get(list_brains))
+        .route("/v1/brains/{brain}/memories", get(memories))
+        .route("/v1/brains/{brain}/search", get(search))
+        .route("/v1/brains/{brain}/forget", post(forget))
+        .route("/v1/brains/{brain}/audit", get(audit))
+        .route(
+            "/v1/brains/{brain}/attachments",
+            get(attachments).post(attach).delete(detach),
+        )
+}
+
+/// Checks the bearer token and opens the store.
+fn authorized_store(state: &ApiState, headers: &HeaderMap) -> Result<BrainStore, ApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    match token {
+        Some(token) if token == state.token => {}
+        Some(_) => {
+            return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "auth_failed",
+                "brain API token is not valid",
+            ));
+        }
+        None => {
+            return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "auth_required",
+                "brain API requires a bearer token",
+            ));
+        }
+    }
+    BrainStore::new(state.brain_home.clone()).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "brain_store_init_failed",
+            e.to_string(),
+        )
+    })
+}
+
+fn resolve(store: &BrainStore, brain: &str) -> Result<BrainSummary, ApiError> {
+    let brain = (brain != ACTIVE_BRAIN).then_some(brain);
+    store
+        .resolve_brain_or_active(brain)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, "brain_not_found", e.to_string()))
+}
+
+fn store_error(e: anyhow::Error) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "brain_operation_failed",
+        e.to_string(),
+    )
+}
+
+fn branch_memories(store: &BrainStore, brain_id: &str) -> Result<Vec<MemoryObject>, ApiError> {
+    let branch = store.active_branch_state(brain_id).map_err(store_error)?;
+    Ok(branch.memory_objects.into_values().collect())
+}
+
+async fn list_brains(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brains = store.list_brains().map_err(store_error)?;
+    let active = store.active_brain_id().map_err(store_error)?;
+    Ok(Json(json!({"brains": brains, "active": active})))
+}
+
+async fn memories(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    Query(query): Query<MemoriesQuery>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let memories = branch_memories(&store, &brain.brain_id)?
+        .into_iter()
+        .filter(|m| query.include_forgotten || m.is_live())
+        .filter(|m| query.subject.as_ref().is_none_or(|s| *s == m.subject))
+        .filter(|m| query.predicate.as_ref().is_none_or(|p| *p == m.predicate))
+        .collect::<Vec<_>>();
+    Ok(Json(
+        json!({"brain_id": brain.brain_id, "memories": memories}),
+    ))
+}
+
+/// Case-insensitive substring match over live memories' subject, predicate and value.
+async fn search(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let needle = query.q.trim().to_lowercase();
+    if needle.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "missing_query",
+            "search requires a non-empty q",
+        ));
+    }
+    let matches = branch_memories(&store, &brain.brain_id)?
+        .into_iter()
+        .filter(MemoryObject::is_live)
+        .filter(|m| {
+            let value = match &m.value {
+                JsonValue::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            [m.subject.as_str(), m.predicate.as_str(), value.as_str()]
+                .iter()
+                .any(|field| field.to_lowercase().contains(&needle))
+        })
+        .take(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .collect::<Vec<_>>();
+    Ok(Json(
+        json!({"brain_id": brain.brain_id, "memories": matches}),
+    ))
+}
+
+async fn forget(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    Json(body): Json<ForgetBody>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let suppressed = store
+        .forget_suppress(
+            &brain.brain_id,
+            &body.subject,
+            &body.predicate,
+            &body.scope,
+            &body.reason,
+        )
+        .map_err(store_error)?;
+    Ok(Json(
+        json!({"brain_id": brain.brain_id, "suppressed": suppressed}),
+    ))
+}
+
+async fn audit(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let entries = store.audit_trace(&brain.brain_id).map_err(store_error)?;
+    Ok(Json(json!({"brain_id": brain.brain_id, "audit": entries})))
+}
+
+async fn attachments(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let grants = store
+        .list_attachments(&brain.brain_id)
+        .map_err(store_error)?;
+    Ok(Json(
+        json!({"brain_id": brain.brain_id, "attachments": grants}),
+    ))
+}
+
+async fn attach(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    Json(grant): Json<AttachmentGrant>,
+) -> Result<(StatusCode, Json<JsonValue>), ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    store
+        .attach(&brain.brain_id, grant.clone())
+        .map_err(store_error)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({"brain_id": brain.brain_id, "attachment": grant})),
+    ))
+}
+
+async fn detach(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    Query(query): Query<DetachQuery>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = authorized_store(&state, &headers)?;
+    let brain = resolve(&store, &brain)?;
+    let removed = store
+        .detach(&brain.brain_id, &query.agent, query.model.as_deref())
+        .map_err(store_error)?;
+    Ok(Json(
+        json!({"brain_id": brain.brain_id, "removed": removed}),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use brain_store::CreateBrainRequest;
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_memories_search_and_forget_behind_a_token() {
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_API", "test-secret-api");
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let store = BrainStore::new(Some(temp.path().to_path_buf())).unwrap();
+        let brain = store
+            .create_brain(CreateBrainRequest {
+                name: "api-test".to_string(),
+                tenant_id: "local".to_string(),
+                passphrase_env: Some("TEST_BRAIN_SECRET_API".to_string()),
+            })
+            .unwrap();
+        let memory = |id: &str, predicate: &str, value: &str| MemoryObject {
+            id: id.to_string(),
+            subject: "user:local".to_string(),
+            predicate: predicate.to_string(),
+            value: json!(value),
+            memory_type: "normative.preference".to_string(),
+            suppressed: false,
+            recorded_at: None,
+            superseded_by: None,
+        };
+        store
+            .add_memory_objects(
+                &brain.brain_id,
+                vec![
+                    memory("m1", "prefers_beverage", "Green tea"),
+                    memory("m2", "lives_in", "Lisbon"),
+                ],
+            )
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (stop, stopped) = oneshot::channel::<()>();
+        let config = BrainApiConfig {
+            bind_addr: listener.local_addr().unwrap(),
+            brain_home: Some(temp.path().to_path_buf()),
+            token: "api-token".to_string(),
+        };
+        tokio::spawn(serve_on_listener(listener, config, async {
+            let _ = stopped.await;
+        }));
+        let client = reqwest::Client::new();
+        let get = |path: &str, token: &str| {
+            client
+                .get(format!("{base}{path}"))
+                .bearer_auth(token)
+                .send()
+        };
+
+        let resp = get("/v1/brains", "wrong").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let body: JsonValue = get("/v1/brains/api-test/search?q=TEA", "api-token")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["memories"].as_array().unwrap().len(), 1);
+        assert_eq!(body["memories"][0]["id"], "m1");
+
+        let resp = client
+            .post(format!("{base}/v1/brains/api-test/forget"))
+            .bearer_auth("api-token")
+            .json(&json!({"subject": "user:local", "predicate": "lives_in"}))
+            .send()
+            .await
+            .unwrap();
+        let body: JsonValue = resp.json().await.unwrap();
+        assert_eq!(body["suppressed"], 1);
+
+        let path = format!("/v1/brains/{}/memories", brain.brain_id);
+        let body: JsonValue = get(&path, "api-token").await.unwrap().json().await.unwrap();
+        assert_eq!(body["memories"].as_array().unwrap().len(), 1);
+
+        let _ = stop.send(());
+    }
+}
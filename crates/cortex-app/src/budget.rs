@@ -0,0 +1,101 @@
+use std::sync::{Mutex, PoisonError};
+
+use chrono::{NaiveDate, Utc};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlannerBudget {
+    pub max_tokens_per_request: Option<u64>,
+    pub max_tokens_per_day: Option<u64>,
+}
+
+impl PlannerBudget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens_per_request.is_none() && self.max_tokens_per_day.is_none()
+    }
+}
+
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budget: PlannerBudget,
+    usage: Mutex<(NaiveDate, u64)>,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: PlannerBudget) -> Self {
+        Self {
+            budget,
+            usage: Mutex::new((Utc::now().date_naive(), 0)),
+        }
+    }
+
+    /// Whether a request estimated at `estimated_tokens` fits the budget. When it does, the
+    /// estimate is reserved against today's total at once, so concurrent requests cannot
+    /// together overshoot the daily cap; settle it with [`Self::record`].
+    pub fn allows(&self, estimated_tokens: u64) -> bool {
+        if let Some(cap) = self.budget.max_tokens_per_request
+            && estimated_tokens > cap
+        {
+            return false;
+        }
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        roll_over(&mut usage);
+        let total = usage.1.saturating_add(estimated_tokens);
+        if self
+            .budget
+            .max_tokens_per_day
+            .is_some_and(|cap| total > cap)
+        {
+            return false;
+        }
+        usage.1 = total;
+        true
+    }
+
+    /// Replaces a reservation made by [`Self::allows`] with the tokens actually used.
+    pub fn record(&self, reserved: u64, used: u64) {
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        roll_over(&mut usage);
+        usage.1 = usage.1.saturating_sub(reserved).saturating_add(used);
+    }
+
+    pub fn used_today(&self) -> u64 {
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        roll_over(&mut usage);
+        usage.1
+    }
+}
+
+fn roll_over(usage: &mut (NaiveDate, u64)) {
+    let today = Utc::now().date_naive();
+    if usage.0 != today {
+        *usage = (today, 0);
+    }
+}
+
+/// Rough token estimate (~4 bytes per token) used before the planner reports real usage.
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.len() as u64).div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_estimates_are_reserved_until_recorded() {
+        let tracker = BudgetTracker::new(PlannerBudget {
+            max_tokens_per_request: Some(500),
+            max_tokens_per_day: Some(1000),
+        });
+        assert!(!tracker.allows(600));
+        assert!(tracker.allows(400));
+        assert!(tracker.allows(400));
+        // Both reservations are held, so a third concurrent request does not fit.
+        assert!(!tracker.allows(400));
+        assert_eq!(tracker.used_today(), 800);
+
+        tracker.record(400, 100);
+        assert_eq!(tracker.used_today(), 500);
+        assert!(tracker.allows(400));
+    }
+}
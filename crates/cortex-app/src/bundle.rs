@@ -0,0 +1,199 @@
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value as JsonValue;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Token shapes that are scrubbed even when the exact value is not known up front.
+const SECRET_PATTERNS: [&str; 5] = [
+    r"ctx_[A-Za-z0-9]{8,}",
+    r"sk-[A-Za-z0-9_-]{16,}",
+    r"AKIA[0-9A-Z]{16}",
+    r"(?i)(bearer\s+)[A-Za-z0-9._~+/=-]+",
+    r#"(?i)((?:api[_-]?key|secret|token|password)["']?\s*[:=]\s*["']?)[^\s"',}&]+"#,
+];
+
+/// Masks secrets in text and JSON before it goes into a debug bundle.
+pub struct Scrubber {
+    secrets: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Scrubber {
+    /// `secrets` are exact values (API keys, passphrases) to mask wherever they appear.
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Result<Self> {
+        // Short values would mask unrelated text; longest first so overlapping keys fully vanish.
+        let mut secrets = secrets
+            .into_iter()
+            .filter(|s| s.trim().len() >= 8)
+            .collect::<Vec<_>>();
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        secrets.dedup();
+        let patterns = SECRET_PATTERNS
+            .iter()
+            .map(|p| Regex::new(p))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { secrets, patterns })
+    }
+
+    pub fn scrub(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for secret in &self.secrets {
+            out = out.replace(secret.as_str(), REDACTED);
+        }
+        for pattern in &self.patterns {
+            // Patterns with a capture group keep the label (`Bearer `, `api_key=`) visible.
+            let replacement = if pattern.captures_len() > 1 {
+                format!("${{1}}{REDACTED}")
+            } else {
+                REDACTED.to_string()
+            };
+            out = pattern.replace_all(&out, replacement.as_str()).into_owned();
+        }
+        out
+    }
+
+    /// Blanks string values under secret-looking keys and scrubs every other string.
+    pub fn scrub_json(&self, value: &mut JsonValue) {
+        match value {
+            JsonValue::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if is_secret_key(key) && value.is_string() {
+                        *value = REDACTED.into();
+                    } else {
+                        self.scrub_json(value);
+                    }
+                }
+            }
+            JsonValue::Array(items) => items.iter_mut().for_each(|v| self.scrub_json(v)),
+            JsonValue::String(text) => *text = self.scrub(text),
+            _ => {}
+        }
+    }
+}
+
+/// `*_ref` and `*_env` fields name where a secret lives, not the secret itself.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    !key.ends_with("_ref")
+        && !key.ends_with("_env")
+        && key != "secret_storage"
+        && ["key", "secret", "token", "password", "authorization"]
+            .iter()
+            .any(|word| key.contains(word))
+}
+
+/// Writes an uncompressed (stored) zip archive, which every unzip tool reads.
+#[derive(Default)]
+pub struct ZipWriter {
+    buf: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, data: &[u8]) {
+        let crc = crc32(data);
+        let offset = self.buf.len() as u32;
+        let size = data.len() as u32;
+        // Version 2.0, UTF-8 names, stored, DOS date 1980-01-01.
+        let common = |out: &mut Vec<u8>| {
+            out.extend_from_slice(&20u16.to_le_bytes());
+            out.extend_from_slice(&0x0800u16.to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&0x21u16.to_le_bytes());
+            out.extend_from_slice(&crc.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+        };
+
+        self.buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        common(&mut self.buf);
+        self.buf.extend_from_slice(name.as_bytes());
+        self.buf.extend_from_slice(data);
+
+        self.central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes());
+        common(&mut self.central);
+        // Comment length, disk number, internal and external attributes.
+        self.central.extend_from_slice(&[0; 10]);
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.entries += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let offset = self.buf.len() as u32;
+        let size = self.central.len() as u32;
+        self.buf.append(&mut self.central);
+        self.buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.buf.extend_from_slice(&[0; 4]);
+        self.buf.extend_from_slice(&self.entries.to_le_bytes());
+        self.buf.extend_from_slice(&self.entries.to_le_bytes());
+        self.buf.extend_from_slice(&size.to_le_bytes());
+        self.buf.extend_from_slice(&offset.to_le_bytes());
+        self.buf.extend_from_slice(&0u16.to_le_bytes());
+        self.buf
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn scrubs_secrets_and_writes_a_readable_zip() {
+        let scrubber =
+            Scrubber::new(["hunter2-passphrase".to_string(), "short".to_string()]).unwrap();
+        let line = "auth Bearer abc.def key=ctx_0123456789abcdef pass hunter2-passphrase short";
+        assert_eq!(
+            scrubber.scrub(line),
+            "auth Bearer [REDACTED] key=[REDACTED] pass [REDACTED] short"
+        );
+
+        let mut cfg = json!({
+            "proxy_api_key": "ctx_0123456789abcdef",
+            "brain_secret_env": "CORTEX_BRAIN_SECRET",
+            "providers": {"openai": {"planner_api_key_ref": "provider.openai.api_key"}},
+            "notes": ["uses sk-abcdefghijklmnopqrstuv"]
+        });
+        scrubber.scrub_json(&mut cfg);
+        assert_eq!(cfg["proxy_api_key"], REDACTED);
+        assert_eq!(cfg["brain_secret_env"], "CORTEX_BRAIN_SECRET");
+        assert_eq!(
+            cfg["providers"]["openai"]["planner_api_key_ref"],
+            "provider.openai.api_key"
+        );
+        assert_eq!(cfg["notes"][0], "uses [REDACTED]");
+
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let mut zip = ZipWriter::default();
+        zip.add("version.json", b"{}");
+        zip.add("logs/proxy.log", b"line\n");
+        let bytes = zip.finish();
+        assert!(bytes.starts_with(b"PK\x03\x04"));
+        let eocd = &bytes[bytes.len() - 22..];
+        assert!(eocd.starts_with(b"PK\x05\x06"));
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmvm_proto::PublicManifest;
+
+use crate::proof::ProofBundle;
+use crate::types::SamplingParams;
+
+const MAX_CACHE_ENTRIES: usize = 512;
+
+#[derive(Debug, Clone)]
+pub struct CachedCompletion {
+    pub content: String,
+    pub assertions: Vec<serde_json::Value>,
+    pub verified_blocks: Vec<String>,
+    pub narrative_blocks: Vec<String>,
+    /// Anchor digests per assertion, in `assertions` order.
+    pub citations: Vec<Vec<String>>,
+    pub status: String,
+    pub semantic_root: Option<String>,
+    pub trace_root: Option<String>,
+    pub error_code: Option<String>,
+    pub plan_prompt: String,
+    pub plan_source: String,
+    /// What the planner was sent, after provider overrides.
+    pub sampling: SamplingParams,
+    pub proof: Option<ProofBundle>,
+}
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, CachedCompletion)>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn key(subject: &str, message: &str, state_hash: &str) -> String {
+        format!(
+            "{}\u{1f}{}\u{1f}{}",
+            subject,
+            state_hash,
+            normalize_message(message)
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedCompletion> {
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: CachedCompletion) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let ttl = self.ttl;
+        entries.retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        if entries.len() >= MAX_CACHE_ENTRIES
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(k, _)| k.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+/// Kernel manifests per (endpoint, brain, subject). A manifest only changes when something is
+/// appended to or forgotten from the kernel, so entries live until the proxy sees that happen
+/// for their endpoint (or the TTL passes, to bound drift from writers other than this proxy).
+#[derive(Debug)]
+pub struct ManifestCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), (Instant, PublicManifest)>>,
+}
+
+impl ManifestCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn key(brain_id: Option<&str>, subject: &str) -> String {
+        format!("{}\u{1f}{}", brain_id.unwrap_or_default(), subject)
+    }
+
+    pub fn get(&self, endpoint: &str, key: &str) -> Option<PublicManifest> {
+        let mut entries = self.entries.lock().ok()?;
+        let slot = (endpoint.to_string(), key.to_string());
+        match entries.get(&slot) {
+            Some((stored_at, manifest)) if stored_at.elapsed() < self.ttl => Some(manifest.clone()),
+            Some(_) => {
+                entries.remove(&slot);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, endpoint: &str, key: String, manifest: &PublicManifest) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let ttl = self.ttl;
+        entries.retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        if entries.len() >= MAX_CACHE_ENTRIES
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(k, _)| k.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(
+            (endpoint.to_string(), key),
+            (Instant::now(), manifest.clone()),
+        );
+    }
+
+    /// Drops every manifest fetched from `endpoint`, e.g. after an append or forget there.
+    pub fn invalidate(&self, endpoint: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(e, _), _| e != endpoint);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+fn normalize_message(message: &str) -> String {
+    message
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_are_dropped_when_their_endpoint_changes() {
+        let cache = ManifestCache::new(Duration::from_secs(60));
+        let manifest = PublicManifest {
+            request_id: "req-1".to_string(),
+            ..Default::default()
+        };
+        let key = ManifestCache::key(Some("brain-a"), "user:local");
+        cache.insert("http://a", key.clone(), &manifest);
+        cache.insert("http://b", key.clone(), &manifest);
+        assert_eq!(cache.get("http://a", &key), Some(manifest.clone()));
+        assert!(
+            cache
+                .get("http://a", &ManifestCache::key(None, "user:local"))
+                .is_none()
+        );
+
+        cache.invalidate("http://a");
+        assert!(cache.get("http://a", &key).is_none());
+        assert_eq!(cache.get("http://b", &key), Some(manifest));
+
+        let expired = ManifestCache::new(Duration::ZERO);
+        expired.insert("http://a", key.clone(), &PublicManifest::default());
+        assert!(expired.get("http://a", &key).is_none());
+    }
+}
@@ -1,32 +1,78 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
-use adapter_rmvm::RmvmAdapter;
+use adapter_rmvm::{RmvmAdapter, pipe};
 use anyhow::{Result, bail};
-use brain_store::{AttachmentGrant, BrainStore, CreateBrainRequest, MergeStrategy};
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use planner_guard::deterministic_plan_from_manifest;
+use brain_store::{
+    AttachmentGrant, BrainStore, ConsolidateOptions, CreateBrainRequest, KeyQuota, MergeStrategy,
+    RetentionPolicy, RuleAction, RuleEntry,
+};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use planner_guard::{
+    LintFinding, Severity, deterministic_plan_from_manifest, lint_plan, manifest_to_json,
+    parse_manifest_json, parse_plan_json, validate_plan_against_manifest,
+};
 use reqwest::Client;
 use rmvm_grpc::{
     AppendEventRequest, GetManifestRequest, GrpcKernelService, RmvmExecutorServer,
 };
-use rmvm_proto::{ExecuteRequest, ExecutionStatus, Scope};
+use rmvm_proto::{ExecuteRequest, ExecuteResponse, ExecutionStatus, Scope};
 use tonic::transport::Server;
 use uuid::Uuid;
 
+use crate::audit;
+use crate::brain_api::{BrainApiConfig, serve_brain_api};
+use crate::budget::PlannerBudget;
+use crate::completions;
+use crate::embeddings::{
+    DEFAULT_EMBEDDING_MODEL, DEFAULT_SHORTLIST, EmbeddingConfig, EmbeddingProvider,
+};
+use crate::endpoints::parse_mapping;
+use crate::logging::{self, FileLog};
+use crate::maintain::JOBS;
+use crate::mock_rmvm::{MockFixture, serve_mock};
 use crate::product::{
-    ConnectRequest, ConnectSetRequest, ConnectStatusRequest, LogsRequest, ModeSetRequest,
-    ModeStatusRequest, RestartPolicy, SetupRequest, StatusRequest, StopRequest, UpRequest,
-    brain_current, ensure_saved_brain_secret_env, load_saved_proxy_api_key, open_config,
-    provider_list, provider_set_model, provider_use, run_connect, run_connect_set,
-    run_connect_status, run_logs, run_mode_set, run_mode_status, run_setup, run_status, run_stop,
-    run_uninstall, run_up,
+    ConnectConfigRequest, ConnectRequest, ConnectSetRequest, ConnectStatusRequest,
+    DebugBundleRequest, EnvRequest, LanSettings, LogsRequest, MaintainRequest, ModeSetRequest,
+    ModeStatusRequest, ProfileCreateRequest, ProviderAddRequest, RestartPolicy, SetupRequest,
+    StatusRequest, StopRequest, UpRequest, brain_current, brain_endpoint, brain_endpoints,
+    brain_store, config_get, config_set, doctor_home_check, doctor_port_checks,
+    doctor_secret_checks, ensure_saved_brain_secret_env, flush_managed_kernel, json_output,
+    load_saved_proxy_api_key, named_provider_planner, open_config, planner_routes, profile_create,
+    profile_list, profile_switch, provider_add, provider_list, provider_remove, provider_route,
+    provider_routes, provider_set_model, provider_use, proxy_live_settings, rotate_proxy_key,
+    run_connect, run_connect_config, run_connect_set, run_connect_status, run_debug_bundle,
+    run_env, run_logs, run_maintain, run_mode_set, run_mode_status, run_setup, run_status,
+    run_stop, run_uninstall, run_up, select_home, select_instance, select_output, select_profile,
 };
-use crate::proxy::{PlannerConfig, PlannerMode, ProxyConfig, parse_addr, serve};
+use crate::proof::{ProofBundle, check_ledger, verify};
+use crate::proxy::{
+    AzureSettings, DEFAULT_AZURE_API_VERSION, EnvelopeDetail, PlannerConfig, PlannerHedge,
+    PlannerMode, PlannerOptions, ProxyConfig, SettingsLoader, TlsFiles, WriteBackMode,
+    assertion_fields_json, bedrock_credentials_from_env, error_code_name, parse_addr, serve,
+};
+use crate::redact::{LEDGER_REDACTION_MAP, RedactionConfig, restore};
+use crate::render::RenderTemplate;
+use crate::replay::replay_recorded_plan;
+use crate::report::render_markdown;
+use crate::update::{SelfUpdateRequest, self_update};
+use crate::webhooks::WebhookConfig;
 
 #[derive(Debug, Parser)]
 #[command(name = "cortex", about = "Portable Brain + Proxy UX CLI")]
 pub struct Cli {
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    #[arg(long, global = true)]
+    instance: Option<String>,
+    #[arg(long, global = true)]
+    output: Option<String>,
+    #[arg(long, global = true)]
+    home: Option<PathBuf>,
     #[command(subcommand)]
     command: TopCommand,
 }
@@ -62,11 +108,44 @@ enum TopCommand {
     Uninstall(UninstallCmd),
     Status(StatusCmd),
     Logs(LogsCmd),
+    Maintain(MaintainCmd),
     Provider {
         #[command(subcommand)]
         command: ProviderCommand,
     },
     Open(OpenCmd),
+    Replay(ReplayCmd),
+    VerifyProof(VerifyProofCmd),
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommand,
+    },
+    Env(EnvCmd),
+    Plan {
+        #[command(subcommand)]
+        command: PlanCommand,
+    },
+    Manifest {
+        #[command(subcommand)]
+        command: ManifestCommand,
+    },
+    Exec(ExecCmd),
+    Completions(CompletionsCmd),
+    SelfUpdate(SelfUpdateCmd),
+    #[command(hide = true, name = "__names")]
+    Names(NamesCmd),
     #[command(hide = true)]
     Rmvm {
         #[command(subcommand)]
@@ -85,10 +164,37 @@ enum BrainCommand {
     Branch(BranchCmd),
     Merge(MergeCmd),
     Forget(ForgetCmd),
+    Consolidate(ConsolidateCmd),
     Attach(AttachCmd),
     Detach(DetachCmd),
     Audit(AuditCmd),
     Current(CurrentCmd),
+    Redactions(RedactionsCmd),
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+    Retention {
+        #[command(subcommand)]
+        command: RetentionCommand,
+    },
+    Endpoint(BrainEndpointCmd),
+    Endpoints(ListCmd),
+    Serve(BrainServeCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum RulesCommand {
+    Add(RulesAddCmd),
+    List(RulesListCmd),
+    Remove(RulesRemoveCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum RetentionCommand {
+    Set(RetentionSetCmd),
+    List(RulesListCmd),
+    Remove(RetentionRemoveCmd),
 }
 
 #[derive(Debug, Subcommand)]
@@ -99,6 +205,28 @@ enum ProxyCommand {
 #[derive(Debug, Subcommand)]
 enum AuthCommand {
     MapKey(MapKeyCmd),
+    SetQuota(SetQuotaCmd),
+    RotateProxyKey(RotateProxyKeyCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum ServiceCommand {
+    Install,
+    Uninstall,
+    Status(ServiceStatusCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    List(ProfileListCmd),
+    Create(ProfileCreateCmd),
+    Switch(ProfileSwitchCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    Get(ConfigGetCmd),
+    Set(ConfigSetCmd),
 }
 
 #[derive(Debug, Subcommand)]
@@ -106,6 +234,10 @@ enum ProviderCommand {
     List(ProviderListCmd),
     Use(ProviderUseCmd),
     SetModel(ProviderSetModelCmd),
+    Route(ProviderRouteCmd),
+    Routes(ProviderListCmd),
+    Add(ProviderAddCmd),
+    Remove(ProviderRemoveCmd),
 }
 
 #[derive(Debug, Subcommand)]
@@ -113,6 +245,7 @@ enum ConnectCommand {
     Status(ConnectStatusCmd),
     Enable(ConnectToggleCmd),
     Disable(ConnectToggleCmd),
+    Config(ConnectConfigCmd),
 }
 
 #[derive(Debug, Subcommand)]
@@ -121,9 +254,25 @@ enum ModeCommand {
     Status(ModeStatusCmd),
 }
 
+#[derive(Debug, Subcommand)]
+enum DebugCommand {
+    Bundle(DebugBundleCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum PlanCommand {
+    Lint(PlanLintCmd),
+}
+
+#[derive(Debug, Subcommand)]
+enum ManifestCommand {
+    Get(ManifestGetCmd),
+}
+
 #[derive(Debug, Subcommand)]
 enum RmvmCommand {
     Serve(RmvmServeCmd),
+    Mock(RmvmMockCmd),
 }
 
 #[derive(Debug, Args)]
@@ -148,15 +297,41 @@ struct ListCmd {
     json: bool,
 }
 
+#[derive(Debug, Args)]
+struct BrainEndpointCmd {
+    target: String,
+    endpoint: Option<String>,
+    #[arg(long, conflicts_with = "endpoint")]
+    remove: bool,
+    #[arg(long, default_value = "auto")]
+    restart: String,
+}
+
+#[derive(Debug, Args)]
+struct BrainServeCmd {
+    #[arg(long, default_value = "127.0.0.1:8092")]
+    addr: String,
+    #[arg(long, env = "CORTEX_BRAIN_API_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+}
+
 #[derive(Debug, Args)]
 struct ExportCmd {
     brain: String,
     #[arg(long)]
     out: PathBuf,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Cbrain)]
+    format: ExportFormat,
     #[arg(long)]
     signing_key: Option<String>,
 }
 
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Cbrain,
+    Markdown,
+}
+
 #[derive(Debug, Args)]
 struct ImportCmd {
     #[arg(long = "in")]
@@ -207,6 +382,18 @@ struct ForgetCmd {
     brain: Option<String>,
 }
 
+#[derive(Debug, Args)]
+struct ConsolidateCmd {
+    #[arg(long)]
+    brain: Option<String>,
+    #[arg(long, default_value_t = 5)]
+    episodic_run: usize,
+    #[arg(long)]
+    dry_run: bool,
+    #[arg(long)]
+    json: bool,
+}
+
 #[derive(Debug, Args)]
 struct AttachCmd {
     #[arg(long = "agent")]
@@ -225,6 +412,57 @@ struct AttachCmd {
     brain: Option<String>,
 }
 
+#[derive(Debug, Args)]
+struct RulesAddCmd {
+    #[arg(long, default_value = "*")]
+    class: String,
+    #[arg(long)]
+    sinks: String,
+    #[arg(long, default_value = "redact")]
+    action: String,
+    #[arg(long)]
+    id: Option<String>,
+    #[arg(long, default_value = "")]
+    description: String,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct RulesListCmd {
+    #[arg(long)]
+    json: bool,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct RulesRemoveCmd {
+    id: String,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct RetentionSetCmd {
+    #[arg(long)]
+    class: String,
+    #[arg(long, required_unless_present = "never", conflicts_with = "never")]
+    days: Option<u32>,
+    #[arg(long)]
+    never: bool,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct RetentionRemoveCmd {
+    #[arg(long)]
+    class: String,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
 #[derive(Debug, Args)]
 struct DetachCmd {
     #[arg(long = "agent")]
@@ -273,12 +511,114 @@ struct ServeCmd {
     planner_model: String,
     #[arg(long, env = "CORTEX_PLANNER_API_KEY")]
     planner_api_key: Option<String>,
+    #[arg(long, env = "CORTEX_PLANNER_MODEL_PATH")]
+    planner_model_path: Option<PathBuf>,
     #[arg(long, env = "CORTEX_PLANNER_TIMEOUT_SECS", default_value = "30")]
     planner_timeout_secs: u64,
+    #[arg(long, env = "CORTEX_PLANNER_AZURE_DEPLOYMENT")]
+    planner_azure_deployment: Option<String>,
+    #[arg(
+        long,
+        env = "CORTEX_PLANNER_AZURE_API_VERSION",
+        default_value = DEFAULT_AZURE_API_VERSION
+    )]
+    planner_azure_api_version: String,
+    #[arg(long, env = "CORTEX_PLANNER_TEMPERATURE")]
+    planner_temperature: Option<f64>,
+    #[arg(long, env = "CORTEX_PLANNER_MAX_TOKENS")]
+    planner_max_tokens: Option<u32>,
+    #[arg(long = "planner-header", value_parser = parse_header)]
+    planner_headers: Vec<(String, String)>,
+    #[arg(long, env = "CORTEX_PLANNER_ORGANIZATION")]
+    planner_organization: Option<String>,
+    #[arg(long, env = "CORTEX_PLANNER_PROJECT")]
+    planner_project: Option<String>,
+    #[arg(long, env = "CORTEX_PLANNER_HEDGE_DELAY_MS")]
+    planner_hedge_delay_ms: Option<u64>,
+    #[arg(long, env = "CORTEX_PLANNER_HEDGE_PROVIDER")]
+    planner_hedge_provider: Option<String>,
     #[arg(long, hide = true)]
     provider_name: Option<String>,
     #[arg(long, hide = true)]
     proxy_api_key: Option<String>,
+    #[arg(long)]
+    brain_home: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_REQUIRE_API_KEY")]
+    require_api_key: bool,
+    #[arg(long, env = "CORTEX_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_LOG_FILE")]
+    log_file: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_LOG_FILE_MAX_MB", default_value_t = 10)]
+    log_file_max_mb: u64,
+    #[arg(long, env = "CORTEX_LOG_FILE_KEEP", default_value_t = 5)]
+    log_file_keep: usize,
+    #[arg(long, env = "CORTEX_LOG_FILE_LEVEL", default_value = "info")]
+    log_file_level: String,
+    #[arg(long, env = "CORTEX_REQUEST_LOG_LEVEL", default_value = "info")]
+    request_log_level: String,
+    #[arg(long, env = "CORTEX_NO_COMPRESSION")]
+    no_compression: bool,
+    #[arg(long, env = "CORTEX_COMPRESSION_MIN_BYTES", default_value_t = 1024)]
+    compression_min_bytes: u16,
+    #[arg(long, env = "CORTEX_RESPONSE_CACHE_TTL_SECS", default_value = "0")]
+    response_cache_ttl_secs: u64,
+    #[arg(long, env = "CORTEX_IDEMPOTENCY_TTL_SECS", default_value = "600")]
+    idempotency_ttl_secs: u64,
+    #[arg(long, env = "CORTEX_MANIFEST_CACHE_TTL_SECS", default_value = "300")]
+    manifest_cache_ttl_secs: u64,
+    #[arg(long, env = "CORTEX_EMBEDDING_PROVIDER")]
+    embedding_provider: Option<String>,
+    #[arg(long, env = "CORTEX_EMBEDDING_BASE_URL")]
+    embedding_base_url: Option<String>,
+    #[arg(long, env = "CORTEX_EMBEDDING_MODEL", default_value = DEFAULT_EMBEDDING_MODEL)]
+    embedding_model: String,
+    #[arg(long, env = "CORTEX_EMBEDDING_API_KEY", hide_env_values = true)]
+    embedding_api_key: Option<String>,
+    #[arg(long, env = "CORTEX_EMBEDDING_SHORTLIST", default_value_t = DEFAULT_SHORTLIST)]
+    embedding_shortlist: usize,
+    #[arg(long, env = "CORTEX_PLANNER_MAX_TOKENS_PER_REQUEST")]
+    planner_max_tokens_per_request: Option<u64>,
+    #[arg(long, env = "CORTEX_PLANNER_MAX_TOKENS_PER_DAY")]
+    planner_max_tokens_per_day: Option<u64>,
+    #[arg(
+        long = "webhook-url",
+        env = "CORTEX_WEBHOOK_URLS",
+        value_delimiter = ','
+    )]
+    webhook_urls: Vec<String>,
+    #[arg(long, env = "CORTEX_WEBHOOK_SECRET")]
+    webhook_secret: Option<String>,
+    #[arg(long, env = "CORTEX_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+    #[arg(long, env = "CORTEX_WRITE_BACK", default_value = "off")]
+    write_back: String,
+    #[arg(long, env = "CORTEX_REDACT_PII")]
+    redact_pii: bool,
+    #[arg(long = "redact-pattern", env = "CORTEX_REDACT_PATTERNS")]
+    redact_patterns: Vec<String>,
+    #[arg(long, env = "CORTEX_REDACT_BEFORE_APPEND")]
+    redact_before_append: bool,
+    #[arg(long, env = "CORTEX_ENVELOPE_DETAIL", default_value = "full")]
+    envelope_detail: String,
+    #[arg(long, env = "CORTEX_RENDER_TEMPLATE", default_value = "plain")]
+    render_template: String,
+    #[arg(long, env = "CORTEX_USAGE_FILE")]
+    usage_file: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_MODEL_ROUTES")]
+    model_routes: bool,
+    #[arg(long, env = "CORTEX_NO_HYDRATE")]
+    no_hydrate: bool,
+    #[arg(long, env = "CORTEX_SYNC_INTERVAL_SECS", default_value_t = 60)]
+    sync_interval_secs: u64,
+    #[arg(long, env = "CORTEX_SYNC_STATUS_FILE")]
+    sync_status_file: Option<PathBuf>,
+    #[arg(long = "brain-endpoint", value_parser = parse_mapping)]
+    brain_endpoints: Vec<(String, String)>,
+    #[arg(long)]
+    reload_from_config: bool,
 }
 
 #[derive(Debug, Args)]
@@ -291,6 +631,31 @@ struct MapKeyCmd {
     brain: String,
     #[arg(long, default_value = "user:local")]
     subject: String,
+    /// Bind the key to an agent so its attachment grant always applies.
+    #[arg(long)]
+    agent: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct SetQuotaCmd {
+    #[arg(long = "api-key")]
+    api_key: String,
+    #[arg(long)]
+    requests_per_day: Option<u64>,
+    #[arg(long)]
+    requests_per_month: Option<u64>,
+    #[arg(long)]
+    planner_tokens_per_day: Option<u64>,
+    #[arg(long)]
+    planner_tokens_per_month: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+struct RotateProxyKeyCmd {
+    #[arg(long, default_value_t = 3600)]
+    grace_secs: u64,
+    #[arg(long, default_value = "auto")]
+    restart: String,
 }
 
 #[derive(Debug, Args)]
@@ -319,8 +684,20 @@ struct DoctorCmd {
     planner_model: String,
     #[arg(long, env = "CORTEX_PLANNER_API_KEY")]
     planner_api_key: Option<String>,
+    #[arg(long, env = "CORTEX_PLANNER_MODEL_PATH")]
+    planner_model_path: Option<PathBuf>,
+    #[arg(long, env = "CORTEX_PLANNER_AZURE_DEPLOYMENT")]
+    planner_azure_deployment: Option<String>,
+    #[arg(
+        long,
+        env = "CORTEX_PLANNER_AZURE_API_VERSION",
+        default_value = DEFAULT_AZURE_API_VERSION
+    )]
+    planner_azure_api_version: String,
     #[arg(long, default_value = "10")]
     timeout_secs: u64,
+    #[arg(long)]
+    json: bool,
 }
 
 struct DoctorCheck {
@@ -375,6 +752,14 @@ struct UpCmd {
     provider: Option<String>,
     #[arg(long, default_value_t = true)]
     reuse_external_rmvm: bool,
+    #[arg(long)]
+    watch_config: bool,
+    #[arg(long)]
+    lan: bool,
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -395,6 +780,10 @@ struct UninstallCmd {
     all: bool,
     #[arg(long)]
     yes: bool,
+    #[arg(long, requires = "all")]
+    purge_brains: bool,
+    #[arg(long, requires = "purge_brains")]
+    export_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -405,6 +794,14 @@ struct StatusCmd {
     verbose: bool,
     #[arg(long)]
     copy: bool,
+    #[arg(long)]
+    usage: bool,
+    #[arg(long)]
+    all_instances: bool,
+    #[arg(long, conflicts_with_all = ["json", "copy", "all_instances"])]
+    watch: bool,
+    #[arg(long, default_value_t = 2)]
+    interval_secs: u64,
 }
 
 #[derive(Debug, Args)]
@@ -415,6 +812,24 @@ struct LogsCmd {
     tail: usize,
     #[arg(long)]
     follow: bool,
+    #[arg(long)]
+    json: bool,
+    #[arg(long)]
+    level: Option<String>,
+    #[arg(long)]
+    since: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct MaintainCmd {
+    #[arg(long, conflicts_with_all = ["job", "status"])]
+    daemon: bool,
+    #[arg(long, value_parser = JOBS)]
+    job: Vec<String>,
+    #[arg(long, conflicts_with = "job")]
+    status: bool,
+    #[arg(long)]
+    json: bool,
 }
 
 #[derive(Debug, Args)]
@@ -428,6 +843,13 @@ struct ConnectToggleCmd {
     name: String,
 }
 
+#[derive(Debug, Args)]
+struct ConnectConfigCmd {
+    app: String,
+    #[arg(long)]
+    write: bool,
+}
+
 #[derive(Debug, Args)]
 struct ModeSetCmd {
     mode: String,
@@ -463,12 +885,137 @@ struct ProviderSetModelCmd {
     restart: String,
 }
 
+#[derive(Debug, Args)]
+struct ProviderAddCmd {
+    name: String,
+    #[arg(long)]
+    base_url: Option<String>,
+    #[arg(long)]
+    model: String,
+    #[arg(long, default_value = "openai")]
+    mode: String,
+    #[arg(long)]
+    api_key: Option<String>,
+    #[arg(long)]
+    api_key_env: Option<String>,
+    #[arg(long)]
+    azure_deployment: Option<String>,
+    #[arg(long)]
+    azure_api_version: Option<String>,
+    #[arg(long)]
+    model_path: Option<PathBuf>,
+    #[arg(long)]
+    temperature: Option<f64>,
+    #[arg(long)]
+    max_tokens: Option<u32>,
+    #[arg(long)]
+    timeout_secs: Option<u64>,
+    #[arg(long = "header", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+    #[arg(long)]
+    organization: Option<String>,
+    #[arg(long)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct ProviderRemoveCmd {
+    name: String,
+}
+
+#[derive(Debug, Args)]
+struct ProviderRouteCmd {
+    model: String,
+    provider: Option<String>,
+    #[arg(long, conflicts_with = "provider")]
+    remove: bool,
+    #[arg(long, default_value = "auto")]
+    restart: String,
+}
+
+#[derive(Debug, Args)]
+struct ServiceStatusCmd {
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct ProfileListCmd {
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct ProfileCreateCmd {
+    name: String,
+    #[arg(long)]
+    from: Option<String>,
+    #[arg(long)]
+    proxy_addr: Option<String>,
+    #[arg(long)]
+    rmvm_port: Option<u16>,
+    #[arg(long)]
+    brains_home: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct ProfileSwitchCmd {
+    name: String,
+}
+
+#[derive(Debug, Args)]
+struct CompletionsCmd {
+    shell: Shell,
+}
+
+#[derive(Debug, Args)]
+struct SelfUpdateCmd {
+    #[arg(long)]
+    version: Option<String>,
+    #[arg(long)]
+    check: bool,
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct NamesCmd {
+    kind: String,
+}
+
+#[derive(Debug, Args)]
+struct ConfigGetCmd {
+    key: Option<String>,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct ConfigSetCmd {
+    key: String,
+    value: String,
+    #[arg(long, default_value = "prompt")]
+    restart: String,
+}
+
 #[derive(Debug, Args)]
 struct CurrentCmd {
     #[arg(long)]
     json: bool,
 }
 
+#[derive(Debug, Args)]
+struct RedactionsCmd {
+    #[arg(long)]
+    request_id: Option<String>,
+    #[arg(long)]
+    restore: Option<String>,
+    #[arg(long)]
+    json: bool,
+    #[arg(long)]
+    brain: Option<String>,
+}
+
 #[derive(Debug, Args)]
 struct OpenCmd {
     #[arg(long)]
@@ -477,6 +1024,100 @@ struct OpenCmd {
     url: bool,
 }
 
+#[derive(Debug, Args)]
+struct ReplayCmd {
+    request_id: String,
+    #[arg(long, env = "CORTEX_BRAIN")]
+    brain: Option<String>,
+    #[arg(
+        long,
+        env = "CORTEX_ENDPOINT",
+        default_value = "grpc://127.0.0.1:50051"
+    )]
+    endpoint: String,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct VerifyProofCmd {
+    #[arg(
+        conflicts_with = "semantic_root",
+        required_unless_present = "semantic_root"
+    )]
+    file: Option<PathBuf>,
+    #[arg(long, requires = "assertions")]
+    semantic_root: Option<String>,
+    #[arg(long, requires = "semantic_root")]
+    trace_root: Option<String>,
+    #[arg(long, requires = "semantic_root")]
+    assertions: Option<PathBuf>,
+    #[arg(long, requires = "semantic_root")]
+    manifest_sha256: Option<String>,
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    #[arg(long)]
+    brain: Option<String>,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct EnvCmd {
+    #[arg(long)]
+    shell: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct DebugBundleCmd {
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct PlanLintCmd {
+    #[arg(long)]
+    file: PathBuf,
+    #[arg(long, conflicts_with = "endpoint")]
+    manifest: Option<PathBuf>,
+    #[arg(long)]
+    endpoint: Option<String>,
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct ManifestGetCmd {
+    #[arg(
+        long,
+        env = "CORTEX_ENDPOINT",
+        default_value = "grpc://127.0.0.1:50051"
+    )]
+    endpoint: String,
+    #[arg(long)]
+    request_id: Option<String>,
+    #[arg(long)]
+    subject: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct ExecCmd {
+    #[arg(long)]
+    plan: PathBuf,
+    #[arg(long, default_value = "user:local")]
+    subject: String,
+    #[arg(long)]
+    event: Option<String>,
+    #[arg(
+        long,
+        env = "CORTEX_ENDPOINT",
+        default_value = "grpc://127.0.0.1:50051"
+    )]
+    endpoint: String,
+    #[arg(long)]
+    json: bool,
+}
+
 #[derive(Debug, Args)]
 struct RmvmServeCmd {
     #[arg(long, env = "RMVM_SERVER_ADDR", default_value = "127.0.0.1:50051")]
@@ -487,11 +1128,51 @@ struct RmvmServeCmd {
     max_encoding_bytes: usize,
     #[arg(long, env = "RMVM_REQUEST_TIMEOUT_SECS", default_value_t = 30)]
     request_timeout_secs: u64,
+    /// Also serve `cortex.brain.v1.BrainService`; needs a token and a loopback address.
+    #[arg(long, env = "RMVM_BRAIN_SERVICE", default_value_t = false, action = ArgAction::Set)]
+    brain_service: bool,
+    #[arg(long, env = "RMVM_BRAIN_SERVICE_TOKEN", hide_env_values = true)]
+    brain_service_token: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct RmvmMockCmd {
+    #[arg(long, env = "RMVM_SERVER_ADDR", default_value = "127.0.0.1:50051")]
+    addr: String,
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+}
+
+/// Only `proxy serve` writes a trace file; every other command logs to stdout alone.
+fn file_log(command: &TopCommand) -> Option<FileLog> {
+    let TopCommand::Proxy {
+        command: ProxyCommand::Serve(c),
+    } = command
+    else {
+        return None;
+    };
+    Some(FileLog {
+        path: c.log_file.clone()?,
+        max_bytes: c.log_file_max_mb.max(1) * 1024 * 1024,
+        keep: c.log_file_keep,
+        level: c.log_file_level.clone(),
+        request_level: c.request_log_level.clone(),
+    })
 }
 
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
-    match cli.command {
+    logging::init(file_log(&cli.command))?;
+    let profile_home = if matches!(cli.command, TopCommand::Profile { .. }) {
+        None
+    } else {
+        select_profile(cli.profile.as_deref())?
+    };
+    select_instance(cli.instance.as_deref())?;
+    select_output(cli.output.as_deref())?;
+    select_home(cli.home.as_deref(), profile_home)?;
+    audit::init_from_env()?;
+    let result = match cli.command {
         TopCommand::Brain { command } => handle_brain(command).await,
         TopCommand::Proxy { command } => handle_proxy(command).await,
         TopCommand::Auth { command } => handle_auth(command).await,
@@ -507,15 +1188,61 @@ pub async fn run() -> Result<()> {
         TopCommand::Uninstall(command) => handle_uninstall(command).await,
         TopCommand::Status(command) => handle_status(command).await,
         TopCommand::Logs(command) => handle_logs(command).await,
+        TopCommand::Maintain(command) => {
+            run_maintain(MaintainRequest {
+                daemon: command.daemon,
+                jobs: command.job,
+                status: command.status,
+                json: json_mode(command.json),
+            })
+            .await
+        }
         TopCommand::Provider { command } => handle_provider(command).await,
         TopCommand::Open(command) => handle_open(command).await,
+        TopCommand::Replay(command) => handle_replay(command).await,
+        TopCommand::VerifyProof(command) => handle_verify_proof(command),
+        TopCommand::Service { command } => handle_service(command),
+        TopCommand::Profile { command } => handle_profile(command),
+        TopCommand::Config { command } => handle_config(command).await,
+        TopCommand::Debug {
+            command: DebugCommand::Bundle(command),
+        } => run_debug_bundle(DebugBundleRequest { out: command.out }),
+        TopCommand::Env(command) => run_env(EnvRequest {
+            shell: command.shell,
+        }),
+        TopCommand::Plan {
+            command: PlanCommand::Lint(command),
+        } => handle_plan_lint(command).await,
+        TopCommand::Manifest {
+            command: ManifestCommand::Get(command),
+        } => handle_manifest_get(command).await,
+        TopCommand::Exec(command) => handle_exec(command).await,
+        TopCommand::Completions(command) => {
+            completions::write(command.shell, &mut Cli::command(), &mut std::io::stdout())
+        }
+        TopCommand::Names(command) => completions::print_names(&command.kind),
+        TopCommand::SelfUpdate(command) => {
+            self_update(SelfUpdateRequest {
+                version: command.version,
+                check: command.check,
+                force: command.force,
+            })
+            .await
+        }
         TopCommand::Rmvm { command } => handle_rmvm(command).await,
-    }
+    };
+    audit::flush(Duration::from_secs(5)).await;
+    result
+}
+
+/// A command's own `--json` flag, or the global `--output json` / `CORTEX_OUTPUT=json`.
+fn json_mode(flag: bool) -> bool {
+    flag || json_output()
 }
 
 async fn handle_brain(cmd: BrainCommand) -> Result<()> {
     let _ = ensure_saved_brain_secret_env();
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
     match cmd {
         BrainCommand::Create(c) => {
             let store = if let Some(path) = c.path {
@@ -528,16 +1255,24 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
                 tenant_id: c.tenant,
                 passphrase_env: c.passphrase_env,
             })?;
-            println!("Created brain {} ({})", created.name, created.brain_id);
-            println!("Set active with: cortex brain use {}", created.brain_id);
+            if json_output() {
+                println!("{}", serde_json::to_string_pretty(&created)?);
+            } else {
+                println!("Created brain {} ({})", created.name, created.brain_id);
+                println!("Set active with: cortex brain use {}", created.brain_id);
+            }
         }
         BrainCommand::Use(c) => {
             let s = store.set_active_brain(&c.brain)?;
-            println!("Active brain set: {} ({})", s.name, s.brain_id);
+            if json_output() {
+                println!("{}", serde_json::to_string_pretty(&s)?);
+            } else {
+                println!("Active brain set: {} ({})", s.name, s.brain_id);
+            }
         }
         BrainCommand::List(c) => {
             let list = store.list_brains()?;
-            if c.json {
+            if json_mode(c.json) {
                 println!("{}", serde_json::to_string_pretty(&list)?);
             } else {
                 let active = store.active_brain_id()?;
@@ -554,13 +1289,38 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
                 }
             }
         }
+        BrainCommand::Export(c) if c.format == ExportFormat::Markdown => {
+            let brain = store.resolve_brain(&c.brain)?;
+            let state = store.decrypted_state(&brain.brain_id)?;
+            let report = render_markdown(&brain, &state, &chrono::Utc::now().to_rfc3339());
+            if c.out.as_os_str() == "-" {
+                print!("{report}");
+            } else {
+                std::fs::write(&c.out, report)?;
+                println!(
+                    "Wrote decrypted report for brain {} to {}",
+                    brain.name,
+                    c.out.display()
+                );
+            }
+        }
         BrainCommand::Export(c) => {
             let _ = c.signing_key;
-            store.export_brain(&c.brain, &c.out)?;
-            println!("Exported brain {} to {}", c.brain, c.out.display());
+            // `--out -` streams the package to stdout, so the status line goes to stderr.
+            if c.out.as_os_str() == "-" {
+                store.export_brain_to(&c.brain, std::io::stdout().lock())?;
+                eprintln!("Exported brain {} to stdout", c.brain);
+            } else {
+                store.export_brain(&c.brain, &c.out)?;
+                println!("Exported brain {} to {}", c.brain, c.out.display());
+            }
         }
         BrainCommand::Import(c) => {
-            let res = store.import_brain(&c.input, c.name, c.verify_only)?;
+            let res = if c.input.as_os_str() == "-" {
+                store.import_brain_from(std::io::stdin().lock(), c.name, c.verify_only)?
+            } else {
+                store.import_brain(&c.input, c.name, c.verify_only)?
+            };
             if c.verify_only {
                 println!("Import verification passed: {}", c.input.display());
             } else if let Some(summary) = res {
@@ -601,6 +1361,34 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
                 suppressed, c.subject, c.predicate
             );
         }
+        BrainCommand::Consolidate(c) => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            let report = store.consolidate(
+                &brain.brain_id,
+                ConsolidateOptions {
+                    episodic_run: c.episodic_run,
+                    dry_run: c.dry_run,
+                },
+            )?;
+            if json_mode(c.json) {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!(
+                    "{} brain {}: {} duplicates, {} superseded, {} episodic summaries (live {} -> {})",
+                    if c.dry_run {
+                        "Would consolidate"
+                    } else {
+                        "Consolidated"
+                    },
+                    brain.brain_id,
+                    report.duplicates,
+                    report.superseded,
+                    report.episodic_summaries,
+                    report.live_before,
+                    report.live_after
+                );
+            }
+        }
         BrainCommand::Attach(c) => {
             let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
             store.attach(
@@ -614,7 +1402,137 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
                     expires_at: c.ttl,
                 },
             )?;
-            println!("Attachment saved for brain {}", brain.brain_id);
+            println!("Attachment saved for brain {}", brain.brain_id);
+        }
+        BrainCommand::Rules {
+            command: RulesCommand::Add(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            let action = match c.action.trim().to_ascii_lowercase().as_str() {
+                "redact" => RuleAction::Redact,
+                "block" => RuleAction::Block,
+                other => bail!("invalid rule action '{other}'; expected redact|block"),
+            };
+            let allowed_sinks = split_csv(&c.sinks);
+            if allowed_sinks.is_empty() {
+                bail!("--sinks needs at least one sink (use 'none' to allow no sink)");
+            }
+            let id = c
+                .id
+                .unwrap_or_else(|| format!("rule-{}", &Uuid::new_v4().simple().to_string()[..8]));
+            store.add_rule(
+                &brain.brain_id,
+                RuleEntry {
+                    id: id.clone(),
+                    description: c.description,
+                    allowed_sinks,
+                    memory_class: c.class,
+                    action,
+                },
+            )?;
+            println!("Rule {id} saved for brain {}", brain.brain_id);
+        }
+        BrainCommand::Rules {
+            command: RulesCommand::List(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            let rules = store.rules(&brain.brain_id)?;
+            if json_mode(c.json) {
+                println!("{}", serde_json::to_string_pretty(&rules)?);
+            } else {
+                for rule in rules {
+                    println!(
+                        "{} class={} sinks={} action={:?} {}",
+                        rule.id,
+                        rule.memory_class,
+                        rule.allowed_sinks.join(","),
+                        rule.action,
+                        rule.description
+                    );
+                }
+            }
+        }
+        BrainCommand::Rules {
+            command: RulesCommand::Remove(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            if !store.remove_rule(&brain.brain_id, &c.id)? {
+                bail!("no rule {} in brain {}", c.id, brain.brain_id);
+            }
+            println!("Removed rule {}", c.id);
+        }
+        BrainCommand::Retention {
+            command: RetentionCommand::Set(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            store.set_retention(
+                &brain.brain_id,
+                RetentionPolicy {
+                    memory_class: c.class.clone(),
+                    max_age_days: c.days,
+                },
+            )?;
+            match c.days {
+                Some(days) => println!("Memory class {} expires after {days} days", c.class),
+                None => println!("Memory class {} is kept forever", c.class),
+            }
+        }
+        BrainCommand::Retention {
+            command: RetentionCommand::List(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            let policies = store.retention(&brain.brain_id)?;
+            if json_mode(c.json) {
+                println!("{}", serde_json::to_string_pretty(&policies)?);
+            } else {
+                for policy in policies {
+                    println!(
+                        "{} {}",
+                        policy.memory_class,
+                        policy
+                            .max_age_days
+                            .map_or("never".to_string(), |days| format!("{days}d"))
+                    );
+                }
+            }
+        }
+        BrainCommand::Retention {
+            command: RetentionCommand::Remove(c),
+        } => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            if !store.remove_retention(&brain.brain_id, &c.class)? {
+                bail!(
+                    "no retention policy for {} in brain {}",
+                    c.class,
+                    brain.brain_id
+                );
+            }
+            println!("Removed retention policy for {}", c.class);
+        }
+        BrainCommand::Endpoint(c) => {
+            if c.endpoint.is_none() && !c.remove {
+                bail!("pass an RMVM endpoint, or --remove");
+            }
+            brain_endpoint(&c.target, c.endpoint, parse_restart_policy(&c.restart)?).await?;
+        }
+        BrainCommand::Endpoints(c) => {
+            brain_endpoints(json_mode(c.json))?;
+        }
+        BrainCommand::Serve(c) => {
+            let token = match c.token {
+                Some(token) => token,
+                None => {
+                    let token = format!("cbk_{}", Uuid::new_v4().simple());
+                    eprintln!("Brain API token (set CORTEX_BRAIN_API_TOKEN to reuse): {token}");
+                    token
+                }
+            };
+            serve_brain_api(BrainApiConfig {
+                bind_addr: parse_addr(&c.addr)?,
+                brain_home: Some(store.home_dir().to_path_buf()),
+                token,
+            })
+            .await?;
         }
         BrainCommand::Detach(c) => {
             let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
@@ -630,7 +1548,7 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
             if c.since.is_some() || c.until.is_some() {
                 // v0: filters accepted for UX compatibility; strict timestamp filtering lands in next cut.
             }
-            if c.json {
+            if json_mode(c.json) {
                 println!("{}", serde_json::to_string_pretty(&rows)?);
             } else {
                 for row in rows {
@@ -639,7 +1557,41 @@ async fn handle_brain(cmd: BrainCommand) -> Result<()> {
             }
         }
         BrainCommand::Current(c) => {
-            brain_current(c.json)?;
+            brain_current(json_mode(c.json))?;
+        }
+        BrainCommand::Redactions(c) => {
+            let brain = store.resolve_brain_or_active(c.brain.as_deref())?;
+            let mut rows = store.ledger(&brain.brain_id)?;
+            rows.retain(|e| e.operation == LEDGER_REDACTION_MAP);
+            if let Some(request_id) = c.request_id.as_deref() {
+                rows.retain(|e| e.payload["request_id"].as_str() == Some(request_id));
+            }
+            if let Some(text) = c.restore.as_deref() {
+                let map: BTreeMap<String, String> = rows
+                    .iter()
+                    .filter_map(|e| e.payload["map"].as_object())
+                    .flatten()
+                    .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                    .collect();
+                println!("{}", restore(text, &map));
+            } else if json_mode(c.json) {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                for row in rows {
+                    let request_id = row.payload["request_id"].as_str().unwrap_or("-");
+                    if let Some(map) = row.payload["map"].as_object() {
+                        for (placeholder, original) in map {
+                            println!(
+                                "{} {} {} => {}",
+                                row.ts,
+                                request_id,
+                                placeholder,
+                                original.as_str().unwrap_or_default()
+                            );
+                        }
+                    }
+                }
+            }
         }
     }
     Ok(())
@@ -651,22 +1603,129 @@ async fn handle_proxy(cmd: ProxyCommand) -> Result<()> {
             let _ = RmvmAdapter::new(c.endpoint.clone());
             let bind_addr = parse_addr(&c.addr)?;
             let planner_mode = PlannerMode::parse(&c.planner_mode)?;
+            let write_back = WriteBackMode::parse(&c.write_back)?;
+            let envelope_detail = EnvelopeDetail::parse(&c.envelope_detail)?;
+            let render_template = RenderTemplate::parse(&c.render_template)?;
+            let planner_timeout = Duration::from_secs(c.planner_timeout_secs);
+            let embeddings = match c.embedding_provider.as_deref() {
+                Some(provider) => Some(EmbeddingConfig {
+                    provider: EmbeddingProvider::parse(provider)?,
+                    base_url: c.embedding_base_url,
+                    model: c.embedding_model,
+                    api_key: c
+                        .embedding_api_key
+                        .or_else(|| std::env::var("OPENAI_API_KEY").ok()),
+                    azure_api_version: c.planner_azure_api_version.clone(),
+                    timeout: planner_timeout,
+                    shortlist: c.embedding_shortlist,
+                }),
+                None => None,
+            };
+            let planner_hedge = match c.planner_hedge_delay_ms {
+                Some(delay_ms) => {
+                    let planner = c
+                        .planner_hedge_provider
+                        .as_deref()
+                        .map(|name| named_provider_planner(name, planner_timeout))
+                        .transpose()?;
+                    if let Some(hedge) = planner.as_ref()
+                        && !matches!(
+                            hedge.mode,
+                            PlannerMode::OpenAi | PlannerMode::AzureOpenAi | PlannerMode::Bedrock
+                        )
+                    {
+                        bail!(
+                            "--planner-hedge-provider must use an openai, azure-openai or bedrock planner"
+                        );
+                    }
+                    Some(PlannerHedge {
+                        delay: Duration::from_millis(delay_ms),
+                        planner,
+                    })
+                }
+                None if c.planner_hedge_provider.is_some() => {
+                    bail!("--planner-hedge-provider requires --planner-hedge-delay-ms")
+                }
+                None => None,
+            };
+            let planner_routes = if c.model_routes {
+                planner_routes(planner_timeout)?
+            } else {
+                BTreeMap::new()
+            };
+            let tls = match (c.tls_cert, c.tls_key) {
+                (Some(cert), Some(key)) => Some(TlsFiles { cert, key }),
+                (None, None) => None,
+                _ => bail!("--tls-cert and --tls-key must be given together"),
+            };
             serve(ProxyConfig {
                 bind_addr,
                 endpoint: c.endpoint,
                 default_brain: c.brain,
-                brain_home: None,
+                brain_home: c.brain_home,
                 planner: PlannerConfig {
                     mode: planner_mode,
                     base_url: c.planner_base_url,
+                    azure: Some(AzureSettings {
+                        deployment: c
+                            .planner_azure_deployment
+                            .unwrap_or_else(|| c.planner_model.clone()),
+                        api_version: c.planner_azure_api_version,
+                    }),
                     model: c.planner_model,
-                    api_key: c
-                        .planner_api_key
-                        .or_else(|| std::env::var("OPENAI_API_KEY").ok()),
-                    timeout: Duration::from_secs(c.planner_timeout_secs),
+                    api_key: c.planner_api_key.or_else(|| {
+                        if planner_mode == PlannerMode::Bedrock {
+                            bedrock_credentials_from_env()
+                        } else {
+                            std::env::var("OPENAI_API_KEY").ok()
+                        }
+                    }),
+                    timeout: planner_timeout,
+                    local_model_path: c.planner_model_path,
+                    options: PlannerOptions {
+                        temperature: c.planner_temperature,
+                        max_tokens: c.planner_max_tokens,
+                        headers: c.planner_headers.into_iter().collect(),
+                        organization: c.planner_organization,
+                        project: c.planner_project,
+                    },
                 },
+                planner_routes,
+                planner_hedge,
                 provider_name: c.provider_name,
                 proxy_api_key: c.proxy_api_key,
+                require_api_key: c.require_api_key,
+                tls,
+                compression_min_bytes: (!c.no_compression).then_some(c.compression_min_bytes),
+                response_cache_ttl: Some(Duration::from_secs(c.response_cache_ttl_secs)),
+                idempotency_ttl: Some(Duration::from_secs(c.idempotency_ttl_secs)),
+                manifest_cache_ttl: Some(Duration::from_secs(c.manifest_cache_ttl_secs)),
+                embeddings,
+                planner_budget: PlannerBudget {
+                    max_tokens_per_request: c.planner_max_tokens_per_request,
+                    max_tokens_per_day: c.planner_max_tokens_per_day,
+                },
+                webhooks: WebhookConfig {
+                    urls: c.webhook_urls,
+                    secret: c.webhook_secret,
+                },
+                admin_token: c.admin_token,
+                write_back,
+                redaction: RedactionConfig {
+                    pii: c.redact_pii,
+                    patterns: c.redact_patterns,
+                    before_append: c.redact_before_append,
+                },
+                envelope_detail,
+                render_template,
+                usage_file: c.usage_file,
+                hydrate: !c.no_hydrate,
+                sync_interval: Some(Duration::from_secs(c.sync_interval_secs)),
+                sync_status_file: c.sync_status_file,
+                brain_endpoints: c.brain_endpoints.into_iter().collect(),
+                reload: c.reload_from_config.then(|| {
+                    SettingsLoader(Arc::new(move || proxy_live_settings(planner_timeout)))
+                }),
             })
             .await
         }
@@ -674,7 +1733,7 @@ async fn handle_proxy(cmd: ProxyCommand) -> Result<()> {
 }
 
 async fn handle_auth(cmd: AuthCommand) -> Result<()> {
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
     match cmd {
         AuthCommand::MapKey(c) => {
             let brain = store.resolve_brain(&c.brain)?;
@@ -686,7 +1745,50 @@ async fn handle_auth(cmd: AuthCommand) -> Result<()> {
                 );
             }
             store.map_api_key(&c.api_key, &c.tenant, &brain.brain_id, &c.subject)?;
-            println!("Mapped API key to brain {}", brain.brain_id);
+            if let Some(agent) = c.agent.as_deref() {
+                store.set_api_key_agent(&c.api_key, Some(agent))?;
+            }
+            if json_output() {
+                let mapped = serde_json::json!({
+                    "brain_id": brain.brain_id,
+                    "tenant_id": c.tenant,
+                    "subject": c.subject,
+                    "agent_id": c.agent,
+                });
+                println!("{}", serde_json::to_string_pretty(&mapped)?);
+            } else {
+                println!("Mapped API key to brain {}", brain.brain_id);
+            }
+        }
+        AuthCommand::SetQuota(c) => {
+            let mapping = store.set_api_key_quota(
+                &c.api_key,
+                KeyQuota {
+                    requests_per_day: c.requests_per_day,
+                    requests_per_month: c.requests_per_month,
+                    planner_tokens_per_day: c.planner_tokens_per_day,
+                    planner_tokens_per_month: c.planner_tokens_per_month,
+                },
+            )?;
+            if json_output() {
+                let quota = serde_json::json!({
+                    "brain_id": mapping.brain_id,
+                    "unlimited": mapping.quota.is_unlimited(),
+                    "quota": mapping.quota,
+                });
+                println!("{}", serde_json::to_string_pretty(&quota)?);
+            } else if mapping.quota.is_unlimited() {
+                println!("Cleared quota for API key on brain {}", mapping.brain_id);
+            } else {
+                println!("{}", serde_json::to_string_pretty(&mapping.quota)?);
+            }
+        }
+        AuthCommand::RotateProxyKey(c) => {
+            rotate_proxy_key(
+                Duration::from_secs(c.grace_secs),
+                parse_restart_policy(&c.restart)?,
+            )
+            .await?;
         }
     }
     Ok(())
@@ -708,6 +1810,19 @@ async fn handle_setup(cmd: SetupCmd) -> Result<()> {
         rmvm_port: cmd.rmvm_port,
         force: cmd.force,
     })?;
+    if json_output() {
+        let view = serde_json::json!({
+            "active_brain": out.brain_id,
+            "active_provider": out.provider,
+            "planner_model": out.model,
+            "proxy_addr": out.proxy_addr,
+            "base_url": format!("http://{}/v1", out.proxy_addr),
+            "rmvm_mode": out.rmvm_mode,
+            "rmvm_endpoint": out.rmvm_endpoint,
+        });
+        println!("{}", serde_json::to_string_pretty(&view)?);
+        return Ok(());
+    }
     println!("Setup complete:");
     println!("  brain={}", out.brain_id);
     println!("  provider={} model={}", out.provider, out.model);
@@ -720,7 +1835,9 @@ async fn handle_setup(cmd: SetupCmd) -> Result<()> {
 async fn handle_connect(command: Option<ConnectCommand>, non_interactive: bool) -> Result<()> {
     match command {
         None => run_connect(ConnectRequest { non_interactive }),
-        Some(ConnectCommand::Status(c)) => run_connect_status(ConnectStatusRequest { json: c.json }),
+        Some(ConnectCommand::Status(c)) => run_connect_status(ConnectStatusRequest {
+            json: json_mode(c.json),
+        }),
         Some(ConnectCommand::Enable(c)) => run_connect_set(ConnectSetRequest {
             name: c.name,
             enabled: true,
@@ -729,17 +1846,28 @@ async fn handle_connect(command: Option<ConnectCommand>, non_interactive: bool)
             name: c.name,
             enabled: false,
         }),
+        Some(ConnectCommand::Config(c)) => run_connect_config(ConnectConfigRequest {
+            app: c.app,
+            write: c.write,
+        }),
     }
 }
 
 async fn handle_mode(command: ModeCommand) -> Result<()> {
     match command {
         ModeCommand::Set(c) => run_mode_set(ModeSetRequest { mode: c.mode }),
-        ModeCommand::Status(c) => run_mode_status(ModeStatusRequest { json: c.json }),
+        ModeCommand::Status(c) => run_mode_status(ModeStatusRequest {
+            json: json_mode(c.json),
+        }),
     }
 }
 
 async fn handle_up(cmd: UpCmd) -> Result<()> {
+    let lan = match (cmd.lan, cmd.tls_cert, cmd.tls_key) {
+        (true, tls_cert, tls_key) => Some(LanSettings { tls_cert, tls_key }),
+        (false, None, None) => None,
+        (false, _, _) => bail!("--tls-cert and --tls-key require --lan"),
+    };
     run_up(UpRequest {
         detached: parse_bool_flag("detached", &cmd.detached)?,
         proxy_addr: cmd.proxy_addr,
@@ -748,11 +1876,23 @@ async fn handle_up(cmd: UpCmd) -> Result<()> {
         brain: cmd.brain,
         provider: cmd.provider,
         reuse_external_rmvm: cmd.reuse_external_rmvm,
+        watch_config: cmd.watch_config,
+        lan,
     })
     .await
 }
 
 async fn handle_stop(cmd: StopCmd) -> Result<()> {
+    if cmd.all || !cmd.proxy_only {
+        match flush_managed_kernel().await {
+            Ok(Some(report)) if report.added > 0 && !json_output() => eprintln!(
+                "Saved {} new memories into brain {}",
+                report.added, report.brain_id
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: could not save kernel memory into the brain: {e}"),
+        }
+    }
     run_stop(StopRequest {
         all: cmd.all,
         proxy_only: cmd.proxy_only,
@@ -765,14 +1905,22 @@ async fn handle_uninstall(cmd: UninstallCmd) -> Result<()> {
     run_uninstall(crate::product::UninstallRequest {
         all: cmd.all,
         yes: cmd.yes,
+        purge_brains: cmd.purge_brains,
+        export_dir: cmd.export_dir,
     })
+    .await
 }
 
 async fn handle_status(cmd: StatusCmd) -> Result<()> {
     run_status(StatusRequest {
-        json: cmd.json,
+        json: json_mode(cmd.json),
         verbose: cmd.verbose,
         copy: cmd.copy,
+        usage: cmd.usage,
+        all_instances: cmd.all_instances,
+        watch: cmd
+            .watch
+            .then(|| Duration::from_secs(cmd.interval_secs.max(1))),
     })
     .await
 }
@@ -782,19 +1930,47 @@ async fn handle_logs(cmd: LogsCmd) -> Result<()> {
         service: cmd.service,
         tail: cmd.tail,
         follow: cmd.follow,
+        json: json_mode(cmd.json),
+        level: cmd.level,
+        since: cmd.since,
     })
     .await
 }
 
 async fn handle_provider(cmd: ProviderCommand) -> Result<()> {
     match cmd {
-        ProviderCommand::List(c) => provider_list(c.json).await,
+        ProviderCommand::List(c) => provider_list(json_mode(c.json)).await,
         ProviderCommand::Use(c) => {
             provider_use(&c.name, c.model, parse_restart_policy(&c.restart)?).await
         }
         ProviderCommand::SetModel(c) => {
             provider_set_model(c.provider, c.model, parse_restart_policy(&c.restart)?).await
         }
+        ProviderCommand::Route(c) => {
+            if c.provider.is_none() && !c.remove {
+                bail!("pass a provider to route to, or --remove");
+            }
+            provider_route(&c.model, c.provider, parse_restart_policy(&c.restart)?).await
+        }
+        ProviderCommand::Routes(c) => provider_routes(json_mode(c.json)),
+        ProviderCommand::Add(c) => provider_add(ProviderAddRequest {
+            name: c.name,
+            base_url: c.base_url,
+            model: c.model,
+            mode: c.mode,
+            api_key: c.api_key,
+            api_key_env: c.api_key_env,
+            azure_deployment: c.azure_deployment,
+            azure_api_version: c.azure_api_version,
+            model_path: c.model_path,
+            temperature: c.temperature,
+            max_tokens: c.max_tokens,
+            timeout_secs: c.timeout_secs,
+            extra_headers: c.headers.into_iter().collect(),
+            organization: c.organization,
+            project: c.project,
+        }),
+        ProviderCommand::Remove(c) => provider_remove(&c.name),
     }
 }
 
@@ -802,36 +1978,379 @@ async fn handle_open(cmd: OpenCmd) -> Result<()> {
     open_config(cmd.print_only, cmd.url).await
 }
 
+fn handle_service(cmd: ServiceCommand) -> Result<()> {
+    match cmd {
+        ServiceCommand::Install => crate::service::install(),
+        ServiceCommand::Uninstall => crate::service::uninstall(),
+        ServiceCommand::Status(c) => crate::service::status(json_mode(c.json)),
+    }
+}
+
+fn handle_profile(cmd: ProfileCommand) -> Result<()> {
+    match cmd {
+        ProfileCommand::List(c) => profile_list(json_mode(c.json)),
+        ProfileCommand::Create(c) => profile_create(ProfileCreateRequest {
+            name: c.name,
+            from: c.from,
+            proxy_addr: c.proxy_addr,
+            rmvm_port: c.rmvm_port,
+            brains_home: c.brains_home,
+        }),
+        ProfileCommand::Switch(c) => profile_switch(&c.name),
+    }
+}
+
+async fn handle_config(cmd: ConfigCommand) -> Result<()> {
+    match cmd {
+        ConfigCommand::Get(c) => config_get(c.key.as_deref(), json_mode(c.json)),
+        ConfigCommand::Set(c) => {
+            config_set(&c.key, &c.value, parse_restart_policy(&c.restart)?).await
+        }
+    }
+}
+
+async fn handle_replay(cmd: ReplayCmd) -> Result<()> {
+    let _ = ensure_saved_brain_secret_env();
+    let store = brain_store()?;
+    let brain = store.resolve_brain_or_active(cmd.brain.as_deref())?;
+    let Some(report) =
+        replay_recorded_plan(&cmd.endpoint, &store, &brain.brain_id, &cmd.request_id).await?
+    else {
+        bail!(
+            "no recorded execution proof for {} in brain {}",
+            cmd.request_id,
+            brain.brain_id
+        );
+    };
+    if json_mode(cmd.json) {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "replay {}: status={} {}",
+            report.request_id,
+            report.status,
+            if report.matches { "MATCH" } else { "DRIFT" }
+        );
+        println!(
+            "  semantic_root recorded={} replayed={}",
+            report.recorded_semantic_root.as_deref().unwrap_or("-"),
+            report.replayed_semantic_root.as_deref().unwrap_or("-")
+        );
+        println!(
+            "  trace_root    recorded={} replayed={}",
+            report.recorded_trace_root.as_deref().unwrap_or("-"),
+            report.replayed_trace_root.as_deref().unwrap_or("-")
+        );
+        if let Some(error) = report.error.as_deref() {
+            println!("  error: {error}");
+        }
+    }
+    if !report.matches {
+        bail!("replayed semantic root does not match the recorded proof");
+    }
+    Ok(())
+}
+
+fn handle_verify_proof(cmd: VerifyProofCmd) -> Result<()> {
+    let read = |path: &PathBuf| {
+        std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))
+    };
+    let bundle = match cmd.file.as_ref() {
+        Some(path) => ProofBundle::from_json(&serde_json::from_str(&read(path)?)?)?,
+        None => ProofBundle {
+            semantic_root: cmd.semantic_root.clone().unwrap_or_default(),
+            trace_root: cmd.trace_root.clone().unwrap_or_default(),
+            manifest_sha256: cmd.manifest_sha256.clone(),
+            assertions: match cmd.assertions.as_ref() {
+                Some(path) => serde_json::from_str(&read(path)?)?,
+                None => Vec::new(),
+            },
+        },
+    };
+    let manifest = match cmd.manifest.as_ref() {
+        Some(path) => Some(parse_manifest_json(&read(path)?)?),
+        None => None,
+    };
+    let mut report = verify(&bundle, manifest.as_ref());
+    if let Some(brain) = cmd.brain.as_deref() {
+        let _ = ensure_saved_brain_secret_env();
+        let store = brain_store()?;
+        let brain = store.resolve_brain(brain)?;
+        check_ledger(&mut report, &store, &brain.brain_id)?;
+    }
+    if json_mode(cmd.json) {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let outcome = if report.verified {
+            "VERIFIED"
+        } else {
+            "FAILED"
+        };
+        println!(
+            "proof {outcome}: {}/{} assertions included",
+            report.included, report.assertions
+        );
+        println!("  semantic_root {}", report.semantic_root);
+        println!("  leaf encoding {}", report.leaf_encoding);
+        if !report.trace_root.is_empty() {
+            println!("  trace_root    {}", report.trace_root);
+        }
+        let linked = match report.manifest_linked {
+            Some(true) => "linked",
+            Some(false) => "not linked",
+            None => "not checked",
+        };
+        println!("  manifest      {linked}");
+        if cmd.brain.is_some() {
+            let recorded = report.ledger_request_id.as_deref();
+            println!("  ledger        {}", recorded.unwrap_or("not recorded"));
+        }
+        for failure in &report.failures {
+            println!("  failure: {failure}");
+        }
+    }
+    if !report.verified {
+        bail!("proof verification failed");
+    }
+    Ok(())
+}
+
+async fn handle_plan_lint(cmd: PlanLintCmd) -> Result<()> {
+    let raw = std::fs::read_to_string(&cmd.file)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", cmd.file.display()))?;
+    let manifest = if let Some(path) = cmd.manifest.as_ref() {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+        Some(parse_manifest_json(&raw)?)
+    } else if let Some(endpoint) = cmd.endpoint.as_ref() {
+        let adapter = RmvmAdapter::new(endpoint.clone());
+        adapter
+            .get_manifest(GetManifestRequest {
+                request_id: format!("lint-{}", Uuid::new_v4().simple()),
+            })
+            .await?
+            .manifest
+    } else {
+        None
+    };
+    let findings = match parse_plan_json(&raw, "lint") {
+        Ok(plan) => lint_plan(&plan, manifest.as_ref()),
+        Err(e) => vec![LintFinding {
+            severity: Severity::Error,
+            code: "parse_failed",
+            step: None,
+            message: e.to_string(),
+        }],
+    };
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    if json_mode(cmd.json) {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "ok": errors == 0,
+                "manifest_checked": manifest.is_some(),
+                "findings": findings,
+            }))?
+        );
+    } else {
+        for f in &findings {
+            let step = f.step.map(|i| format!("step {i}: ")).unwrap_or_default();
+            let severity = match f.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            println!("{severity}[{}] {step}{}", f.code, f.message);
+        }
+        if manifest.is_none() {
+            println!(
+                "note: no --manifest or --endpoint given; handle and selector refs were not checked"
+            );
+        }
+        println!(
+            "{}: {errors} error(s), {} warning(s)",
+            cmd.file.display(),
+            findings.len() - errors
+        );
+    }
+    if errors > 0 {
+        bail!("plan has {errors} error(s)");
+    }
+    Ok(())
+}
+
+async fn handle_manifest_get(cmd: ManifestGetCmd) -> Result<()> {
+    let adapter = RmvmAdapter::new(cmd.endpoint.clone());
+    let mut manifest = adapter
+        .get_manifest(GetManifestRequest {
+            request_id: cmd
+                .request_id
+                .unwrap_or_else(|| format!("manifest-{}", Uuid::new_v4().simple())),
+        })
+        .await?
+        .manifest
+        .ok_or_else(|| anyhow::anyhow!("{} returned no manifest", cmd.endpoint))?;
+    if let Some(subject) = cmd.subject.as_deref() {
+        manifest
+            .handles
+            .retain(|h| h.meta.as_ref().is_some_and(|m| m.subject == subject));
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&manifest_to_json(&manifest))?
+    );
+    Ok(())
+}
+
+async fn handle_exec(cmd: ExecCmd) -> Result<()> {
+    let raw = std::fs::read_to_string(&cmd.plan)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", cmd.plan.display()))?;
+    let (request_id, execute) =
+        execute_plan_json(&cmd.endpoint, &raw, &cmd.subject, cmd.event).await?;
+    let status = ExecutionStatus::try_from(execute.status).unwrap_or(ExecutionStatus::Unspecified);
+    let assertions = execute
+        .assertions
+        .iter()
+        .map(assertion_fields_json)
+        .collect::<Vec<_>>();
+    let semantic_root = execute.proof.as_ref().map(|p| p.semantic_root.clone());
+    let trace_root = execute.proof.as_ref().map(|p| p.trace_root.clone());
+    let error = execute
+        .error
+        .as_ref()
+        .map(|e| format!("{}: {}", error_code_name(e), e.message));
+    if json_mode(cmd.json) {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "request_id": request_id,
+                "status": status.as_str_name(),
+                "assertions": assertions,
+                "verified_blocks": execute
+                    .rendered
+                    .as_ref()
+                    .map(|r| r.verified_blocks.clone())
+                    .unwrap_or_default(),
+                "semantic_root": semantic_root,
+                "trace_root": trace_root,
+                "error": error,
+            }))?
+        );
+    } else {
+        println!("exec {request_id}: status={}", status.as_str_name());
+        for assertion in &assertions {
+            println!("  assertion {assertion}");
+        }
+        println!(
+            "  semantic_root={}",
+            semantic_root.as_deref().unwrap_or("-")
+        );
+        println!("  trace_root={}", trace_root.as_deref().unwrap_or("-"));
+        if let Some(error) = error.as_deref() {
+            println!("  error: {error}");
+        }
+    }
+    if status != ExecutionStatus::Ok {
+        bail!("execution finished with status {}", status.as_str_name());
+    }
+    Ok(())
+}
+
+/// Appends `event` if given, then executes `raw_plan` against the kernel's manifest once it
+/// passes the same validation the proxy applies to planner output.
+async fn execute_plan_json(
+    endpoint: &str,
+    raw_plan: &str,
+    subject: &str,
+    event: Option<String>,
+) -> Result<(String, ExecuteResponse)> {
+    let plan = parse_plan_json(raw_plan, &format!("exec-{}", Uuid::new_v4().simple()))?;
+    let adapter = RmvmAdapter::new(endpoint.to_string());
+    if let Some(text) = event {
+        adapter
+            .append_event(AppendEventRequest {
+                request_id: plan.request_id.clone(),
+                subject: subject.to_string(),
+                text,
+                scope: Scope::Global as i32,
+            })
+            .await?;
+    }
+    let manifest = adapter
+        .get_manifest(GetManifestRequest {
+            request_id: plan.request_id.clone(),
+        })
+        .await?
+        .manifest
+        .ok_or_else(|| anyhow::anyhow!("{endpoint} returned no manifest"))?;
+    validate_plan_against_manifest(&plan, &manifest)?;
+
+    let request_id = plan.request_id.clone();
+    let execute = adapter
+        .execute(ExecuteRequest {
+            manifest: Some(manifest),
+            plan: Some(plan),
+        })
+        .await?;
+    Ok((request_id, execute))
+}
+
 async fn handle_rmvm(cmd: RmvmCommand) -> Result<()> {
     match cmd {
         RmvmCommand::Serve(c) => {
-            let addr = c
-                .addr
-                .parse()
-                .map_err(|e| anyhow::anyhow!("invalid RMVM address '{}': {e}", c.addr))?;
             let service = GrpcKernelService::default();
             let service = RmvmExecutorServer::new(service)
                 .max_decoding_message_size(c.max_decoding_bytes)
                 .max_encoding_message_size(c.max_encoding_bytes);
-            println!(
-                "RMVM gRPC server listening on {} (decode={} encode={} timeout={}s)",
-                addr, c.max_decoding_bytes, c.max_encoding_bytes, c.request_timeout_secs
+            let pipe_name = pipe::pipe_name(&c.addr);
+            let addr = pipe_name
+                .is_none()
+                .then(|| c.addr.parse::<SocketAddr>())
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("invalid RMVM address '{}': {e}", c.addr))?;
+            let brains = c
+                .brain_service
+                .then(|| brain_grpc::local_server(addr, c.brain_service_token.as_deref()))
+                .transpose()?;
+            tracing::info!(
+                "RMVM gRPC server listening on {} (decode={} encode={} timeout={}s brain_service={})",
+                c.addr,
+                c.max_decoding_bytes,
+                c.max_encoding_bytes,
+                c.request_timeout_secs,
+                c.brain_service
             );
-            Server::builder()
+            let router = Server::builder()
                 .timeout(Duration::from_secs(c.request_timeout_secs))
                 .add_service(service)
-                .serve(addr)
-                .await?;
+                .add_optional_service(brains);
+            match (addr, pipe_name) {
+                (Some(addr), _) => router.serve(addr).await?,
+                (None, name) => pipe::serve(router, name.unwrap_or_default()).await?,
+            }
             Ok(())
         }
+        RmvmCommand::Mock(c) => {
+            let fixture = match &c.fixture {
+                Some(path) => MockFixture::load(path)?,
+                None => MockFixture::default(),
+            };
+            serve_mock(parse_addr(&c.addr)?, fixture).await
+        }
     }
 }
 
 async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
+    // Before the secret env is exported, which would generate a missing brain secret.
+    let secret_checks = doctor_secret_checks(cmd.brain.as_deref());
     let _ = ensure_saved_brain_secret_env();
+    let json = json_mode(cmd.json);
     let timeout = Duration::from_secs(cmd.timeout_secs);
     let http = Client::builder().timeout(timeout).build()?;
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
 
     let planner_mode = PlannerMode::parse(&cmd.planner_mode)?;
     let proxy_base_url = cmd.proxy_base_url.trim_end_matches('/').to_string();
@@ -846,9 +2365,10 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
         .or_else(|| std::env::var("OPENAI_API_KEY").ok())
         .or_else(|| load_saved_proxy_api_key().ok().flatten());
 
-    let mut failures = 0usize;
+    let mut checks = Vec::new();
     let mut subject_for_dry_run = "user:local".to_string();
     let mut active_brain_id: Option<String> = None;
+    record_doctor_results(&mut checks, vec![doctor_home_check(store.home_dir())], json);
 
     let brain_check = match store.resolve_brain_or_active(cmd.brain.as_deref()) {
         Ok(brain) => {
@@ -865,7 +2385,8 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
             details: format!("could not resolve active brain: {e}"),
         },
     };
-    failures += print_doctor_check(brain_check);
+    record_doctor_check(&mut checks, brain_check, json);
+    record_doctor_results(&mut checks, secret_checks, json);
 
     let api_key_check = match resolved_proxy_api_key.as_deref() {
         Some(api_key) => match store.resolve_api_key(api_key) {
@@ -920,7 +2441,7 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
             details: "missing API key; set OPENAI_API_KEY or pass --api-key".to_string(),
         },
     };
-    failures += print_doctor_check(api_key_check);
+    record_doctor_check(&mut checks, api_key_check, json);
 
     let planner_check = match planner_mode {
         PlannerMode::Fallback => DoctorCheck {
@@ -933,18 +2454,61 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
             ok: true,
             details: "planner mode is byo; per-request X-Cortex-Plan header expected".to_string(),
         },
-        PlannerMode::OpenAi => {
-            let planner_url = format!(
-                "{}/chat/completions",
-                cmd.planner_base_url.trim_end_matches('/')
-            );
+        PlannerMode::Local => {
+            let model = cmd.planner_model_path.as_ref().filter(|p| p.is_file());
+            let built = cfg!(feature = "local-planner");
+            DoctorCheck {
+                label: "planner_reachable",
+                ok: built && model.is_some(),
+                details: match (built, model) {
+                    (false, _) => {
+                        "local planner needs a build with --features local-planner".to_string()
+                    }
+                    (true, Some(path)) => format!("local planner model {}", path.display()),
+                    (true, None) => {
+                        "local planner requires CORTEX_PLANNER_MODEL_PATH to a GGUF file"
+                            .to_string()
+                    }
+                },
+            }
+        }
+        PlannerMode::Bedrock => {
+            let configured =
+                cmd.planner_api_key.is_some() || bedrock_credentials_from_env().is_some();
+            DoctorCheck {
+                label: "planner_reachable",
+                ok: configured,
+                details: if configured {
+                    format!(
+                        "bedrock planner credentials configured for {}",
+                        cmd.planner_base_url
+                    )
+                } else {
+                    "bedrock planner requires AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY"
+                        .to_string()
+                },
+            }
+        }
+        PlannerMode::OpenAi | PlannerMode::AzureOpenAi => {
+            let azure = planner_mode == PlannerMode::AzureOpenAi;
+            let base_url = cmd.planner_base_url.trim_end_matches('/');
+            let planner_url = if azure {
+                format!(
+                    "{base_url}/openai/deployments/{}/chat/completions",
+                    cmd.planner_azure_deployment
+                        .as_deref()
+                        .unwrap_or(&cmd.planner_model)
+                )
+            } else {
+                format!("{base_url}/chat/completions")
+            };
             let payload = serde_json::json!({
                 "model": cmd.planner_model,
                 "messages": [{"role": "user", "content": "Return only {}"}],
                 "temperature": 0,
                 "max_tokens": 1
             });
-            let requires_key = planner_base_url_requires_api_key(&cmd.planner_base_url);
+            let requires_key = azure || planner_base_url_requires_api_key(&cmd.planner_base_url);
             if planner_api_key.is_none() && requires_key {
                 DoctorCheck {
                     label: "planner_reachable",
@@ -956,10 +2520,12 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
                 }
             } else {
                 let request = http.post(&planner_url).json(&payload);
-                let request = if let Some(api_key) = planner_api_key.clone() {
-                    request.bearer_auth(api_key)
-                } else {
-                    request
+                let request = match planner_api_key.clone() {
+                    Some(api_key) if azure => request
+                        .query(&[("api-version", cmd.planner_azure_api_version.as_str())])
+                        .header("api-key", api_key),
+                    Some(api_key) => request.bearer_auth(api_key),
+                    None => request,
                 };
                 match request.send().await {
                     Ok(response) => {
@@ -987,7 +2553,7 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
             }
         }
     };
-    failures += print_doctor_check(planner_check);
+    record_doctor_check(&mut checks, planner_check, json);
 
     let proxy_check = match http.get(&healthz_url).send().await {
         Ok(response) => {
@@ -1023,16 +2589,28 @@ async fn handle_doctor(cmd: DoctorCmd) -> Result<()> {
             details: format!("could not reach {}: {e}", healthz_url),
         },
     };
-    failures += print_doctor_check(proxy_check);
+    let proxy_up = proxy_check.ok;
+    record_doctor_check(&mut checks, proxy_check, json);
+    if proxy_up && let Some(api_key) = resolved_proxy_api_key.as_deref() {
+        let chat_check = run_proxy_chat_check(&http, &proxy_base_url, api_key).await;
+        record_doctor_check(&mut checks, chat_check, json);
+    }
+    record_doctor_results(&mut checks, doctor_port_checks().await, json);
 
     let dry_run_check = run_dry_execute_check(&cmd.endpoint, &subject_for_dry_run).await;
-    failures += print_doctor_check(dry_run_check);
+    record_doctor_check(&mut checks, dry_run_check, json);
 
+    let failures = checks.iter().filter(|c| !c.ok).count();
+    if json {
+        print_doctor_json(&checks)?;
+    }
     if failures > 0 {
         bail!("doctor found {} failing check(s)", failures);
     }
 
-    println!("doctor summary: all checks passed");
+    if !json {
+        println!("doctor summary: all checks passed");
+    }
     Ok(())
 }
 
@@ -1121,6 +2699,77 @@ async fn run_dry_execute_check(endpoint: &str, subject: &str) -> DoctorCheck {
     }
 }
 
+/// Sends one chat completion through the proxy with the resolved key, so auth, brain routing and
+/// the planner are exercised the way a client would hit them.
+async fn run_proxy_chat_check(http: &Client, proxy_base_url: &str, api_key: &str) -> DoctorCheck {
+    let url = format!("{proxy_base_url}/chat/completions");
+    let payload = serde_json::json!({
+        "model": "cortex-doctor",
+        "messages": [{"role": "user", "content": "[doctor] end-to-end proxy check"}],
+        "max_tokens": 1
+    });
+    let response = match http
+        .post(&url)
+        .bearer_auth(api_key)
+        .header(
+            "x-cortex-conversation",
+            format!("doctor-{}", Uuid::new_v4().simple()),
+        )
+        .json(&payload)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            return DoctorCheck {
+                label: "proxy_chat",
+                ok: false,
+                details: format!("POST {url} failed: {e}"),
+            };
+        }
+    };
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let cortex_status = header("x-cortex-status");
+    let plan_source = header("x-cortex-plan-source");
+    if !status.is_success() {
+        let code = match header("x-cortex-error-code") {
+            Some(code) => code,
+            None => response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body["error"]["code"].as_str().map(str::to_string))
+                .unwrap_or_else(|| "unknown".to_string()),
+        };
+        return DoctorCheck {
+            label: "proxy_chat",
+            ok: false,
+            details: format!("POST {url} returned HTTP {status} code={code}"),
+        };
+    }
+    match (cortex_status, plan_source) {
+        (Some(cortex_status), Some(plan_source)) => DoctorCheck {
+            label: "proxy_chat",
+            ok: true,
+            details: format!("HTTP {status} status={cortex_status} plan_source={plan_source}"),
+        },
+        _ => DoctorCheck {
+            label: "proxy_chat",
+            ok: false,
+            details: format!(
+                "HTTP {status} without x-cortex-status/x-cortex-plan-source; {url} is not a cortex proxy"
+            ),
+        },
+    }
+}
+
 fn derive_healthz_url(proxy_base_url: &str) -> String {
     let mut base = proxy_base_url.trim_end_matches('/').to_string();
     if base.ends_with("/v1") {
@@ -1136,21 +2785,109 @@ fn planner_base_url_requires_api_key(base_url: &str) -> bool {
         || normalized.contains("ollama"))
 }
 
-fn print_doctor_check(check: DoctorCheck) -> usize {
-    if check.ok {
-        println!("[OK]   {} {}", check.label, check.details);
-        0
-    } else {
-        println!("[FAIL] {} {}", check.label, check.details);
-        1
+fn doctor_remediation(label: &str) -> &'static str {
+    match label {
+        "brain_unlocked" => {
+            "create or select a brain (cortex brain use <name>) and export its passphrase env var"
+        }
+        "secret_storage" => "fix config.json or CORTEX_SECRET_STORAGE (auto|keyring)",
+        "keyring_available" => {
+            "unlock or install an OS keyring service, or run cortex config set secret_storage auto"
+        }
+        "fallback_secrets" => {
+            "restore the original secrets.key from a backup, or re-enter secrets with cortex setup"
+        }
+        "brain_secret" => {
+            "see \"Stored brain secret does not unlock the brain\" in docs/common_problems.md"
+        }
+        "proxy_port" | "rmvm_port" => {
+            "stop the other listener, or start on the free port shown with the cortex up command"
+        }
+        "api_key_mapped" => "map a proxy key with cortex auth map-key, or pass --api-key",
+        "planner_reachable" => {
+            "check --planner-base-url and CORTEX_PLANNER_API_KEY, or use --planner-mode fallback"
+        }
+        "proxy_reachable" => "start the proxy with cortex up, or pass --proxy-base-url",
+        "proxy_chat" => {
+            "check the key mapping with cortex auth map-key and the proxy log with cortex logs"
+        }
+        "dry_run_execute" => "start the RMVM runtime with cortex up, or pass --endpoint",
+        _ => "see docs/common_problems.md",
+    }
+}
+
+fn record_doctor_check(checks: &mut Vec<DoctorCheck>, check: DoctorCheck, json: bool) {
+    if !json {
+        if check.ok {
+            println!("[OK]   {} {}", check.label, check.details);
+        } else {
+            println!("[FAIL] {} {}", check.label, check.details);
+            println!("       fix: {}", doctor_remediation(check.label));
+        }
+    }
+    checks.push(check);
+}
+
+/// Records checks implemented in `product`, which report details or the failure reason.
+fn record_doctor_results(
+    checks: &mut Vec<DoctorCheck>,
+    results: Vec<(&'static str, Result<String>)>,
+    json: bool,
+) {
+    for (label, result) in results {
+        let check = match result {
+            Ok(details) => DoctorCheck {
+                label,
+                ok: true,
+                details,
+            },
+            Err(e) => DoctorCheck {
+                label,
+                ok: false,
+                details: format!("{e:#}"),
+            },
+        };
+        record_doctor_check(checks, check, json);
     }
 }
 
+fn print_doctor_json(checks: &[DoctorCheck]) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&doctor_report(checks))?);
+    Ok(())
+}
+
+fn doctor_report(checks: &[DoctorCheck]) -> serde_json::Value {
+    let failed = checks.iter().filter(|c| !c.ok).count();
+    serde_json::json!({
+        "ok": failed == 0,
+        "summary": {
+            "total": checks.len(),
+            "passed": checks.len() - failed,
+            "failed": failed,
+        },
+        "checks": checks
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "label": c.label,
+                    "ok": c.ok,
+                    "details": c.details,
+                    "remediation": (!c.ok).then(|| doctor_remediation(c.label)),
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
 fn parse_restart_policy(value: &str) -> Result<RestartPolicy> {
     match value.trim().to_ascii_lowercase().as_str() {
         "auto" => Ok(RestartPolicy::Auto),
+        "prompt" => Ok(RestartPolicy::Prompt),
         "never" => Ok(RestartPolicy::Never),
-        other => bail!("invalid restart policy '{}'; expected auto|never", other),
+        other => bail!(
+            "invalid restart policy '{}'; expected auto|prompt|never",
+            other
+        ),
     }
 }
 
@@ -1162,6 +2899,15 @@ fn parse_bool_flag(name: &str, value: &str) -> Result<bool> {
     }
 }
 
+fn parse_header(value: &str) -> Result<(String, String)> {
+    match value.split_once('=') {
+        Some((name, header)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), header.trim().to_string()))
+        }
+        _ => bail!("invalid header '{value}'; expected <name>=<value>"),
+    }
+}
+
 fn split_csv(s: &str) -> Vec<String> {
     s.split(',')
         .map(str::trim)
@@ -1169,3 +2915,131 @@ fn split_csv(s: &str) -> Vec<String> {
         .map(ToOwned::to_owned)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::tests::{MockMode, spawn_mock_rmvm};
+
+    #[tokio::test]
+    async fn exec_validates_the_plan_and_returns_proof_roots() {
+        let (endpoint, stop) = spawn_mock_rmvm(MockMode::Ok).await;
+        let plan = |handle: &str| {
+            format!(
+                r#"{{"requestId":"req-exec","steps":[{{"out":"r0","op":{{"kind":"fetch","handleRef":"{handle}"}}}}],"outputs":["r0"]}}"#
+            )
+        };
+
+        let (request_id, execute) = execute_plan_json(
+            &endpoint,
+            &plan("H1"),
+            "user:local",
+            Some("I prefer tea.".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(request_id, "req-exec");
+        assert_eq!(execute.status, ExecutionStatus::Ok as i32);
+        let proof = execute.proof.unwrap();
+        assert_eq!(proof.semantic_root, "sem-root-ok");
+        assert_eq!(proof.trace_root, "trace-root-ok");
+        assert_eq!(
+            assertion_fields_json(&execute.assertions[0])["subject"],
+            "user:local"
+        );
+
+        let err = execute_plan_json(&endpoint, &plan("H9"), "user:local", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown handle ref H9"), "{err}");
+        assert!(
+            execute_plan_json(&endpoint, "not a plan", "user:local", None)
+                .await
+                .is_err()
+        );
+        let _ = stop.send(());
+    }
+
+    #[tokio::test]
+    async fn doctor_chat_check_needs_auth_and_cortex_headers() {
+        use axum::Json;
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::response::IntoResponse;
+        use axum::routing::post;
+
+        let app = axum::Router::new().route(
+            "/v1/chat/completions",
+            post(|headers: HeaderMap| async move {
+                let auth = headers
+                    .get("authorization")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default();
+                let body = Json(serde_json::json!({"choices": []}));
+                match auth {
+                    "Bearer mapped-key" => (
+                        [
+                            ("x-cortex-status", "OK"),
+                            ("x-cortex-plan-source", "fallback"),
+                        ],
+                        body,
+                    )
+                        .into_response(),
+                    "Bearer other-proxy" => body.into_response(),
+                    _ => (
+                        StatusCode::UNAUTHORIZED,
+                        Json(serde_json::json!({"error": {"code": "invalid_api_key"}})),
+                    )
+                        .into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let http = Client::new();
+
+        let check = run_proxy_chat_check(&http, &base, "mapped-key").await;
+        assert!(check.ok, "{}", check.details);
+        assert!(check.details.ends_with("status=OK plan_source=fallback"));
+
+        let check = run_proxy_chat_check(&http, &base, "unmapped-key").await;
+        assert!(!check.ok);
+        assert!(check.details.contains("HTTP 401"), "{}", check.details);
+        assert!(check.details.ends_with("code=invalid_api_key"));
+
+        let check = run_proxy_chat_check(&http, &base, "other-proxy").await;
+        assert!(!check.ok);
+        assert!(check.details.contains("is not a cortex proxy"));
+    }
+
+    #[test]
+    fn doctor_json_lists_checks_with_hints_for_failures() {
+        let checks = vec![
+            DoctorCheck {
+                label: "brain_unlocked",
+                ok: true,
+                details: "brain personal".to_string(),
+            },
+            DoctorCheck {
+                label: "proxy_reachable",
+                ok: false,
+                details: "connection refused".to_string(),
+            },
+        ];
+        let report = doctor_report(&checks);
+        assert_eq!(report["ok"], false);
+        assert_eq!(
+            report["summary"],
+            serde_json::json!({"total": 2, "passed": 1, "failed": 1})
+        );
+        assert_eq!(report["checks"][0]["label"], "brain_unlocked");
+        assert!(report["checks"][0]["remediation"].is_null());
+        assert_eq!(report["checks"][1]["details"], "connection refused");
+        assert_eq!(
+            report["checks"][1]["remediation"],
+            doctor_remediation("proxy_reachable")
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::Write;
+
+use anyhow::{Result, bail};
+use clap::Command;
+use clap_complete::Shell;
+
+use crate::product::{brain_store, provider_names};
+
+/// Hidden command the shell snippets call to complete brain and provider names.
+pub const NAMES_COMMAND: &str = "__names";
+
+const BASH_DYNAMIC: &str = r#"
+_cortex_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    if [[ ${COMP_CWORD} -eq 3 ]]; then
+        local kind=""
+        case "${COMP_WORDS[1]} ${COMP_WORDS[2]}" in
+            "brain use"|"brain open"|"brain export"|"brain branch") kind=brains ;;
+            "provider use") kind=providers ;;
+        esac
+        if [[ -n "$kind" ]]; then
+            COMPREPLY=($(compgen -W "$(cortex __names "$kind" 2>/dev/null)" -- "$cur"))
+            return 0
+        fi
+    fi
+    _cortex "$@"
+}
+complete -F _cortex_dynamic -o bashdefault -o default cortex
+"#;
+
+const FISH_DYNAMIC: &str = r#"
+complete -c cortex -n "__fish_seen_subcommand_from brain; and __fish_seen_subcommand_from use open export branch" -f -a "(cortex __names brains 2>/dev/null)"
+complete -c cortex -n "__fish_seen_subcommand_from provider; and __fish_seen_subcommand_from use" -f -a "(cortex __names providers 2>/dev/null)"
+"#;
+
+/// Writes the clap-generated script for `shell`; bash and fish also complete brain and
+/// provider names by calling back into `cortex`.
+pub fn write(shell: Shell, cmd: &mut Command, out: &mut impl Write) -> Result<()> {
+    clap_complete::generate(shell, cmd, "cortex", out);
+    match shell {
+        Shell::Bash => out.write_all(BASH_DYNAMIC.as_bytes())?,
+        Shell::Fish => out.write_all(FISH_DYNAMIC.as_bytes())?,
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn print_names(kind: &str) -> Result<()> {
+    let names = match kind {
+        "brains" => brain_store()?
+            .list_brains()?
+            .into_iter()
+            .map(|b| b.name)
+            .collect(),
+        "providers" => provider_names()?,
+        other => bail!("unknown completion kind '{other}', expected brains|providers"),
+    };
+    for name in names {
+        println!("{name}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Arg, Command};
+
+    use super::*;
+
+    #[test]
+    fn bash_script_wraps_generated_completion() {
+        let mut cmd = Command::new("cortex").subcommand(
+            Command::new("brain").subcommand(Command::new("use").arg(Arg::new("brain"))),
+        );
+        let mut out = Vec::new();
+        write(Shell::Bash, &mut cmd, &mut out).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("_cortex()"));
+        assert!(script.contains("complete -F _cortex_dynamic"));
+        assert!(script.contains(&format!("cortex {NAMES_COMMAND} \"$kind\"")));
+
+        let mut out = Vec::new();
+        write(Shell::Zsh, &mut cmd, &mut out).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains(NAMES_COMMAND));
+    }
+}
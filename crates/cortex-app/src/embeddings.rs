@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::Client;
+use rmvm_proto::{HandleRef, PublicManifest};
+use serde_json::{Value as JsonValue, json};
+
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+pub const DEFAULT_SHORTLIST: usize = 24;
+
+const MAX_INDEXED_SIGNATURES: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingProvider {
+    /// `POST {base_url}/embeddings`, also served by most OpenAI-compatible gateways.
+    OpenAi,
+    AzureOpenAi,
+    /// `POST {base_url}/api/embed`.
+    Ollama,
+}
+
+impl EmbeddingProvider {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "azure-openai" | "azure_openai" | "azure" => Ok(Self::AzureOpenAi),
+            "ollama" => Ok(Self::Ollama),
+            other => Err(anyhow!(
+                "unsupported embedding provider '{other}', expected openai|azure-openai|ollama"
+            )),
+        }
+    }
+
+    fn default_base_url(self) -> &'static str {
+        match self {
+            Self::OpenAi | Self::AzureOpenAi => "https://api.openai.com/v1",
+            Self::Ollama => "http://127.0.0.1:11434",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingConfig {
+    pub provider: EmbeddingProvider,
+    pub base_url: Option<String>,
+    /// Model name, or the deployment name for Azure OpenAI.
+    pub model: String,
+    pub api_key: Option<String>,
+    pub azure_api_version: String,
+    pub timeout: Duration,
+    /// Handles kept for the planner; manifests at or under this size are not ranked.
+    pub shortlist: usize,
+}
+
+#[derive(Debug)]
+pub struct EmbeddingClient {
+    config: EmbeddingConfig,
+    http: Client,
+}
+
+impl EmbeddingClient {
+    pub fn new(config: EmbeddingConfig) -> Result<Self> {
+        if config.provider != EmbeddingProvider::Ollama && config.api_key.is_none() {
+            bail!("embedding provider requires CORTEX_EMBEDDING_API_KEY");
+        }
+        let http = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .context("failed to build embedding HTTP client")?;
+        Ok(Self { config, http })
+    }
+
+    /// One vector per input, in input order.
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let config = &self.config;
+        let base_url = config
+            .base_url
+            .as_deref()
+            .unwrap_or(config.provider.default_base_url())
+            .trim_end_matches('/');
+        let api_key = config.api_key.as_deref().unwrap_or_default();
+        let request = match config.provider {
+            EmbeddingProvider::OpenAi => self
+                .http
+                .post(format!("{base_url}/embeddings"))
+                .bearer_auth(api_key)
+                .json(&json!({"model": config.model, "input": inputs})),
+            EmbeddingProvider::AzureOpenAi => self
+                .http
+                .post(format!(
+                    "{base_url}/openai/deployments/{}/embeddings",
+                    config.model
+                ))
+                .query(&[("api-version", config.azure_api_version.as_str())])
+                .header("api-key", api_key)
+                .json(&json!({"input": inputs})),
+            EmbeddingProvider::Ollama => self
+                .http
+                .post(format!("{base_url}/api/embed"))
+                .json(&json!({"model": config.model, "input": inputs})),
+        };
+        let resp = request.send().await.context("embedding request failed")?;
+        let status = resp.status();
+        let body = resp.text().await.context("embedding request failed")?;
+        if !status.is_success() {
+            bail!(
+                "embedding provider returned HTTP {}: {body}",
+                status.as_u16()
+            );
+        }
+        let root: JsonValue =
+            serde_json::from_str(&body).context("embedding response is not JSON")?;
+        let vectors = match config.provider {
+            EmbeddingProvider::Ollama => root["embeddings"]
+                .as_array()
+                .ok_or_else(|| anyhow!("embedding response missing embeddings"))?
+                .iter()
+                .map(parse_vector)
+                .collect::<Result<Vec<_>>>()?,
+            EmbeddingProvider::OpenAi | EmbeddingProvider::AzureOpenAi => {
+                let mut data = root["data"]
+                    .as_array()
+                    .ok_or_else(|| anyhow!("embedding response missing data"))?
+                    .iter()
+                    .map(|item| {
+                        let index = item["index"].as_u64().unwrap_or_default();
+                        parse_vector(&item["embedding"]).map(|v| (index, v))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                data.sort_by_key(|(index, _)| *index);
+                data.into_iter().map(|(_, v)| v).collect()
+            }
+        };
+        if vectors.len() != inputs.len() {
+            bail!(
+                "embedding provider returned {} vectors for {} inputs",
+                vectors.len(),
+                inputs.len()
+            );
+        }
+        Ok(vectors)
+    }
+}
+
+fn parse_vector(value: &JsonValue) -> Result<Vec<f32>> {
+    value
+        .as_array()
+        .ok_or_else(|| anyhow!("embedding is not an array"))?
+        .iter()
+        .map(|x| {
+            x.as_f64()
+                .map(|x| x as f32)
+                .ok_or_else(|| anyhow!("embedding has a non-numeric component"))
+        })
+        .collect()
+}
+
+/// Shortlists manifest handles by similarity between the user message and each handle's
+/// signature. Signature vectors are kept across requests, so a brain's handles are embedded
+/// once and later turns only embed the message.
+#[derive(Debug)]
+pub struct HandleRanker {
+    client: EmbeddingClient,
+    shortlist: usize,
+    index: Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl HandleRanker {
+    pub fn new(config: EmbeddingConfig) -> Result<Self> {
+        let shortlist = config.shortlist.max(1);
+        Ok(Self {
+            client: EmbeddingClient::new(config)?,
+            shortlist,
+            index: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Keeps the `shortlist` handles closest to `message`, in manifest order. Returns how many
+    /// handles were dropped.
+    pub async fn shortlist(&self, message: &str, manifest: &mut PublicManifest) -> Result<usize> {
+        if manifest.handles.len() <= self.shortlist {
+            return Ok(0);
+        }
+        let signatures = manifest.handles.iter().map(signature).collect::<Vec<_>>();
+        let mut inputs = vec![message.to_string()];
+        {
+            let index = self
+                .index
+                .lock()
+                .map_err(|_| anyhow!("handle index poisoned"))?;
+            for s in &signatures {
+                if !index.contains_key(s) && !inputs[1..].contains(s) {
+                    inputs.push(s.clone());
+                }
+            }
+        }
+        let mut vectors = self.client.embed(&inputs).await?.into_iter();
+        let query = vectors
+            .next()
+            .ok_or_else(|| anyhow!("no embedding for the user message"))?;
+
+        let fresh = inputs
+            .into_iter()
+            .skip(1)
+            .zip(vectors)
+            .collect::<HashMap<_, _>>();
+        let mut index = self
+            .index
+            .lock()
+            .map_err(|_| anyhow!("handle index poisoned"))?;
+        let scores = signatures
+            .iter()
+            .map(|s| {
+                fresh
+                    .get(s)
+                    .or_else(|| index.get(s))
+                    .map_or(f32::MIN, |v| cosine(&query, v))
+            })
+            .collect::<Vec<_>>();
+        if index.len() + fresh.len() > MAX_INDEXED_SIGNATURES {
+            index.clear();
+        }
+        index.extend(fresh);
+        drop(index);
+
+        let keep = top_k(&scores, self.shortlist);
+        let before = manifest.handles.len();
+        let mut position = 0;
+        manifest.handles.retain(|_| {
+            position += 1;
+            keep[position - 1]
+        });
+        Ok(before - manifest.handles.len())
+    }
+}
+
+/// The text a handle is ranked by: what it is about and the kernel's summary of it.
+fn signature(handle: &HandleRef) -> String {
+    let (subject, predicate) = handle
+        .meta
+        .as_ref()
+        .map(|m| (m.subject.as_str(), m.predicate_label.as_str()))
+        .unwrap_or_default();
+    format!(
+        "{subject} {predicate} ({}): {}",
+        handle.type_id, handle.signature_summary
+    )
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 { 0.0 } else { dot / denom }
+}
+
+/// Marks the `k` highest scores; ties keep the earlier position.
+fn top_k(scores: &[f32], k: usize) -> Vec<bool> {
+    let mut order = (0..scores.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+    let mut keep = vec![false; scores.len()];
+    for i in order.into_iter().take(k) {
+        keep[i] = true;
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use rmvm_proto::HandleMeta;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const TOPICS: [&str; 3] = ["beverage", "city", "name"];
+
+    async fn embeddings(Json(body): Json<JsonValue>) -> Json<JsonValue> {
+        let data = body["input"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let text = text.as_str().unwrap().to_lowercase();
+                let embedding = TOPICS
+                    .iter()
+                    .map(|t| if text.contains(t) { 1.0 } else { 0.01 })
+                    .collect::<Vec<f32>>();
+                json!({"index": index, "embedding": embedding})
+            })
+            .rev()
+            .collect::<Vec<_>>();
+        Json(json!({"data": data}))
+    }
+
+    fn handle(r: &str, predicate: &str) -> HandleRef {
+        HandleRef {
+            r#ref: r.to_string(),
+            meta: Some(HandleMeta {
+                subject: "user:local".to_string(),
+                predicate_label: predicate.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn shortlists_handles_closest_to_the_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/embeddings", post(embeddings));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let ranker = HandleRanker::new(EmbeddingConfig {
+            provider: EmbeddingProvider::OpenAi,
+            base_url: Some(format!("http://{addr}")),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            api_key: Some("test-key".to_string()),
+            azure_api_version: String::new(),
+            timeout: Duration::from_secs(5),
+            shortlist: 1,
+        })
+        .unwrap();
+        let mut manifest = PublicManifest {
+            handles: vec![
+                handle("H1", "prefers_beverage"),
+                handle("H2", "home_city"),
+                handle("H3", "preferred_name"),
+            ],
+            ..Default::default()
+        };
+        let dropped = ranker
+            .shortlist("which city do I live in?", &mut manifest)
+            .await
+            .unwrap();
+        assert_eq!(dropped, 2);
+        assert_eq!(manifest.handles[0].r#ref, "H2");
+        assert_eq!(ranker.index.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use brain_store::{BrainStore, BrainSummary};
+
+/// Prefix of mapping keys that cover every brain of a tenant.
+pub const TENANT_PREFIX: &str = "tenant:";
+
+/// RMVM endpoint serving each brain. Keys are brain ids or names, or `tenant:<id>`; brains
+/// without a mapping use `default`.
+#[derive(Debug, Clone, Default)]
+pub struct RmvmEndpoints {
+    pub default: String,
+    pub mapped: BTreeMap<String, String>,
+}
+
+impl RmvmEndpoints {
+    pub fn new(default: String, mapped: BTreeMap<String, String>) -> Self {
+        Self { default, mapped }
+    }
+
+    /// A brain mapping wins over its tenant's mapping.
+    pub fn for_brain(&self, brain: &BrainSummary) -> &str {
+        [brain.brain_id.as_str(), brain.name.as_str()]
+            .into_iter()
+            .find_map(|key| self.mapped.get(key))
+            .or_else(|| {
+                self.mapped
+                    .get(&format!("{TENANT_PREFIX}{}", brain.tenant_id))
+            })
+            .unwrap_or(&self.default)
+    }
+
+    /// Endpoint for `brain_id`; a brain the store cannot resolve uses the default.
+    pub fn resolve(&self, store: &BrainStore, brain_id: &str) -> String {
+        if self.mapped.is_empty() {
+            return self.default.clone();
+        }
+        store
+            .resolve_brain(brain_id)
+            .map(|brain| self.for_brain(&brain).to_string())
+            .unwrap_or_else(|_| self.default.clone())
+    }
+}
+
+/// Parses `--brain-endpoint <brain|tenant:id>=<endpoint>`.
+pub fn parse_mapping(value: &str) -> Result<(String, String)> {
+    match value.split_once('=') {
+        Some((key, endpoint)) if !key.trim().is_empty() && !endpoint.trim().is_empty() => {
+            Ok((key.trim().to_string(), endpoint.trim().to_string()))
+        }
+        _ => bail!("invalid brain endpoint '{value}'; expected <brain|tenant:id>=<endpoint>"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brain_mappings_win_over_tenant_mappings() {
+        let endpoints = RmvmEndpoints::new(
+            "grpc://127.0.0.1:50051".to_string(),
+            BTreeMap::from([
+                (
+                    "tenant:team".to_string(),
+                    "grpc://kernel.team:50051".to_string(),
+                ),
+                ("notes".to_string(), "grpc://notes:50051".to_string()),
+            ]),
+        );
+        let brain = |name: &str, tenant: &str| BrainSummary {
+            brain_id: format!("{name}-id"),
+            name: name.to_string(),
+            tenant_id: tenant.to_string(),
+            updated_at: String::new(),
+            active_branch: "main".to_string(),
+        };
+        assert_eq!(
+            endpoints.for_brain(&brain("shared", "team")),
+            "grpc://kernel.team:50051"
+        );
+        assert_eq!(
+            endpoints.for_brain(&brain("notes", "team")),
+            "grpc://notes:50051"
+        );
+        assert_eq!(
+            endpoints.for_brain(&brain("personal", "local")),
+            "grpc://127.0.0.1:50051"
+        );
+
+        assert_eq!(
+            parse_mapping("tenant:team = grpc://kernel.team:50051").unwrap(),
+            (
+                "tenant:team".to_string(),
+                "grpc://kernel.team:50051".to_string()
+            )
+        );
+        assert!(parse_mapping("notes").is_err());
+        assert!(parse_mapping("=grpc://x").is_err());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use adapter_rmvm::RmvmAdapter;
+use anyhow::{Context, Result};
+use brain_store::{BrainStore, BranchState, LedgerEvent, MemoryObject, SuppressionRecord};
+use chrono::{DateTime, Utc};
+use rmvm_grpc::{AppendEventRequest, ForgetRequest, GetManifestRequest};
+use rmvm_proto::{ExecutionStatus, HandleRef, Scope};
+use serde::Serialize;
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+/// Ledger operation recording text the proxy appended to the kernel.
+pub const LEDGER_MEMORY_APPEND: &str = "memory.append";
+/// Ledger marker written by a flush. `memory.append` entries before it are covered by the
+/// memory objects that flush captured, so hydration skips them.
+pub const LEDGER_KERNEL_FLUSH: &str = "kernel.flush";
+
+#[derive(Debug, Default, Serialize)]
+pub struct HydrationReport {
+    pub brain_id: String,
+    pub appended: usize,
+    pub forgotten: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FlushReport {
+    pub brain_id: String,
+    pub handles: usize,
+    pub added: usize,
+    /// Kernel handles for memory the brain forgot after it was written; forgotten again.
+    pub conflicts: usize,
+}
+
+/// Ledger entries written since the last flush.
+fn since_last_flush(ledger: &[LedgerEvent]) -> &[LedgerEvent] {
+    let start = ledger
+        .iter()
+        .rposition(|e| e.operation == LEDGER_KERNEL_FLUSH)
+        .map_or(0, |i| i + 1);
+    &ledger[start..]
+}
+
+/// Drops `memory.append` entries and older flush markers that precede the last flush marker;
+/// hydration never replays them. Returns how many were dropped.
+pub fn compact_flushed(ledger: &mut Vec<LedgerEvent>) -> usize {
+    let Some(last) = ledger
+        .iter()
+        .rposition(|e| e.operation == LEDGER_KERNEL_FLUSH)
+    else {
+        return 0;
+    };
+    let before = ledger.len();
+    let mut index = 0;
+    ledger.retain(|e| {
+        let flushed = index < last
+            && (e.operation == LEDGER_MEMORY_APPEND || e.operation == LEDGER_KERNEL_FLUSH);
+        index += 1;
+        !flushed
+    });
+    before - ledger.len()
+}
+
+/// Events that rebuild a branch in an empty kernel: live memory objects first, then text
+/// appended since the last flush, in ledger order.
+pub fn hydration_events(brain_id: &str, branch: &BranchState) -> Vec<AppendEventRequest> {
+    let objects = branch
+        .memory_objects
+        .values()
+        .filter(|o| o.is_live())
+        .map(|o| {
+            let value = match &o.value {
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (
+                o.subject.clone(),
+                format!("{}: {value}", o.predicate),
+                Scope::Global,
+            )
+        });
+    let appended = since_last_flush(&branch.ledger)
+        .iter()
+        .filter(|e| e.operation == LEDGER_MEMORY_APPEND)
+        .filter_map(|e| {
+            let field = |name: &str| e.payload.get(name).and_then(JsonValue::as_str);
+            let scope = field("scope")
+                .and_then(Scope::from_str_name)
+                .unwrap_or(Scope::Global);
+            Some((
+                field("subject")?.to_string(),
+                field("text")?.to_string(),
+                scope,
+            ))
+        });
+    objects
+        .chain(appended)
+        .map(|(subject, text, scope)| AppendEventRequest {
+            request_id: format!("hydrate-{brain_id}"),
+            subject,
+            text,
+            scope: scope as i32,
+        })
+        .collect()
+}
+
+/// Replays the brain's active branch into the kernel at `endpoint`, then re-applies its forget
+/// suppressions so forgotten memory stays forgotten. Applied suppression ids go into `applied`.
+pub async fn hydrate_brain(
+    endpoint: &str,
+    store: &BrainStore,
+    brain_id: &str,
+    applied: &mut BTreeSet<String>,
+) -> Result<HydrationReport> {
+    let branch = store.active_branch_state(brain_id)?;
+    let adapter = RmvmAdapter::new(endpoint.to_string());
+    let mut report = HydrationReport {
+        brain_id: brain_id.to_string(),
+        ..Default::default()
+    };
+    for event in hydration_events(brain_id, &branch) {
+        adapter.append_event(event).await?;
+        report.appended += 1;
+    }
+    report.forgotten = apply_forgets(&adapter, brain_id, &branch.suppressions, applied).await?;
+    Ok(report)
+}
+
+async fn forget(
+    adapter: &RmvmAdapter,
+    brain_id: &str,
+    suppression: &SuppressionRecord,
+) -> Result<ExecutionStatus> {
+    let forget = adapter
+        .forget(ForgetRequest {
+            request_id: format!("sync-{brain_id}"),
+            subject: suppression.subject.clone(),
+            predicate_label: suppression.predicate.clone(),
+            scope: Scope::from_str_name(&suppression.scope).unwrap_or(Scope::Global) as i32,
+            reason: suppression.reason.clone(),
+        })
+        .await?;
+    let status = ExecutionStatus::try_from(forget.status).unwrap_or(ExecutionStatus::Unspecified);
+    if status != ExecutionStatus::Ok {
+        warn!(
+            "brain {brain_id}: forget {} {} returned {}",
+            suppression.subject,
+            suppression.predicate,
+            status.as_str_name()
+        );
+    }
+    Ok(status)
+}
+
+/// Sends brain-side forgets the kernel has not seen yet. A suppression counts as applied once
+/// the kernel answered, even when it had nothing to forget. Partial suppressions are skipped:
+/// a kernel forget would also drop the live memory they leave. Returns how many succeeded.
+pub async fn apply_forgets(
+    adapter: &RmvmAdapter,
+    brain_id: &str,
+    suppressions: &[SuppressionRecord],
+    applied: &mut BTreeSet<String>,
+) -> Result<usize> {
+    let mut forgotten = 0;
+    for suppression in suppressions {
+        if applied.contains(&suppression.id) || suppression.is_partial() {
+            continue;
+        }
+        if forget(adapter, brain_id, suppression).await? == ExecutionStatus::Ok {
+            forgotten += 1;
+        }
+        applied.insert(suppression.id.clone());
+    }
+    Ok(forgotten)
+}
+
+/// Memory object for a kernel handle. Ids hash the handle's content, not its ref, so the same
+/// memory maps to the same object across kernels.
+pub fn kernel_object(handle: &HandleRef) -> Option<MemoryObject> {
+    let meta = handle.meta.as_ref()?;
+    let key = [
+        meta.subject.as_str(),
+        meta.predicate_label.as_str(),
+        handle.type_id.as_str(),
+        handle.signature_summary.as_str(),
+    ]
+    .join("\0");
+    Some(MemoryObject {
+        id: format!(
+            "kernel-{}",
+            &format!("{:x}", Sha256::digest(key.as_bytes()))[..16]
+        ),
+        subject: meta.subject.clone(),
+        predicate: meta.predicate_label.clone(),
+        value: JsonValue::String(handle.signature_summary.clone()),
+        memory_type: handle.type_id.clone(),
+        suppressed: false,
+        recorded_at: None,
+        superseded_by: None,
+    })
+}
+
+/// The brain forget a kernel handle conflicts with: same subject and predicate, and the handle
+/// was valid before the forget. The brain wins; handles without a start time count as older.
+pub fn conflicting_forget<'a>(
+    handle: &HandleRef,
+    suppressions: &'a [SuppressionRecord],
+) -> Option<&'a SuppressionRecord> {
+    let meta = handle.meta.as_ref()?;
+    let valid_from = meta
+        .temporal
+        .as_ref()
+        .and_then(|t| t.valid_from.as_ref())
+        .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos.max(0) as u32));
+    suppressions.iter().rev().find(|s| {
+        !s.is_partial()
+            && s.subject == meta.subject
+            && s.predicate == meta.predicate_label
+            && DateTime::parse_from_rfc3339(&s.ts)
+                .ok()
+                .is_none_or(|at| valid_from.is_none_or(|from| from < at.with_timezone(&Utc)))
+    })
+}
+
+/// Persists kernel memory the brain does not hold yet. Subjects that API keys map only to other
+/// brains are left out, and handles that conflict with a brain forget are forgotten again.
+pub async fn flush_kernel(
+    endpoint: &str,
+    store: &BrainStore,
+    brain_id: &str,
+) -> Result<FlushReport> {
+    let adapter = RmvmAdapter::new(endpoint.to_string());
+    let manifest = adapter
+        .get_manifest(GetManifestRequest {
+            request_id: format!("flush-{brain_id}"),
+        })
+        .await?
+        .manifest
+        .context("rmvm returned no manifest")?;
+    let mappings = store.list_api_keys()?;
+    let own = mappings
+        .iter()
+        .filter(|m| m.brain_id == brain_id)
+        .map(|m| m.subject.as_str())
+        .collect::<BTreeSet<_>>();
+    let foreign = mappings
+        .iter()
+        .filter(|m| m.brain_id != brain_id && !own.contains(m.subject.as_str()))
+        .map(|m| m.subject.as_str())
+        .collect::<BTreeSet<_>>();
+
+    let branch = store.active_branch_state(brain_id)?;
+    let mut new_objects = Vec::new();
+    let mut conflicts = BTreeMap::new();
+    for handle in &manifest.handles {
+        let Some(object) = kernel_object(handle) else {
+            continue;
+        };
+        if foreign.contains(object.subject.as_str()) {
+            continue;
+        }
+        if let Some(suppression) = conflicting_forget(handle, &branch.suppressions) {
+            conflicts.insert(suppression.id.as_str(), suppression);
+        } else if !branch.memory_objects.contains_key(&object.id) {
+            new_objects.push(object);
+        }
+    }
+    for suppression in conflicts.values() {
+        forget(&adapter, brain_id, suppression).await?;
+    }
+    let pending_appends = since_last_flush(&branch.ledger)
+        .iter()
+        .any(|e| e.operation == LEDGER_MEMORY_APPEND);
+
+    let mut report = FlushReport {
+        brain_id: brain_id.to_string(),
+        handles: manifest.handles.len(),
+        added: 0,
+        conflicts: conflicts.len(),
+    };
+    if !new_objects.is_empty() {
+        report.added = store.add_memory_objects(brain_id, new_objects)?;
+    }
+    if report.added > 0 || pending_appends {
+        store.append_ledger_event(
+            brain_id,
+            LEDGER_KERNEL_FLUSH,
+            json!({"handles": report.handles, "added": report.added}),
+        )?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use rmvm_proto::HandleMeta;
+
+    use super::*;
+
+    #[test]
+    fn replays_live_objects_then_appended_text() {
+        let object = |id: &str, suppressed: bool| MemoryObject {
+            id: id.to_string(),
+            subject: "user:local".to_string(),
+            predicate: format!("pred_{id}"),
+            value: json!("tea"),
+            memory_type: "normative.preference".to_string(),
+            suppressed,
+            recorded_at: None,
+            superseded_by: None,
+        };
+        let event = |operation: &str, payload: JsonValue| LedgerEvent {
+            id: "e".to_string(),
+            ts: "2026-01-01T00:00:00Z".to_string(),
+            operation: operation.to_string(),
+            payload,
+        };
+        let mut branch = BranchState::default();
+        branch
+            .memory_objects
+            .insert("a".to_string(), object("a", false));
+        branch
+            .memory_objects
+            .insert("b".to_string(), object("b", true));
+        branch.ledger = vec![
+            event(
+                LEDGER_MEMORY_APPEND,
+                json!({"subject": "user:local", "text": "I like tea", "scope": "SCOPE_SESSION"}),
+            ),
+            event("rmvm.execute.proof", json!({"request_id": "req-1"})),
+            event(LEDGER_MEMORY_APPEND, json!({"text": "no subject"})),
+        ];
+
+        let events = hydration_events("brain-1", &branch);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].text, "pred_a: tea");
+        assert_eq!(events[1].text, "I like tea");
+        assert_eq!(events[1].scope, Scope::Session as i32);
+        assert_eq!(events[1].request_id, "hydrate-brain-1");
+
+        branch
+            .ledger
+            .push(event(LEDGER_KERNEL_FLUSH, json!({"added": 1})));
+        assert_eq!(hydration_events("brain-1", &branch).len(), 1);
+
+        let mut ledger = branch.ledger.clone();
+        assert_eq!(compact_flushed(&mut ledger), 2);
+        assert_eq!(ledger.len(), 2);
+        assert_eq!(ledger[0].operation, "rmvm.execute.proof");
+        assert_eq!(compact_flushed(&mut ledger), 0);
+    }
+
+    #[test]
+    fn kernel_objects_are_keyed_by_content_and_lose_to_forgets() {
+        let handle = |r: &str, summary: &str| HandleRef {
+            r#ref: r.to_string(),
+            type_id: "normative.preference".to_string(),
+            signature_summary: summary.to_string(),
+            meta: Some(HandleMeta {
+                subject: "user:local".to_string(),
+                predicate_label: "prefers_beverage".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let handles = [
+            handle("H1", "tea"),
+            handle("H7", "tea"),
+            handle("H2", "coffee"),
+            HandleRef::default(),
+        ];
+        let objects = handles.iter().filter_map(kernel_object).collect::<Vec<_>>();
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[0].id, objects[1].id);
+        assert_ne!(objects[0].id, objects[2].id);
+        assert_eq!(objects[2].value, json!("coffee"));
+
+        let suppression = |predicate: &str| SuppressionRecord {
+            id: format!("s-{predicate}"),
+            ts: "2026-01-02T00:00:00Z".to_string(),
+            subject: "user:local".to_string(),
+            predicate: predicate.to_string(),
+            scope: "SCOPE_GLOBAL".to_string(),
+            reason: "test".to_string(),
+            suppressed_count: 0,
+            object_ids: Vec::new(),
+        };
+        let forgets = [suppression("visited"), suppression("prefers_beverage")];
+        assert_eq!(
+            conflicting_forget(&handles[0], &forgets).map(|s| s.id.as_str()),
+            Some("s-prefers_beverage")
+        );
+        assert!(conflicting_forget(&handles[0], &forgets[..1]).is_none());
+    }
+}
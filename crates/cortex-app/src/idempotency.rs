@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+
+const MAX_ENTRIES: usize = 512;
+
+/// A finished response, kept byte for byte so a retry gets exactly what the first call got.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[derive(Debug)]
+struct Entry {
+    at: Instant,
+    fingerprint: String,
+    /// `None` while the first request is still running.
+    response: Option<StoredResponse>,
+}
+
+pub enum Claim<'a> {
+    /// First use of the key; the caller runs the request and completes the slot.
+    Run(Slot<'a>),
+    Replay(StoredResponse),
+    InProgress,
+    /// The key was already used for a different request.
+    Mismatch,
+    /// Every entry belongs to a request that is still running, so there is no room to track
+    /// another one.
+    Full,
+}
+
+/// Responses by `Idempotency-Key`, scoped to the caller. Only successful responses are kept;
+/// a failed or abandoned request frees its key so the client can retry it.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn key(caller: &str, idempotency_key: &str) -> String {
+        format!("{caller}\u{1f}{idempotency_key}")
+    }
+
+    pub fn claim(&self, key: String, fingerprint: &str) -> Claim<'_> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let ttl = self.ttl;
+        // A running request keeps its key until its slot completes or drops, however long it
+        // takes, so a retry cannot run it a second time.
+        entries.retain(|_, entry| entry.response.is_none() || entry.at.elapsed() < ttl);
+        if let Some(entry) = entries.get(&key) {
+            if entry.fingerprint != fingerprint {
+                return Claim::Mismatch;
+            }
+            return match &entry.response {
+                Some(response) => Claim::Replay(response.clone()),
+                None => Claim::InProgress,
+            };
+        }
+        if entries.len() >= MAX_ENTRIES {
+            let Some(oldest) = entries
+                .iter()
+                .filter(|(_, entry)| entry.response.is_some())
+                .min_by_key(|(_, entry)| entry.at)
+                .map(|(k, _)| k.clone())
+            else {
+                return Claim::Full;
+            };
+            entries.remove(&oldest);
+        }
+        entries.insert(
+            key.clone(),
+            Entry {
+                at: Instant::now(),
+                fingerprint: fingerprint.to_string(),
+                response: None,
+            },
+        );
+        Claim::Run(Slot {
+            cache: self,
+            key,
+            done: false,
+        })
+    }
+}
+
+/// A claimed key. Dropping it without `complete` (an error, or the client went away) frees
+/// the key again.
+pub struct Slot<'a> {
+    cache: &'a IdempotencyCache,
+    key: String,
+    done: bool,
+}
+
+impl Slot<'_> {
+    pub fn complete(mut self, response: StoredResponse) {
+        self.done = true;
+        let mut entries = self
+            .cache
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get_mut(&self.key) {
+            entry.at = Instant::now();
+            entry.response = Some(response);
+        }
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.cache
+                .entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_key_runs_once_and_then_replays() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let key = IdempotencyCache::key("key:abc", "retry-1");
+        let Claim::Run(slot) = cache.claim(key.clone(), "body-a") else {
+            panic!("first claim should run");
+        };
+        assert!(matches!(
+            cache.claim(key.clone(), "body-a"),
+            Claim::InProgress
+        ));
+        slot.complete(StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"{}"),
+        });
+        assert!(matches!(cache.claim(key.clone(), "body-a"), Claim::Replay(r) if r.body == "{}"));
+        assert!(matches!(
+            cache.claim(key.clone(), "body-b"),
+            Claim::Mismatch
+        ));
+
+        let failed = IdempotencyCache::key("key:abc", "retry-2");
+        drop(cache.claim(failed.clone(), "body-a"));
+        assert!(matches!(cache.claim(failed, "body-a"), Claim::Run(_)));
+    }
+
+    #[test]
+    fn a_running_request_outlives_the_ttl() {
+        let cache = IdempotencyCache::new(Duration::from_millis(10));
+        let key = IdempotencyCache::key("key:abc", "slow");
+        let Claim::Run(slot) = cache.claim(key.clone(), "body-a") else {
+            panic!("first claim should run");
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(matches!(
+            cache.claim(key.clone(), "body-a"),
+            Claim::InProgress
+        ));
+        slot.complete(StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"{}"),
+        });
+        assert!(matches!(cache.claim(key, "body-a"), Claim::Replay(_)));
+    }
+
+    #[test]
+    fn running_requests_are_bounded() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let slots: Vec<_> = (0..MAX_ENTRIES)
+            .map(
+                |i| match cache.claim(IdempotencyCache::key("key:abc", &i.to_string()), "body") {
+                    Claim::Run(slot) => slot,
+                    _ => panic!("claim {i} should run"),
+                },
+            )
+            .collect();
+        let extra = IdempotencyCache::key("key:abc", "extra");
+        assert!(matches!(cache.claim(extra.clone(), "body"), Claim::Full));
+        drop(slots);
+        assert!(matches!(cache.claim(extra, "body"), Claim::Run(_)));
+    }
+}
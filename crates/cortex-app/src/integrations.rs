@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Value as JsonValue, json};
+
+/// Client apps `cortex connect config` knows how to point at the proxy.
+pub const APPS: [&str; 5] = ["cursor", "continue", "librechat", "open_webui", "raycast"];
+
+/// Display name used for the model / provider entry Cortex adds to client configs.
+const ENTRY_NAME: &str = "Cortex";
+
+#[derive(Debug, Clone)]
+pub struct ClientSettings {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+}
+
+/// Where an app's Cortex settings go and what to put there.
+#[derive(Debug)]
+pub struct AppConfig {
+    /// Config file Cortex can write, or `None` when the settings are entered in the app's UI
+    /// or its deployment environment.
+    pub path: Option<PathBuf>,
+    /// File contents to write when `path` is set; otherwise the exact settings to paste.
+    pub snippet: String,
+    /// Set when `path` exists in a format Cortex does not edit in place.
+    pub manual_reason: Option<String>,
+}
+
+pub fn is_app(name: &str) -> bool {
+    APPS.contains(&name)
+}
+
+fn home() -> Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| anyhow!("failed to resolve home dir"))
+}
+
+/// Returns the directory whose presence means the app is installed, when there is one.
+pub fn detect(app: &str) -> Option<PathBuf> {
+    let dir = match app {
+        "cursor" => dirs::config_dir()?.join("Cursor"),
+        "continue" => dirs::home_dir()?.join(".continue"),
+        "raycast" => dirs::home_dir()?.join(".config").join("raycast"),
+        // LibreChat and Open WebUI are usually deployed with Docker, not installed locally.
+        _ => return None,
+    };
+    dir.exists().then_some(dir)
+}
+
+/// Reports `configured` when the app's config file already points at `base_url`.
+pub fn health(app: &str, base_url: &str) -> &'static str {
+    let configured = config_file(app)
+        .ok()
+        .flatten()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .is_some_and(|raw| raw.contains(base_url));
+    if configured {
+        "configured"
+    } else if detect(app).is_some() {
+        "detected"
+    } else if matches!(app, "librechat" | "open_webui") {
+        "manual"
+    } else {
+        "not_detected"
+    }
+}
+
+fn config_file(app: &str) -> Result<Option<PathBuf>> {
+    Ok(match app {
+        "continue" => {
+            let dir = home()?.join(".continue");
+            let legacy = dir.join("config.json");
+            Some(if legacy.exists() {
+                legacy
+            } else {
+                dir.join("config.yaml")
+            })
+        }
+        "raycast" => Some(
+            home()?
+                .join(".config")
+                .join("raycast")
+                .join("ai")
+                .join("providers.yaml"),
+        ),
+        _ => None,
+    })
+}
+
+pub fn render(app: &str, settings: &ClientSettings) -> Result<AppConfig> {
+    let path = config_file(app)?;
+    let existing = path
+        .as_ref()
+        .filter(|p| p.exists())
+        .map(|p| fs::read_to_string(p).with_context(|| format!("failed to read {}", p.display())))
+        .transpose()?;
+    let (snippet, manual_reason) = match app {
+        "cursor" => (cursor_snippet(settings), None),
+        "librechat" => (librechat_snippet(settings), None),
+        "open_webui" => (open_webui_snippet(settings), None),
+        "continue" => match existing {
+            Some(raw) if path.as_ref().is_some_and(|p| p.ends_with("config.json")) => {
+                (merge_continue_json(&raw, settings)?, None)
+            }
+            Some(_) => (
+                continue_yaml_entry(settings),
+                Some("add this entry under `models:` in the existing config.yaml".to_string()),
+            ),
+            None => (format!("models:\n{}", continue_yaml_entry(settings)), None),
+        },
+        "raycast" => match existing {
+            Some(_) => (
+                raycast_yaml_entry(settings),
+                Some(
+                    "add this entry under `providers:` in the existing providers.yaml".to_string(),
+                ),
+            ),
+            None => (
+                format!("providers:\n{}", raycast_yaml_entry(settings)),
+                None,
+            ),
+        },
+        other => bail!(
+            "unknown app '{other}', expected one of: {}",
+            APPS.join(", ")
+        ),
+    };
+    Ok(AppConfig {
+        path,
+        snippet,
+        manual_reason,
+    })
+}
+
+/// Writes the rendered config, keeping a `.bak` copy of a file that is updated in place.
+pub fn write(config: &AppConfig) -> Result<PathBuf> {
+    let Some(path) = config.path.as_ref() else {
+        bail!("this app has no config file Cortex can write; paste the settings above instead");
+    };
+    if let Some(reason) = config.manual_reason.as_ref() {
+        bail!("{} is not edited automatically; {reason}", path.display());
+    }
+    if path.exists() {
+        let backup = path.with_extension(format!(
+            "{}.bak",
+            path.extension().and_then(|e| e.to_str()).unwrap_or("cfg")
+        ));
+        fs::copy(path, &backup).with_context(|| format!("failed to back up {}", path.display()))?;
+    } else if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &config.snippet)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path.clone())
+}
+
+/// Swaps `old_key` for `new_key` in the app's config file when Cortex configured it, keeping a
+/// `.bak` copy. Returns the file that was updated.
+pub fn replace_key(app: &str, old_key: &str, new_key: &str) -> Result<Option<PathBuf>> {
+    let Some(path) = config_file(app)?.filter(|p| p.exists()) else {
+        return Ok(None);
+    };
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    if !raw.contains(old_key) {
+        return Ok(None);
+    }
+    write(&AppConfig {
+        path: Some(path),
+        snippet: raw.replace(old_key, new_key),
+        manual_reason: None,
+    })
+    .map(Some)
+}
+
+fn yaml_str(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn cursor_snippet(s: &ClientSettings) -> String {
+    format!(
+        "Cursor > Settings > Models:\n\
+         \x20 OpenAI API Key: {}\n\
+         \x20 Override OpenAI Base URL: {}\n\
+         \x20 Add model: {}\n",
+        s.api_key, s.base_url, s.model
+    )
+}
+
+fn librechat_snippet(s: &ClientSettings) -> String {
+    format!(
+        "# librechat.yaml (use http://host.docker.internal:<port>/v1 when LibreChat runs in Docker)\n\
+         endpoints:\n\
+         \x20 custom:\n\
+         \x20   - name: {name}\n\
+         \x20     apiKey: {key}\n\
+         \x20     baseURL: {url}\n\
+         \x20     models:\n\
+         \x20       default: [{model}]\n\
+         \x20       fetch: false\n\
+         \x20     titleConvo: true\n\
+         \x20     modelDisplayLabel: {name}\n",
+        name = yaml_str(ENTRY_NAME),
+        key = yaml_str(&s.api_key),
+        url = yaml_str(&s.base_url),
+        model = yaml_str(&s.model),
+    )
+}
+
+fn open_webui_snippet(s: &ClientSettings) -> String {
+    format!(
+        "# Open WebUI environment (or Admin Settings > Connections > OpenAI API)\n\
+         OPENAI_API_BASE_URL={}\n\
+         OPENAI_API_KEY={}\n\
+         # default model: {}\n",
+        s.base_url, s.api_key, s.model
+    )
+}
+
+fn continue_yaml_entry(s: &ClientSettings) -> String {
+    format!(
+        "  - name: {}\n    provider: openai\n    model: {}\n    apiBase: {}\n    apiKey: {}\n",
+        yaml_str(ENTRY_NAME),
+        yaml_str(&s.model),
+        yaml_str(&s.base_url),
+        yaml_str(&s.api_key),
+    )
+}
+
+fn raycast_yaml_entry(s: &ClientSettings) -> String {
+    format!(
+        "  - id: cortex\n    name: {}\n    base_url: {}\n    api_keys:\n      openai: {}\n    models:\n      - id: {}\n        name: {}\n        context: 128000\n",
+        yaml_str(ENTRY_NAME),
+        yaml_str(&s.base_url),
+        yaml_str(&s.api_key),
+        yaml_str(&s.model),
+        yaml_str(&s.model),
+    )
+}
+
+/// Replaces any previous Cortex model in a legacy Continue `config.json`, keeping the rest.
+fn merge_continue_json(raw: &str, s: &ClientSettings) -> Result<String> {
+    let mut root: JsonValue = if raw.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(raw).context("invalid Continue config.json")?
+    };
+    let obj = root
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Continue config.json must be a JSON object"))?;
+    let models = obj.entry("models").or_insert_with(|| json!([]));
+    let models = models
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("Continue config.json `models` must be an array"))?;
+    models.retain(|m| m.get("title").and_then(JsonValue::as_str) != Some(ENTRY_NAME));
+    models.push(json!({
+        "title": ENTRY_NAME,
+        "provider": "openai",
+        "model": s.model,
+        "apiBase": s.base_url,
+        "apiKey": s.api_key,
+    }));
+    Ok(serde_json::to_string_pretty(&root)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_client_configs() {
+        let settings = ClientSettings {
+            base_url: "http://127.0.0.1:8080/v1".to_string(),
+            api_key: "ctx_key".to_string(),
+            model: "gpt-4o-mini".to_string(),
+        };
+        let existing = r#"{"models":[{"title":"Cortex","model":"old"},{"title":"Local"}],"tabAutocompleteModel":{}}"#;
+        let merged: JsonValue =
+            serde_json::from_str(&merge_continue_json(existing, &settings).unwrap()).unwrap();
+        let models = merged["models"].as_array().unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0]["title"], "Local");
+        assert_eq!(models[1]["apiBase"], "http://127.0.0.1:8080/v1");
+        assert!(merged.get("tabAutocompleteModel").is_some());
+        assert!(merge_continue_json("[]", &settings).is_err());
+
+        assert!(continue_yaml_entry(&settings).contains("    apiKey: \"ctx_key\"\n"));
+        assert!(raycast_yaml_entry(&settings).contains("      openai: \"ctx_key\"\n"));
+        assert!(librechat_snippet(&settings).contains("       default: [\"gpt-4o-mini\"]\n"));
+        assert!(
+            open_webui_snippet(&settings)
+                .contains("OPENAI_API_BASE_URL=http://127.0.0.1:8080/v1\n")
+        );
+        assert!(
+            cursor_snippet(&settings)
+                .contains("  Override OpenAI Base URL: http://127.0.0.1:8080/v1\n")
+        );
+        assert!(render("zed", &settings).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde_json::{Map, Value as JsonValue};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, fmt as tracing_fmt};
+
+/// Set to `json` to write one JSON object per log line; `cortex up` sets it for its services.
+pub const LOG_FORMAT_ENV: &str = "CORTEX_LOG_FORMAT";
+
+/// Target of the one-line-per-request events, filtered apart from internal logging.
+pub const REQUEST_TARGET: &str = "cortex_app::request";
+
+const LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+/// A JSON log file next to stdout, rotated by size.
+#[derive(Debug, Clone)]
+pub struct FileLog {
+    pub path: PathBuf,
+    pub max_bytes: u64,
+    /// Rotated files kept as `<path>.1` (newest) to `<path>.<keep>`.
+    pub keep: usize,
+    /// Filter for everything but request events, e.g. `info` or `info,cortex_app=debug`.
+    pub level: String,
+    /// Level for request events; `off` leaves them out of the file.
+    pub request_level: String,
+}
+
+pub fn init(file: Option<FileLog>) -> Result<()> {
+    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info,cortex_app=debug".to_string());
+    let json = std::env::var(LOG_FORMAT_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("json"));
+    let stdout = if json {
+        tracing_fmt::layer().event_format(JsonFormat).boxed()
+    } else {
+        tracing_fmt::layer().with_target(false).compact().boxed()
+    };
+    let file = file
+        .map(|file| -> Result<_> {
+            let filter = EnvFilter::try_new(format!(
+                "{},{REQUEST_TARGET}={}",
+                file.level, file.request_level
+            ))
+            .context("invalid log file level")?;
+            let writer = RotatingFile::open(&file.path, file.max_bytes, file.keep)?;
+            Ok(tracing_fmt::layer()
+                .event_format(JsonFormat)
+                .with_writer(Arc::new(writer))
+                .with_filter(filter))
+        })
+        .transpose()?;
+    tracing_subscriber::registry()
+        .with(stdout.with_filter(EnvFilter::new(filter)))
+        .with(file)
+        .init();
+    Ok(())
+}
+
+/// Appends to `path` and, once a write would take it past `max_bytes`, shifts it to `path.1`
+/// (and older files up by one, dropping the last) before starting a fresh file.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    current: Mutex<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_bytes: u64, keep: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = append(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            keep,
+            current: Mutex::new((file, len)),
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&self) -> io::Result<File> {
+        if self.keep == 0 {
+            return File::create(&self.path);
+        }
+        let _ = fs::remove_file(self.rotated(self.keep));
+        for n in (1..self.keep).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        append(&self.path)
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// Each event is formatted first and written in one call, so lines are never split across files.
+impl Write for &RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        if current.1 > 0 && current.1 + buf.len() as u64 > self.max_bytes {
+            *current = (self.rotate()?, 0);
+        }
+        current.0.write_all(buf)?;
+        current.1 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        current.0.flush()
+    }
+}
+
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let meta = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "ts".to_string(),
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Millis, true)
+                .into(),
+        );
+        line.insert(
+            "level".to_string(),
+            meta.level().to_string().to_ascii_lowercase().into(),
+        );
+        line.insert("target".to_string(), meta.target().into());
+        event.record(&mut JsonFields(&mut line));
+        writeln!(writer, "{}", JsonValue::Object(line))
+    }
+}
+
+struct JsonFields<'a>(&'a mut Map<String, JsonValue>);
+
+impl Visit for JsonFields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// Which lines `cortex logs` shows. Lines that are not JSON (e.g. an external sidecar's
+/// stdout) count as `info` and have no timestamp, so `--since` hides them.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub min_level: Option<usize>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl LogFilter {
+    pub fn parse(level: Option<&str>, since: Option<&str>, now: DateTime<Utc>) -> Result<Self> {
+        let min_level = level
+            .map(|level| {
+                level_rank(level).ok_or_else(|| {
+                    anyhow!("unknown level '{level}', expected trace|debug|info|warn|error")
+                })
+            })
+            .transpose()?;
+        let since = since.map(|value| parse_since(value, now)).transpose()?;
+        Ok(Self { min_level, since })
+    }
+}
+
+fn level_rank(level: &str) -> Option<usize> {
+    let level = level.trim().to_ascii_lowercase();
+    let level = if level == "warning" { "warn" } else { &level };
+    LEVELS.iter().position(|l| *l == level)
+}
+
+/// Accepts a relative age (`30s`, `10m`, `2h`, `1d`) or an RFC 3339 timestamp.
+fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    let value = value.trim();
+    let Some((split, _)) = value.char_indices().last() else {
+        bail!("--since must not be empty");
+    };
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("invalid --since '{value}', expected e.g. 10m or an RFC 3339 time"))?;
+    let age = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        _ => bail!("invalid --since unit in '{value}', expected s|m|h|d"),
+    };
+    Ok(now - age)
+}
+
+/// Parses one log line of `service`, applies `filter`, and renders it as JSON (tagged with the
+/// service) or as a readable text line.
+pub fn render_line(raw: &str, service: &str, filter: &LogFilter, json: bool) -> Option<String> {
+    let raw = raw.trim_end();
+    if raw.is_empty() {
+        return None;
+    }
+    let mut record = match serde_json::from_str::<JsonValue>(raw) {
+        Ok(JsonValue::Object(record)) => record,
+        _ => {
+            let mut record = Map::new();
+            record.insert("message".to_string(), raw.into());
+            record
+        }
+    };
+    let level = record
+        .get("level")
+        .and_then(JsonValue::as_str)
+        .unwrap_or("info")
+        .to_ascii_lowercase();
+    if let Some(min) = filter.min_level
+        && level_rank(&level).unwrap_or(0) < min
+    {
+        return None;
+    }
+    let ts = record
+        .get("ts")
+        .and_then(JsonValue::as_str)
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+    if let Some(since) = filter.since
+        && ts.is_none_or(|ts| ts < since)
+    {
+        return None;
+    }
+    if json {
+        record
+            .entry("service")
+            .or_insert_with(|| service.to_string().into());
+        return Some(JsonValue::Object(record).to_string());
+    }
+    let message = record
+        .remove("message")
+        .and_then(|m| m.as_str().map(ToOwned::to_owned))
+        .unwrap_or_default();
+    let mut out = match record.get("ts").and_then(JsonValue::as_str) {
+        Some(ts) => format!("{ts} {:<5} {message}", level.to_ascii_uppercase()),
+        None => message,
+    };
+    for (key, value) in &record {
+        if matches!(key.as_str(), "ts" | "level" | "target") {
+            continue;
+        }
+        match value.as_str() {
+            Some(text) => out.push_str(&format!(" {key}={text}")),
+            None => out.push_str(&format!(" {key}={value}")),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_level_and_age_and_renders_text() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let filter = LogFilter::parse(Some("warn"), Some("10m"), now).unwrap();
+        let warn = r#"{"ts":"2026-01-01T11:55:00.000Z","level":"warn","target":"cortex_app","message":"slow planner","ms":812}"#;
+        let old = r#"{"ts":"2026-01-01T11:00:00.000Z","level":"error","message":"stale"}"#;
+        let info = r#"{"ts":"2026-01-01T11:59:00.000Z","level":"info","message":"ok"}"#;
+
+        assert_eq!(
+            render_line(warn, "proxy", &filter, false).as_deref(),
+            Some("2026-01-01T11:55:00.000Z WARN  slow planner ms=812")
+        );
+        assert!(render_line(old, "proxy", &filter, false).is_none());
+        assert!(render_line(info, "proxy", &filter, false).is_none());
+        assert!(render_line("plain sidecar output", "rmvm", &filter, false).is_none());
+        assert_eq!(
+            render_line("plain sidecar output", "rmvm", &LogFilter::default(), true).as_deref(),
+            Some(r#"{"message":"plain sidecar output","service":"rmvm"}"#)
+        );
+        assert!(LogFilter::parse(Some("loud"), None, now).is_err());
+        assert!(LogFilter::parse(None, Some("10y"), now).is_err());
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_the_newest_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("proxy.jsonl");
+        let file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["aaaaaaa\n", "bbbbbbb\n", "ccccccc\n", "ddddddd\n"] {
+            (&file).write_all(line.as_bytes()).unwrap();
+        }
+        let read = |p: &Path| fs::read_to_string(p).unwrap();
+        assert_eq!(read(&path), "ddddddd\n");
+        assert_eq!(read(&file.rotated(1)), "ccccccc\n");
+        assert_eq!(read(&file.rotated(2)), "bbbbbbb\n");
+        assert!(!file.rotated(3).exists());
+    }
+}
@@ -1,17 +1,37 @@
+mod audit;
+mod auth_cache;
+mod brain_api;
+mod budget;
+mod bundle;
+mod cache;
 mod cli;
+mod completions;
+mod embeddings;
+mod endpoints;
+mod hydrate;
+mod idempotency;
+mod integrations;
+mod logging;
+mod maintain;
+mod mock_rmvm;
 mod product;
+mod proof;
 mod proxy;
+mod redact;
+mod render;
+mod replay;
+mod report;
+mod rules;
+mod service;
+mod session;
+mod shell_env;
+mod sync;
 mod types;
+mod update;
+mod usage;
+mod webhooks;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info,cortex_app=debug".to_string()),
-        )
-        .with_target(false)
-        .compact()
-        .init();
-
     cli::run().await
 }
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use brain_store::{BrainStore, ConsolidateOptions};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use tracing::{info, warn};
+
+use crate::hydrate::{compact_flushed, flush_kernel};
+use crate::product::brain_store;
+
+pub const JOBS: [&str; 5] = ["backup", "compaction", "expiry", "consolidation", "sync"];
+/// Backups kept per brain; older ones are deleted after each new backup.
+const BACKUPS_KEPT: usize = 7;
+const EPISODIC_RUN: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JobSchedule {
+    pub enabled: bool,
+    pub every_secs: u64,
+}
+
+pub fn default_schedules() -> BTreeMap<String, JobSchedule> {
+    [
+        ("backup", 86_400),
+        ("compaction", 86_400),
+        ("expiry", 3_600),
+        ("consolidation", 86_400),
+        ("sync", 900),
+    ]
+    .into_iter()
+    .map(|(job, every_secs)| {
+        (
+            job.to_string(),
+            JobSchedule {
+                enabled: true,
+                every_secs,
+            },
+        )
+    })
+    .collect()
+}
+
+/// Outcome of a job's latest run, shown by `cortex status` and `cortex maintain --status`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub last_run_at: Option<String>,
+    pub summary: Option<String>,
+    pub last_error: Option<String>,
+    pub runs: u64,
+    pub failures: u64,
+}
+
+/// Enabled jobs whose last run is at least one interval old. A job that never ran is due.
+pub fn due_jobs(
+    schedules: &BTreeMap<String, JobSchedule>,
+    status: &BTreeMap<String, JobStatus>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    schedules
+        .iter()
+        .filter(|(job, schedule)| {
+            schedule.enabled
+                && status
+                    .get(*job)
+                    .and_then(|s| s.last_run_at.as_deref())
+                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                    .is_none_or(|last| {
+                        now.signed_duration_since(last).num_seconds() >= schedule.every_secs as i64
+                    })
+        })
+        .map(|(job, _)| job.clone())
+        .collect()
+}
+
+/// Runs maintenance jobs against every brain in the store. `sync` only covers `active_brain`,
+/// since that is the brain the kernel at `endpoint` serves.
+pub struct Maintenance {
+    pub backups_dir: PathBuf,
+    pub endpoint: Option<String>,
+    pub active_brain: Option<String>,
+}
+
+impl Maintenance {
+    /// Runs `jobs` in order and records each outcome in the status file at `path`.
+    pub async fn run(&self, jobs: &[String], path: &Path) -> BTreeMap<String, JobStatus> {
+        let mut statuses = read_status_file(path).unwrap_or_default();
+        for job in jobs {
+            let result = self.run_job(job).await;
+            let status = statuses.entry(job.clone()).or_default();
+            status.last_run_at = Some(Utc::now().to_rfc3339());
+            status.runs += 1;
+            match result {
+                Ok(summary) => {
+                    info!("maintenance job {job}: {summary}");
+                    status.summary = Some(summary);
+                    status.last_error = None;
+                }
+                Err(err) => {
+                    warn!("maintenance job {job} failed: {err:#}");
+                    status.summary = None;
+                    status.last_error = Some(format!("{err:#}"));
+                    status.failures += 1;
+                }
+            }
+            if let Err(err) = write_status_file(path, &statuses) {
+                warn!(
+                    "failed to write maintenance status {}: {err}",
+                    path.display()
+                );
+            }
+        }
+        statuses
+    }
+
+    /// One job over its brains. Each brain the job changed gets a `maintenance.<job>` audit
+    /// entry; a failing brain does not stop the others.
+    async fn run_job(&self, job: &str) -> Result<String> {
+        let store = brain_store()?;
+        let brains = if job == "sync" {
+            if self.endpoint.is_none() {
+                return Ok("skipped: RMVM not running".to_string());
+            }
+            let Some(brain) = self.active_brain.as_deref() else {
+                return Ok("skipped: no active brain".to_string());
+            };
+            vec![store.resolve_brain(brain)?.brain_id]
+        } else {
+            store
+                .list_brains()?
+                .into_iter()
+                .map(|b| b.brain_id)
+                .collect()
+        };
+        let mut changed = 0;
+        let mut errors = Vec::new();
+        for brain_id in &brains {
+            let details = match self.run_for_brain(job, &store, brain_id).await {
+                Ok(Some(details)) => details,
+                Ok(None) => continue,
+                Err(err) => {
+                    errors.push(format!("{brain_id}: {err:#}"));
+                    continue;
+                }
+            };
+            changed += 1;
+            let action = format!("maintenance.{job}");
+            if let Err(err) = store.record_audit(brain_id, "maintenance", &action, details) {
+                errors.push(format!("{brain_id}: {err:#}"));
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("; "));
+        }
+        Ok(format!("{} brain(s), {changed} changed", brains.len()))
+    }
+
+    /// Audit details when the job changed `brain_id`, `None` when there was nothing to do.
+    async fn run_for_brain(
+        &self,
+        job: &str,
+        store: &BrainStore,
+        brain_id: &str,
+    ) -> Result<Option<JsonValue>> {
+        Ok(match job {
+            "backup" => {
+                let dir = self.backups_dir.join(brain_id);
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("failed to create {}", dir.display()))?;
+                let file = dir.join(format!("{}.cbrain", Utc::now().format("%Y%m%dT%H%M%SZ")));
+                store.export_brain(brain_id, &file)?;
+                let pruned = prune_backups(&dir, BACKUPS_KEPT)?;
+                Some(json!({"file": file.display().to_string(), "pruned": pruned}))
+            }
+            "compaction" => {
+                let removed = store.compact_ledger(brain_id, compact_flushed)?;
+                (removed > 0).then(|| json!({"ledger_removed": removed}))
+            }
+            "expiry" => {
+                let now = Utc::now();
+                let attachments = store.sweep_expired_attachments(brain_id, now)?;
+                let memories = store.sweep_retention(brain_id, now)?;
+                (attachments > 0 || memories > 0).then(
+                    || json!({"attachments_expired": attachments, "memories_expired": memories}),
+                )
+            }
+            "consolidation" => {
+                let report = store.consolidate(
+                    brain_id,
+                    ConsolidateOptions {
+                        episodic_run: EPISODIC_RUN,
+                        dry_run: false,
+                    },
+                )?;
+                (report.live_after != report.live_before).then(|| json!(report))
+            }
+            "sync" => {
+                let Some(endpoint) = self.endpoint.as_deref() else {
+                    return Ok(None);
+                };
+                let report = flush_kernel(endpoint, store, brain_id).await?;
+                (report.added > 0 || report.conflicts > 0).then(|| json!(report))
+            }
+            other => bail!("unknown maintenance job '{other}'"),
+        })
+    }
+}
+
+/// Deletes all but the newest `keep` backups in `dir`. Names sort by time.
+fn prune_backups(dir: &Path, keep: usize) -> Result<usize> {
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cbrain"))
+        .collect::<Vec<_>>();
+    files.sort();
+    let excess = files.len().saturating_sub(keep);
+    for path in &files[..excess] {
+        fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+    Ok(excess)
+}
+
+fn write_status_file(path: &Path, statuses: &BTreeMap<String, JobStatus>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(statuses)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read_status_file(path: &Path) -> Result<BTreeMap<String, JobStatus>> {
+    let raw = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&raw)
+        .with_context(|| format!("invalid maintenance status {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedules_due_jobs_and_prunes_old_backups() {
+        let now = DateTime::parse_from_rfc3339("2026-01-02T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut schedules = default_schedules();
+        schedules.get_mut("sync").unwrap().enabled = false;
+        let ran_at = |ts: &str| JobStatus {
+            last_run_at: Some(ts.to_string()),
+            ..Default::default()
+        };
+        let status = BTreeMap::from([
+            ("backup".to_string(), ran_at("2026-01-01T12:00:00Z")),
+            ("compaction".to_string(), ran_at("2026-01-01T00:00:00Z")),
+            ("expiry".to_string(), ran_at("2026-01-01T23:30:00Z")),
+        ]);
+        assert_eq!(
+            due_jobs(&schedules, &status, now),
+            vec!["compaction".to_string(), "consolidation".to_string()]
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        for name in ["20260101T000000Z", "20260102T000000Z", "20260103T000000Z"] {
+            fs::write(temp.path().join(format!("{name}.cbrain")), "{}").unwrap();
+        }
+        fs::write(temp.path().join("notes.txt"), "").unwrap();
+        assert_eq!(prune_backups(temp.path(), 2).unwrap(), 1);
+        assert!(!temp.path().join("20260101T000000Z.cbrain").exists());
+        assert!(temp.path().join("20260103T000000Z.cbrain").exists());
+        assert!(temp.path().join("notes.txt").exists());
+
+        let path = temp.path().join("maintenance.json");
+        write_status_file(&path, &status).unwrap();
+        assert_eq!(read_status_file(&path).unwrap().len(), 3);
+    }
+}
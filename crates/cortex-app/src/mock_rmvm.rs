@@ -0,0 +1,499 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use rmvm_grpc::{
+    AppendEventRequest, AppendEventResponse, ForgetRequest, ForgetResponse, GetManifestRequest,
+    GetManifestResponse, RmvmExecutor, RmvmExecutorServer,
+};
+use rmvm_proto::cortex::rmvm::v3_1::value::V;
+use rmvm_proto::{
+    AssertionMerkleProof, AssertionType, CanonicalCitation, ErrorCode, ExecuteRequest,
+    ExecuteResponse, ExecutionError, ExecutionStatus, HandleAvailability, HandleMeta, HandleRef,
+    PlanBudget, PublicManifest, RenderedOutput, Scope, StallInfo, TrustTier, Value,
+    VerifiedAssertion,
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+/// A fake kernel for `cortex rmvm mock`: the manifest lists `handles`, and each Execute call
+/// answers with the next entry of `responses`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockFixture {
+    #[serde(default = "default_handles")]
+    pub handles: Vec<MockHandle>,
+    #[serde(default = "default_responses")]
+    pub responses: Vec<MockResponse>,
+    /// What happens after the last response: start over, or keep answering with it.
+    #[serde(default)]
+    pub after_last: AfterLast,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterLast {
+    #[default]
+    Cycle,
+    Repeat,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockHandle {
+    #[serde(rename = "ref")]
+    pub handle_ref: String,
+    pub subject: String,
+    pub predicate: String,
+    #[serde(default = "default_type_id")]
+    pub type_id: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub availability: MockAvailability,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockAvailability {
+    #[default]
+    Ready,
+    ArchivalPending,
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockStatus {
+    Ok,
+    Rejected,
+    RangeExceeded,
+    AuthDenied,
+    Stall,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockResponse {
+    pub status: MockStatus,
+    #[serde(default)]
+    pub assertions: Vec<MockAssertion>,
+    #[serde(default)]
+    pub verified: Vec<String>,
+    #[serde(default)]
+    pub narrative: Vec<String>,
+    /// `code` is an RMVM error code name, e.g. `TYPE_MISMATCH`.
+    #[serde(default)]
+    pub error: Option<MockError>,
+    /// Handle a `stall` response waits on; defaults to the first handle.
+    #[serde(default)]
+    pub stall_handle: Option<String>,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockAssertion {
+    #[serde(rename = "type", default = "default_assertion_type")]
+    pub assertion_type: String,
+    pub fields: BTreeMap<String, JsonValue>,
+    #[serde(default)]
+    pub citations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockError {
+    pub code: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+fn default_type_id() -> String {
+    "normative.preference".to_string()
+}
+
+fn default_assertion_type() -> String {
+    AssertionType::AssertUserPreference
+        .as_str_name()
+        .to_string()
+}
+
+fn default_handles() -> Vec<MockHandle> {
+    vec![MockHandle {
+        handle_ref: "H1".to_string(),
+        subject: "user:local".to_string(),
+        predicate: "prefers_beverage".to_string(),
+        type_id: default_type_id(),
+        summary: "prefers_beverage=tea".to_string(),
+        availability: MockAvailability::Ready,
+    }]
+}
+
+fn default_responses() -> Vec<MockResponse> {
+    vec![MockResponse {
+        status: MockStatus::Ok,
+        assertions: vec![MockAssertion {
+            assertion_type: default_assertion_type(),
+            fields: BTreeMap::from([
+                ("subject".to_string(), JsonValue::from("user:local")),
+                ("predicate".to_string(), JsonValue::from("prefers_beverage")),
+                ("value".to_string(), JsonValue::from("tea")),
+            ]),
+            citations: Vec::new(),
+        }],
+        verified: vec!["Verified: user prefers tea.".to_string()],
+        narrative: Vec::new(),
+        error: None,
+        stall_handle: None,
+        delay_ms: 0,
+    }]
+}
+
+impl Default for MockFixture {
+    fn default() -> Self {
+        Self {
+            handles: default_handles(),
+            responses: default_responses(),
+            after_last: AfterLast::Cycle,
+        }
+    }
+}
+
+impl MockFixture {
+    /// Reads a YAML (or JSON) fixture and checks its names resolve.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let fixture = Self::from_yaml(&raw)
+            .with_context(|| format!("invalid mock fixture {}", path.display()))?;
+        Ok(fixture)
+    }
+
+    pub fn from_yaml(raw: &str) -> Result<Self> {
+        let fixture: Self = serde_yaml::from_str(raw)?;
+        fixture.validate()?;
+        Ok(fixture)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.responses.is_empty() {
+            bail!("responses must not be empty");
+        }
+        for (i, response) in self.responses.iter().enumerate() {
+            if let Some(error) = &response.error
+                && ErrorCode::from_str_name(&error.code).is_none()
+            {
+                bail!("responses[{i}]: unknown error code '{}'", error.code);
+            }
+            for assertion in &response.assertions {
+                if AssertionType::from_str_name(&assertion.assertion_type).is_none() {
+                    bail!(
+                        "responses[{i}]: unknown assertion type '{}'",
+                        assertion.assertion_type
+                    );
+                }
+            }
+            if let Some(handle) = &response.stall_handle
+                && !self.handles.iter().any(|h| &h.handle_ref == handle)
+            {
+                bail!("responses[{i}]: stall_handle '{handle}' is not in handles");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn json_to_rmvm_value(value: &JsonValue) -> Value {
+    let v = match value {
+        JsonValue::String(s) => V::S(s.clone()),
+        JsonValue::Bool(b) => V::B(*b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => V::I64(i),
+            None => V::F64(n.as_f64().unwrap_or_default()),
+        },
+        other => V::S(other.to_string()),
+    };
+    Value { v: Some(v) }
+}
+
+#[derive(Debug)]
+pub struct MockRmvm {
+    fixture: MockFixture,
+    calls: AtomicUsize,
+}
+
+impl MockRmvm {
+    pub fn new(fixture: MockFixture) -> Self {
+        Self {
+            fixture,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn next_response(&self) -> (usize, &MockResponse) {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        let len = self.fixture.responses.len();
+        let index = match self.fixture.after_last {
+            AfterLast::Cycle => call % len,
+            AfterLast::Repeat => call.min(len - 1),
+        };
+        (call, &self.fixture.responses[index])
+    }
+
+    fn manifest(&self, request_id: String) -> PublicManifest {
+        let handles = self
+            .fixture
+            .handles
+            .iter()
+            .map(|h| HandleRef {
+                r#ref: h.handle_ref.clone(),
+                type_id: h.type_id.clone(),
+                availability: match h.availability {
+                    MockAvailability::Ready => HandleAvailability::Ready,
+                    MockAvailability::ArchivalPending => HandleAvailability::ArchivalPending,
+                    MockAvailability::Offline => HandleAvailability::Offline,
+                } as i32,
+                meta: Some(HandleMeta {
+                    subject: h.subject.clone(),
+                    predicate_label: h.predicate.clone(),
+                    trust_tier: TrustTier::Tier3Confirmed as i32,
+                    taint: Vec::new(),
+                    temporal: None,
+                    scope: Scope::Global as i32,
+                }),
+                signature_summary: h.summary.clone(),
+                conflict_group_id: String::new(),
+            })
+            .collect();
+        PublicManifest {
+            request_id,
+            handles,
+            selectors: Vec::new(),
+            context: Vec::new(),
+            budget: Some(PlanBudget {
+                max_ops: 8,
+                max_join_depth: 2,
+                max_fanout: 8,
+                max_total_cost: 8.0,
+            }),
+        }
+    }
+
+    fn execute_response(&self, call: usize, response: &MockResponse) -> ExecuteResponse {
+        let status = match response.status {
+            MockStatus::Ok => ExecutionStatus::Ok,
+            MockStatus::Rejected => ExecutionStatus::Rejected,
+            MockStatus::RangeExceeded => ExecutionStatus::RangeExceeded,
+            MockStatus::AuthDenied => ExecutionStatus::AuthDenied,
+            MockStatus::Stall => ExecutionStatus::Stall,
+        };
+        let assertions = response
+            .assertions
+            .iter()
+            .map(|a| VerifiedAssertion {
+                assertion_type: AssertionType::from_str_name(&a.assertion_type)
+                    .unwrap_or(AssertionType::Unspecified) as i32,
+                fields: a
+                    .fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), json_to_rmvm_value(v)))
+                    .collect(),
+                citations: a
+                    .citations
+                    .iter()
+                    .map(|c| CanonicalCitation {
+                        anchor_digest: c.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        let stall = (response.status == MockStatus::Stall).then(|| {
+            let handle_ref = response
+                .stall_handle
+                .clone()
+                .or_else(|| self.fixture.handles.first().map(|h| h.handle_ref.clone()))
+                .unwrap_or_default();
+            StallInfo {
+                handle_ref,
+                availability: HandleAvailability::ArchivalPending as i32,
+                estimated_ready_at: None,
+                retrieval_ticket: format!("mock-ticket-{call}"),
+            }
+        });
+        ExecuteResponse {
+            status: status as i32,
+            assertions,
+            proof: Some(AssertionMerkleProof {
+                semantic_root: format!("mock-semantic-root-{call}"),
+                trace_root: format!("mock-trace-root-{call}"),
+                inclusion: Vec::new(),
+            }),
+            rendered: Some(RenderedOutput {
+                verified_blocks: response.verified.clone(),
+                narrative_blocks: response.narrative.clone(),
+            }),
+            stall,
+            error: response.error.as_ref().map(|e| ExecutionError {
+                code: ErrorCode::from_str_name(&e.code).unwrap_or(ErrorCode::Unspecified) as i32,
+                message: e.message.clone(),
+                hints: Vec::new(),
+            }),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl RmvmExecutor for MockRmvm {
+    async fn append_event(
+        &self,
+        request: Request<AppendEventRequest>,
+    ) -> Result<Response<AppendEventResponse>, Status> {
+        let req = request.into_inner();
+        Ok(Response::new(AppendEventResponse {
+            event_id: format!("mock-evt-{}", req.request_id),
+            handle_refs: self
+                .fixture
+                .handles
+                .iter()
+                .map(|h| h.handle_ref.clone())
+                .collect(),
+        }))
+    }
+
+    async fn get_manifest(
+        &self,
+        request: Request<GetManifestRequest>,
+    ) -> Result<Response<GetManifestResponse>, Status> {
+        let manifest = self.manifest(request.into_inner().request_id);
+        Ok(Response::new(GetManifestResponse {
+            manifest: Some(manifest),
+        }))
+    }
+
+    async fn execute(
+        &self,
+        _request: Request<ExecuteRequest>,
+    ) -> Result<Response<ExecuteResponse>, Status> {
+        let (call, response) = self.next_response();
+        if response.delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(response.delay_ms)).await;
+        }
+        Ok(Response::new(self.execute_response(call, response)))
+    }
+
+    async fn forget(
+        &self,
+        _request: Request<ForgetRequest>,
+    ) -> Result<Response<ForgetResponse>, Status> {
+        Ok(Response::new(ForgetResponse {
+            status: ExecutionStatus::Ok as i32,
+            assertions: Vec::new(),
+            rendered: Some(RenderedOutput {
+                verified_blocks: Vec::new(),
+                narrative_blocks: Vec::new(),
+            }),
+            error: None,
+        }))
+    }
+}
+
+pub async fn serve_mock(addr: SocketAddr, fixture: MockFixture) -> Result<()> {
+    info!(
+        "mock RMVM listening on {addr} ({} handle(s), {} scripted response(s))",
+        fixture.handles.len(),
+        fixture.responses.len()
+    );
+    Server::builder()
+        .add_service(RmvmExecutorServer::new(MockRmvm::new(fixture)))
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("mock RMVM server failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scripts_responses_from_a_yaml_fixture() {
+        let fixture = MockFixture::from_yaml(
+            r#"
+handles:
+  - ref: H7
+    subject: user:local
+    predicate: lives_in
+    availability: archival_pending
+responses:
+  - status: ok
+    verified: ["Verified: user lives in Lisbon."]
+    assertions:
+      - fields: {subject: user:local, predicate: lives_in, value: Lisbon, years: 3}
+  - status: rejected
+    error: {code: TYPE_MISMATCH, message: bad plan}
+  - status: stall
+after_last: repeat
+"#,
+        )
+        .unwrap();
+        let mock = MockRmvm::new(fixture);
+
+        let manifest = mock
+            .get_manifest(Request::new(GetManifestRequest {
+                request_id: "req-1".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .manifest
+            .unwrap();
+        assert_eq!(manifest.request_id, "req-1");
+        assert_eq!(manifest.handles[0].r#ref, "H7");
+        assert_eq!(
+            manifest.handles[0].availability,
+            HandleAvailability::ArchivalPending as i32
+        );
+
+        let mut statuses = Vec::new();
+        for _ in 0..4 {
+            let resp = mock
+                .execute(Request::new(ExecuteRequest::default()))
+                .await
+                .unwrap()
+                .into_inner();
+            statuses.push(ExecutionStatus::try_from(resp.status).unwrap());
+            if resp.status == ExecutionStatus::Ok as i32 {
+                let years = &resp.assertions[0].fields["years"];
+                assert_eq!(years.v, Some(V::I64(3)));
+            }
+            if resp.status == ExecutionStatus::Stall as i32 {
+                assert_eq!(resp.stall.unwrap().handle_ref, "H7");
+            }
+        }
+        assert_eq!(
+            statuses,
+            vec![
+                ExecutionStatus::Ok,
+                ExecutionStatus::Rejected,
+                ExecutionStatus::Stall,
+                ExecutionStatus::Stall
+            ]
+        );
+
+        assert!(MockFixture::from_yaml("responses: []").is_err());
+        assert!(
+            MockFixture::from_yaml("responses:\n  - status: rejected\n    error: {code: NOPE}")
+                .is_err()
+        );
+    }
+}
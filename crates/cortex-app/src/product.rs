@@ -1,13 +1,14 @@
 use std::collections::BTreeMap;
 use std::env;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Write};
-use std::net::{SocketAddr, TcpStream};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
 use std::time::Duration;
 
-use adapter_rmvm::RmvmAdapter;
+use adapter_rmvm::{RmvmAdapter, pipe};
 use anyhow::{Context, Result, anyhow, bail};
 use base64::Engine as _;
 use base64::engine::general_purpose::STANDARD as B64;
@@ -20,26 +21,72 @@ use rand::rngs::OsRng;
 use reqwest::Client;
 use rmvm_grpc::GetManifestRequest;
 use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
 use tokio::time::sleep;
 use uuid::Uuid;
 
-const CONFIG_VERSION: u32 = 1;
+use crate::bundle::{Scrubber, ZipWriter};
+use crate::endpoints::{RmvmEndpoints, TENANT_PREFIX};
+use crate::hydrate::{FlushReport, flush_kernel};
+use crate::integrations::{self, ClientSettings};
+use crate::logging::{LOG_FORMAT_ENV, LogFilter, render_line};
+use crate::maintain::{
+    JOBS, JobSchedule, JobStatus, Maintenance, default_schedules, due_jobs, read_status_file,
+};
+use crate::proxy::{
+    AzureSettings, DEFAULT_AZURE_API_VERSION, LiveSettings, PlannerConfig, PlannerMode,
+    PlannerOptions, bedrock_credentials_from_env,
+};
+use crate::shell_env::{self, EnvShell};
+use crate::sync::{SyncStatus, read_sync_file};
+use crate::usage::{KeyUsage, read_usage_file};
+
+const CONFIG_VERSION: u32 = 2;
+/// Upgrade steps applied to the raw config JSON; entry `i` turns version `i + 1` into `i + 2`.
+const CONFIG_MIGRATIONS: [fn(&mut JsonValue) -> Result<()>; CONFIG_VERSION as usize - 1] =
+    [migrate_config_v1_to_v2];
 const CONFIG_FILE: &str = "config.json";
 const RUNTIME_FILE: &str = "runtime.json";
+const USAGE_FILE: &str = "usage.json";
+const SYNC_FILE: &str = "sync.json";
+const MAINTENANCE_FILE: &str = "maintenance.json";
+const BACKUPS_DIR: &str = "backups";
 const LOG_DIR: &str = "logs";
 const FALLBACK_SECRETS_FILE: &str = "secrets.enc.json";
 const FALLBACK_KEY_FILE: &str = "secrets.key";
 const KEYRING_SERVICE: &str = "cortex-brain";
+const SECRET_STORAGE_ENV: &str = "CORTEX_SECRET_STORAGE";
+const PROFILE_ENV: &str = "CORTEX_PROFILE";
+const DEFAULT_PROFILE: &str = "default";
+const PROFILES_DIR: &str = "profiles";
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+const INSTANCE_ENV: &str = "CORTEX_INSTANCE";
+const INSTANCES_DIR: &str = "instances";
+const DEFAULT_INSTANCE: &str = "default";
+/// `json` switches commands to machine-readable output on stdout, same as `--output json`.
+const OUTPUT_ENV: &str = "CORTEX_OUTPUT";
 
 const DEFAULT_PROXY_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_PROXY_PORT: u16 = 8080;
 const DEFAULT_RMVM_HOST: &str = "127.0.0.1";
 const DEFAULT_RMVM_PORT: u16 = 50051;
 const DEFAULT_BRAIN_SECRET_ENV: &str = "CORTEX_BRAIN_SECRET";
+/// How often the maintenance scheduler checks for due jobs.
+const MAINTENANCE_TICK: Duration = Duration::from_secs(30);
+const CONFIG_WATCH_TICK: Duration = Duration::from_secs(2);
+
+/// The brain store root for this process, resolved once by [`select_home`].
+static BRAINS_HOME: OnceLock<PathBuf> = OnceLock::new();
 
 fn default_memory_mode() -> String {
     "auto".to_string()
 }
 
+fn default_secret_storage() -> String {
+    "auto".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProductConfig {
     pub version: u32,
@@ -54,11 +101,41 @@ pub struct ProductConfig {
     pub providers: BTreeMap<String, ProviderProfile>,
     #[serde(default = "default_memory_mode")]
     pub memory_mode: String,
+    /// `auto` keeps an encrypted file copy next to the keyring; `keyring` stores secrets only
+    /// in the OS keyring.
+    #[serde(default = "default_secret_storage")]
+    pub secret_storage: String,
     #[serde(default = "default_connectors")]
     pub connectors: BTreeMap<String, ConnectorProfile>,
+    /// Request `model` name -> provider profile used to plan that request.
+    #[serde(default)]
+    pub model_routes: BTreeMap<String, String>,
+    /// Brain id or `tenant:<id>` -> RMVM endpoint serving it; other brains use `rmvm`.
+    #[serde(default)]
+    pub brain_endpoints: BTreeMap<String, String>,
+    /// Brain store root for this profile; `--home` and `CORTEX_HOME` override it, and unset
+    /// uses `~/.cortex`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brains_home: Option<String>,
+    /// Named instances started with `cortex up --instance <name>` next to the default one.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instances: BTreeMap<String, InstanceSettings>,
+    /// Recurring jobs run by `cortex maintain --daemon` and the foreground supervisor.
+    #[serde(default = "default_schedules")]
+    pub maintenance: BTreeMap<String, JobSchedule>,
 }
 
+/// Ports and brain binding of one instance; its runtime state and logs live in
+/// `<state dir>/instances/<name>`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceSettings {
+    pub proxy_addr: String,
+    pub rmvm_port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brain: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RmvmSettings {
     pub mode: String,
     pub endpoint: Option<String>,
@@ -74,6 +151,28 @@ pub struct ProviderProfile {
     pub planner_base_url: String,
     pub planner_model: String,
     pub planner_api_key_ref: Option<String>,
+    /// Azure OpenAI deployment name; defaults to `planner_model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure_deployment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure_api_version: Option<String>,
+    /// GGUF model for the `local` planner mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_model_path: Option<PathBuf>,
+    /// Planner request overrides; unset fields keep the client's values or the proxy defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_headers: BTreeMap<String, String>,
+    /// OpenAI organization and project ids, sent as request headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,10 +187,32 @@ pub struct ConnectorProfile {
 pub struct RuntimeState {
     pub proxy_pid: Option<u32>,
     pub rmvm_pid: Option<u32>,
+    /// OS-reported process start times, used to tell our processes from reused PIDs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_started: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rmvm_started: Option<String>,
     pub rmvm_mode: String,
     pub rmvm_endpoint: String,
     pub proxy_addr: String,
     pub last_started_at: Option<String>,
+    /// Set while `cortex up --detached=false` supervises the services.
+    #[serde(default)]
+    pub foreground: bool,
+    /// Set while the proxy is exposed to the network by `cortex up --lan`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lan: Option<LanSettings>,
+    /// Brain store root the running proxy was started with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brain_home: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -130,6 +251,8 @@ pub struct UpRequest {
     pub brain: Option<String>,
     pub provider: Option<String>,
     pub reuse_external_rmvm: bool,
+    pub watch_config: bool,
+    pub lan: Option<LanSettings>,
 }
 
 #[derive(Debug, Clone)]
@@ -145,6 +268,10 @@ pub struct StatusRequest {
     pub json: bool,
     pub verbose: bool,
     pub copy: bool,
+    pub usage: bool,
+    pub all_instances: bool,
+    /// Redraw a compact view at this interval until Ctrl-C.
+    pub watch: Option<Duration>,
 }
 
 #[derive(Debug, Clone)]
@@ -152,6 +279,18 @@ pub struct LogsRequest {
     pub service: String,
     pub tail: usize,
     pub follow: bool,
+    pub json: bool,
+    pub level: Option<String>,
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintainRequest {
+    pub daemon: bool,
+    /// Run these jobs now instead of the due ones.
+    pub jobs: Vec<String>,
+    pub status: bool,
+    pub json: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -170,6 +309,22 @@ pub struct ConnectStatusRequest {
     pub json: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct EnvRequest {
+    pub shell: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DebugBundleRequest {
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectConfigRequest {
+    pub app: String,
+    pub write: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ModeSetRequest {
     pub mode: String,
@@ -184,16 +339,59 @@ pub struct ModeStatusRequest {
 pub struct UninstallRequest {
     pub all: bool,
     pub yes: bool,
+    pub purge_brains: bool,
+    pub export_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RestartPolicy {
     Auto,
+    Prompt,
     Never,
 }
 
+#[derive(Debug, Clone)]
+pub struct ProviderAddRequest {
+    pub name: String,
+    /// Not needed by the `local` mode.
+    pub base_url: Option<String>,
+    pub model: String,
+    pub mode: String,
+    pub api_key: Option<String>,
+    pub api_key_env: Option<String>,
+    pub azure_deployment: Option<String>,
+    pub azure_api_version: Option<String>,
+    pub model_path: Option<PathBuf>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub timeout_secs: Option<u64>,
+    pub extra_headers: BTreeMap<String, String>,
+    pub organization: Option<String>,
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileCreateRequest {
+    pub name: String,
+    pub from: Option<String>,
+    pub proxy_addr: Option<String>,
+    pub rmvm_port: Option<u16>,
+    pub brains_home: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProfileView {
+    name: String,
+    active: bool,
+    proxy_addr: String,
+    rmvm_port: u16,
+    brains_home: Option<String>,
+    config_path: String,
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct StatusView {
+    profile: String,
     active_brain: Option<String>,
     active_provider: String,
     planner_model: Option<String>,
@@ -210,6 +408,45 @@ struct StatusView {
     runtime_rmvm_pid: Option<u32>,
     config_path: String,
     state_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<BTreeMap<String, KeyUsage>>,
+    sync: BTreeMap<String, SyncStatus>,
+    maintenance: BTreeMap<String, JobStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpView {
+    active_brain: Option<String>,
+    active_provider: String,
+    planner_model: String,
+    proxy_addr: String,
+    base_url: String,
+    api_key: Option<String>,
+    dashboard_url: String,
+    proxy_pid: Option<u32>,
+    rmvm_pid: Option<u32>,
+    rmvm_mode: String,
+    rmvm_endpoint: String,
+    foreground: bool,
+    lan_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct StoppedService {
+    service: &'static str,
+    pid: Option<u32>,
+    stopped: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct InstanceView {
+    name: String,
+    proxy_addr: String,
+    rmvm_port: u16,
+    brain: Option<String>,
+    proxy_pid: Option<u32>,
+    proxy_healthy: bool,
+    state_path: String,
 }
 
 #[derive(Debug, Clone)]
@@ -227,6 +464,22 @@ impl Paths {
         self.state_dir.join(RUNTIME_FILE)
     }
 
+    fn usage_file(&self) -> PathBuf {
+        self.state_dir.join(USAGE_FILE)
+    }
+
+    fn sync_file(&self) -> PathBuf {
+        self.state_dir.join(SYNC_FILE)
+    }
+
+    fn maintenance_file(&self) -> PathBuf {
+        self.state_dir.join(MAINTENANCE_FILE)
+    }
+
+    fn backups_dir(&self) -> PathBuf {
+        self.state_dir.join(BACKUPS_DIR)
+    }
+
     fn logs_dir(&self) -> PathBuf {
         self.state_dir.join(LOG_DIR)
     }
@@ -235,6 +488,11 @@ impl Paths {
         self.logs_dir().join("proxy.log")
     }
 
+    /// The proxy's own rotating JSON trace; `proxy.log` still captures its raw stdout.
+    fn proxy_trace_file(&self) -> PathBuf {
+        self.logs_dir().join("proxy.jsonl")
+    }
+
     fn rmvm_log_file(&self) -> PathBuf {
         self.logs_dir().join("rmvm.log")
     }
@@ -248,7 +506,33 @@ impl Paths {
     }
 }
 
+/// Paths of the selected profile; `default` uses the top-level config and state dirs.
 pub fn default_paths() -> Result<Paths> {
+    let base = base_paths()?;
+    let paths = profile_paths(&base, &current_profile(&base));
+    Ok(match current_instance() {
+        Some(name) => instance_paths(&paths, &name),
+        None => paths,
+    })
+}
+
+/// Instances share the profile config and keep runtime state, usage and logs apart.
+fn instance_paths(profile: &Paths, name: &str) -> Paths {
+    Paths {
+        config_dir: profile.config_dir.clone(),
+        state_dir: profile.state_dir.join(INSTANCES_DIR).join(name),
+    }
+}
+
+/// `CORTEX_INSTANCE` (also set by `--instance`); unset means the default instance.
+fn current_instance() -> Option<String> {
+    env::var(INSTANCE_ENV)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty() && v != DEFAULT_INSTANCE)
+}
+
+fn base_paths() -> Result<Paths> {
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow!("failed to resolve config dir"))?
         .join("cortex");
@@ -260,6 +544,101 @@ pub fn default_paths() -> Result<Paths> {
     })
 }
 
+fn profile_paths(base: &Paths, name: &str) -> Paths {
+    if name == DEFAULT_PROFILE {
+        return base.clone();
+    }
+    Paths {
+        config_dir: base.config_dir.join(PROFILES_DIR).join(name),
+        state_dir: base.state_dir.join(PROFILES_DIR).join(name),
+    }
+}
+
+/// `CORTEX_PROFILE` (also set by `--profile`), then the profile saved by `cortex profile switch`.
+fn current_profile(base: &Paths) -> String {
+    env::var(PROFILE_ENV)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| saved_profile(base))
+}
+
+fn saved_profile(base: &Paths) -> String {
+    fs::read_to_string(base.config_dir.join(ACTIVE_PROFILE_FILE))
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn validate_profile_name(name: &str) -> Result<()> {
+    validate_name("profile", name)
+}
+
+fn validate_name(kind: &str, name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("invalid {kind} name '{name}': use letters, digits, '-' or '_'");
+    }
+    Ok(())
+}
+
+fn profile_names(base: &Paths) -> Vec<String> {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+    if let Ok(entries) = fs::read_dir(base.config_dir.join(PROFILES_DIR)) {
+        let mut named = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join(CONFIG_FILE).exists())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name != DEFAULT_PROFILE)
+            .collect::<Vec<_>>();
+        named.sort();
+        names.extend(named);
+    }
+    names
+}
+
+/// Replaces the profile's ports and brain with those of instance `name`, if it exists.
+fn apply_instance(cfg: &mut ProductConfig, name: &str) {
+    if let Some(settings) = cfg.instances.get(name).cloned() {
+        cfg.proxy_addr = settings.proxy_addr;
+        cfg.rmvm.port = settings.rmvm_port;
+        if settings.brain.is_some() {
+            cfg.active_brain = settings.brain;
+        }
+    }
+}
+
+/// The profile config as seen by the current instance.
+fn load_instance_config(paths: &Paths) -> Result<ProductConfig> {
+    let mut cfg = load_config(paths)?;
+    if let Some(name) = current_instance() {
+        apply_instance(&mut cfg, &name);
+    }
+    Ok(cfg)
+}
+
+/// Every profile config, plus a copy per instance with that instance's ports applied.
+fn port_owners(base: &Paths) -> Result<Vec<ProductConfig>> {
+    let mut owners = Vec::new();
+    for name in profile_names(base) {
+        let Some(cfg) = read_config(&profile_paths(base, &name))? else {
+            continue;
+        };
+        for instance in cfg.instances.keys() {
+            let mut owner = cfg.clone();
+            apply_instance(&mut owner, instance);
+            owners.push(owner);
+        }
+        owners.push(cfg);
+    }
+    Ok(owners)
+}
+
 fn ensure_dirs(paths: &Paths) -> Result<()> {
     fs::create_dir_all(&paths.config_dir)?;
     fs::create_dir_all(&paths.state_dir)?;
@@ -267,7 +646,7 @@ fn ensure_dirs(paths: &Paths) -> Result<()> {
     Ok(())
 }
 
-fn sidecar_binary_name() -> &'static str {
+pub fn sidecar_binary_name() -> &'static str {
     if cfg!(target_os = "windows") {
         "rmvm-grpc-server.exe"
     } else {
@@ -303,6 +682,15 @@ fn default_providers() -> BTreeMap<String, ProviderProfile> {
             planner_base_url: "https://api.openai.com/v1".to_string(),
             planner_model: "gpt-4o-mini".to_string(),
             planner_api_key_ref: Some("provider.openai.api_key".to_string()),
+            azure_deployment: None,
+            azure_api_version: None,
+            local_model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
         },
     );
     profiles.insert(
@@ -313,6 +701,15 @@ fn default_providers() -> BTreeMap<String, ProviderProfile> {
             planner_base_url: "https://api.anthropic.com/v1/".to_string(),
             planner_model: "claude-opus-4-6".to_string(),
             planner_api_key_ref: Some("provider.claude.api_key".to_string()),
+            azure_deployment: None,
+            azure_api_version: None,
+            local_model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
         },
     );
     profiles.insert(
@@ -323,6 +720,15 @@ fn default_providers() -> BTreeMap<String, ProviderProfile> {
             planner_base_url: "https://generativelanguage.googleapis.com/v1beta/openai/".to_string(),
             planner_model: "gemini-3-flash-preview".to_string(),
             planner_api_key_ref: Some("provider.gemini.api_key".to_string()),
+            azure_deployment: None,
+            azure_api_version: None,
+            local_model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
         },
     );
     profiles.insert(
@@ -333,6 +739,15 @@ fn default_providers() -> BTreeMap<String, ProviderProfile> {
             planner_base_url: "http://127.0.0.1:11434/v1".to_string(),
             planner_model: "llama3.1".to_string(),
             planner_api_key_ref: None,
+            azure_deployment: None,
+            azure_api_version: None,
+            local_model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
         },
     );
     profiles.insert(
@@ -343,6 +758,15 @@ fn default_providers() -> BTreeMap<String, ProviderProfile> {
             planner_base_url: "http://unused".to_string(),
             planner_model: "byo-plan".to_string(),
             planner_api_key_ref: None,
+            azure_deployment: None,
+            azure_api_version: None,
+            local_model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
         },
     );
     profiles
@@ -395,6 +819,17 @@ fn default_connectors() -> BTreeMap<String, ConnectorProfile> {
             notes: "Uses local Ollama backend via Cortex planner config.".to_string(),
         },
     );
+    for app in integrations::APPS {
+        connectors.insert(
+            app.to_string(),
+            ConnectorProfile {
+                name: app.to_string(),
+                kind: "app".to_string(),
+                enabled: false,
+                notes: format!("Run `cortex connect config {app}` to configure."),
+            },
+        );
+    }
     connectors
 }
 
@@ -417,25 +852,95 @@ fn default_config() -> ProductConfig {
         },
         providers: default_providers(),
         memory_mode: default_memory_mode(),
+        secret_storage: default_secret_storage(),
         connectors: default_connectors(),
+        model_routes: BTreeMap::new(),
+        brain_endpoints: BTreeMap::new(),
+        brains_home: None,
+        instances: BTreeMap::new(),
+        maintenance: default_schedules(),
     }
 }
 
-fn load_config(paths: &Paths) -> Result<ProductConfig> {
-    ensure_dirs(paths)?;
+fn read_config(paths: &Paths) -> Result<Option<ProductConfig>> {
     let path = paths.config_file();
     if !path.exists() {
-        let cfg = default_config();
-        save_config(paths, &cfg)?;
-        return Ok(cfg);
+        return Ok(None);
     }
     let raw = fs::read_to_string(&path)
         .with_context(|| format!("failed to read {}", path.display()))?;
-    let mut cfg: ProductConfig =
+    let mut value: JsonValue =
         serde_json::from_str(&raw).with_context(|| format!("invalid {}", path.display()))?;
-    if cfg.providers.is_empty() {
-        cfg.providers = default_providers();
+    // Configs written before the field existed count as version 1.
+    let version = value
+        .get("version")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(1)
+        .max(1);
+    if version > u64::from(CONFIG_VERSION) {
+        bail!(
+            "{} uses config version {} but this cortex supports up to {}; upgrade with `cortex self-update`",
+            path.display(),
+            version,
+            CONFIG_VERSION
+        );
+    }
+    if version < u64::from(CONFIG_VERSION) {
+        let backup = path.with_extension(format!("v{version}.json.bak"));
+        fs::copy(&path, &backup)
+            .with_context(|| format!("failed to back up {}", path.display()))?;
+        for step in &CONFIG_MIGRATIONS[version as usize - 1..] {
+            step(&mut value).with_context(|| format!("failed to migrate {}", path.display()))?;
+        }
+        value["version"] = CONFIG_VERSION.into();
+        fs::write(&path, serde_json::to_string_pretty(&value)?)?;
+        eprintln!(
+            "Migrated {} from config version {} to {} (backup: {})",
+            path.display(),
+            version,
+            CONFIG_VERSION,
+            backup.display()
+        );
+    }
+    let cfg =
+        serde_json::from_value(value).with_context(|| format!("invalid {}", path.display()))?;
+    Ok(Some(cfg))
+}
+
+/// Version 1 configs could have an empty provider table or memory mode that `load_config` used
+/// to patch on every read; version 2 stores the defaults.
+fn migrate_config_v1_to_v2(value: &mut JsonValue) -> Result<()> {
+    let cfg = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("config must be a JSON object"))?;
+    if cfg
+        .get("providers")
+        .and_then(JsonValue::as_object)
+        .is_none_or(|providers| providers.is_empty())
+    {
+        cfg.insert(
+            "providers".to_string(),
+            serde_json::to_value(default_providers())?,
+        );
+    }
+    if cfg
+        .get("memory_mode")
+        .and_then(JsonValue::as_str)
+        .is_none_or(|mode| mode.trim().is_empty())
+    {
+        cfg.insert("memory_mode".to_string(), default_memory_mode().into());
     }
+    Ok(())
+}
+
+fn load_config(paths: &Paths) -> Result<ProductConfig> {
+    ensure_dirs(paths)?;
+    let Some(mut cfg) = read_config(paths)? else {
+        let cfg = default_config();
+        save_config(paths, &cfg)?;
+        return Ok(cfg);
+    };
+    // New connectors ship with new releases, so they are merged in on every load.
     if cfg.connectors.is_empty() {
         cfg.connectors = default_connectors();
     } else {
@@ -443,9 +948,6 @@ fn load_config(paths: &Paths) -> Result<ProductConfig> {
             cfg.connectors.entry(name).or_insert(connector);
         }
     }
-    if cfg.memory_mode.trim().is_empty() {
-        cfg.memory_mode = default_memory_mode();
-    }
     Ok(cfg)
 }
 
@@ -472,6 +974,117 @@ fn save_runtime(paths: &Paths, state: &RuntimeState) -> Result<()> {
     Ok(())
 }
 
+/// Loads `runtime.json` without PIDs that exited or now belong to another program (e.g. after
+/// a reboot), so callers never report or signal an unrelated process.
+fn load_live_runtime(paths: &Paths) -> Result<Option<RuntimeState>> {
+    let Some(mut state) = load_runtime(paths)? else {
+        return Ok(None);
+    };
+    let mut stale = Vec::new();
+    if let Some(pid) = state.proxy_pid
+        && !is_cortex_process(pid, state.proxy_started.as_deref())
+    {
+        state.proxy_pid = None;
+        state.proxy_started = None;
+        stale.push(("proxy", pid));
+    }
+    if let Some(pid) = state.rmvm_pid
+        && !is_cortex_process(pid, state.rmvm_started.as_deref())
+    {
+        state.rmvm_pid = None;
+        state.rmvm_started = None;
+        stale.push(("rmvm", pid));
+    }
+    if stale.is_empty() {
+        return Ok(Some(state));
+    }
+    for (name, pid) in stale {
+        eprintln!(
+            "Cleared stale {name} pid={pid} from runtime state (process is gone or the PID was reused)."
+        );
+    }
+    if state.proxy_pid.is_none() && state.rmvm_pid.is_none() {
+        clear_runtime(paths)?;
+        return Ok(None);
+    }
+    save_runtime(paths, &state)?;
+    Ok(Some(state))
+}
+
+/// A recorded start time must match; without one (older runtime files, or no start time on
+/// this OS) the command line must look like cortex or the RMVM sidecar.
+fn is_cortex_process(pid: u32, recorded_start: Option<&str>) -> bool {
+    let Some(command) = process_command(pid) else {
+        return false;
+    };
+    if let (Some(recorded), Some(actual)) = (recorded_start, process_start_time(pid)) {
+        return recorded == actual;
+    }
+    let command = command.to_ascii_lowercase();
+    command.contains("cortex") || command.contains("rmvm")
+}
+
+fn process_start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        // Field 22 of /proc/<pid>/stat; the command name before it may contain spaces.
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        let (_, rest) = stat.rsplit_once(')')?;
+        rest.split_whitespace().nth(19).map(ToOwned::to_owned)
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        ps_field(pid, "lstart=")
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+fn process_command(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+        let command = String::from_utf8_lossy(&raw).replace('\0', " ");
+        if command.trim().is_empty() {
+            return fs::read_to_string(format!("/proc/{pid}/comm")).ok();
+        }
+        Some(command)
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        ps_field(pid, "command=")
+    }
+    #[cfg(not(unix))]
+    {
+        let output = Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+            .output()
+            .ok()?;
+        let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // "cortex.exe","1234",...; without a match tasklist prints an INFO line instead.
+        line.starts_with('"').then(|| {
+            line.split(',')
+                .next()
+                .unwrap_or_default()
+                .trim_matches('"')
+                .to_string()
+        })
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn ps_field(pid: u32, field: &str) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", field, "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
 fn clear_runtime(paths: &Paths) -> Result<()> {
     let path = paths.runtime_file();
     if path.exists() {
@@ -660,7 +1273,89 @@ fn decrypt_secret(paths: &Paths, sealed: &str) -> Result<String> {
     String::from_utf8(plaintext).context("fallback secret is not utf-8")
 }
 
+fn keyring_unavailable(err: keyring::Error) -> anyhow::Error {
+    anyhow!(
+        "OS keyring unavailable: {err}. secret_storage=keyring never writes secrets to disk; \
+         unlock or install a keyring service, or run `cortex config set secret_storage auto`"
+    )
+}
+
+fn keyring_get(key: &str) -> Result<Option<String>> {
+    match secret_entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(keyring_unavailable(err)),
+    }
+}
+
+fn keyring_set(key: &str, value: &str) -> Result<()> {
+    secret_entry(key)?
+        .set_password(value)
+        .map_err(keyring_unavailable)
+}
+
+/// Moves every file-backed secret into the OS keyring, then removes the secrets file and its
+/// key. Nothing is deleted unless each secret reads back from the keyring.
+fn migrate_fallback_secrets(paths: &Paths) -> Result<usize> {
+    let map = load_fallback_secrets(paths)?;
+    for (key, sealed) in &map {
+        let value = decrypt_secret(paths, sealed)?;
+        keyring_set(key, &value)?;
+        if keyring_get(key)?.as_deref() != Some(value.as_str()) {
+            bail!(
+                "keyring did not return secret '{}' after writing it; kept {}",
+                key,
+                paths.fallback_secrets_file().display()
+            );
+        }
+    }
+    for path in [paths.fallback_secrets_file(), paths.fallback_key_file()] {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(map.len())
+}
+
+/// True when secrets must live only in the OS keyring (`secret_storage=keyring`, or
+/// `CORTEX_SECRET_STORAGE=keyring`). Left-over file secrets are migrated on first use.
+fn keyring_only(paths: &Paths) -> Result<bool> {
+    if configured_secret_storage(paths)? != "keyring" {
+        return Ok(false);
+    }
+    if paths.fallback_secrets_file().exists() || paths.fallback_key_file().exists() {
+        let moved = migrate_fallback_secrets(paths)?;
+        eprintln!("Moved {moved} secret(s) from the fallback file into the OS keyring.");
+    }
+    Ok(true)
+}
+
+fn configured_secret_storage(paths: &Paths) -> Result<String> {
+    let storage = match env::var(SECRET_STORAGE_ENV) {
+        Ok(value) => value,
+        Err(_) => read_config(paths)?
+            .map(|cfg| cfg.secret_storage)
+            .unwrap_or_else(default_secret_storage),
+    };
+    normalize_secret_storage(&storage)
+}
+
+fn normalize_secret_storage(storage: &str) -> Result<String> {
+    let normalized = storage.trim().to_ascii_lowercase();
+    match normalized.as_str() {
+        "auto" | "keyring" => Ok(normalized),
+        _ => bail!(
+            "invalid secret_storage '{}'; expected auto|keyring",
+            storage
+        ),
+    }
+}
+
 fn put_secret(paths: &Paths, key: &str, value: &str) -> Result<()> {
+    if keyring_only(paths)? {
+        return keyring_set(key, value);
+    }
     let mut map = load_fallback_secrets(paths)?;
     map.insert(key.to_string(), encrypt_secret(paths, value)?);
     save_fallback_secrets(paths, &map)?;
@@ -670,7 +1365,27 @@ fn put_secret(paths: &Paths, key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
+fn delete_secret(paths: &Paths, key: &str) -> Result<()> {
+    if keyring_only(paths)? {
+        return match secret_entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(keyring_unavailable(err)),
+        };
+    }
+    let mut map = load_fallback_secrets(paths)?;
+    if map.remove(key).is_some() {
+        save_fallback_secrets(paths, &map)?;
+    }
+    if let Ok(entry) = secret_entry(key) {
+        let _ = entry.delete_credential();
+    }
+    Ok(())
+}
+
 fn get_secret(paths: &Paths, key: &str) -> Result<Option<String>> {
+    if keyring_only(paths)? {
+        return keyring_get(key);
+    }
     let map = load_fallback_secrets(paths)?;
     if let Some(sealed) = map.get(key) {
         return Ok(Some(decrypt_secret(paths, sealed)?));
@@ -762,15 +1477,108 @@ async fn probe_rmvm(endpoint: &str) -> bool {
 }
 
 async fn probe_proxy(proxy_addr: &str) -> bool {
-    let healthz = format!("http://{}/healthz", proxy_addr.trim_end_matches('/'));
-    let client = match Client::builder().timeout(Duration::from_secs(2)).build() {
+    let addr = proxy_addr.trim_end_matches('/');
+    // A LAN proxy with TLS only answers HTTPS, often with a self-signed certificate.
+    let client = match Client::builder()
+        .timeout(Duration::from_secs(2))
+        .danger_accept_invalid_certs(true)
+        .build()
+    {
         Ok(c) => c,
         Err(_) => return false,
     };
-    let Ok(resp) = client.get(healthz).send().await else {
-        return false;
+    for scheme in ["http", "https"] {
+        if let Ok(resp) = client
+            .get(format!("{scheme}://{addr}/healthz"))
+            .send()
+            .await
+            && resp.status().is_success()
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Port checks for `cortex doctor`. A bound port passes when this profile's running services
+/// hold it (or an RMVM server `cortex up` would reuse); otherwise the fix names a free port.
+pub async fn doctor_port_checks() -> Vec<(&'static str, Result<String>)> {
+    let loaded = default_paths().and_then(|paths| {
+        let cfg = load_instance_config(&paths)?;
+        let runtime = load_runtime(&paths)?.unwrap_or_default();
+        Ok((cfg, runtime))
+    });
+    let (cfg, runtime) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => return vec![("ports", Err(e))],
+    };
+    vec![
+        ("proxy_port", check_proxy_port(&cfg, &runtime).await),
+        ("rmvm_port", check_rmvm_port(&cfg, &runtime).await),
+    ]
+}
+
+async fn check_proxy_port(cfg: &ProductConfig, runtime: &RuntimeState) -> Result<String> {
+    let addr = cfg
+        .proxy_addr
+        .parse::<SocketAddr>()
+        .with_context(|| format!("invalid proxy_addr '{}'", cfg.proxy_addr))?;
+    if !probe_tcp(&cfg.proxy_addr) {
+        return Ok(format!("{addr} is free"));
+    }
+    if let Some(pid) = runtime.proxy_pid
+        && runtime.proxy_addr == cfg.proxy_addr
+        && is_cortex_process(pid, runtime.proxy_started.as_deref())
+    {
+        return Ok(format!(
+            "{addr} is held by this profile's proxy (pid {pid})"
+        ));
+    }
+    let listener = if probe_proxy(&cfg.proxy_addr).await {
+        "a cortex proxy from another profile or instance"
+    } else {
+        "a non-cortex process"
+    };
+    let fix = match free_port_after(addr) {
+        Some(port) => format!("run `cortex up --proxy-addr {}:{port}`", addr.ip()),
+        None => "pass a free port with --proxy-addr".to_string(),
     };
-    resp.status().is_success()
+    bail!("{addr} is in use by {listener}; {fix}")
+}
+
+async fn check_rmvm_port(cfg: &ProductConfig, runtime: &RuntimeState) -> Result<String> {
+    if cfg.rmvm.mode == "external" {
+        return Ok(format!("external RMVM at {}", rmvm_endpoint(cfg)));
+    }
+    if let Some(name) = pipe::pipe_name(&managed_rmvm_addr(cfg)) {
+        return Ok(format!("managed RMVM uses {}", pipe::pipe_path(name)));
+    }
+    let bind = format!("{}:{}", cfg.rmvm.host, cfg.rmvm.port);
+    if !probe_tcp(&bind) {
+        return Ok(format!("{bind} is free"));
+    }
+    if let Some(pid) = runtime.rmvm_pid
+        && is_cortex_process(pid, runtime.rmvm_started.as_deref())
+    {
+        return Ok(format!("{bind} is held by this profile's RMVM (pid {pid})"));
+    }
+    if probe_rmvm(&format!("grpc://{bind}")).await {
+        return Ok(format!(
+            "{bind} is served by an RMVM this profile did not start; cortex up reuses it"
+        ));
+    }
+    let fix = match bind.parse::<SocketAddr>().ok().and_then(free_port_after) {
+        Some(port) => format!("run `cortex up --rmvm-port {port}`"),
+        None => "pass a free port with --rmvm-port".to_string(),
+    };
+    bail!("{bind} is in use by a non-RMVM process; {fix}")
+}
+
+/// First port above `addr` on the same host that can be bound right now.
+fn free_port_after(addr: SocketAddr) -> Option<u16> {
+    (1..=100)
+        .filter_map(|step| addr.port().checked_add(step))
+        .find(|port| TcpListener::bind((addr.ip(), *port)).is_ok())
 }
 
 fn rmvm_endpoint(cfg: &ProductConfig) -> String {
@@ -780,12 +1588,28 @@ fn rmvm_endpoint(cfg: &ProductConfig) -> String {
             .clone()
             .unwrap_or_else(|| format!("grpc://{}:{}", cfg.rmvm.host, cfg.rmvm.port))
     } else {
-        format!("grpc://{}:{}", cfg.rmvm.host, cfg.rmvm.port)
+        let addr = managed_rmvm_addr(cfg);
+        if addr.starts_with(pipe::SCHEME) {
+            addr
+        } else {
+            format!("grpc://{addr}")
+        }
     }
 }
 
-fn provider_display_name(name: &str) -> String {
-    match name.to_ascii_lowercase().as_str() {
+/// Where a managed RMVM listens. Windows uses a named pipe, since localhost ports there often
+/// collide with other software and trigger firewall prompts; the port stays in the pipe name so
+/// profiles and instances do not share one.
+fn managed_rmvm_addr(cfg: &ProductConfig) -> String {
+    if cfg!(windows) {
+        format!("{}cortex-rmvm-{}", pipe::SCHEME, cfg.rmvm.port)
+    } else {
+        format!("{}:{}", cfg.rmvm.host, cfg.rmvm.port)
+    }
+}
+
+fn provider_display_name(name: &str) -> String {
+    match name.to_ascii_lowercase().as_str() {
         "openai" => "OpenAI".to_string(),
         "claude" => "Claude".to_string(),
         "gemini" => "Gemini".to_string(),
@@ -799,7 +1623,7 @@ fn active_brain_label(cfg: &ProductConfig) -> String {
     let Some(active) = cfg.active_brain.as_ref() else {
         return "<none>".to_string();
     };
-    if let Ok(store) = BrainStore::new(None) {
+    if let Ok(store) = brain_store() {
         if let Ok(summary) = store.resolve_brain(active) {
             return summary.name;
         }
@@ -839,6 +1663,9 @@ fn connector_health(cfg: &ProductConfig, connector: &ConnectorProfile) -> String
             }
         }
         "chatgpt_web" | "claude_web" | "gemini_web" => "extension_required".to_string(),
+        app if integrations::is_app(app) => {
+            integrations::health(app, &format!("http://{}/v1", cfg.proxy_addr)).to_string()
+        }
         _ => "unknown".to_string(),
     }
 }
@@ -875,11 +1702,9 @@ fn open_log(path: &Path) -> Result<File> {
         .with_context(|| format!("failed to open log {}", path.display()))
 }
 
-fn spawn_rmvm_sidecar(cfg: &ProductConfig, paths: &Paths) -> Result<u32> {
+fn rmvm_command(cfg: &ProductConfig) -> Result<Command> {
     let bin = sidecar_path(cfg)?;
-    let addr = format!("{}:{}", cfg.rmvm.host, cfg.rmvm.port);
-    let stdout = open_log(&paths.rmvm_log_file())?;
-    let stderr = open_log(&paths.rmvm_log_file())?;
+    let addr = managed_rmvm_addr(cfg);
     let mut cmd = if bin.exists() {
         let mut cmd = Command::new(bin);
         cmd.env("RMVM_SERVER_ADDR", addr);
@@ -891,7 +1716,15 @@ fn spawn_rmvm_sidecar(cfg: &ProductConfig, paths: &Paths) -> Result<u32> {
         cmd.arg("rmvm").arg("serve").arg("--addr").arg(addr);
         cmd
     };
-    let child = cmd
+    cmd.env(LOG_FORMAT_ENV, "json")
+        .env("CORTEX_HOME", brains_home()?);
+    Ok(cmd)
+}
+
+fn spawn_rmvm_sidecar(cfg: &ProductConfig, paths: &Paths) -> Result<u32> {
+    let stdout = open_log(&paths.rmvm_log_file())?;
+    let stderr = open_log(&paths.rmvm_log_file())?;
+    let child = rmvm_command(cfg)?
         .stdin(Stdio::null())
         .stdout(Stdio::from(stdout))
         .stderr(Stdio::from(stderr))
@@ -900,21 +1733,24 @@ fn spawn_rmvm_sidecar(cfg: &ProductConfig, paths: &Paths) -> Result<u32> {
     Ok(child.id())
 }
 
-fn spawn_proxy(
+fn proxy_command(
     cfg: &ProductConfig,
     paths: &Paths,
     endpoint: &str,
     provider: &ProviderProfile,
     planner_api_key: Option<String>,
-) -> Result<u32> {
+    lan: Option<&LanSettings>,
+) -> Result<Command> {
     let exe = env::current_exe().context("failed to resolve cortex executable path")?;
-    let stdout = open_log(&paths.proxy_log_file())?;
-    let stderr = open_log(&paths.proxy_log_file())?;
+    let addr = match lan {
+        Some(_) => lan_bind_addr(&cfg.proxy_addr)?.to_string(),
+        None => cfg.proxy_addr.clone(),
+    };
     let mut cmd = Command::new(exe);
     cmd.arg("proxy")
         .arg("serve")
         .arg("--addr")
-        .arg(&cfg.proxy_addr)
+        .arg(addr)
         .arg("--endpoint")
         .arg(endpoint)
         .arg("--planner-mode")
@@ -925,22 +1761,178 @@ fn spawn_proxy(
         .arg(&provider.planner_model)
         .arg("--provider-name")
         .arg(&cfg.active_provider)
-        .stdin(Stdio::null())
-        .stdout(Stdio::from(stdout))
-        .stderr(Stdio::from(stderr));
+        .arg("--usage-file")
+        .arg(paths.usage_file())
+        .arg("--sync-status-file")
+        .arg(paths.sync_file())
+        .arg("--log-file")
+        .arg(paths.proxy_trace_file())
+        .arg("--brain-home")
+        .arg(brains_home()?)
+        .env(LOG_FORMAT_ENV, "json");
+    if let Some(deployment) = provider.azure_deployment.as_ref() {
+        cmd.arg("--planner-azure-deployment").arg(deployment);
+    }
+    if let Some(api_version) = provider.azure_api_version.as_ref() {
+        cmd.arg("--planner-azure-api-version").arg(api_version);
+    }
+    if let Some(model_path) = provider.local_model_path.as_ref() {
+        cmd.arg("--planner-model-path").arg(model_path);
+    }
+    if let Some(timeout_secs) = provider.timeout_secs {
+        cmd.arg("--planner-timeout-secs")
+            .arg(timeout_secs.to_string());
+    }
+    if let Some(temperature) = provider.temperature {
+        cmd.arg("--planner-temperature")
+            .arg(temperature.to_string());
+    }
+    if let Some(max_tokens) = provider.max_tokens {
+        cmd.arg("--planner-max-tokens").arg(max_tokens.to_string());
+    }
+    for (name, value) in &provider.extra_headers {
+        cmd.arg("--planner-header").arg(format!("{name}={value}"));
+    }
+    if let Some(organization) = provider.organization.as_ref() {
+        cmd.arg("--planner-organization").arg(organization);
+    }
+    if let Some(project) = provider.project.as_ref() {
+        cmd.arg("--planner-project").arg(project);
+    }
     if let Some(brain) = cfg.active_brain.as_ref() {
         cmd.arg("--brain").arg(brain);
     }
     if let Some(api_key) = cfg.proxy_api_key.as_ref() {
         cmd.arg("--proxy-api-key").arg(api_key);
     }
+    if !cfg.model_routes.is_empty() {
+        cmd.arg("--model-routes");
+    }
+    for (brain, endpoint) in &cfg.brain_endpoints {
+        cmd.arg("--brain-endpoint")
+            .arg(format!("{brain}={endpoint}"));
+    }
+    if let Some(lan) = lan {
+        cmd.arg("--require-api-key");
+        if let (Some(cert), Some(key)) = (lan.tls_cert.as_ref(), lan.tls_key.as_ref()) {
+            cmd.arg("--tls-cert").arg(cert).arg("--tls-key").arg(key);
+        }
+    }
+    cmd.arg("--reload-from-config");
     if let Some(api_key) = planner_api_key {
         cmd.env("CORTEX_PLANNER_API_KEY", api_key);
     }
-    let child = cmd.spawn().context("failed to spawn cortex proxy")?;
+    Ok(cmd)
+}
+
+fn spawn_proxy(
+    cfg: &ProductConfig,
+    paths: &Paths,
+    endpoint: &str,
+    provider: &ProviderProfile,
+    planner_api_key: Option<String>,
+    lan: Option<&LanSettings>,
+) -> Result<u32> {
+    let stdout = open_log(&paths.proxy_log_file())?;
+    let stderr = open_log(&paths.proxy_log_file())?;
+    let child = proxy_command(cfg, paths, endpoint, provider, planner_api_key, lan)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(stdout))
+        .stderr(Stdio::from(stderr))
+        .spawn()
+        .context("failed to spawn cortex proxy")?;
     Ok(child.id())
 }
 
+/// Children of `cortex up --detached=false`. Their output is echoed to the terminal with a
+/// service prefix and still appended to the usual log files.
+#[derive(Default)]
+struct Foreground {
+    children: Vec<(&'static str, Child)>,
+}
+
+impl Foreground {
+    fn spawn(&mut self, name: &'static str, mut cmd: Command, log: &Path) -> Result<u32> {
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", name))?;
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(name, stdout, open_log(log)?);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(name, stderr, open_log(log)?);
+        }
+        let pid = child.id();
+        self.children.push((name, child));
+        Ok(pid)
+    }
+
+    /// Blocks until Ctrl-C or until a child exits, then stops the remaining children.
+    /// A child stopped through `cortex stop` (no longer in the runtime state) is a clean exit.
+    async fn wait(mut self, paths: &Paths) -> Result<()> {
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        let exited = loop {
+            tokio::select! {
+                _ = &mut ctrl_c => break None,
+                _ = sleep(Duration::from_millis(500)) => {}
+            }
+            if let Some((name, pid)) = self
+                .children
+                .iter_mut()
+                .find(|(_, child)| matches!(child.try_wait(), Ok(Some(_))))
+                .map(|(name, child)| (*name, child.id()))
+            {
+                break Some((name, pid));
+            }
+        };
+        self.shutdown();
+        let Some((name, pid)) = exited else {
+            return Ok(());
+        };
+        let requested = load_runtime(paths)?
+            .is_none_or(|r| r.proxy_pid != Some(pid) && r.rmvm_pid != Some(pid));
+        if requested {
+            return Ok(());
+        }
+        bail!("{} exited; stopped the remaining services", name)
+    }
+
+    fn shutdown(&mut self) {
+        for (name, child) in self.children.iter_mut().rev() {
+            if matches!(child.try_wait(), Ok(Some(_))) {
+                continue;
+            }
+            println!("Stopping {}...", name);
+            kill_pid(child.id(), false);
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while std::time::Instant::now() < deadline {
+                if matches!(child.try_wait(), Ok(Some(_))) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn forward_lines(name: &'static str, stream: impl Read + Send + 'static, mut log: File) {
+    std::thread::spawn(move || {
+        let filter = LogFilter::default();
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if let Some(rendered) = render_line(&line, name, &filter, false) {
+                println!("[{}] {}", name, rendered);
+            }
+            let _ = writeln!(log, "{}", line);
+        }
+    });
+}
+
 fn kill_pid(pid: u32, force: bool) {
     #[cfg(target_os = "windows")]
     {
@@ -984,7 +1976,10 @@ async fn wait_for_proxy(addr: &str, timeout: Duration) -> bool {
 }
 
 fn is_interactive(non_interactive: bool) -> bool {
-    !non_interactive && atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout)
+    !non_interactive
+        && !json_output()
+        && atty::is(atty::Stream::Stdin)
+        && atty::is(atty::Stream::Stdout)
 }
 
 fn planner_api_key(paths: &Paths, provider: &ProviderProfile) -> Result<Option<String>> {
@@ -995,8 +1990,10 @@ fn planner_api_key(paths: &Paths, provider: &ProviderProfile) -> Result<Option<S
 }
 
 fn provider_requires_planner_key(provider: &ProviderProfile) -> bool {
-    if provider.planner_mode != "openai" {
-        return false;
+    match PlannerMode::parse(&provider.planner_mode) {
+        Ok(PlannerMode::AzureOpenAi) => return true,
+        Ok(PlannerMode::OpenAi) => {}
+        _ => return false,
     }
     let base_url = provider.planner_base_url.to_ascii_lowercase();
     !(base_url.contains("127.0.0.1")
@@ -1067,6 +2064,8 @@ pub fn run_setup(req: SetupRequest) -> Result<SetupResult> {
         cfg.rmvm.endpoint = None;
     }
     cfg.tenant = req.tenant.clone();
+    // Pin the store this setup used, so later commands and spawned services resolve the same one.
+    cfg.brains_home = Some(brains_home()?.display().to_string());
 
     if let Some(profile) = cfg.providers.get_mut(&provider_name) {
         profile.planner_model = model.clone();
@@ -1127,13 +2126,13 @@ pub fn run_setup(req: SetupRequest) -> Result<SetupResult> {
             .and_then(|k| get_secret(&paths, k).ok().flatten())
             .is_none()
     {
-        println!(
+        eprintln!(
             "Warning: provider '{}' has no planner API key configured. Set CORTEX_PLANNER_API_KEY or rerun setup with --planner-api-key.",
             provider_name
         );
     }
 
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
     let mut brain_summary = match store.resolve_brain(&brain_name) {
         Ok(summary) => summary,
         Err(_) => store.create_brain(CreateBrainRequest {
@@ -1153,7 +2152,7 @@ pub fn run_setup(req: SetupRequest) -> Result<SetupResult> {
             tenant_id: cfg.tenant.clone(),
             passphrase_env: Some(cfg.brain_secret_env.clone()),
         })?;
-        println!(
+        eprintln!(
             "Existing brain could not be unlocked with current secret; created fresh brain {} ({})",
             replacement_name, brain_summary.brain_id
         );
@@ -1180,16 +2179,26 @@ pub fn run_setup(req: SetupRequest) -> Result<SetupResult> {
 }
 
 pub async fn run_up(req: UpRequest) -> Result<()> {
-    if !req.detached {
-        bail!(
-            "--detached=false is not supported yet; use detached mode (default) and run `cortex logs --follow`"
-        );
-    }
-
     let paths = default_paths()?;
     let mut cfg = load_config(&paths)?;
     ensure_brain_secret_env(&paths, &cfg)?;
 
+    let instance = current_instance();
+    if let Some(name) = instance.as_deref() {
+        if !cfg.instances.contains_key(name) {
+            // A new instance gets ports no other profile or instance uses.
+            let offset = free_port_offset(&port_owners(&base_paths()?)?);
+            cfg.instances.insert(
+                name.to_string(),
+                InstanceSettings {
+                    proxy_addr: format!("127.0.0.1:{}", DEFAULT_PROXY_PORT + offset),
+                    rmvm_port: DEFAULT_RMVM_PORT + offset,
+                    brain: None,
+                },
+            );
+        }
+        apply_instance(&mut cfg, name);
+    }
     if let Some(brain) = req.brain.as_ref() {
         cfg.active_brain = Some(brain.clone());
     }
@@ -1211,7 +2220,7 @@ pub async fn run_up(req: UpRequest) -> Result<()> {
     }
 
     if cfg.active_brain.is_none() {
-        let store = BrainStore::new(None)?;
+        let store = brain_store()?;
         if let Ok(active) = store.active_brain_id() {
             cfg.active_brain = active;
         }
@@ -1222,18 +2231,44 @@ pub async fn run_up(req: UpRequest) -> Result<()> {
 
     let provider = resolve_provider(&cfg, None)?.clone();
     let planner_key = planner_api_key(&paths, &provider)?;
-    save_config(&paths, &cfg)?;
+    match instance.as_deref() {
+        // Instance overrides stay in the instance entry, not in the profile defaults.
+        Some(name) => {
+            let mut stored = load_config(&paths)?;
+            stored.instances.insert(
+                name.to_string(),
+                InstanceSettings {
+                    proxy_addr: cfg.proxy_addr.clone(),
+                    rmvm_port: cfg.rmvm.port,
+                    brain: cfg.active_brain.clone(),
+                },
+            );
+            save_config(&paths, &stored)?;
+        }
+        None => save_config(&paths, &cfg)?,
+    }
 
-    let mut runtime = load_runtime(&paths)?.unwrap_or_default();
+    let mut runtime = load_live_runtime(&paths)?.unwrap_or_default();
+    // A dashboard restart runs a plain `cortex up`, which keeps a running LAN proxy on the LAN.
+    let lan = match req.lan.as_ref().or(runtime.lan.as_ref()) {
+        Some(lan) => Some(check_lan(&cfg, lan)?),
+        None => None,
+    };
+    let mut foreground = (!req.detached).then(Foreground::default);
 
     let endpoint = if cfg.rmvm.mode == "external" {
         rmvm_endpoint(&cfg)
     } else {
         let bind = format!("{}:{}", cfg.rmvm.host, cfg.rmvm.port);
-        let ep = format!("grpc://{}", bind);
-        if probe_tcp(&bind) {
+        let ep = rmvm_endpoint(&cfg);
+        let in_use = match pipe::pipe_name(&ep) {
+            Some(_) => probe_rmvm(&ep).await,
+            None => probe_tcp(&bind),
+        };
+        if in_use {
             if probe_rmvm(&ep).await && req.reuse_external_rmvm {
                 runtime.rmvm_pid = None;
+                runtime.rmvm_started = None;
                 runtime.rmvm_mode = "external".to_string();
             } else if !probe_rmvm(&ep).await {
                 bail!(
@@ -1242,10 +2277,17 @@ pub async fn run_up(req: UpRequest) -> Result<()> {
                 );
             }
         } else {
-            let pid = spawn_rmvm_sidecar(&cfg, &paths)?;
+            let pid = match foreground.as_mut() {
+                Some(fg) => fg.spawn("rmvm", rmvm_command(&cfg)?, &paths.rmvm_log_file())?,
+                None => spawn_rmvm_sidecar(&cfg, &paths)?,
+            };
             runtime.rmvm_pid = Some(pid);
+            runtime.rmvm_started = process_start_time(pid);
             runtime.rmvm_mode = "managed".to_string();
             if !wait_for_rmvm(&ep, Duration::from_secs(10)).await {
+                if let Some(fg) = foreground.as_mut() {
+                    fg.shutdown();
+                }
                 bail!(
                     "managed RMVM failed health check; see {}",
                     paths.rmvm_log_file().display()
@@ -1258,14 +2300,40 @@ pub async fn run_up(req: UpRequest) -> Result<()> {
     if let Some(pid) = runtime.proxy_pid {
         kill_pid(pid, true);
     }
-    let proxy_pid = spawn_proxy(&cfg, &paths, &endpoint, &provider, planner_key)?;
+    let proxy_pid = match foreground.as_mut() {
+        Some(fg) => fg.spawn(
+            "proxy",
+            proxy_command(
+                &cfg,
+                &paths,
+                &endpoint,
+                &provider,
+                planner_key,
+                lan.as_ref(),
+            )?,
+            &paths.proxy_log_file(),
+        )?,
+        None => spawn_proxy(
+            &cfg,
+            &paths,
+            &endpoint,
+            &provider,
+            planner_key,
+            lan.as_ref(),
+        )?,
+    };
     if !wait_for_proxy(&cfg.proxy_addr, Duration::from_secs(10)).await {
+        if let Some(mut fg) = foreground {
+            fg.shutdown();
+        }
         bail!(
             "proxy failed health check; see {}",
             paths.proxy_log_file().display()
         );
     }
     runtime.proxy_pid = Some(proxy_pid);
+    runtime.proxy_started = process_start_time(proxy_pid);
+    runtime.brain_home = Some(brains_home()?);
     runtime.proxy_addr = cfg.proxy_addr.clone();
     runtime.rmvm_endpoint = endpoint.clone();
     if runtime.rmvm_mode.is_empty() {
@@ -1276,41 +2344,258 @@ pub async fn run_up(req: UpRequest) -> Result<()> {
         };
     }
     runtime.last_started_at = Some(chrono::Utc::now().to_rfc3339());
+    runtime.foreground = foreground.is_some();
+    runtime.lan = lan.clone();
     save_runtime(&paths, &runtime)?;
 
-    println!("RMVM: {} ({})", runtime.rmvm_mode, runtime.rmvm_endpoint);
-    println!("Proxy: running on http://{}", cfg.proxy_addr);
-    println!("Dashboard: {}", dashboard_url(&cfg));
-    print_connect_info_block(&cfg, Some(&provider));
-    println!("Tip: paste Base URL and API Key in your AI app settings (not in chat text).");
+    let scheme = match lan.as_ref() {
+        Some(lan) if lan.tls_cert.is_some() => "https",
+        _ => "http",
+    };
+    let lan_url = lan
+        .as_ref()
+        .map(|_| lan_base_url(&cfg.proxy_addr, scheme))
+        .transpose()?;
+    if json_output() {
+        let view = UpView {
+            active_brain: cfg.active_brain.clone(),
+            active_provider: cfg.active_provider.clone(),
+            planner_model: provider.planner_model.clone(),
+            proxy_addr: cfg.proxy_addr.clone(),
+            base_url: format!("{scheme}://{}/v1", cfg.proxy_addr),
+            api_key: cfg.proxy_api_key.clone(),
+            dashboard_url: dashboard_url(&cfg),
+            proxy_pid: runtime.proxy_pid,
+            rmvm_pid: runtime.rmvm_pid,
+            rmvm_mode: runtime.rmvm_mode.clone(),
+            rmvm_endpoint: runtime.rmvm_endpoint.clone(),
+            foreground: runtime.foreground,
+            lan_url,
+        };
+        println!("{}", serde_json::to_string_pretty(&view)?);
+    } else {
+        println!("RMVM: {} ({})", runtime.rmvm_mode, runtime.rmvm_endpoint);
+        println!("Proxy: running on {scheme}://{}", cfg.proxy_addr);
+        println!("Dashboard: {}", dashboard_url(&cfg));
+        print_connect_info_block(&cfg, Some(&provider));
+        println!("Tip: paste Base URL and API Key in your AI app settings (not in chat text).");
+        if let Some(lan_url) = lan_url {
+            println!();
+            println!("LAN URL: {lan_url} (API key required; dashboard stays on this machine)");
+            println!(
+                "LAN connect info (paste into a QR generator): {}",
+                json!({"base_url": lan_url, "api_key": cfg.proxy_api_key})
+            );
+            if scheme == "http" {
+                println!(
+                    "Warning: LAN traffic is unencrypted; pass --tls-cert and --tls-key to serve HTTPS."
+                );
+            }
+        }
+    }
+    if let Some(fg) = foreground {
+        eprintln!("Running in the foreground; press Ctrl-C to stop.");
+        let maintenance = tokio::spawn(maintenance_loop(paths.clone()));
+        let watcher = req
+            .watch_config
+            .then(|| tokio::spawn(watch_config(paths.clone())));
+        let result = fg.wait(&paths).await;
+        maintenance.abort();
+        if let Some(watcher) = watcher {
+            watcher.abort();
+        }
+        clear_runtime(&paths)?;
+        return result;
+    }
+    if req.watch_config {
+        eprintln!(
+            "Watching config for changes; press Ctrl-C to stop watching (services keep running)."
+        );
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = watch_config(paths.clone()) => {}
+        }
+    }
+    Ok(())
+}
+
+/// `--lan` exposes the proxy to the network, so it only starts when every request must carry
+/// a mapped API key. Returns the settings with TLS paths made absolute for later restarts.
+fn check_lan(cfg: &ProductConfig, lan: &LanSettings) -> Result<LanSettings> {
+    let Some(api_key) = cfg.proxy_api_key.as_deref() else {
+        bail!("--lan requires a proxy API key; run `cortex setup` first");
+    };
+    if brain_store()?.resolve_api_key(api_key)?.is_none() {
+        bail!("--lan requires the proxy API key to be mapped to a brain; run `cortex setup` again");
+    }
+    let (tls_cert, tls_key) = match (lan.tls_cert.as_ref(), lan.tls_key.as_ref()) {
+        (Some(cert), Some(key)) => {
+            let canonical = |path: &PathBuf| {
+                fs::canonicalize(path)
+                    .with_context(|| format!("TLS file {} not found", path.display()))
+            };
+            (Some(canonical(cert)?), Some(canonical(key)?))
+        }
+        (None, None) => (None, None),
+        _ => bail!("--tls-cert and --tls-key must be given together"),
+    };
+    Ok(LanSettings { tls_cert, tls_key })
+}
+
+/// The proxy port on every interface.
+fn lan_bind_addr(proxy_addr: &str) -> Result<SocketAddr> {
+    let addr = proxy_addr
+        .parse::<SocketAddr>()
+        .with_context(|| format!("invalid proxy address '{proxy_addr}'"))?;
+    Ok(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port()))
+}
+
+/// This machine's address on the interface with the default route. Connecting a UDP socket
+/// only picks the route; nothing is sent.
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+fn lan_base_url(proxy_addr: &str, scheme: &str) -> Result<String> {
+    let port = lan_bind_addr(proxy_addr)?.port();
+    let host = lan_ip().map_or_else(|| "<this-machine-ip>".to_string(), |ip| ip.to_string());
+    Ok(format!("{scheme}://{host}:{port}/v1"))
+}
+
+/// What a proxy reload reads: the raw `config.json` and the planner keys of the active and
+/// routed providers, so `cortex provider add --api-key` edits count as changes too.
+#[derive(PartialEq)]
+struct ConfigSnapshot {
+    raw: Vec<u8>,
+    planner_keys: BTreeMap<String, Option<String>>,
+}
+
+fn config_snapshot(paths: &Paths) -> Result<(ConfigSnapshot, ProductConfig)> {
+    let raw = fs::read(paths.config_file())
+        .with_context(|| format!("failed to read {}", paths.config_file().display()))?;
+    let cfg = load_instance_config(paths)?;
+    let mut planner_keys = BTreeMap::new();
+    for name in std::iter::once(&cfg.active_provider).chain(cfg.model_routes.values()) {
+        if let Some(profile) = cfg.providers.get(name) {
+            planner_keys.insert(name.clone(), planner_api_key(paths, profile)?);
+        }
+    }
+    Ok((ConfigSnapshot { raw, planner_keys }, cfg))
+}
+
+/// Polls the config for `cortex up --watch-config` and hot-reloads the running proxy on every
+/// change. Listen address and RMVM changes still need `cortex up`.
+async fn watch_config(paths: Paths) {
+    let mut last = config_snapshot(&paths).ok();
+    loop {
+        sleep(CONFIG_WATCH_TICK).await;
+        let (snapshot, cfg) = match config_snapshot(&paths) {
+            Ok(read) => read,
+            Err(err) => {
+                eprintln!("config watch: {err:#}");
+                continue;
+            }
+        };
+        if last.as_ref().is_some_and(|(prev, _)| *prev == snapshot) {
+            continue;
+        }
+        if let Some((_, prev)) = last.as_ref()
+            && (prev.proxy_addr != cfg.proxy_addr || prev.rmvm != cfg.rmvm)
+        {
+            eprintln!(
+                "config watch: proxy_addr or rmvm settings changed; run `cortex up` to apply them"
+            );
+        }
+        match apply_config_change(&paths, &cfg) {
+            Ok(()) => eprintln!(
+                "config watch: reloaded proxy (provider={}, brain={})",
+                cfg.active_provider,
+                cfg.active_brain.as_deref().unwrap_or("-")
+            ),
+            Err(err) => eprintln!("config watch: not applied: {err:#}"),
+        }
+        last = Some((snapshot, cfg));
+    }
+}
+
+fn apply_config_change(paths: &Paths, cfg: &ProductConfig) -> Result<()> {
+    validate_config(cfg)?;
+    let pid = load_live_runtime(paths)?
+        .and_then(|runtime| runtime.proxy_pid)
+        .ok_or_else(|| anyhow!("proxy is not running"))?;
+    reload_proxy(pid)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn reload_proxy(pid: u32) -> Result<()> {
+    let status = Command::new("kill")
+        .arg("-HUP")
+        .arg(pid.to_string())
+        .status()
+        .context("failed to run kill")?;
+    if !status.success() {
+        bail!("failed to signal proxy pid {pid}");
+    }
     Ok(())
 }
 
+#[cfg(target_os = "windows")]
+fn reload_proxy(_pid: u32) -> Result<()> {
+    bail!("live reload needs SIGHUP, which Windows lacks; run `cortex up` to apply changes")
+}
+
 pub fn run_stop(req: StopRequest) -> Result<()> {
     let paths = default_paths()?;
-    let state = load_runtime(&paths)?;
+    let state = load_live_runtime(&paths)?;
+    let json = json_output();
     let Some(state) = state else {
-        println!("Nothing running.");
+        if json {
+            println!("{}", serde_json::json!({ "services": [] }));
+        } else {
+            println!("Nothing running.");
+        }
         return Ok(());
     };
 
     let stop_proxy = req.all || (!req.rmvm_only && !req.proxy_only) || req.proxy_only;
     let stop_rmvm = req.all || (!req.rmvm_only && !req.proxy_only) || req.rmvm_only;
 
+    let mut services = Vec::new();
     if stop_proxy {
         if let Some(pid) = state.proxy_pid {
             kill_pid(pid, req.force);
-            println!("Stopped proxy pid={}", pid);
-        } else {
-            println!("Proxy not running.");
         }
+        services.push(StoppedService {
+            service: "proxy",
+            pid: state.proxy_pid,
+            stopped: state.proxy_pid.is_some(),
+        });
     }
     if stop_rmvm {
         if let Some(pid) = state.rmvm_pid {
             kill_pid(pid, req.force);
-            println!("Stopped rmvm pid={}", pid);
-        } else {
-            println!("RMVM is external or not running.");
+        }
+        services.push(StoppedService {
+            service: "rmvm",
+            pid: state.rmvm_pid,
+            stopped: state.rmvm_pid.is_some(),
+        });
+    }
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "services": services }))?
+        );
+    } else {
+        for service in &services {
+            match (service.service, service.pid) {
+                (name, Some(pid)) => println!("Stopped {name} pid={pid}"),
+                ("proxy", None) => println!("Proxy not running."),
+                (_, None) => println!("RMVM is external or not running."),
+            }
         }
     }
 
@@ -1320,19 +2605,143 @@ pub fn run_stop(req: StopRequest) -> Result<()> {
         let mut next = state;
         if stop_proxy {
             next.proxy_pid = None;
+            next.proxy_started = None;
         }
         if stop_rmvm {
             next.rmvm_pid = None;
+            next.rmvm_started = None;
         }
         save_runtime(&paths, &next)?;
     }
     Ok(())
 }
 
-pub async fn run_status(req: StatusRequest) -> Result<()> {
+/// Saves memory held by the managed RMVM into the active brain before `cortex stop` kills it.
+/// Returns `None` when no managed RMVM is running or no brain is active.
+pub async fn flush_managed_kernel() -> Result<Option<FlushReport>> {
     let paths = default_paths()?;
+    let Some(state) = load_live_runtime(&paths)? else {
+        return Ok(None);
+    };
+    if state.rmvm_pid.is_none() {
+        return Ok(None);
+    }
     let cfg = load_config(&paths)?;
-    let runtime = load_runtime(&paths)?.unwrap_or_default();
+    let Some(brain) = cfg.active_brain.as_deref() else {
+        return Ok(None);
+    };
+    ensure_brain_secret_env(&paths, &cfg)?;
+    let store = brain_store()?;
+    let brain = store.resolve_brain(brain)?;
+    let endpoints = RmvmEndpoints::new(state.rmvm_endpoint, cfg.brain_endpoints);
+    flush_kernel(endpoints.for_brain(&brain), &store, &brain.brain_id)
+        .await
+        .map(Some)
+}
+
+/// Runs `jobs`, or the jobs that are due when none are given, against the current profile and
+/// returns their new status. `sync` only runs while the RMVM answers.
+async fn maintenance_pass(paths: &Paths, jobs: &[String]) -> Result<BTreeMap<String, JobStatus>> {
+    let cfg = load_instance_config(paths)?;
+    ensure_brain_secret_env(paths, &cfg)?;
+    let status_file = paths.maintenance_file();
+    let jobs = if jobs.is_empty() {
+        let status = read_status_file(&status_file).unwrap_or_default();
+        due_jobs(&cfg.maintenance, &status, chrono::Utc::now())
+    } else {
+        jobs.to_vec()
+    };
+    let default = match load_live_runtime(paths)? {
+        Some(runtime) if !runtime.rmvm_endpoint.is_empty() => runtime.rmvm_endpoint,
+        _ => rmvm_endpoint(&cfg),
+    };
+    let endpoints = RmvmEndpoints::new(default.clone(), cfg.brain_endpoints.clone());
+    let endpoint = match cfg.active_brain.as_deref() {
+        Some(brain) => endpoints.resolve(&brain_store()?, brain),
+        None => default,
+    };
+    let maintenance = Maintenance {
+        backups_dir: paths.backups_dir(),
+        endpoint: probe_rmvm(&endpoint).await.then_some(endpoint),
+        active_brain: cfg.active_brain.clone(),
+    };
+    let mut statuses = maintenance.run(&jobs, &status_file).await;
+    statuses.retain(|job, _| jobs.contains(job));
+    Ok(statuses)
+}
+
+/// Scheduler run by `cortex up --detached=false` and `cortex maintain --daemon`.
+async fn maintenance_loop(paths: Paths) {
+    loop {
+        if let Err(err) = maintenance_pass(&paths, &[]).await {
+            eprintln!("maintenance: {err:#}");
+        }
+        sleep(MAINTENANCE_TICK).await;
+    }
+}
+
+fn print_job_status(job: &str, status: &JobStatus) {
+    println!(
+        "maintenance job={} last_run={} runs={} failures={}{}",
+        job,
+        status.last_run_at.as_deref().unwrap_or("-"),
+        status.runs,
+        status.failures,
+        match (&status.last_error, &status.summary) {
+            (Some(err), _) => format!(" error={err}"),
+            (None, Some(summary)) => format!(" result={summary}"),
+            (None, None) => String::new(),
+        }
+    );
+}
+
+pub async fn run_maintain(req: MaintainRequest) -> Result<()> {
+    let paths = default_paths()?;
+    for job in &req.jobs {
+        if !JOBS.contains(&job.as_str()) {
+            bail!(
+                "unknown maintenance job '{job}'; expected one of {}",
+                JOBS.join("|")
+            );
+        }
+    }
+    if req.daemon {
+        eprintln!("Running maintenance jobs; press Ctrl-C to stop.");
+        tokio::select! {
+            _ = maintenance_loop(paths) => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+        return Ok(());
+    }
+    let statuses = if req.status {
+        read_status_file(&paths.maintenance_file()).unwrap_or_default()
+    } else {
+        let ran = maintenance_pass(&paths, &req.jobs).await?;
+        if ran.is_empty() {
+            eprintln!("No maintenance jobs are due.");
+        }
+        ran
+    };
+    if req.json {
+        println!("{}", serde_json::to_string_pretty(&statuses)?);
+    } else {
+        for (job, status) in &statuses {
+            print_job_status(job, status);
+        }
+    }
+    Ok(())
+}
+
+pub async fn run_status(req: StatusRequest) -> Result<()> {
+    if req.all_instances {
+        return print_instances(req.json).await;
+    }
+    let paths = default_paths()?;
+    if let Some(interval) = req.watch {
+        return watch_status(&paths, interval).await;
+    }
+    let cfg = load_instance_config(&paths)?;
+    let runtime = load_live_runtime(&paths)?.unwrap_or_default();
     let endpoint = if runtime.rmvm_endpoint.is_empty() {
         rmvm_endpoint(&cfg)
     } else {
@@ -1344,7 +2753,15 @@ pub async fn run_status(req: StatusRequest) -> Result<()> {
         return Ok(());
     }
     let planner_model = provider.as_ref().map(|p| p.planner_model.clone());
+    let usage = if req.usage && paths.usage_file().exists() {
+        Some(read_usage_file(&paths.usage_file())?)
+    } else if req.usage {
+        Some(BTreeMap::new())
+    } else {
+        None
+    };
     let view = StatusView {
+        profile: current_profile(&base_paths()?),
         active_brain: cfg.active_brain.clone(),
         active_provider: cfg.active_provider.clone(),
         planner_model,
@@ -1365,10 +2782,14 @@ pub async fn run_status(req: StatusRequest) -> Result<()> {
         runtime_rmvm_pid: runtime.rmvm_pid,
         config_path: paths.config_file().display().to_string(),
         state_path: paths.state_dir.display().to_string(),
+        usage,
+        sync: read_sync_file(&paths.sync_file()).unwrap_or_default(),
+        maintenance: read_status_file(&paths.maintenance_file()).unwrap_or_default(),
     };
     if req.json {
         println!("{}", serde_json::to_string_pretty(&view)?);
     } else {
+        println!("profile={}", view.profile);
         println!("brain={}", view.active_brain.as_deref().unwrap_or("<none>"));
         println!(
             "provider={} model={}",
@@ -1388,6 +2809,23 @@ pub async fn run_status(req: StatusRequest) -> Result<()> {
             "runtime proxy_pid={:?} rmvm_pid={:?}",
             view.runtime_proxy_pid, view.runtime_rmvm_pid
         );
+        for (brain_id, sync) in &view.sync {
+            println!(
+                "sync brain={} last_sync={} added={} forgets={} conflicts={}{}",
+                brain_id,
+                sync.last_sync_at.as_deref().unwrap_or("-"),
+                sync.memories_added,
+                sync.forgets_applied,
+                sync.conflicts,
+                sync.last_error
+                    .as_deref()
+                    .map(|e| format!(" error={e}"))
+                    .unwrap_or_default()
+            );
+        }
+        for (job, status) in &view.maintenance {
+            print_job_status(job, status);
+        }
         println!("dashboard={}", view.dashboard_url);
         let overall = if view.proxy_healthy && view.rmvm_healthy {
             "healthy"
@@ -1400,17 +2838,67 @@ pub async fn run_status(req: StatusRequest) -> Result<()> {
             println!("state={}", view.state_path);
             println!("hint=run `cortex open` to view the local dashboard");
         }
+        if let Some(usage) = view.usage.as_ref() {
+            print_usage(usage);
+        }
     }
     Ok(())
 }
 
-fn print_tail(path: &Path, tail: usize) -> Result<()> {
-    if !path.exists() {
-        println!("{} not found", path.display());
+fn print_usage(usage: &BTreeMap<String, KeyUsage>) {
+    if usage.is_empty() {
+        println!("usage: no requests recorded yet");
+        return;
+    }
+    for (key, u) in usage {
+        println!(
+            "usage key={} requests={} errors={} error_rate={:.1}% est_tokens={} planner_tokens={} last_seen={}",
+            key,
+            u.requests,
+            u.errors,
+            u.error_rate() * 100.0,
+            u.estimated_tokens,
+            u.planner_tokens,
+            u.last_seen.as_deref().unwrap_or("-")
+        );
+        if !u.agents.is_empty() {
+            let agents = u
+                .agents
+                .iter()
+                .map(|(agent, count)| format!("{agent}={count}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            println!("  agents {}", agents);
+        }
+    }
+}
+
+/// Where `cortex logs` reads from and how it filters and renders lines.
+struct LogView<'a> {
+    service: &'a str,
+    path: PathBuf,
+    filter: &'a LogFilter,
+    json: bool,
+}
+
+impl LogView<'_> {
+    fn render(&self, line: &str) -> Option<String> {
+        render_line(line, self.service, self.filter, self.json)
+    }
+}
+
+fn print_tail(view: &LogView<'_>, tail: usize) -> Result<()> {
+    if !view.path.exists() {
+        if !view.json {
+            println!("{} not found", view.path.display());
+        }
         return Ok(());
     }
-    let content = fs::read_to_string(path)?;
-    let lines = content.lines().collect::<Vec<_>>();
+    let content = fs::read_to_string(&view.path)?;
+    let lines = content
+        .lines()
+        .filter_map(|line| view.render(line))
+        .collect::<Vec<_>>();
     let start = lines.len().saturating_sub(tail);
     for line in &lines[start..] {
         println!("{}", line);
@@ -1422,24 +2910,35 @@ fn file_len(path: &Path) -> u64 {
     fs::metadata(path).map(|m| m.len()).unwrap_or(0)
 }
 
-fn print_new_bytes(path: &Path, offset: u64) -> Result<u64> {
-    if !path.exists() {
+/// Prints complete lines appended since `offset`; a trailing partial line waits for the next poll.
+fn print_new_lines(view: &LogView<'_>, offset: u64) -> Result<u64> {
+    if !view.path.exists() {
         return Ok(offset);
     }
-    let mut file = File::open(path)?;
+    let mut file = File::open(&view.path)?;
     let len = file.metadata()?.len();
-    if len <= offset {
+    // A shorter file was rotated since the last poll; read the new one from the start.
+    let offset = if len < offset { 0 } else { offset };
+    if len == offset {
         return Ok(offset);
     }
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
     let start = offset as usize;
-    if start < buffer.len() {
-        let chunk = &buffer[start..];
-        print!("{}", String::from_utf8_lossy(chunk));
-        std::io::stdout().flush()?;
+    let Some(end) = buffer[start.min(buffer.len())..]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| start + i + 1)
+    else {
+        return Ok(offset);
+    };
+    for line in String::from_utf8_lossy(&buffer[start..end]).lines() {
+        if let Some(line) = view.render(line) {
+            println!("{}", line);
+        }
     }
-    Ok(len)
+    std::io::stdout().flush()?;
+    Ok(end as u64)
 }
 
 pub async fn run_logs(req: LogsRequest) -> Result<()> {
@@ -1448,41 +2947,296 @@ pub async fn run_logs(req: LogsRequest) -> Result<()> {
     if service != "proxy" && service != "rmvm" && service != "all" {
         bail!("--service must be proxy|rmvm|all");
     }
+    let filter = LogFilter::parse(
+        req.level.as_deref(),
+        req.since.as_deref(),
+        chrono::Utc::now(),
+    )?;
+    let mut views = Vec::new();
     if service == "proxy" || service == "all" {
-        println!("== proxy ==");
-        print_tail(&paths.proxy_log_file(), req.tail)?;
+        views.push(LogView {
+            service: "proxy",
+            path: Some(paths.proxy_trace_file())
+                .filter(|p| p.exists())
+                .unwrap_or_else(|| paths.proxy_log_file()),
+            filter: &filter,
+            json: req.json,
+        });
     }
     if service == "rmvm" || service == "all" {
-        println!("== rmvm ==");
-        print_tail(&paths.rmvm_log_file(), req.tail)?;
+        views.push(LogView {
+            service: "rmvm",
+            path: paths.rmvm_log_file(),
+            filter: &filter,
+            json: req.json,
+        });
+    }
+    for view in &views {
+        if !req.json {
+            println!("== {} ==", view.service);
+        }
+        print_tail(view, req.tail)?;
     }
     if !req.follow {
         return Ok(());
     }
-    let mut offset_proxy = file_len(&paths.proxy_log_file());
-    let mut offset_rmvm = file_len(&paths.rmvm_log_file());
+    let mut offsets = views.iter().map(|v| file_len(&v.path)).collect::<Vec<_>>();
     loop {
-        if service == "proxy" || service == "all" {
-            offset_proxy = print_new_bytes(&paths.proxy_log_file(), offset_proxy)?;
-        }
-        if service == "rmvm" || service == "all" {
-            offset_rmvm = print_new_bytes(&paths.rmvm_log_file(), offset_rmvm)?;
+        for (view, offset) in views.iter().zip(offsets.iter_mut()) {
+            *offset = print_new_lines(view, *offset)?;
         }
         sleep(Duration::from_millis(750)).await;
     }
 }
 
-pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
-    if !req.yes {
-        let prompt = if req.all {
-            "This will stop Cortex, permanently remove local data (brains, auth mappings, config, logs), and uninstall local binaries. Continue?"
-        } else {
-            "This will stop Cortex services. Continue?"
-        };
-        if !confirm_action(prompt)? {
-            println!("Uninstall canceled.");
-            return Ok(());
-        }
+/// Prints variables that point OpenAI-compatible tools and `cortex` subcommands at the
+/// configured proxy, for `eval "$(cortex env)"`.
+pub fn run_env(req: EnvRequest) -> Result<()> {
+    let shell = EnvShell::parse(req.shell.as_deref())?;
+    let paths = default_paths()?;
+    let cfg = load_instance_config(&paths)?;
+    let Some(api_key) = cfg.proxy_api_key.clone() else {
+        bail!("no proxy API key yet; run `cortex setup` first");
+    };
+    let provider = resolve_provider(&cfg, None)?;
+    let mut vars = vec![
+        ("OPENAI_BASE_URL", format!("http://{}/v1", cfg.proxy_addr)),
+        ("OPENAI_API_KEY", api_key),
+        ("CORTEX_ENDPOINT", rmvm_endpoint(&cfg)),
+        ("CORTEX_PLANNER_MODE", provider.planner_mode.clone()),
+        ("CORTEX_PLANNER_BASE_URL", provider.planner_base_url.clone()),
+        ("CORTEX_PLANNER_MODEL", provider.planner_model.clone()),
+        (PROFILE_ENV, current_profile(&base_paths()?)),
+    ];
+    if let Some(model_path) = provider.local_model_path.as_ref() {
+        vars.push((
+            "CORTEX_PLANNER_MODEL_PATH",
+            model_path.display().to_string(),
+        ));
+    }
+    let overrides = [
+        (
+            "CORTEX_PLANNER_TIMEOUT_SECS",
+            provider.timeout_secs.map(|v| v.to_string()),
+        ),
+        (
+            "CORTEX_PLANNER_TEMPERATURE",
+            provider.temperature.map(|v| v.to_string()),
+        ),
+        (
+            "CORTEX_PLANNER_MAX_TOKENS",
+            provider.max_tokens.map(|v| v.to_string()),
+        ),
+        ("CORTEX_PLANNER_ORGANIZATION", provider.organization.clone()),
+        ("CORTEX_PLANNER_PROJECT", provider.project.clone()),
+    ];
+    vars.extend(
+        overrides
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?))),
+    );
+    if let Some(brain) = cfg.active_brain.clone() {
+        vars.push(("CORTEX_BRAIN", brain));
+    }
+    if let Some(instance) = current_instance() {
+        vars.push((INSTANCE_ENV, instance));
+    }
+    if json_output() {
+        let map = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), JsonValue::from(value.as_str())))
+            .collect::<serde_json::Map<_, _>>();
+        println!("{}", serde_json::to_string_pretty(&map)?);
+    } else {
+        print!("{}", shell_env::render(shell, &vars));
+    }
+    Ok(())
+}
+
+/// Log lines per service included in a debug bundle.
+const BUNDLE_LOG_LINES: usize = 2000;
+
+/// Environment variables whose values are masked in a debug bundle.
+const BUNDLE_SECRET_ENVS: [&str; 6] = [
+    "CORTEX_PLANNER_API_KEY",
+    "CORTEX_BRAIN_SECRET",
+    "OPENAI_API_KEY",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+];
+
+/// Zips redacted config, runtime state, recent logs and `cortex doctor --json` output for a
+/// bug report. Secrets known to this profile are masked along with common token shapes.
+pub fn run_debug_bundle(req: DebugBundleRequest) -> Result<()> {
+    let paths = default_paths()?;
+    let cfg = load_instance_config(&paths).ok();
+
+    let mut secrets = BUNDLE_SECRET_ENVS
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .collect::<Vec<_>>();
+    if let Some(cfg) = cfg.as_ref() {
+        secrets.extend(cfg.proxy_api_key.clone());
+        secrets.extend(env::var(&cfg.brain_secret_env).ok());
+        let refs = cfg
+            .providers
+            .values()
+            .filter_map(|p| p.planner_api_key_ref.as_deref())
+            .chain([cfg.brain_secret_ref.as_str()]);
+        for secret_ref in refs {
+            secrets.extend(get_secret(&paths, secret_ref).ok().flatten());
+        }
+    }
+    let scrubber = Scrubber::new(secrets)?;
+
+    let mut zip = ZipWriter::default();
+    let mut files = Vec::new();
+    let mut add = |name: &str, data: String| {
+        zip.add(name, data.as_bytes());
+        files.push(name.to_string());
+    };
+
+    let version = serde_json::json!({
+        "cortex_version": env!("CARGO_PKG_VERSION"),
+        "os": env::consts::OS,
+        "arch": env::consts::ARCH,
+        "profile": current_profile(&base_paths()?),
+        "instance": current_instance(),
+        "config_version": CONFIG_VERSION,
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+    });
+    add("version.json", serde_json::to_string_pretty(&version)?);
+    for (name, path) in [
+        ("config.json", paths.config_file()),
+        ("runtime.json", paths.runtime_file()),
+    ] {
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let text = match serde_json::from_str::<JsonValue>(&raw) {
+            Ok(mut value) => {
+                scrubber.scrub_json(&mut value);
+                serde_json::to_string_pretty(&value)?
+            }
+            Err(_) => scrubber.scrub(&raw),
+        };
+        add(name, text);
+    }
+    for (name, path) in [
+        ("logs/proxy.log", paths.proxy_log_file()),
+        ("logs/proxy.jsonl", paths.proxy_trace_file()),
+        ("logs/rmvm.log", paths.rmvm_log_file()),
+    ] {
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let lines = raw.lines().collect::<Vec<_>>();
+        let recent = lines[lines.len().saturating_sub(BUNDLE_LOG_LINES)..].join("\n");
+        add(name, scrubber.scrub(&recent) + "\n");
+    }
+
+    let mut doctor = Command::new(env::current_exe()?);
+    doctor.args(["doctor", "--json", "--timeout-secs", "5"]);
+    doctor.arg("--home").arg(brains_home()?);
+    if let Some(cfg) = cfg.as_ref() {
+        doctor
+            .arg("--proxy-base-url")
+            .arg(format!("http://{}/v1", cfg.proxy_addr))
+            .arg("--endpoint")
+            .arg(rmvm_endpoint(cfg));
+    }
+    match doctor.stdin(Stdio::null()).output() {
+        Ok(output) => {
+            add(
+                "doctor.json",
+                scrubber.scrub(&String::from_utf8_lossy(&output.stdout)),
+            );
+            if !output.stderr.is_empty() {
+                add(
+                    "doctor.stderr.txt",
+                    scrubber.scrub(&String::from_utf8_lossy(&output.stderr)),
+                );
+            }
+        }
+        Err(err) => add(
+            "doctor.stderr.txt",
+            format!("failed to run doctor: {err}\n"),
+        ),
+    }
+
+    let out = req.out.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "cortex-debug-{}.zip",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+    fs::write(&out, zip.finish()).with_context(|| format!("failed to write {}", out.display()))?;
+    if json_output() {
+        let view = serde_json::json!({ "bundle": out.display().to_string(), "files": files });
+        println!("{}", serde_json::to_string_pretty(&view)?);
+    } else {
+        println!(
+            "Wrote debug bundle {} ({} files)",
+            out.display(),
+            files.len()
+        );
+        println!("Secrets and API keys are masked; review the archive before sharing it.");
+    }
+    Ok(())
+}
+
+pub async fn run_uninstall(req: UninstallRequest) -> Result<()> {
+    let export_dir = if req.purge_brains {
+        match req.export_dir {
+            Some(dir) => Some(dir),
+            None if req.yes => {
+                bail!("--purge-brains --yes needs --export-dir for the final export")
+            }
+            None => {
+                let Some(dir) = prompt_optional("Directory for a final export of every brain")?
+                else {
+                    println!("Uninstall canceled: --purge-brains needs an export directory.");
+                    return Ok(());
+                };
+                Some(PathBuf::from(dir))
+            }
+        }
+    } else {
+        None
+    };
+    if !req.yes {
+        let prompt = if req.purge_brains {
+            "This will stop Cortex, export and then permanently remove every brain and all local data (auth mappings, config, logs), and uninstall local binaries. Continue?"
+        } else if req.all {
+            "This will stop Cortex, permanently remove local data (auth mappings, config, logs), and uninstall local binaries. Brains are kept. Continue?"
+        } else {
+            "This will stop Cortex services. Continue?"
+        };
+        if !confirm_action(prompt)? {
+            println!("Uninstall canceled.");
+            return Ok(());
+        }
+    }
+
+    // Memory held by the kernel is saved into the brain before it stops, as `cortex stop`
+    // does; a purge stops here when that fails.
+    match flush_managed_kernel().await {
+        Ok(Some(report)) if report.added > 0 => println!(
+            "Saved {} new memories into brain {}",
+            report.added, report.brain_id
+        ),
+        Ok(_) => {}
+        Err(e) if req.purge_brains => {
+            bail!("could not save kernel memory into the brain, nothing was removed: {e}")
+        }
+        Err(e) => println!("Warning: could not save kernel memory into the brain: {e}"),
+    }
+
+    if req.all
+        && let Err(e) = crate::service::uninstall()
+    {
+        println!("Warning: could not remove the Cortex service: {e}");
     }
 
     let stop_result = run_stop(StopRequest {
@@ -1495,6 +3249,20 @@ pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
         println!("Warning: could not fully stop services: {e}");
     }
 
+    // Nothing is removed unless every brain was exported first.
+    if let Some(dir) = export_dir.as_ref() {
+        let exported = export_all_brains(&brain_store()?, &default_paths()?, dir)
+            .context("final export failed, nothing was removed")?;
+        println!(
+            "Exported {} brain(s) to {} (listed in {UNINSTALL_EXPORT_RECORD}).",
+            exported.len(),
+            dir.display()
+        );
+        println!(
+            "Restore with `cortex brain import --in <file>`; the brain secrets are still needed."
+        );
+    }
+
     if !req.all {
         println!("Cortex services stopped.");
         println!("Tip: run `cortex uninstall --all --yes` to remove local Cortex data.");
@@ -1502,7 +3270,7 @@ pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
     }
 
     let paths = default_paths()?;
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
     let brain_home = store.home_dir().to_path_buf();
 
     let mut removed_data = Vec::new();
@@ -1514,7 +3282,7 @@ pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
         remove_dir_if_exists(&paths.state_dir)?;
         removed_data.push(paths.state_dir.display().to_string());
     }
-    if brain_home.exists() {
+    if req.purge_brains && brain_home.exists() {
         remove_dir_if_exists(&brain_home)?;
         removed_data.push(brain_home.display().to_string());
     }
@@ -1527,6 +3295,12 @@ pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
             println!("  removed {}", p);
         }
     }
+    if !req.purge_brains && brain_home.exists() {
+        println!(
+            "Kept brains in {} (use --purge-brains to remove them).",
+            brain_home.display()
+        );
+    }
 
     let (removed_bins, scheduled_bins, binary_warnings) = remove_local_binaries()?;
     if !removed_bins.is_empty() {
@@ -1551,6 +3325,66 @@ pub fn run_uninstall(req: UninstallRequest) -> Result<()> {
     Ok(())
 }
 
+const UNINSTALL_EXPORT_RECORD: &str = "cortex-export.json";
+
+#[derive(Debug, Serialize)]
+struct ExportedBrain {
+    brain_id: String,
+    name: String,
+    file: String,
+    bytes: usize,
+    sha256: String,
+}
+
+/// Writes every brain to `dir` as a `.cbrain` package, still encrypted under its own secret,
+/// and records what was written so the purge can be checked and undone.
+fn export_all_brains(store: &BrainStore, paths: &Paths, dir: &Path) -> Result<Vec<ExportedBrain>> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create export dir {}", dir.display()))?;
+    let target = dir.canonicalize()?;
+    for removed in [
+        store.home_dir(),
+        paths.config_dir.as_path(),
+        paths.state_dir.as_path(),
+    ] {
+        if let Ok(removed) = removed.canonicalize()
+            && target.starts_with(&removed)
+        {
+            bail!(
+                "export dir {} is inside {}, which uninstall removes",
+                dir.display(),
+                removed.display()
+            );
+        }
+    }
+    let mut exported = Vec::new();
+    for brain in store.list_brains()? {
+        let file = format!("{}.cbrain", brain.brain_id);
+        let path = dir.join(&file);
+        store
+            .export_brain(&brain.brain_id, &path)
+            .with_context(|| format!("failed to export brain {}", brain.name))?;
+        let raw = fs::read(&path)?;
+        exported.push(ExportedBrain {
+            brain_id: brain.brain_id,
+            name: brain.name,
+            file,
+            bytes: raw.len(),
+            sha256: format!("{:x}", Sha256::digest(&raw)),
+        });
+    }
+    let record = json!({
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "brains_home": store.home_dir(),
+        "brains": exported,
+    });
+    fs::write(
+        dir.join(UNINSTALL_EXPORT_RECORD),
+        serde_json::to_vec_pretty(&record)?,
+    )?;
+    Ok(exported)
+}
+
 #[derive(Debug, Serialize)]
 struct ConnectorStatusRow {
     name: String,
@@ -1670,6 +3504,49 @@ pub fn run_connect_status(req: ConnectStatusRequest) -> Result<()> {
     Ok(())
 }
 
+pub fn run_connect_config(req: ConnectConfigRequest) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    let Some(api_key) = cfg.proxy_api_key.clone() else {
+        bail!("no proxy API key yet; run `cortex setup` first");
+    };
+    let model = cfg
+        .providers
+        .get(&cfg.active_provider)
+        .map(|p| p.planner_model.clone())
+        .unwrap_or_default();
+    let settings = ClientSettings {
+        base_url: format!("http://{}/v1", cfg.proxy_addr),
+        api_key,
+        model,
+    };
+    let config = integrations::render(&req.app, &settings)?;
+    if req.write {
+        let path = integrations::write(&config)?;
+        if let Some(connector) = cfg.connectors.get_mut(&req.app) {
+            connector.enabled = true;
+        }
+        save_config(&paths, &cfg)?;
+        println!(
+            "Wrote Cortex settings for {} to {}",
+            req.app,
+            path.display()
+        );
+        return Ok(());
+    }
+    match (&config.path, &config.manual_reason) {
+        (Some(path), Some(reason)) => println!("# {} ({reason})", path.display()),
+        (Some(path), None) => println!(
+            "# {} (run `cortex connect config {} --write` to apply)",
+            path.display(),
+            req.app
+        ),
+        (None, _) => {}
+    }
+    print!("{}", config.snippet);
+    Ok(())
+}
+
 pub fn run_mode_set(req: ModeSetRequest) -> Result<()> {
     let paths = default_paths()?;
     let mut cfg = load_config(&paths)?;
@@ -1694,11 +3571,16 @@ pub fn run_mode_status(req: ModeStatusRequest) -> Result<()> {
 }
 
 async fn maybe_restart_proxy(paths: &Paths, cfg: &ProductConfig) -> Result<()> {
-    let runtime = load_runtime(paths)?;
+    let runtime = load_live_runtime(paths)?;
     let Some(mut runtime) = runtime else {
         println!("Proxy is not running; config updated.");
         return Ok(());
     };
+    let mut cfg = cfg.clone();
+    if let Some(name) = current_instance() {
+        apply_instance(&mut cfg, &name);
+    }
+    let cfg = &cfg;
     if let Some(pid) = runtime.proxy_pid {
         kill_pid(pid, true);
     }
@@ -1709,7 +3591,14 @@ async fn maybe_restart_proxy(paths: &Paths, cfg: &ProductConfig) -> Result<()> {
     } else {
         runtime.rmvm_endpoint.clone()
     };
-    let proxy_pid = spawn_proxy(cfg, paths, &endpoint, &provider, planner_key)?;
+    let proxy_pid = spawn_proxy(
+        cfg,
+        paths,
+        &endpoint,
+        &provider,
+        planner_key,
+        runtime.lan.as_ref(),
+    )?;
     if !wait_for_proxy(&cfg.proxy_addr, Duration::from_secs(10)).await {
         bail!(
             "proxy restart failed health check; see {}",
@@ -1717,11 +3606,113 @@ async fn maybe_restart_proxy(paths: &Paths, cfg: &ProductConfig) -> Result<()> {
         );
     }
     runtime.proxy_pid = Some(proxy_pid);
+    runtime.proxy_started = process_start_time(proxy_pid);
+    runtime.brain_home = Some(brains_home()?);
     save_runtime(paths, &runtime)?;
     println!("Proxy restarted on {}", cfg.proxy_addr);
     Ok(())
 }
 
+async fn apply_restart_policy(
+    paths: &Paths,
+    cfg: &ProductConfig,
+    restart: RestartPolicy,
+) -> Result<()> {
+    let restart = match restart {
+        RestartPolicy::Auto => true,
+        RestartPolicy::Never => false,
+        RestartPolicy::Prompt => {
+            load_live_runtime(paths)?.is_some_and(|r| r.proxy_pid.is_some())
+                && atty::is(atty::Stream::Stdin)
+                && confirm_action("Restart the proxy now to apply the change?")?
+        }
+    };
+    if restart {
+        maybe_restart_proxy(paths, cfg).await?;
+    }
+    Ok(())
+}
+
+/// Replaces the proxy API key: maps a new key to the active brain with the old key's subject
+/// and quota, keeps the old key working for `grace`, updates client configs that hold it, and
+/// applies the change to a running proxy.
+pub async fn rotate_proxy_key(grace: Duration, restart: RestartPolicy) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    let store = brain_store()?;
+    let old_key = cfg.proxy_api_key.clone();
+    let old_mapping = match old_key.as_deref() {
+        Some(key) => store.resolve_api_key(key)?,
+        None => None,
+    };
+    // The new key inherits the old key's mapping; the active brain is only the default for a
+    // proxy that never had a key.
+    let (tenant_id, brain_id, subject) = match old_mapping.as_ref() {
+        Some(m) => (m.tenant_id.clone(), m.brain_id.clone(), m.subject.clone()),
+        None => {
+            let brain = store.resolve_brain_or_active(cfg.active_brain.as_deref())?;
+            (brain.tenant_id, brain.brain_id, "user:local".to_string())
+        }
+    };
+
+    let new_key = random_api_key();
+    store.map_api_key(&new_key, &tenant_id, &brain_id, &subject)?;
+    if let Some(mapping) = old_mapping.as_ref() {
+        if !mapping.quota.is_unlimited() {
+            store.set_api_key_quota(&new_key, mapping.quota)?;
+        }
+        if mapping.agent_id.is_some() {
+            store.set_api_key_agent(&new_key, mapping.agent_id.as_deref())?;
+        }
+    }
+    let old_expires_at = match old_key.as_deref() {
+        Some(key) => {
+            let at = chrono::Utc::now() + chrono::Duration::from_std(grace)?;
+            store.expire_api_key(key, at)?.then_some(at.to_rfc3339())
+        }
+        None => None,
+    };
+    cfg.proxy_api_key = Some(new_key.clone());
+    save_config(&paths, &cfg)?;
+    store.record_audit(
+        &brain_id,
+        "cli",
+        "auth.rotate_proxy_key",
+        json!({"old_key_expires_at": old_expires_at}),
+    )?;
+
+    let mut updated = Vec::new();
+    if let Some(old_key) = old_key.as_deref() {
+        for app in integrations::APPS {
+            if let Some(path) = integrations::replace_key(app, old_key, &new_key)? {
+                updated.push(path.display().to_string());
+            }
+        }
+    }
+    if json_output() {
+        let view = json!({
+            "api_key": new_key,
+            "brain_id": brain_id,
+            "old_key_expires_at": old_expires_at,
+            "updated_configs": updated,
+        });
+        println!("{}", serde_json::to_string_pretty(&view)?);
+    } else {
+        println!("New proxy API key: {new_key}");
+        match old_expires_at.as_deref() {
+            Some(at) => println!("The previous key keeps working until {at}."),
+            None => println!("There was no previous key mapped."),
+        }
+        for path in &updated {
+            println!("  updated {path}");
+        }
+        println!(
+            "Update apps configured by hand (e.g. Cursor, Open WebUI) and re-run `eval \"$(cortex env)\"`."
+        );
+    }
+    apply_restart_policy(&paths, &cfg, restart).await
+}
+
 pub async fn provider_list(json: bool) -> Result<()> {
     let paths = default_paths()?;
     let cfg = load_config(&paths)?;
@@ -1753,10 +3744,7 @@ pub async fn provider_use(name: &str, model: Option<String>, restart: RestartPol
     }
     save_config(&paths, &cfg)?;
     println!("Active provider set to {}", name);
-    if restart == RestartPolicy::Auto {
-        maybe_restart_proxy(&paths, &cfg).await?;
-    }
-    Ok(())
+    apply_restart_policy(&paths, &cfg, restart).await
 }
 
 pub async fn provider_set_model(
@@ -1774,9 +3762,240 @@ pub async fn provider_set_model(
     profile.planner_model = model.clone();
     save_config(&paths, &cfg)?;
     println!("Provider {} model set to {}", provider_name, model);
-    if restart == RestartPolicy::Auto {
-        maybe_restart_proxy(&paths, &cfg).await?;
+    apply_restart_policy(&paths, &cfg, restart).await
+}
+
+pub async fn provider_route(
+    model: &str,
+    provider: Option<String>,
+    restart: RestartPolicy,
+) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    match provider {
+        Some(provider) => {
+            if !cfg.providers.contains_key(&provider) {
+                bail!("unknown provider '{}'", provider);
+            }
+            cfg.model_routes.insert(model.to_string(), provider.clone());
+            println!("Model {} now plans with provider {}", model, provider);
+        }
+        None => {
+            if cfg.model_routes.remove(model).is_none() {
+                bail!("no route for model '{}'", model);
+            }
+            println!("Removed route for model {}", model);
+        }
+    }
+    save_config(&paths, &cfg)?;
+    apply_restart_policy(&paths, &cfg, restart).await
+}
+
+pub fn provider_add(req: ProviderAddRequest) -> Result<()> {
+    add_provider(&default_paths()?, req)
+}
+
+fn add_provider(paths: &Paths, req: ProviderAddRequest) -> Result<()> {
+    let mut cfg = load_config(paths)?;
+    let name = req.name.trim().to_string();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "invalid provider name '{}': use letters, digits, '-' or '_'",
+            req.name
+        );
+    }
+    if cfg.providers.contains_key(&name) {
+        bail!("provider '{}' already exists; remove it first", name);
+    }
+    let mode = PlannerMode::parse(&req.mode)?;
+    if mode != PlannerMode::AzureOpenAi
+        && (req.azure_deployment.is_some() || req.azure_api_version.is_some())
+    {
+        bail!("--azure-deployment and --azure-api-version require --mode azure-openai");
+    }
+    let local_model_path = match (mode, req.model_path) {
+        (PlannerMode::Local, Some(path)) => Some(
+            fs::canonicalize(&path)
+                .with_context(|| format!("--model-path {} not found", path.display()))?,
+        ),
+        (PlannerMode::Local, None) => bail!("--mode local requires --model-path to a GGUF file"),
+        (_, Some(_)) => bail!("--model-path requires --mode local"),
+        (_, None) => None,
+    };
+    let base_url = match req.base_url {
+        Some(raw) => {
+            let base_url = raw.trim().trim_end_matches('/').to_string();
+            let parsed = reqwest::Url::parse(&base_url)
+                .map_err(|e| anyhow!("invalid --base-url '{}': {e}", raw))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                bail!("--base-url must be an http(s) URL");
+            }
+            base_url
+        }
+        None if mode == PlannerMode::Local => "http://unused".to_string(),
+        None => bail!("--base-url is required for --mode {}", req.mode.trim()),
+    };
+    if req.model.trim().is_empty() {
+        bail!("--model must not be empty");
+    }
+    if req.timeout_secs == Some(0) {
+        bail!("--timeout-secs must be greater than 0");
+    }
+    let mut profile = ProviderProfile {
+        name: name.clone(),
+        planner_mode: req.mode.trim().to_ascii_lowercase(),
+        planner_base_url: base_url,
+        planner_model: req.model.trim().to_string(),
+        planner_api_key_ref: None,
+        azure_deployment: req.azure_deployment,
+        azure_api_version: req.azure_api_version,
+        local_model_path,
+        temperature: req.temperature,
+        max_tokens: req.max_tokens,
+        timeout_secs: req.timeout_secs,
+        extra_headers: req.extra_headers,
+        organization: req.organization,
+        project: req.project,
+    };
+    let api_key = req
+        .api_key
+        .or_else(|| req.api_key_env.as_ref().and_then(|v| env::var(v).ok()));
+    if api_key.is_some() || provider_requires_planner_key(&profile) {
+        profile.planner_api_key_ref = Some(format!("provider.{}.api_key", name));
+    }
+    if let (Some(value), Some(secret_ref)) = (api_key, profile.planner_api_key_ref.as_ref()) {
+        put_secret(paths, secret_ref, &value)?;
+    } else if provider_requires_planner_key(&profile) {
+        println!(
+            "Warning: provider '{}' has no API key; pass --api-key or set CORTEX_PLANNER_API_KEY.",
+            name
+        );
+    }
+    println!(
+        "Added provider {} mode={} model={} base_url={}",
+        name, profile.planner_mode, profile.planner_model, profile.planner_base_url
+    );
+    cfg.providers.insert(name.clone(), profile);
+    save_config(paths, &cfg)?;
+    println!("Use it with: cortex provider use {}", name);
+    Ok(())
+}
+
+pub fn provider_remove(name: &str) -> Result<()> {
+    remove_provider(&default_paths()?, name)
+}
+
+fn remove_provider(paths: &Paths, name: &str) -> Result<()> {
+    let mut cfg = load_config(paths)?;
+    if !cfg.providers.contains_key(name) {
+        bail!("unknown provider '{}'", name);
+    }
+    if cfg.active_provider == name {
+        bail!(
+            "provider '{}' is active; switch first with `cortex provider use <other>`",
+            name
+        );
+    }
+    let routed = cfg
+        .model_routes
+        .iter()
+        .filter(|(_, provider)| provider.as_str() == name)
+        .map(|(model, _)| model.as_str())
+        .collect::<Vec<_>>();
+    if !routed.is_empty() {
+        bail!(
+            "provider '{}' is used by model routes: {} (remove them with `cortex provider route <model> --remove`)",
+            name,
+            routed.join(", ")
+        );
+    }
+    if let Some(profile) = cfg.providers.remove(name)
+        && let Some(secret_ref) = profile.planner_api_key_ref
+        && secret_ref == format!("provider.{}.api_key", name)
+    {
+        delete_secret(paths, &secret_ref)?;
+    }
+    save_config(paths, &cfg)?;
+    println!("Removed provider {}", name);
+    Ok(())
+}
+
+pub fn provider_routes(json: bool) -> Result<()> {
+    let paths = default_paths()?;
+    let cfg = load_config(&paths)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&cfg.model_routes)?);
+    } else if cfg.model_routes.is_empty() {
+        println!(
+            "No model routes; every request plans with {}",
+            cfg.active_provider
+        );
+    } else {
+        for (model, provider) in &cfg.model_routes {
+            println!("{} -> {}", model, provider);
+        }
+    }
+    Ok(())
+}
+
+/// Serves a brain (or every brain of `tenant:<id>`) from its own RMVM endpoint, or removes the
+/// mapping when `endpoint` is `None`. Brain names are stored as ids.
+pub async fn brain_endpoint(
+    target: &str,
+    endpoint: Option<String>,
+    restart: RestartPolicy,
+) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    let key = match target.strip_prefix(TENANT_PREFIX) {
+        Some(tenant) if tenant.trim().is_empty() => bail!("expected tenant:<id>"),
+        Some(_) => target.to_string(),
+        None if endpoint.is_none() && cfg.brain_endpoints.contains_key(target) => {
+            target.to_string()
+        }
+        None => {
+            ensure_brain_secret_env(&paths, &cfg)?;
+            brain_store()?.resolve_brain(target)?.brain_id
+        }
+    };
+    match endpoint {
+        Some(endpoint) => {
+            let endpoint = normalize_grpc_endpoint(endpoint.trim());
+            println!("Brain {target} now runs on {endpoint}");
+            cfg.brain_endpoints.insert(key, endpoint);
+        }
+        None => {
+            if cfg.brain_endpoints.remove(&key).is_none() {
+                bail!("no endpoint mapping for '{target}'");
+            }
+            println!("Brain {target} now runs on the default RMVM");
+        }
+    }
+    validate_config(&cfg)?;
+    save_config(&paths, &cfg)?;
+    apply_restart_policy(&paths, &cfg, restart).await
+}
+
+pub fn brain_endpoints(json: bool) -> Result<()> {
+    let paths = default_paths()?;
+    let cfg = load_instance_config(&paths)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&cfg.brain_endpoints)?);
+        return Ok(());
+    }
+    ensure_brain_secret_env(&paths, &cfg)?;
+    let store = brain_store()?;
+    for (key, endpoint) in &cfg.brain_endpoints {
+        match store.resolve_brain(key) {
+            Ok(brain) => println!("{} [{}] -> {}", brain.name, key, endpoint),
+            Err(_) => println!("{} -> {}", key, endpoint),
+        }
     }
+    println!("* -> {}", rmvm_endpoint(&cfg));
     Ok(())
 }
 
@@ -1784,7 +4003,7 @@ pub fn brain_current(json: bool) -> Result<()> {
     let paths = default_paths()?;
     let cfg = load_config(&paths)?;
     ensure_brain_secret_env(&paths, &cfg)?;
-    let store = BrainStore::new(None)?;
+    let store = brain_store()?;
     let summary = store.resolve_brain_or_active(cfg.active_brain.as_deref())?;
     if json {
         println!("{}", serde_json::to_string_pretty(&summary)?);
@@ -1842,14 +4061,1296 @@ fn open_in_browser(url: &str) -> bool {
     false
 }
 
-pub fn load_saved_proxy_api_key() -> Result<Option<String>> {
+pub fn provider_names() -> Result<Vec<String>> {
     let paths = default_paths()?;
     let cfg = load_config(&paths)?;
-    Ok(cfg.proxy_api_key)
+    Ok(cfg.providers.keys().cloned().collect())
 }
 
-pub fn ensure_saved_brain_secret_env() -> Result<()> {
+fn provider_planner(
+    paths: &Paths,
+    profile: &ProviderProfile,
+    timeout: Duration,
+) -> Result<PlannerConfig> {
+    Ok(PlannerConfig {
+        mode: PlannerMode::parse(&profile.planner_mode)?,
+        base_url: profile.planner_base_url.clone(),
+        model: profile.planner_model.clone(),
+        api_key: planner_api_key(paths, profile)?,
+        timeout: profile
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(timeout),
+        azure: Some(AzureSettings {
+            deployment: profile
+                .azure_deployment
+                .clone()
+                .unwrap_or_else(|| profile.planner_model.clone()),
+            api_version: profile
+                .azure_api_version
+                .clone()
+                .unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string()),
+        }),
+        local_model_path: profile.local_model_path.clone(),
+        options: PlannerOptions {
+            temperature: profile.temperature,
+            max_tokens: profile.max_tokens,
+            headers: profile.extra_headers.clone(),
+            organization: profile.organization.clone(),
+            project: profile.project.clone(),
+        },
+    })
+}
+
+fn model_route_planners(
+    paths: &Paths,
+    cfg: &ProductConfig,
+    timeout: Duration,
+) -> Result<BTreeMap<String, PlannerConfig>> {
+    let mut routes = BTreeMap::new();
+    for (model, provider) in &cfg.model_routes {
+        let profile = resolve_provider(cfg, Some(provider))
+            .with_context(|| format!("model route '{}'", model))?;
+        routes.insert(model.clone(), provider_planner(paths, profile, timeout)?);
+    }
+    Ok(routes)
+}
+
+/// Planner settings for each routed model name, with planner keys read from the secret store.
+pub fn planner_routes(timeout: Duration) -> Result<BTreeMap<String, PlannerConfig>> {
     let paths = default_paths()?;
     let cfg = load_config(&paths)?;
-    ensure_brain_secret_env(&paths, &cfg)
+    model_route_planners(&paths, &cfg, timeout)
+}
+
+/// Planner settings for the provider `name`, with its planner key read from the secret store.
+pub fn named_provider_planner(name: &str, timeout: Duration) -> Result<PlannerConfig> {
+    let paths = default_paths()?;
+    let cfg = load_config(&paths)?;
+    let mut planner = provider_planner(&paths, resolve_provider(&cfg, Some(name))?, timeout)?;
+    if planner.api_key.is_none() && planner.mode == PlannerMode::Bedrock {
+        planner.api_key = bedrock_credentials_from_env();
+    }
+    Ok(planner)
+}
+
+/// What a proxy started by `cortex up` picks up on reload: the active provider and brain,
+/// model routes, the proxy API key, and planner keys from the secret store.
+pub fn proxy_live_settings(timeout: Duration) -> Result<LiveSettings> {
+    let paths = default_paths()?;
+    let cfg = load_instance_config(&paths)?;
+    let mut planner = provider_planner(&paths, resolve_provider(&cfg, None)?, timeout)?;
+    if planner.api_key.is_none() && planner.mode == PlannerMode::Bedrock {
+        planner.api_key = bedrock_credentials_from_env();
+    }
+    Ok(LiveSettings {
+        default_brain: cfg.active_brain.clone(),
+        planner,
+        planner_routes: model_route_planners(&paths, &cfg, timeout)?,
+        provider_name: Some(cfg.active_provider.clone()),
+        proxy_api_key: cfg.proxy_api_key.clone(),
+    })
+}
+
+pub fn save_active_brain(brain_id: &str) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    match current_instance().and_then(|name| cfg.instances.get_mut(&name)) {
+        Some(instance) => instance.brain = Some(brain_id.to_string()),
+        None => cfg.active_brain = Some(brain_id.to_string()),
+    }
+    save_config(&paths, &cfg)
+}
+
+pub fn save_active_provider(name: &str) -> Result<()> {
+    let paths = default_paths()?;
+    let mut cfg = load_config(&paths)?;
+    if !cfg.providers.contains_key(name) {
+        bail!("unknown provider '{}'", name);
+    }
+    cfg.active_provider = name.to_string();
+    save_config(&paths, &cfg)
+}
+
+pub fn is_managed_proxy(pid: u32) -> Result<bool> {
+    managed_proxy(&default_paths()?, pid)
+}
+
+fn managed_proxy(paths: &Paths, pid: u32) -> Result<bool> {
+    // A foreground `cortex up` owns its proxy; a detached restart would tear it down.
+    Ok(load_runtime(paths)?.is_some_and(|r| !r.foreground && r.proxy_pid == Some(pid)))
+}
+
+/// Relaunches the managed stack through a detached `cortex up`; the running proxy cannot
+/// restart itself in-process, so it is replaced by the new one.
+pub fn spawn_detached_up() -> Result<()> {
+    let exe = env::current_exe().context("failed to resolve cortex executable path")?;
+    Command::new(exe)
+        .arg("up")
+        .arg("--home")
+        .arg(brains_home()?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to spawn cortex up")?;
+    Ok(())
+}
+
+pub fn load_saved_proxy_api_key() -> Result<Option<String>> {
+    let paths = default_paths()?;
+    let cfg = load_config(&paths)?;
+    Ok(cfg.proxy_api_key)
+}
+
+pub fn ensure_saved_brain_secret_env() -> Result<()> {
+    let paths = default_paths()?;
+    let cfg = load_config(&paths)?;
+    ensure_brain_secret_env(&paths, &cfg)
+}
+
+/// Where brains are stored, and whether the running proxy reads the same place.
+pub fn doctor_home_check(home: &Path) -> (&'static str, Result<String>) {
+    let check = || -> Result<String> {
+        let brains = BrainStore::new(Some(home.to_path_buf()))?
+            .list_brains()?
+            .len();
+        let paths = default_paths()?;
+        let runtime = load_runtime(&paths)?.unwrap_or_default();
+        if let Some(pid) = runtime.proxy_pid
+            && is_cortex_process(pid, runtime.proxy_started.as_deref())
+            && let Some(proxy_home) = runtime.brain_home.as_ref()
+            && proxy_home != home
+        {
+            bail!(
+                "proxy (pid {pid}) uses {} but this command uses {}; run `cortex up` again or pass --home {}",
+                proxy_home.display(),
+                home.display(),
+                proxy_home.display()
+            );
+        }
+        Ok(format!("{} ({brains} brain(s))", home.display()))
+    };
+    ("brains_home", check())
+}
+
+/// Secret storage checks for `cortex doctor`. They read stored secrets without exporting or
+/// generating one, so drift is reported instead of papered over with a fresh secret.
+pub fn doctor_secret_checks(brain_ref: Option<&str>) -> Vec<(&'static str, Result<String>)> {
+    let loaded = default_paths().and_then(|paths| {
+        let cfg = load_config(&paths)?;
+        let storage = configured_secret_storage(&paths)?;
+        Ok((paths, cfg, storage))
+    });
+    let (paths, cfg, storage) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => return vec![("secret_storage", Err(e))],
+    };
+    vec![
+        ("keyring_available", check_keyring(&storage)),
+        ("fallback_secrets", check_fallback_secrets(&paths)),
+        ("brain_secret", check_brain_secret(&paths, &cfg, brain_ref)),
+    ]
+}
+
+fn check_keyring(storage: &str) -> Result<String> {
+    let entry = secret_entry(&format!("doctor.probe.{}", Uuid::new_v4().simple()))?;
+    let probe = entry
+        .set_password("probe")
+        .and_then(|()| entry.get_password());
+    let _ = entry.delete_credential();
+    match probe {
+        Ok(value) if value == "probe" => Ok(format!(
+            "OS keyring read/write works (secret_storage={storage})"
+        )),
+        Ok(_) => bail!("OS keyring returned a different value than was written"),
+        Err(err) if storage == "keyring" => Err(keyring_unavailable(err)),
+        Err(err) => Ok(format!(
+            "OS keyring unavailable ({err}); secrets use the encrypted fallback file"
+        )),
+    }
+}
+
+fn check_fallback_secrets(paths: &Paths) -> Result<String> {
+    let secrets_file = paths.fallback_secrets_file();
+    let key_file = paths.fallback_key_file();
+    if !secrets_file.exists() {
+        return Ok("no fallback secret file".to_string());
+    }
+    // Checked first because decrypting would generate a new key in its place.
+    if !key_file.exists() {
+        bail!(
+            "{} exists but its key {} is missing",
+            secrets_file.display(),
+            key_file.display()
+        );
+    }
+    let map = load_fallback_secrets(paths)?;
+    let broken = map
+        .iter()
+        .filter(|(_, sealed)| decrypt_secret(paths, sealed).is_err())
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>();
+    if !broken.is_empty() {
+        bail!(
+            "{} of {} secret(s) do not decrypt with {}: {}",
+            broken.len(),
+            map.len(),
+            key_file.display(),
+            broken.join(", ")
+        );
+    }
+    Ok(format!(
+        "{} secret(s) decrypt with {}",
+        map.len(),
+        key_file.display()
+    ))
+}
+
+fn check_brain_secret(
+    paths: &Paths,
+    cfg: &ProductConfig,
+    brain_ref: Option<&str>,
+) -> Result<String> {
+    let secret_ref = cfg.brain_secret_ref.as_str();
+    let from_file = if paths.fallback_key_file().exists() {
+        load_fallback_secrets(paths)?
+            .get(secret_ref)
+            .map(|sealed| decrypt_secret(paths, sealed))
+            .transpose()?
+    } else {
+        None
+    };
+    let from_keyring = secret_entry(secret_ref)
+        .ok()
+        .and_then(|entry| entry.get_password().ok());
+    if let (Some(file), Some(keyring)) = (from_file.as_ref(), from_keyring.as_ref())
+        && file != keyring
+    {
+        bail!(
+            "the keyring and {} hold different values for {secret_ref}; cortex uses the file copy",
+            paths.fallback_secrets_file().display()
+        );
+    }
+    let Some(secret) = from_file.or(from_keyring) else {
+        bail!(
+            "no secret stored under {secret_ref}; the next command will generate a new one, \
+             which cannot unlock existing brains"
+        );
+    };
+    let store = brain_store()?;
+    let brain = store.resolve_brain_or_active(brain_ref)?;
+    store.verify_secret(&brain.brain_id, &secret)?;
+    Ok(format!("{secret_ref} unlocks brain {}", brain.brain_id))
+}
+
+/// Resolves the profile for this process and exports it so every command and the services
+/// spawned by `cortex up` agree on it. Returns the profile's `brains_home` for [`select_home`].
+pub fn select_profile(flag: Option<&str>) -> Result<Option<PathBuf>> {
+    let base = base_paths()?;
+    let name = match flag {
+        Some(name) => name.trim().to_string(),
+        None => current_profile(&base),
+    };
+    validate_profile_name(&name)?;
+    let paths = profile_paths(&base, &name);
+    let cfg = read_config(&paths)?;
+    if cfg.is_none() && name != DEFAULT_PROFILE {
+        bail!("profile '{name}' does not exist; create it with `cortex profile create {name}`");
+    }
+    unsafe {
+        env::set_var(PROFILE_ENV, &name);
+    }
+    Ok(cfg.and_then(|cfg| cfg.brains_home).map(PathBuf::from))
+}
+
+pub fn json_output() -> bool {
+    env::var(OUTPUT_ENV).is_ok_and(|v| v.trim().eq_ignore_ascii_case("json"))
+}
+
+/// Validates `--output` (or `CORTEX_OUTPUT`) and exports it so every command sees one setting.
+pub fn select_output(flag: Option<&str>) -> Result<()> {
+    let format = match flag {
+        Some(format) => format.trim().to_ascii_lowercase(),
+        None => match env::var(OUTPUT_ENV) {
+            Ok(format) => format.trim().to_ascii_lowercase(),
+            Err(_) => return Ok(()),
+        },
+    };
+    if !matches!(format.as_str(), "text" | "json") {
+        bail!("invalid output format '{format}', expected text|json");
+    }
+    unsafe {
+        env::set_var(OUTPUT_ENV, &format);
+    }
+    Ok(())
+}
+
+/// Resolves the brains home once for this process: `--home`, then `CORTEX_HOME`, then the
+/// profile's `brains_home`, then `~/.cortex`. Services started from here are handed it
+/// explicitly.
+pub fn select_home(flag: Option<&Path>, profile_home: Option<PathBuf>) -> Result<()> {
+    let env_home = env::var_os("CORTEX_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from);
+    let home = match resolve_home(flag, env_home, profile_home)? {
+        Some(home) => home,
+        None => BrainStore::new(None)?.home_dir().to_path_buf(),
+    };
+    let _ = BRAINS_HOME.set(home);
+    Ok(())
+}
+
+fn resolve_home(
+    flag: Option<&Path>,
+    env_home: Option<PathBuf>,
+    profile_home: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    match flag {
+        Some(home) => std::path::absolute(home)
+            .map(Some)
+            .with_context(|| format!("invalid --home {}", home.display())),
+        None => Ok(env_home.or(profile_home)),
+    }
+}
+
+/// The brain store root every command and spawned service should agree on.
+pub fn brains_home() -> Result<PathBuf> {
+    match BRAINS_HOME.get() {
+        Some(home) => Ok(home.clone()),
+        None => Ok(BrainStore::new(None)?.home_dir().to_path_buf()),
+    }
+}
+
+/// The brain store under [`brains_home`].
+pub fn brain_store() -> Result<BrainStore> {
+    BrainStore::new(Some(brains_home()?))
+}
+
+/// Validates `--instance` (or `CORTEX_INSTANCE`) and exports it for child processes.
+pub fn select_instance(flag: Option<&str>) -> Result<()> {
+    let name = match flag {
+        Some(name) => name.trim().to_string(),
+        None => match current_instance() {
+            Some(name) => name,
+            None => return Ok(()),
+        },
+    };
+    validate_name("instance", &name)?;
+    unsafe {
+        env::set_var(INSTANCE_ENV, &name);
+    }
+    Ok(())
+}
+
+async fn watch_status(paths: &Paths, interval: Duration) -> Result<()> {
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut last_requests = None;
+    loop {
+        let cfg = load_instance_config(paths)?;
+        let runtime = load_live_runtime(paths)?.unwrap_or_default();
+        let endpoint = if runtime.rmvm_endpoint.is_empty() {
+            rmvm_endpoint(&cfg)
+        } else {
+            runtime.rmvm_endpoint.clone()
+        };
+        let proxy_healthy = probe_proxy(&cfg.proxy_addr).await;
+        let rmvm_healthy = probe_rmvm(&endpoint).await;
+        let usage = if paths.usage_file().exists() {
+            read_usage_file(&paths.usage_file()).unwrap_or_default()
+        } else {
+            BTreeMap::new()
+        };
+        let lines = watch_lines(
+            &cfg,
+            &runtime,
+            &endpoint,
+            [proxy_healthy, rmvm_healthy],
+            &usage,
+            &mut last_requests,
+        );
+
+        // Clear the screen and move the cursor home before redrawing.
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "cortex status  {}  every {}s, Ctrl-C to exit",
+            chrono::Local::now().format("%H:%M:%S"),
+            interval.as_secs()
+        );
+        for line in lines {
+            println!("{line}");
+        }
+        std::io::stdout().flush()?;
+
+        tokio::select! {
+            _ = &mut ctrl_c => return Ok(()),
+            _ = sleep(interval) => {}
+        }
+    }
+}
+
+/// The body of one `status --watch` frame; `last_requests` carries the request count between
+/// frames so each one shows how many arrived since the previous refresh.
+fn watch_lines(
+    cfg: &ProductConfig,
+    runtime: &RuntimeState,
+    endpoint: &str,
+    [proxy_healthy, rmvm_healthy]: [bool; 2],
+    usage: &BTreeMap<String, KeyUsage>,
+    last_requests: &mut Option<u64>,
+) -> Vec<String> {
+    let requests: u64 = usage.values().map(|u| u.requests).sum();
+    let errors: u64 = usage.values().map(|u| u.errors).sum();
+    let health = |ok: bool| if ok { "healthy" } else { "DOWN" };
+    let pid = |pid: Option<u32>| pid.map_or_else(|| "-".to_string(), |p| p.to_string());
+    let lines = vec![
+        format!(
+            "proxy  {:<24} {:<8} pid={}",
+            cfg.proxy_addr,
+            health(proxy_healthy),
+            pid(runtime.proxy_pid)
+        ),
+        format!(
+            "rmvm   {:<24} {:<8} pid={}",
+            endpoint,
+            health(rmvm_healthy),
+            pid(runtime.rmvm_pid)
+        ),
+        format!(
+            "brain={} provider={}",
+            cfg.active_brain.as_deref().unwrap_or("<none>"),
+            cfg.active_provider
+        ),
+        format!(
+            "requests={} (+{} since last refresh) errors={}",
+            requests,
+            last_requests.map_or(0, |last| requests.saturating_sub(last)),
+            errors
+        ),
+    ];
+    *last_requests = Some(requests);
+    lines
+}
+
+async fn print_instances(json: bool) -> Result<()> {
+    let base = base_paths()?;
+    let paths = profile_paths(&base, &current_profile(&base));
+    let cfg = load_config(&paths)?;
+    let names = std::iter::once(DEFAULT_INSTANCE.to_string()).chain(cfg.instances.keys().cloned());
+    let mut views = Vec::new();
+    for name in names {
+        let mut view_cfg = cfg.clone();
+        let state = if name == DEFAULT_INSTANCE {
+            paths.clone()
+        } else {
+            apply_instance(&mut view_cfg, &name);
+            instance_paths(&paths, &name)
+        };
+        let runtime = load_live_runtime(&state)?.unwrap_or_default();
+        views.push(InstanceView {
+            proxy_healthy: probe_proxy(&view_cfg.proxy_addr).await,
+            name,
+            proxy_addr: view_cfg.proxy_addr,
+            rmvm_port: view_cfg.rmvm.port,
+            brain: view_cfg.active_brain,
+            proxy_pid: runtime.proxy_pid,
+            state_path: state.state_dir.display().to_string(),
+        });
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&views)?);
+        return Ok(());
+    }
+    for view in views {
+        println!(
+            "{} proxy={} rmvm_port={} brain={} pid={} healthy={}",
+            view.name,
+            view.proxy_addr,
+            view.rmvm_port,
+            view.brain.as_deref().unwrap_or("<none>"),
+            view.proxy_pid
+                .map_or_else(|| "-".to_string(), |pid| pid.to_string()),
+            view.proxy_healthy
+        );
+    }
+    Ok(())
+}
+
+pub fn profile_list(json: bool) -> Result<()> {
+    let base = base_paths()?;
+    let active = current_profile(&base);
+    let mut views = Vec::new();
+    for name in profile_names(&base) {
+        let paths = profile_paths(&base, &name);
+        let cfg = read_config(&paths)?.unwrap_or_else(default_config);
+        views.push(ProfileView {
+            active: name == active,
+            name,
+            proxy_addr: cfg.proxy_addr,
+            rmvm_port: cfg.rmvm.port,
+            brains_home: cfg.brains_home,
+            config_path: paths.config_file().display().to_string(),
+        });
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&views)?);
+        return Ok(());
+    }
+    for view in views {
+        println!(
+            "{} {} proxy={} rmvm_port={} brains={}",
+            if view.active { "*" } else { " " },
+            view.name,
+            view.proxy_addr,
+            view.rmvm_port,
+            view.brains_home.as_deref().unwrap_or("<default>")
+        );
+    }
+    Ok(())
+}
+
+pub fn profile_create(req: ProfileCreateRequest) -> Result<()> {
+    validate_profile_name(&req.name)?;
+    let base = base_paths()?;
+    let paths = profile_paths(&base, &req.name);
+    if req.name == DEFAULT_PROFILE || paths.config_file().exists() {
+        bail!("profile '{}' already exists", req.name);
+    }
+    let mut cfg = match req.from.as_deref() {
+        Some(from) => {
+            validate_profile_name(from)?;
+            read_config(&profile_paths(&base, from))?
+                .ok_or_else(|| anyhow!("profile '{from}' does not exist"))?
+        }
+        None => default_config(),
+    };
+    let offset = free_port_offset(&port_owners(&base)?);
+    cfg.proxy_addr = req
+        .proxy_addr
+        .unwrap_or_else(|| format!("127.0.0.1:{}", DEFAULT_PROXY_PORT + offset));
+    cfg.rmvm.port = req.rmvm_port.unwrap_or(DEFAULT_RMVM_PORT + offset);
+    let brains_home = match req.brains_home {
+        Some(path) => path,
+        None => dirs::home_dir()
+            .ok_or_else(|| anyhow!("failed to resolve home dir"))?
+            .join(".cortex")
+            .join(PROFILES_DIR)
+            .join(&req.name),
+    };
+    cfg.brains_home = Some(brains_home.display().to_string());
+    // Brains, their secret and the proxy key belong to the profile; providers carry over.
+    cfg.active_brain = None;
+    cfg.brain_secret_ref = format!("brain.{}.secret", req.name);
+    cfg.proxy_api_key = None;
+    save_config(&paths, &cfg)?;
+    println!(
+        "Created profile '{}' (proxy={} rmvm_port={} brains={})",
+        req.name,
+        cfg.proxy_addr,
+        cfg.rmvm.port,
+        brains_home.display()
+    );
+    println!("Next: cortex --profile {} setup", req.name);
+    Ok(())
+}
+
+/// Smallest offset from the default ports that no existing profile or instance uses.
+fn free_port_offset(configs: &[ProductConfig]) -> u16 {
+    let taken = |offset: u16| {
+        configs.iter().any(|cfg| {
+            cfg.proxy_addr.parse::<SocketAddr>().ok().map(|a| a.port())
+                == Some(DEFAULT_PROXY_PORT + offset)
+                || cfg.rmvm.port == DEFAULT_RMVM_PORT + offset
+        })
+    };
+    (1..1000).find(|offset| !taken(*offset)).unwrap_or(1000)
+}
+
+pub fn profile_switch(name: &str) -> Result<()> {
+    validate_profile_name(name)?;
+    let base = base_paths()?;
+    if name != DEFAULT_PROFILE && !profile_paths(&base, name).config_file().exists() {
+        bail!("profile '{name}' does not exist; create it with `cortex profile create {name}`");
+    }
+    fs::create_dir_all(&base.config_dir)?;
+    fs::write(base.config_dir.join(ACTIVE_PROFILE_FILE), name)?;
+    println!("Switched to profile '{name}'");
+    if env::var(PROFILE_ENV).is_ok_and(|v| !v.trim().is_empty() && v.trim() != name) {
+        println!("Note: {PROFILE_ENV} is set and overrides the saved profile in this shell.");
+    }
+    Ok(())
+}
+
+/// Prints one config value (`rmvm.port`), or the whole config when `key` is unset.
+pub fn config_get(key: Option<&str>, json: bool) -> Result<()> {
+    let paths = default_paths()?;
+    let doc = serde_json::to_value(load_config(&paths)?)?;
+    let value = match key {
+        Some(key) => {
+            config_lookup(&doc, key).ok_or_else(|| anyhow!("unknown config key '{key}'"))?
+        }
+        None => &doc,
+    };
+    match value {
+        JsonValue::String(text) if !json => println!("{text}"),
+        value if value.is_object() || value.is_array() => {
+            println!("{}", serde_json::to_string_pretty(value)?)
+        }
+        value => println!("{value}"),
+    }
+    Ok(())
+}
+
+/// Sets one config value, checks that the result still matches the schema, then applies the
+/// restart policy (RMVM settings only take effect on the next `cortex up`).
+pub async fn config_set(key: &str, raw: &str, restart: RestartPolicy) -> Result<()> {
+    if key == "version" {
+        bail!("'version' is managed by cortex and cannot be set");
+    }
+    let paths = default_paths()?;
+    let (cfg, value) = with_config_value(load_config(&paths)?, key, raw)?;
+    if key == "secret_storage" && normalize_secret_storage(&cfg.secret_storage)? == "keyring" {
+        // Migrate before saving so an unusable keyring leaves the config unchanged.
+        let moved = migrate_fallback_secrets(&paths)?;
+        println!("Moved {moved} secret(s) into the OS keyring.");
+    }
+    save_config(&paths, &cfg)?;
+    println!("{key} = {value}");
+    if key.starts_with("rmvm.") {
+        println!("RMVM settings apply on the next start: cortex stop --all && cortex up");
+        return Ok(());
+    }
+    apply_restart_policy(&paths, &cfg, restart).await
+}
+
+/// `cfg` with `key` set from raw CLI input, and the value as it will be saved.
+fn with_config_value(
+    cfg: ProductConfig,
+    key: &str,
+    raw: &str,
+) -> Result<(ProductConfig, JsonValue)> {
+    let mut doc = serde_json::to_value(cfg)?;
+    let slot = config_slot(&mut doc, key)?;
+    *slot = parse_config_value(slot, raw);
+    let cfg: ProductConfig =
+        serde_json::from_value(doc).with_context(|| format!("invalid value for '{key}'"))?;
+    let saved = serde_json::to_value(&cfg)?;
+    let Some(value) = config_lookup(&saved, key).cloned() else {
+        bail!("unknown config key '{key}'");
+    };
+    validate_config(&cfg)?;
+    Ok((cfg, value))
+}
+
+fn config_lookup<'a>(doc: &'a JsonValue, key: &str) -> Option<&'a JsonValue> {
+    key.split('.')
+        .try_fold(doc, |value, part| value.as_object()?.get(part))
+}
+
+/// The value at `key`; the last segment may be missing (unset optional fields are omitted).
+fn config_slot<'a>(doc: &'a mut JsonValue, key: &str) -> Result<&'a mut JsonValue> {
+    let unknown = || anyhow!("unknown config key '{key}'");
+    let (parents, leaf) = match key.rsplit_once('.') {
+        Some((parents, leaf)) => (Some(parents), leaf),
+        None => (None, key),
+    };
+    let mut value = doc;
+    for part in parents.into_iter().flat_map(|p| p.split('.')) {
+        value = value
+            .as_object_mut()
+            .and_then(|o| o.get_mut(part))
+            .ok_or_else(unknown)?;
+    }
+    let object = value.as_object_mut().ok_or_else(unknown)?;
+    Ok(object.entry(leaf).or_insert(JsonValue::Null))
+}
+
+/// Raw CLI input as JSON where it parses (`9090`, `true`, `null`), except that string fields
+/// keep the text as-is so `127.0.0.1:9090` needs no quoting.
+fn parse_config_value(current: &JsonValue, raw: &str) -> JsonValue {
+    if current.is_string() && raw != "null" {
+        return JsonValue::String(raw.to_string());
+    }
+    serde_json::from_str(raw).unwrap_or_else(|_| JsonValue::String(raw.to_string()))
+}
+
+fn validate_config(cfg: &ProductConfig) -> Result<()> {
+    cfg.proxy_addr
+        .parse::<SocketAddr>()
+        .map_err(|e| anyhow!("invalid proxy_addr '{}': {e}", cfg.proxy_addr))?;
+    if !cfg.providers.contains_key(&cfg.active_provider) {
+        bail!(
+            "active_provider '{}' is not a configured provider",
+            cfg.active_provider
+        );
+    }
+    for (name, provider) in &cfg.providers {
+        PlannerMode::parse(&provider.planner_mode)
+            .with_context(|| format!("invalid providers.{name}.planner_mode"))?;
+    }
+    for (model, provider) in &cfg.model_routes {
+        if !cfg.providers.contains_key(provider) {
+            bail!("model_routes.{model} points to unknown provider '{provider}'");
+        }
+    }
+    for (brain, endpoint) in &cfg.brain_endpoints {
+        if endpoint.trim().is_empty() || endpoint.contains('=') {
+            bail!("invalid brain_endpoints.{brain} '{endpoint}'");
+        }
+    }
+    normalize_memory_mode(&cfg.memory_mode)?;
+    normalize_secret_storage(&cfg.secret_storage)?;
+    for (job, schedule) in &cfg.maintenance {
+        if !JOBS.contains(&job.as_str()) {
+            bail!(
+                "unknown maintenance job '{job}'; expected one of {}",
+                JOBS.join("|")
+            );
+        }
+        if schedule.every_secs < MAINTENANCE_TICK.as_secs() {
+            bail!(
+                "maintenance.{job}.every_secs must be at least {}",
+                MAINTENANCE_TICK.as_secs()
+            );
+        }
+    }
+    if !matches!(cfg.rmvm.mode.as_str(), "managed" | "external") {
+        bail!(
+            "invalid rmvm.mode '{}'; expected managed|external",
+            cfg.rmvm.mode
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_paths(root: &Path) -> Paths {
+        Paths {
+            config_dir: root.join("config"),
+            state_dir: root.join("state"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_and_reused_pids_are_cleared_from_runtime_state() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let own_pid = std::process::id();
+        let mut unrelated = Command::new("sleep").arg("30").spawn().unwrap();
+        save_runtime(
+            &paths,
+            &RuntimeState {
+                proxy_pid: Some(own_pid),
+                proxy_started: process_start_time(own_pid),
+                rmvm_pid: Some(unrelated.id()),
+                rmvm_started: Some("not-its-start-time".to_string()),
+                ..RuntimeState::default()
+            },
+        )
+        .unwrap();
+
+        let live = load_live_runtime(&paths).unwrap().unwrap();
+        assert_eq!(live.proxy_pid, Some(own_pid));
+        assert_eq!(live.rmvm_pid, None);
+        assert_eq!(load_runtime(&paths).unwrap().unwrap().rmvm_pid, None);
+
+        unrelated.kill().unwrap();
+        let exited = unrelated.id();
+        unrelated.wait().unwrap();
+        save_runtime(
+            &paths,
+            &RuntimeState {
+                proxy_pid: Some(exited),
+                ..RuntimeState::default()
+            },
+        )
+        .unwrap();
+        assert!(load_live_runtime(&paths).unwrap().is_none());
+        assert!(!paths.runtime_file().exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn foreground_stops_the_stack_when_a_service_exits() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let log = paths.state_dir.join("logs").join("rmvm.log");
+        let mut foreground = Foreground::default();
+        let mut rmvm = Command::new("sh");
+        rmvm.args(["-c", "echo kernel ready; sleep 0.2"]);
+        let rmvm_pid = foreground.spawn("rmvm", rmvm, &log).unwrap();
+        let mut proxy = Command::new("sleep");
+        proxy.arg("30");
+        let proxy_pid = foreground.spawn("proxy", proxy, &log).unwrap();
+        save_runtime(
+            &paths,
+            &RuntimeState {
+                proxy_pid: Some(proxy_pid),
+                rmvm_pid: Some(rmvm_pid),
+                foreground: true,
+                ..RuntimeState::default()
+            },
+        )
+        .unwrap();
+
+        let err = foreground.wait(&paths).await.unwrap_err();
+        assert!(err.to_string().contains("rmvm exited"), "{err}");
+        let proxy_alive = Command::new("kill")
+            .args(["-0", &proxy_pid.to_string()])
+            .status()
+            .unwrap()
+            .success();
+        assert!(!proxy_alive, "the remaining service is stopped");
+        assert!(fs::read_to_string(&log).unwrap().contains("kernel ready"));
+    }
+
+    #[test]
+    fn foreground_proxies_are_not_restarted_by_the_dashboard() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let mut runtime = RuntimeState {
+            proxy_pid: Some(4242),
+            ..RuntimeState::default()
+        };
+        assert!(!managed_proxy(&paths, 4242).unwrap());
+        save_runtime(&paths, &runtime).unwrap();
+        assert!(managed_proxy(&paths, 4242).unwrap());
+        assert!(!managed_proxy(&paths, 4343).unwrap());
+        runtime.foreground = true;
+        save_runtime(&paths, &runtime).unwrap();
+        assert!(!managed_proxy(&paths, 4242).unwrap());
+    }
+
+    #[test]
+    fn profiles_get_their_own_config_and_free_ports() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp_paths(temp.path());
+        assert_eq!(
+            profile_paths(&base, DEFAULT_PROFILE).config_dir,
+            base.config_dir
+        );
+        let work = profile_paths(&base, "work");
+        assert_eq!(work.config_dir, base.config_dir.join("profiles/work"));
+        assert_eq!(work.state_dir, base.state_dir.join("profiles/work"));
+
+        save_config(&base, &default_config()).unwrap();
+        let mut work_cfg = default_config();
+        work_cfg.proxy_addr = format!("127.0.0.1:{}", DEFAULT_PROXY_PORT + 1);
+        save_config(&work, &work_cfg).unwrap();
+        assert_eq!(profile_names(&base), ["default", "work"]);
+        assert_eq!(free_port_offset(&port_owners(&base).unwrap()), 2);
+
+        assert_eq!(saved_profile(&base), DEFAULT_PROFILE);
+        fs::write(base.config_dir.join(ACTIVE_PROFILE_FILE), "work\n").unwrap();
+        assert_eq!(saved_profile(&base), "work");
+        assert!(validate_profile_name("../work").is_err());
+    }
+
+    #[test]
+    fn instances_get_their_own_state_ports_and_brain() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp_paths(temp.path());
+        let work = instance_paths(&base, "work");
+        assert_eq!(work.config_dir, base.config_dir);
+        assert_eq!(work.state_dir, base.state_dir.join("instances/work"));
+        assert_ne!(work.runtime_file(), base.runtime_file());
+
+        let mut cfg = default_config();
+        cfg.active_brain = Some("personal".to_string());
+        cfg.instances.insert(
+            "work".to_string(),
+            InstanceSettings {
+                proxy_addr: format!("127.0.0.1:{}", DEFAULT_PROXY_PORT + 1),
+                rmvm_port: DEFAULT_RMVM_PORT + 1,
+                brain: Some("work".to_string()),
+            },
+        );
+        save_config(&base, &cfg).unwrap();
+        // A new profile or instance must not take the work instance's ports.
+        assert_eq!(free_port_offset(&port_owners(&base).unwrap()), 2);
+
+        let mut applied = cfg.clone();
+        apply_instance(&mut applied, "work");
+        assert_eq!(
+            applied.proxy_addr,
+            format!("127.0.0.1:{}", DEFAULT_PROXY_PORT + 1)
+        );
+        assert_eq!(applied.rmvm.port, DEFAULT_RMVM_PORT + 1);
+        assert_eq!(applied.active_brain.as_deref(), Some("work"));
+        apply_instance(&mut cfg, "unknown");
+        assert_eq!(cfg.proxy_addr, DEFAULT_PROXY_ADDR);
+        assert_eq!(cfg.active_brain.as_deref(), Some("personal"));
+        assert!(validate_name("instance", "work/../x").is_err());
+    }
+
+    #[test]
+    fn watch_frames_show_health_pids_and_new_requests() {
+        let cfg = default_config();
+        let runtime = RuntimeState {
+            proxy_pid: Some(4242),
+            ..RuntimeState::default()
+        };
+        let mut usage = BTreeMap::new();
+        usage.insert(
+            "key-a".to_string(),
+            KeyUsage {
+                requests: 5,
+                errors: 1,
+                ..KeyUsage::default()
+            },
+        );
+        let mut last = None;
+        let first = watch_lines(
+            &cfg,
+            &runtime,
+            "grpc://127.0.0.1:50051",
+            [true, false],
+            &usage,
+            &mut last,
+        );
+        assert!(first[0].contains("healthy") && first[0].ends_with("pid=4242"));
+        assert!(first[1].contains("DOWN") && first[1].ends_with("pid=-"));
+        assert_eq!(first[3], "requests=5 (+0 since last refresh) errors=1");
+
+        usage.get_mut("key-a").unwrap().requests = 8;
+        let second = watch_lines(
+            &cfg,
+            &runtime,
+            "grpc://127.0.0.1:50051",
+            [true, true],
+            &usage,
+            &mut last,
+        );
+        assert_eq!(second[3], "requests=8 (+3 since last refresh) errors=1");
+        assert_eq!(last, Some(8));
+    }
+
+    #[test]
+    fn output_flag_and_env_select_json_for_every_command() {
+        select_output(Some(" JSON ")).unwrap();
+        assert!(json_output());
+        select_output(Some("text")).unwrap();
+        assert!(!json_output());
+        assert!(select_output(Some("yaml")).is_err());
+
+        unsafe {
+            env::set_var(OUTPUT_ENV, "Json");
+        }
+        select_output(None).unwrap();
+        assert_eq!(env::var(OUTPUT_ENV).unwrap(), "json");
+        assert!(json_output());
+        unsafe {
+            env::set_var(OUTPUT_ENV, "xml");
+        }
+        assert!(select_output(None).is_err());
+        unsafe {
+            env::remove_var(OUTPUT_ENV);
+        }
+        select_output(None).unwrap();
+        assert!(!json_output());
+    }
+
+    #[test]
+    fn old_configs_are_migrated_with_a_backup_and_future_ones_refused() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        fs::create_dir_all(&paths.config_dir).unwrap();
+        let mut v1 = serde_json::to_value(default_config()).unwrap();
+        v1["version"] = 1.into();
+        v1["providers"] = json!({});
+        v1["memory_mode"] = "".into();
+        let original = serde_json::to_string_pretty(&v1).unwrap();
+        fs::write(paths.config_file(), &original).unwrap();
+
+        let cfg = read_config(&paths).unwrap().unwrap();
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        assert_eq!(cfg.providers.len(), default_providers().len());
+        assert_eq!(cfg.memory_mode, default_memory_mode());
+        let backup = paths.config_dir.join("config.v1.json.bak");
+        assert_eq!(fs::read_to_string(backup).unwrap(), original);
+        let stored: JsonValue =
+            serde_json::from_str(&fs::read_to_string(paths.config_file()).unwrap()).unwrap();
+        assert_eq!(stored["version"], CONFIG_VERSION);
+
+        let mut future = stored;
+        future["version"] = (CONFIG_VERSION + 1).into();
+        let future = serde_json::to_string_pretty(&future).unwrap();
+        fs::write(paths.config_file(), &future).unwrap();
+        let err = read_config(&paths).unwrap_err();
+        assert!(err.to_string().contains("config version"), "{err}");
+        assert_eq!(fs::read_to_string(paths.config_file()).unwrap(), future);
+    }
+
+    #[test]
+    fn config_values_are_parsed_and_validated_before_saving() {
+        let (cfg, value) =
+            with_config_value(default_config(), "proxy_addr", "127.0.0.1:9090").unwrap();
+        assert_eq!(cfg.proxy_addr, "127.0.0.1:9090");
+        assert_eq!(value, json!("127.0.0.1:9090"));
+        let (cfg, _) = with_config_value(cfg, "rmvm.port", "50999").unwrap();
+        assert_eq!(cfg.rmvm.port, 50999);
+
+        for (key, raw) in [
+            ("proxy_addr", "not-an-address"),
+            ("rmvm.port", "high"),
+            ("active_provider", "nobody"),
+            ("rmvm.colour", "blue"),
+            ("nothing.here", "1"),
+        ] {
+            assert!(
+                with_config_value(cfg.clone(), key, raw).is_err(),
+                "{key}={raw} should be refused"
+            );
+        }
+    }
+
+    #[test]
+    fn custom_providers_are_validated_added_and_removed() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let vllm = ProviderAddRequest {
+            name: "vllm".to_string(),
+            base_url: Some("http://127.0.0.1:8000/v1/".to_string()),
+            model: "qwen2.5".to_string(),
+            mode: "openai".to_string(),
+            api_key: None,
+            api_key_env: None,
+            azure_deployment: None,
+            azure_api_version: None,
+            model_path: None,
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: None,
+            extra_headers: BTreeMap::new(),
+            organization: None,
+            project: None,
+        };
+        add_provider(&paths, vllm.clone()).unwrap();
+        let cfg = load_config(&paths).unwrap();
+        let profile = &cfg.providers["vllm"];
+        assert_eq!(profile.planner_base_url, "http://127.0.0.1:8000/v1");
+        assert_eq!(profile.planner_model, "qwen2.5");
+        assert!(profile.planner_api_key_ref.is_none());
+
+        for invalid in [
+            vllm.clone(),
+            ProviderAddRequest {
+                name: "ftp".to_string(),
+                base_url: Some("ftp://models.example".to_string()),
+                ..vllm.clone()
+            },
+            ProviderAddRequest {
+                name: "gguf".to_string(),
+                model_path: Some(temp.path().join("model.gguf")),
+                ..vllm.clone()
+            },
+            ProviderAddRequest {
+                name: "bad name".to_string(),
+                ..vllm.clone()
+            },
+        ] {
+            assert!(add_provider(&paths, invalid).is_err());
+        }
+
+        assert!(remove_provider(&paths, &cfg.active_provider).is_err());
+        let mut routed = load_config(&paths).unwrap();
+        routed
+            .model_routes
+            .insert("qwen2.5".to_string(), "vllm".to_string());
+        save_config(&paths, &routed).unwrap();
+        assert!(remove_provider(&paths, "vllm").is_err());
+        routed.model_routes.clear();
+        save_config(&paths, &routed).unwrap();
+        remove_provider(&paths, "vllm").unwrap();
+        assert!(!load_config(&paths).unwrap().providers.contains_key("vllm"));
+        assert!(remove_provider(&paths, "vllm").is_err());
+    }
+
+    #[test]
+    fn keyring_only_storage_keeps_file_secrets_until_the_keyring_holds_them() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let mut cfg = load_config(&paths).unwrap();
+        put_secret(&paths, "provider.test.api_key", "sk-test").unwrap();
+        assert!(paths.fallback_secrets_file().exists());
+        assert_eq!(
+            get_secret(&paths, "provider.test.api_key")
+                .unwrap()
+                .as_deref(),
+            Some("sk-test")
+        );
+
+        cfg.secret_storage = "plaintext".to_string();
+        assert!(validate_config(&cfg).is_err());
+        cfg.secret_storage = "keyring".to_string();
+        save_config(&paths, &cfg).unwrap();
+        match get_secret(&paths, "provider.test.api_key") {
+            Ok(value) => {
+                assert_eq!(value.as_deref(), Some("sk-test"));
+                assert!(!paths.fallback_secrets_file().exists());
+                assert!(!paths.fallback_key_file().exists());
+            }
+            // Without a usable keyring nothing on disk may be removed.
+            Err(_) => {
+                assert!(paths.fallback_secrets_file().exists());
+                assert!(paths.fallback_key_file().exists());
+            }
+        }
+    }
+
+    #[test]
+    fn doctor_reports_fallback_secrets_that_no_longer_decrypt() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        assert!(check_fallback_secrets(&paths).is_ok());
+        put_secret(&paths, "brain.secret", "brain-pass").unwrap();
+        put_secret(&paths, "provider.test.api_key", "sk-test").unwrap();
+        let details = check_fallback_secrets(&paths).unwrap();
+        assert!(details.starts_with("2 secret(s) decrypt"), "{details}");
+
+        // A key regenerated by another install leaves the sealed values unreadable.
+        fs::write(paths.fallback_key_file(), [7u8; 32]).unwrap();
+        let err = check_fallback_secrets(&paths).unwrap_err().to_string();
+        assert!(err.contains("2 of 2 secret(s) do not decrypt"), "{err}");
+
+        fs::remove_file(paths.fallback_key_file()).unwrap();
+        let err = check_fallback_secrets(&paths).unwrap_err().to_string();
+        assert!(err.contains("is missing"), "{err}");
+        assert!(!paths.fallback_key_file().exists());
+    }
+
+    #[tokio::test]
+    async fn doctor_names_a_free_port_when_the_proxy_port_is_taken() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut cfg = default_config();
+        cfg.proxy_addr = taken.local_addr().unwrap().to_string();
+
+        let err = check_proxy_port(&cfg, &RuntimeState::default())
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("in use by a non-cortex process"), "{err}");
+        assert!(
+            err.contains("run `cortex up --proxy-addr 127.0.0.1:"),
+            "{err}"
+        );
+
+        let own_pid = std::process::id();
+        let ours = RuntimeState {
+            proxy_pid: Some(own_pid),
+            proxy_started: process_start_time(own_pid),
+            proxy_addr: cfg.proxy_addr.clone(),
+            ..RuntimeState::default()
+        };
+        let details = check_proxy_port(&cfg, &ours).await.unwrap();
+        assert!(
+            details.contains("held by this profile's proxy"),
+            "{details}"
+        );
+
+        drop(taken);
+        let details = check_proxy_port(&cfg, &RuntimeState::default())
+            .await
+            .unwrap();
+        assert!(details.ends_with("is free"), "{details}");
+    }
+
+    #[test]
+    fn config_watch_sees_config_and_planner_key_edits() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(temp.path());
+        let mut cfg = load_config(&paths).unwrap();
+        let (first, _) = config_snapshot(&paths).unwrap();
+        assert!(config_snapshot(&paths).unwrap().0 == first);
+
+        cfg.memory_mode = "private".to_string();
+        save_config(&paths, &cfg).unwrap();
+        let (edited, seen) = config_snapshot(&paths).unwrap();
+        assert!(edited != first);
+        assert_eq!(seen.memory_mode, "private");
+
+        let key_ref = cfg.providers[&cfg.active_provider]
+            .planner_api_key_ref
+            .clone()
+            .unwrap();
+        put_secret(&paths, &key_ref, "sk-rotated").unwrap();
+        let (rotated, _) = config_snapshot(&paths).unwrap();
+        assert!(rotated != edited);
+        assert_eq!(
+            rotated.planner_keys[&cfg.active_provider].as_deref(),
+            Some("sk-rotated")
+        );
+
+        let err = apply_config_change(&paths, &seen).unwrap_err();
+        assert!(err.to_string().contains("proxy is not running"), "{err}");
+        cfg.memory_mode = "sometimes".to_string();
+        assert!(apply_config_change(&paths, &cfg).is_err());
+    }
+
+    #[test]
+    fn purge_exports_every_brain_outside_the_removed_dirs() {
+        let temp = tempfile::tempdir().unwrap();
+        let paths = temp_paths(&temp.path().join("cortex"));
+        unsafe {
+            env::set_var("TEST_BRAIN_SECRET_PURGE", "test-secret-purge");
+        }
+        let store = BrainStore::new(Some(temp.path().join("brains"))).unwrap();
+        let brain = store
+            .create_brain(CreateBrainRequest {
+                name: "personal".to_string(),
+                tenant_id: "local".to_string(),
+                passphrase_env: Some("TEST_BRAIN_SECRET_PURGE".to_string()),
+            })
+            .unwrap();
+
+        let inside = store.home_dir().join("exports");
+        assert!(export_all_brains(&store, &paths, &inside).is_err());
+        assert!(export_all_brains(&store, &paths, &paths.state_dir.join("exports")).is_err());
+
+        let dir = temp.path().join("exports");
+        let exported = export_all_brains(&store, &paths, &dir).unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].brain_id, brain.brain_id);
+        let package = fs::read(dir.join(&exported[0].file)).unwrap();
+        assert_eq!(
+            exported[0].sha256,
+            format!("{:x}", Sha256::digest(&package))
+        );
+        let record: JsonValue =
+            serde_json::from_slice(&fs::read(dir.join(UNINSTALL_EXPORT_RECORD)).unwrap()).unwrap();
+        assert_eq!(record["brains"][0]["name"], "personal");
+
+        let restored = BrainStore::new(Some(temp.path().join("restored"))).unwrap();
+        assert!(
+            restored
+                .import_brain(&dir.join(&exported[0].file), None, true)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn home_flag_wins_then_env_then_profile() {
+        let flag = Path::new("/srv/flag");
+        let env_home = Some(PathBuf::from("/srv/env"));
+        let profile_home = Some(PathBuf::from("/srv/profile"));
+        assert_eq!(
+            resolve_home(Some(flag), env_home.clone(), profile_home.clone()).unwrap(),
+            Some(PathBuf::from("/srv/flag"))
+        );
+        assert_eq!(
+            resolve_home(None, env_home, profile_home.clone()).unwrap(),
+            Some(PathBuf::from("/srv/env"))
+        );
+        assert_eq!(
+            resolve_home(None, None, profile_home).unwrap(),
+            Some(PathBuf::from("/srv/profile"))
+        );
+        assert_eq!(resolve_home(None, None, None).unwrap(), None);
+    }
 }
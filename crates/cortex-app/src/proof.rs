@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+use brain_store::BrainStore;
+use planner_guard::manifest_to_json;
+use rmvm_proto::{AssertionType, ExecuteResponse, PublicManifest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::proxy::assertion_fields_json;
+use crate::replay::LEDGER_EXECUTION_PROOF;
+
+/// Everything needed to check an answer offline: the roots, each assertion the caller saw
+/// with its inclusion path, and a digest of the manifest it was executed against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub semantic_root: String,
+    #[serde(default)]
+    pub trace_root: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_sha256: Option<String>,
+    pub assertions: Vec<ProvenAssertion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenAssertion {
+    /// Position in the kernel's assertion list, which fixes the leaf's place in the tree.
+    pub index: u32,
+    pub assertion_type: String,
+    pub fields: BTreeMap<String, JsonValue>,
+    #[serde(default)]
+    pub citations: Vec<String>,
+    #[serde(default)]
+    pub sibling_hashes: Vec<String>,
+}
+
+/// Name of the leaf encoding below, reported with every check. The RMVM v3.1 proto
+/// (`AssertionMerkleProof`) fixes the tree shape but not how an assertion becomes a leaf, so
+/// this is Cortex's own encoding, not one published by the kernel; a kernel that hashes leaves
+/// differently produces roots this check cannot reach.
+pub const LEAF_ENCODING: &str = "cortex-json-v1";
+
+/// Leaf preimage; fields are declared in key order so the JSON is canonical.
+#[derive(Serialize)]
+struct Leaf<'a> {
+    assertion_type: &'a str,
+    citations: &'a [String],
+    fields: &'a BTreeMap<String, JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofReport {
+    pub semantic_root: String,
+    pub trace_root: String,
+    pub assertions: usize,
+    pub included: usize,
+    /// `None` when no manifest was given to check against.
+    pub manifest_linked: Option<bool>,
+    pub leaf_encoding: &'static str,
+    /// Request whose recorded execution proof carries these roots, when a ledger was checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_request_id: Option<String>,
+    pub failures: Vec<String>,
+    pub verified: bool,
+}
+
+/// SHA-256 of the manifest in `cortex manifest get` form.
+pub fn manifest_digest(manifest: &PublicManifest) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(manifest_to_json(manifest).to_string().as_bytes())
+    )
+}
+
+/// The proof of an execution, or `None` when the kernel returned none.
+pub fn proof_bundle(execute: &ExecuteResponse, manifest_sha256: String) -> Option<ProofBundle> {
+    let proof = execute.proof.as_ref()?;
+    let siblings = proof
+        .inclusion
+        .iter()
+        .map(|p| (p.assertion_index, p.sibling_hashes.clone()))
+        .collect::<BTreeMap<_, _>>();
+    let assertions = execute
+        .assertions
+        .iter()
+        .zip(0u32..)
+        .map(|(assertion, index)| ProvenAssertion {
+            index,
+            assertion_type: AssertionType::try_from(assertion.assertion_type)
+                .unwrap_or(AssertionType::Unspecified)
+                .as_str_name()
+                .to_string(),
+            fields: match assertion_fields_json(assertion) {
+                JsonValue::Object(map) => map.into_iter().collect(),
+                _ => BTreeMap::new(),
+            },
+            citations: assertion
+                .citations
+                .iter()
+                .map(|c| c.anchor_digest.clone())
+                .collect(),
+            sibling_hashes: siblings.get(&index).cloned().unwrap_or_default(),
+        })
+        .collect();
+    Some(ProofBundle {
+        semantic_root: proof.semantic_root.clone(),
+        trace_root: proof.trace_root.clone(),
+        manifest_sha256: Some(manifest_sha256),
+        assertions,
+    })
+}
+
+impl ProofBundle {
+    /// Drops assertions about `(subject, predicate)` pairs the caller may not see. The others
+    /// keep their index, so their paths still verify.
+    pub fn drop_denied(&mut self, denied: &[(String, String)]) {
+        self.assertions.retain(|a| {
+            let field = |name: &str| a.fields.get(name).and_then(JsonValue::as_str);
+            !denied.iter().any(|(s, p)| {
+                field("subject") == Some(s.as_str()) && field("predicate") == Some(p.as_str())
+            })
+        });
+    }
+
+    /// Reads a bundle from a saved chat or Responses API body (`cortex.proof`) or a bare bundle.
+    pub fn from_json(value: &JsonValue) -> Result<Self> {
+        let proof = match value.get("cortex") {
+            Some(cortex) => cortex
+                .get("proof")
+                .filter(|p| !p.is_null())
+                .ok_or_else(|| anyhow!("response has no cortex.proof (reduced envelope?)"))?,
+            None => value,
+        };
+        serde_json::from_value(proof.clone()).context("invalid proof bundle")
+    }
+}
+
+fn leaf_hash(assertion: &ProvenAssertion) -> [u8; 32] {
+    let leaf = Leaf {
+        assertion_type: &assertion.assertion_type,
+        citations: &assertion.citations,
+        fields: &assertion.fields,
+    };
+    let bytes = serde_json::to_vec(&leaf).unwrap_or_default();
+    Sha256::digest(bytes).into()
+}
+
+fn decode_hash(hex: &str) -> Result<[u8; 32]> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        bail!("'{hex}' is not a 32-byte hex hash");
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow!("'{hex}' is not a 32-byte hex hash"))?;
+    }
+    Ok(out)
+}
+
+/// Root reached from the assertion's leaf. At each level the node is the left child when its
+/// index is even: `parent = SHA-256(left || right)`.
+fn inclusion_root(assertion: &ProvenAssertion) -> Result<String> {
+    let mut node = leaf_hash(assertion);
+    let mut index = assertion.index;
+    for sibling in &assertion.sibling_hashes {
+        let sibling = decode_hash(sibling)?;
+        let mut hasher = Sha256::new();
+        if index % 2 == 0 {
+            hasher.update(node);
+            hasher.update(sibling);
+        } else {
+            hasher.update(sibling);
+            hasher.update(node);
+        }
+        node = hasher.finalize().into();
+        index /= 2;
+    }
+    Ok(node.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Checks every assertion's inclusion path against the semantic root and, with a manifest,
+/// that the manifest digest matches and each assertion's subject and predicate has a handle.
+pub fn verify(bundle: &ProofBundle, manifest: Option<&PublicManifest>) -> ProofReport {
+    let mut failures = Vec::new();
+    let mut included = 0;
+    for assertion in &bundle.assertions {
+        match inclusion_root(assertion) {
+            Ok(root) if root.eq_ignore_ascii_case(bundle.semantic_root.trim()) => included += 1,
+            Ok(root) => failures.push(format!(
+                "assertion {} leads to root {root}, not the semantic root",
+                assertion.index
+            )),
+            Err(err) => failures.push(format!("assertion {}: {err}", assertion.index)),
+        }
+    }
+    if bundle.assertions.is_empty() {
+        failures.push("no assertions to verify".to_string());
+    } else if included == 0 {
+        failures.push(format!(
+            "no inclusion path reaches the semantic root under leaf encoding {LEAF_ENCODING}; \
+             the RMVM proto does not define leaf hashing, so the kernel may hash leaves differently"
+        ));
+    }
+    let manifest_linked = manifest.map(|manifest| {
+        let before = failures.len();
+        match bundle.manifest_sha256.as_deref() {
+            Some(digest) if digest.eq_ignore_ascii_case(&manifest_digest(manifest)) => {}
+            Some(_) => failures.push("manifest digest does not match the proof".to_string()),
+            None => failures.push("proof carries no manifest digest".to_string()),
+        }
+        for assertion in &bundle.assertions {
+            let field = |name: &str| assertion.fields.get(name).and_then(JsonValue::as_str);
+            let (Some(subject), Some(predicate)) = (field("subject"), field("predicate")) else {
+                continue;
+            };
+            let handled = manifest.handles.iter().any(|h| {
+                h.meta
+                    .as_ref()
+                    .is_some_and(|m| m.subject == subject && m.predicate_label == predicate)
+            });
+            if !handled {
+                failures.push(format!(
+                    "assertion {} ({subject} {predicate}) has no handle in the manifest",
+                    assertion.index
+                ));
+            }
+        }
+        failures.len() == before
+    });
+    ProofReport {
+        semantic_root: bundle.semantic_root.clone(),
+        trace_root: bundle.trace_root.clone(),
+        assertions: bundle.assertions.len(),
+        included,
+        manifest_linked,
+        leaf_encoding: LEAF_ENCODING,
+        ledger_request_id: None,
+        verified: failures.is_empty(),
+        failures,
+    }
+}
+
+/// Looks for the `rmvm.execute.proof` entry `brain_id` recorded with the report's roots.
+pub fn check_ledger(report: &mut ProofReport, store: &BrainStore, brain_id: &str) -> Result<()> {
+    let ledger = store.ledger(brain_id)?;
+    let recorded = ledger.iter().rev().find(|e| {
+        let root = |key: &str| e.payload.get(key).and_then(JsonValue::as_str);
+        e.operation == LEDGER_EXECUTION_PROOF
+            && root("semantic_root") == Some(report.semantic_root.as_str())
+            && (report.trace_root.is_empty()
+                || root("trace_root") == Some(report.trace_root.as_str()))
+    });
+    match recorded {
+        Some(event) => {
+            let request_id = event.payload.get("request_id").and_then(JsonValue::as_str);
+            report.ledger_request_id = Some(request_id.unwrap_or("-").to_string());
+        }
+        None => {
+            report.failures.push(format!(
+                "brain {brain_id} has no recorded execution proof with these roots"
+            ));
+            report.verified = false;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rmvm_proto::{HandleMeta, HandleRef};
+    use serde_json::json;
+
+    use super::*;
+
+    fn hex(bytes: [u8; 32]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    /// Fixed values for the two leaves below and their root, so a change to the leaf encoding
+    /// fails here instead of silently moving every root. They pin `cortex-json-v1`, not a kernel
+    /// capture.
+    const TEA_LEAF: &str = "011d10c3b3f1f3244ce5beff2fb0650ced8ed536081450cac85f3cef523d3326";
+    const COFFEE_LEAF: &str = "cd8104ab6261aeefe4a5f627cc24df39624ecebd966526be940f56bac2b5c39b";
+    const ROOT: &str = "0e60e92cdd996d93443687b466f6d53f53f80e9e8eec8a9a96a337659a3dc136";
+
+    #[test]
+    fn verifies_inclusion_paths_and_manifest_linkage() {
+        let assertion = |index: u32, value: &str| ProvenAssertion {
+            index,
+            assertion_type: "ASSERT_USER_PREFERENCE".to_string(),
+            fields: BTreeMap::from([
+                ("subject".to_string(), json!("user:local")),
+                ("predicate".to_string(), json!("prefers_beverage")),
+                ("value".to_string(), json!(value)),
+            ]),
+            citations: vec!["anchor-1".to_string()],
+            sibling_hashes: Vec::new(),
+        };
+        let (mut first, mut second) = (assertion(0, "tea"), assertion(1, "coffee"));
+        let (leaf0, leaf1) = (leaf_hash(&first), leaf_hash(&second));
+        assert_eq!(hex(leaf0), TEA_LEAF);
+        assert_eq!(hex(leaf1), COFFEE_LEAF);
+        assert_eq!(hex(node(leaf0, leaf1)), ROOT);
+        first.sibling_hashes = vec![COFFEE_LEAF.to_string()];
+        second.sibling_hashes = vec![TEA_LEAF.to_string()];
+        let manifest = PublicManifest {
+            handles: vec![HandleRef {
+                r#ref: "H1".to_string(),
+                meta: Some(HandleMeta {
+                    subject: "user:local".to_string(),
+                    predicate_label: "prefers_beverage".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut bundle = ProofBundle {
+            semantic_root: ROOT.to_string(),
+            trace_root: "trace".to_string(),
+            manifest_sha256: Some(manifest_digest(&manifest)),
+            assertions: vec![first, second],
+        };
+
+        let report = verify(&bundle, Some(&manifest));
+        assert!(report.verified, "{:?}", report.failures);
+        assert_eq!(report.included, 2);
+        assert_eq!(report.manifest_linked, Some(true));
+
+        let body = json!({"cortex": {"status": "OK", "proof": bundle}});
+        assert_eq!(ProofBundle::from_json(&body).unwrap().assertions.len(), 2);
+
+        bundle.drop_denied(&[("user:local".to_string(), "prefers_beverage".to_string())]);
+        assert!(!verify(&bundle, None).verified);
+
+        let mut tampered = ProofBundle::from_json(&body).unwrap();
+        tampered.assertions[1]
+            .fields
+            .insert("value".to_string(), json!("water"));
+        let report = verify(&tampered, Some(&PublicManifest::default()));
+        assert_eq!(report.included, 1);
+        assert_eq!(report.manifest_linked, Some(false));
+        assert!(!report.verified);
+
+        tampered.semantic_root = "0".repeat(64);
+        let report = verify(&tampered, None);
+        assert_eq!(report.included, 0);
+        assert!(report.failures.iter().any(|f| f.contains(LEAF_ENCODING)));
+    }
+}
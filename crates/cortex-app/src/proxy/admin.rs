@@ -0,0 +1,309 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use brain_store::{AttachmentGrant, BrainStore, CreateBrainRequest, KeyQuota};
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use uuid::Uuid;
+
+use super::{ApiError, AppState, OpenAiJson, parse_bearer, tokens_match};
+
+pub(super) fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/admin/brains", get(list_brains).post(create_brain))
+        .route("/admin/brains/{brain}/use", post(use_brain))
+        .route("/admin/brains/{brain}/forget", post(forget))
+        .route("/admin/keys", get(list_keys).post(create_key))
+        .route("/admin/reload", post(reload))
+        .route(
+            "/admin/attachments",
+            get(list_attachments).post(attach).delete(detach),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateBrainBody {
+    name: String,
+    #[serde(default = "default_tenant")]
+    tenant_id: String,
+    passphrase_env: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgetBody {
+    subject: String,
+    predicate: String,
+    #[serde(default = "default_scope")]
+    scope: String,
+    #[serde(default = "default_reason")]
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateKeyBody {
+    brain: Option<String>,
+    api_key: Option<String>,
+    tenant_id: Option<String>,
+    #[serde(default = "default_subject")]
+    subject: String,
+    #[serde(default)]
+    quota: KeyQuota,
+    agent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BrainQuery {
+    brain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachBody {
+    brain: Option<String>,
+    #[serde(flatten)]
+    grant: AttachmentGrant,
+}
+
+#[derive(Debug, Deserialize)]
+struct DetachQuery {
+    brain: Option<String>,
+    agent: String,
+    model: Option<String>,
+}
+
+fn default_tenant() -> String {
+    "local".to_string()
+}
+
+fn default_scope() -> String {
+    "SCOPE_GLOBAL".to_string()
+}
+
+fn default_reason() -> String {
+    "admin api".to_string()
+}
+
+fn default_subject() -> String {
+    "user:local".to_string()
+}
+
+fn check_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(ApiError::forbidden(
+            "admin_disabled",
+            "admin API is disabled; set CORTEX_ADMIN_TOKEN to enable it",
+        ));
+    };
+    match parse_bearer(headers)? {
+        Some(token) if tokens_match(&token, expected) => {}
+        Some(_) => {
+            return Err(ApiError::unauthorized(
+                "auth_failed",
+                "admin token is not valid",
+            ));
+        }
+        None => {
+            return Err(ApiError::unauthorized(
+                "auth_required",
+                "admin API requires a bearer token",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn admin_store(state: &AppState, headers: &HeaderMap) -> Result<BrainStore, ApiError> {
+    check_admin(state, headers)?;
+    BrainStore::new(state.brain_home.clone())
+        .map_err(|e| ApiError::bad_gateway("brain_store_init_failed", e.to_string()))
+}
+
+fn resolve_brain_id(
+    state: &AppState,
+    store: &BrainStore,
+    brain: Option<&str>,
+) -> Result<String, ApiError> {
+    store
+        .resolve_brain_or_active(brain.or(state.live().default_brain.as_deref()))
+        .map(|b| b.brain_id)
+        .map_err(|e| ApiError::not_found("brain_not_found", e.to_string()))
+}
+
+fn store_error(e: anyhow::Error) -> ApiError {
+    ApiError::bad_request("admin_operation_failed", e.to_string())
+}
+
+async fn list_brains(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brains = store.list_brains().map_err(store_error)?;
+    let active = store.active_brain_id().map_err(store_error)?;
+    Ok(Json(json!({ "brains": brains, "active": active })))
+}
+
+async fn create_brain(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    OpenAiJson(body): OpenAiJson<CreateBrainBody>,
+) -> Result<(StatusCode, Json<JsonValue>), ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let created = store
+        .create_brain(CreateBrainRequest {
+            name: body.name,
+            tenant_id: body.tenant_id,
+            passphrase_env: body.passphrase_env,
+        })
+        .map_err(store_error)?;
+    Ok((StatusCode::CREATED, Json(json!(created))))
+}
+
+async fn use_brain(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let summary = store
+        .set_active_brain(&brain)
+        .map_err(|e| ApiError::not_found("brain_not_found", e.to_string()))?;
+    Ok(Json(json!(summary)))
+}
+
+async fn forget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(brain): Path<String>,
+    OpenAiJson(body): OpenAiJson<ForgetBody>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brain_id = resolve_brain_id(&state, &store, Some(&brain))?;
+    let suppressed = store
+        .forget_suppress(
+            &brain_id,
+            &body.subject,
+            &body.predicate,
+            &body.scope,
+            &body.reason,
+        )
+        .map_err(store_error)?;
+    state.sessions.invalidate_brain(Some(&brain_id));
+    Ok(Json(
+        json!({ "brain_id": brain_id, "suppressed": suppressed }),
+    ))
+}
+
+async fn list_keys(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let keys = store.list_api_keys().map_err(store_error)?;
+    Ok(Json(json!({ "keys": keys })))
+}
+
+async fn create_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    OpenAiJson(body): OpenAiJson<CreateKeyBody>,
+) -> Result<(StatusCode, Json<JsonValue>), ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brain = store
+        .resolve_brain_or_active(
+            body.brain
+                .as_deref()
+                .or(state.live().default_brain.as_deref()),
+        )
+        .map_err(|e| ApiError::not_found("brain_not_found", e.to_string()))?;
+    let api_key = body
+        .api_key
+        .unwrap_or_else(|| format!("ctx_{}", Uuid::new_v4().simple()));
+    let tenant_id = body.tenant_id.unwrap_or(brain.tenant_id);
+    store
+        .map_api_key(&api_key, &tenant_id, &brain.brain_id, &body.subject)
+        .map_err(store_error)?;
+    store
+        .set_api_key_quota(&api_key, body.quota)
+        .map_err(store_error)?;
+    if let Some(agent) = body.agent_id.as_deref() {
+        store
+            .set_api_key_agent(&api_key, Some(agent))
+            .map_err(store_error)?;
+    }
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "api_key": api_key,
+            "tenant_id": tenant_id,
+            "brain_id": brain.brain_id,
+            "subject": body.subject,
+            "quota": body.quota,
+            "agent_id": body.agent_id,
+        })),
+    ))
+}
+
+async fn list_attachments(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<BrainQuery>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brain_id = resolve_brain_id(&state, &store, query.brain.as_deref())?;
+    let attachments = store.list_attachments(&brain_id).map_err(store_error)?;
+    Ok(Json(
+        json!({ "brain_id": brain_id, "attachments": attachments }),
+    ))
+}
+
+async fn attach(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    OpenAiJson(body): OpenAiJson<AttachBody>,
+) -> Result<(StatusCode, Json<JsonValue>), ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brain_id = resolve_brain_id(&state, &store, body.brain.as_deref())?;
+    store
+        .attach(&brain_id, body.grant.clone())
+        .map_err(store_error)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "brain_id": brain_id, "attachment": body.grant })),
+    ))
+}
+
+async fn detach(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<DetachQuery>,
+) -> Result<Json<JsonValue>, ApiError> {
+    let store = admin_store(&state, &headers)?;
+    let brain_id = resolve_brain_id(&state, &store, query.brain.as_deref())?;
+    let removed = store
+        .detach(&brain_id, &query.agent, query.model.as_deref())
+        .map_err(store_error)?;
+    Ok(Json(json!({ "brain_id": brain_id, "removed": removed })))
+}
+
+/// Re-reads the proxy's settings in place; in-flight requests finish with the old ones.
+async fn reload(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JsonValue>, ApiError> {
+    check_admin(&state, &headers)?;
+    let live = state
+        .reload_settings()
+        .map_err(|e| ApiError::bad_request("reload_failed", format!("{e:#}")))?;
+    Ok(Json(json!({
+        "reloaded": true,
+        "provider": live.provider_name,
+        "planner_mode": live.planner.mode.as_str(),
+        "planner_model": live.planner.model,
+        "model_routes": live.planner_routes.keys().collect::<Vec<_>>(),
+        "default_brain": live.default_brain,
+    })))
+}
@@ -0,0 +1,259 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
+
+use super::{ApiError, AppState, PLANNER_SYSTEM_PROMPT, PlannerConfig, SamplingParams};
+use crate::webhooks::hmac_sha256;
+
+const SERVICE: &str = "bedrock";
+
+struct Credentials<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    session_token: Option<&'a str>,
+}
+
+/// Planner keys for Bedrock are `ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]`.
+fn parse_credentials(raw: &str) -> Option<Credentials<'_>> {
+    let mut parts = raw.trim().splitn(3, ':');
+    let access_key_id = parts.next()?.trim();
+    let secret_access_key = parts.next()?.trim();
+    let session_token = parts.next().map(str::trim).filter(|t| !t.is_empty());
+    (!access_key_id.is_empty() && !secret_access_key.is_empty()).then_some(Credentials {
+        access_key_id,
+        secret_access_key,
+        session_token,
+    })
+}
+
+/// Builds a Bedrock planner key from the standard AWS credential variables.
+pub fn credentials_from_env() -> Option<String> {
+    let access_key_id = env::var("AWS_ACCESS_KEY_ID").ok()?;
+    let secret_access_key = env::var("AWS_SECRET_ACCESS_KEY").ok()?;
+    Some(match env::var("AWS_SESSION_TOKEN") {
+        Ok(token) if !token.is_empty() => format!("{access_key_id}:{secret_access_key}:{token}"),
+        _ => format!("{access_key_id}:{secret_access_key}"),
+    })
+}
+
+/// Takes the region from `bedrock-runtime.<region>.amazonaws.com` (including VPC endpoint
+/// hosts), then from `AWS_REGION` / `AWS_DEFAULT_REGION`.
+fn region(url: &Url) -> Option<String> {
+    let host = url.host_str().unwrap_or_default();
+    let labels: Vec<&str> = host.split('.').collect();
+    labels
+        .iter()
+        .position(|label| *label == "bedrock-runtime")
+        .and_then(|i| labels.get(i + 1))
+        .filter(|region| !matches!(**region, "amazonaws" | "vpce"))
+        .map(|region| region.to_string())
+        .or_else(|| env::var("AWS_REGION").ok())
+        .or_else(|| env::var("AWS_DEFAULT_REGION").ok())
+        .filter(|region| !region.is_empty())
+}
+
+/// SigV4 URI encoding: everything except unreserved characters is percent-encoded.
+fn uri_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Returns the `Authorization` value for a request signed with only `host`, `x-amz-date` and
+/// (for temporary credentials) `x-amz-security-token`.
+fn authorization(
+    creds: &Credentials<'_>,
+    method: &str,
+    url: &Url,
+    region: &str,
+    service: &str,
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = &amz_date[..8];
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    // Services other than S3 sign the already-encoded path encoded once more.
+    let canonical_uri = url
+        .path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut canonical_headers = format!("host:{host}\nx-amz-date:{amz_date}\n");
+    let mut signed_headers = "host;x-amz-date".to_string();
+    if let Some(token) = creds.session_token {
+        canonical_headers.push_str(&format!("x-amz-security-token:{token}\n"));
+        signed_headers.push_str(";x-amz-security-token");
+    }
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n{}\n{canonical_headers}\n{signed_headers}\n{}",
+        url.query().unwrap_or_default(),
+        hex(&Sha256::digest(payload))
+    );
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac_sha256(
+        format!("AWS4{}", creds.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        creds.access_key_id
+    )
+}
+
+/// Calls the Bedrock Converse API and returns the planner text and total tokens.
+pub(super) async fn request_plan_text(
+    state: &AppState,
+    planner: &PlannerConfig,
+    plan_prompt: &str,
+    sampling: &SamplingParams,
+) -> Result<(String, Option<u64>), ApiError> {
+    let creds = planner
+        .api_key
+        .as_deref()
+        .and_then(parse_credentials)
+        .ok_or_else(|| {
+            ApiError::bad_gateway(
+                "planner_auth_missing",
+                "bedrock planner mode requires AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY or CORTEX_PLANNER_API_KEY=ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]",
+            )
+        })?;
+    let url = Url::parse(&format!(
+        "{}/model/{}/converse",
+        planner.base_url.trim_end_matches('/'),
+        uri_encode(&planner.model)
+    ))
+    .map_err(|e| ApiError::bad_gateway("planner_http_failed", e.to_string()))?;
+    let region = region(&url).ok_or_else(|| {
+        ApiError::bad_gateway(
+            "planner_region_missing",
+            "bedrock planner mode requires a bedrock-runtime.<region> base URL or AWS_REGION",
+        )
+    })?;
+
+    let mut inference = json!({ "temperature": sampling.temperature.unwrap_or(0.0) });
+    if let Some(max_tokens) = sampling.max_tokens {
+        inference["maxTokens"] = json!(max_tokens);
+    }
+    if let Some(top_p) = sampling.top_p {
+        inference["topP"] = json!(top_p);
+    }
+    let payload = json!({
+        "system": [{"text": PLANNER_SYSTEM_PROMPT}],
+        "messages": [{"role": "user", "content": [{"text": plan_prompt}]}],
+        "inferenceConfig": inference
+    });
+    let body = serde_json::to_vec(&payload)
+        .map_err(|e| ApiError::bad_gateway("planner_http_failed", e.to_string()))?;
+
+    let now = Utc::now();
+    let mut request = state
+        .planner_http
+        .post(url.clone())
+        .header("content-type", "application/json")
+        .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+        .header(
+            "authorization",
+            authorization(&creds, "POST", &url, &region, SERVICE, &body, now),
+        );
+    if let Some(token) = creds.session_token {
+        request = request.header("x-amz-security-token", token);
+    }
+    let resp = planner
+        .options
+        .apply(request)
+        .timeout(planner.timeout)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| ApiError::bad_gateway("planner_http_failed", e.to_string()))?;
+
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| ApiError::bad_gateway("planner_http_failed", e.to_string()))?;
+    if !status.is_success() {
+        return Err(ApiError::bad_gateway(
+            "planner_http_failed",
+            format!("planner returned HTTP {}: {}", status.as_u16(), body),
+        ));
+    }
+
+    let root: JsonValue = serde_json::from_str(&body)
+        .map_err(|e| ApiError::bad_gateway("planner_decode_failed", e.to_string()))?;
+    let content = root
+        .pointer("/output/message/content")
+        .and_then(JsonValue::as_array)
+        .and_then(|blocks| blocks.iter().find_map(|b| b.get("text")?.as_str()))
+        .ok_or_else(|| {
+            ApiError::bad_gateway(
+                "planner_decode_failed",
+                "planner response missing output.message.content[].text",
+            )
+        })?;
+    let used_tokens = root
+        .pointer("/usage/totalTokens")
+        .and_then(JsonValue::as_u64);
+    Ok((content.to_string(), used_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_the_aws_get_vanilla_vector() {
+        let creds = Credentials {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            session_token: None,
+        };
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let now = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            authorization(&creds, "GET", &url, "us-east-1", "service", b"", now),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let url =
+            Url::parse("https://bedrock-runtime.eu-west-1.amazonaws.com/model/a%3A0/converse")
+                .unwrap();
+        assert_eq!(region(&url).as_deref(), Some("eu-west-1"));
+        assert_eq!(
+            uri_encode("anthropic.claude-v2:1"),
+            "anthropic.claude-v2%3A1"
+        );
+        let creds = parse_credentials("AKID:secret/key:token").unwrap();
+        assert_eq!(creds.secret_access_key, "secret/key");
+        assert_eq!(creds.session_token, Some("token"));
+        assert!(parse_credentials("only-an-api-key").is_none());
+    }
+}
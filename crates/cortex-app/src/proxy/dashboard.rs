@@ -0,0 +1,137 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::ORIGIN;
+use axum::http::{HeaderMap, Uri};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::routing::post;
+use brain_store::BrainStore;
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use tracing::warn;
+
+use super::{ApiError, AppState, OpenAiJson, parse_bearer, public_base_url, tokens_match};
+use crate::product::{
+    is_managed_proxy, save_active_brain, save_active_provider, spawn_detached_up,
+};
+
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+pub(super) fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/dashboard/actions/use-brain", post(use_brain))
+        .route("/dashboard/actions/use-provider", post(use_provider))
+}
+
+#[derive(Debug, Deserialize)]
+struct UseBrainBody {
+    brain: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UseProviderBody {
+    provider: String,
+}
+
+/// With `require_api_key` the proxy may be reachable from the network, and the dashboard shows
+/// the proxy API key; only loopback callers and requests carrying that key get through.
+pub(super) async fn require_access(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let loopback = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .is_some_and(|ConnectInfo(peer)| peer.ip().is_loopback());
+    if state.require_api_key && !loopback {
+        let expected = state.live().proxy_api_key.clone();
+        match (parse_bearer(request.headers())?, expected) {
+            (Some(token), Some(expected)) if tokens_match(&token, &expected) => {}
+            _ => {
+                return Err(ApiError::unauthorized(
+                    "auth_required",
+                    "the dashboard requires the proxy API key when accessed over the network",
+                ));
+            }
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// The dashboard is unauthenticated and local; refuse cross-site form posts from other origins.
+fn ensure_same_origin(state: &AppState, headers: &HeaderMap, uri: &Uri) -> Result<(), ApiError> {
+    let Some(origin) = headers.get(ORIGIN) else {
+        return Ok(());
+    };
+    let expected = public_base_url(state, headers, uri);
+    if origin
+        .to_str()
+        .is_ok_and(|origin| origin.eq_ignore_ascii_case(&expected))
+    {
+        return Ok(());
+    }
+    Err(ApiError::forbidden(
+        "cross_origin_forbidden",
+        "dashboard actions must come from the dashboard origin",
+    ))
+}
+
+/// Schedules a detached `cortex up` once the response is out; only a proxy started by
+/// `cortex up` is restarted, otherwise the caller is told to restart it manually.
+fn schedule_restart() -> bool {
+    let pid = std::process::id();
+    match is_managed_proxy(pid) {
+        Ok(true) => {}
+        Ok(false) => return false,
+        Err(err) => {
+            warn!("failed to read runtime state for restart: {err}");
+            return false;
+        }
+    }
+    tokio::spawn(async {
+        tokio::time::sleep(RESTART_DELAY).await;
+        if let Err(err) = spawn_detached_up() {
+            warn!("dashboard restart failed: {err}");
+        }
+    });
+    true
+}
+
+async fn use_brain(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    uri: Uri,
+    OpenAiJson(body): OpenAiJson<UseBrainBody>,
+) -> Result<Json<JsonValue>, ApiError> {
+    ensure_same_origin(&state, &headers, &uri)?;
+    let store = BrainStore::new(state.brain_home.clone())
+        .map_err(|e| ApiError::bad_gateway("brain_store_init_failed", e.to_string()))?;
+    let summary = store
+        .set_active_brain(&body.brain)
+        .map_err(|e| ApiError::not_found("brain_not_found", e.to_string()))?;
+    save_active_brain(&summary.brain_id)
+        .map_err(|e| ApiError::bad_gateway("config_update_failed", e.to_string()))?;
+    Ok(Json(
+        json!({ "brain": summary, "restarting": schedule_restart() }),
+    ))
+}
+
+async fn use_provider(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    uri: Uri,
+    OpenAiJson(body): OpenAiJson<UseProviderBody>,
+) -> Result<Json<JsonValue>, ApiError> {
+    ensure_same_origin(&state, &headers, &uri)?;
+    save_active_provider(&body.provider)
+        .map_err(|e| ApiError::bad_request("provider_update_failed", e.to_string()))?;
+    Ok(Json(
+        json!({ "provider": body.provider, "restarting": schedule_restart() }),
+    ))
+}
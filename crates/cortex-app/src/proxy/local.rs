@@ -0,0 +1,151 @@
+//! In-process planner over a GGUF model with llama.cpp, so message text never leaves the
+//! machine. Built only with the `local-planner` feature; without it the mode is rejected.
+
+use super::{ApiError, PlannerConfig, SamplingParams};
+
+#[cfg(not(feature = "local-planner"))]
+pub async fn request_plan_text(
+    _planner: &PlannerConfig,
+    _plan_prompt: &str,
+    _sampling: &SamplingParams,
+) -> Result<String, ApiError> {
+    Err(ApiError::bad_gateway(
+        "local_planner_unavailable",
+        "this cortex build has no local planner; rebuild with --features local-planner",
+    ))
+}
+
+#[cfg(feature = "local-planner")]
+pub use imp::request_plan_text;
+
+#[cfg(feature = "local-planner")]
+mod imp {
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    use anyhow::{Context, Result, anyhow};
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+    use llama_cpp_2::sampling::LlamaSampler;
+
+    use super::super::PLANNER_SYSTEM_PROMPT;
+    use super::{ApiError, PlannerConfig, SamplingParams};
+
+    const CONTEXT_TOKENS: u32 = 8192;
+    const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+    static BACKEND: OnceLock<LlamaBackend> = OnceLock::new();
+    /// The last model loaded; loading takes seconds, so it is kept until another path is asked for.
+    static MODEL: Mutex<Option<(PathBuf, Arc<LlamaModel>)>> = Mutex::new(None);
+
+    fn backend() -> Result<&'static LlamaBackend> {
+        if let Some(backend) = BACKEND.get() {
+            return Ok(backend);
+        }
+        let mut backend = LlamaBackend::init().context("failed to initialize llama.cpp")?;
+        backend.void_logs();
+        Ok(BACKEND.get_or_init(|| backend))
+    }
+
+    fn model(path: &Path) -> Result<Arc<LlamaModel>> {
+        let mut cached = MODEL
+            .lock()
+            .map_err(|_| anyhow!("local model cache poisoned"))?;
+        if let Some((loaded, model)) = cached.as_ref()
+            && loaded == path
+        {
+            return Ok(model.clone());
+        }
+        let model = LlamaModel::load_from_file(backend()?, path, &LlamaModelParams::default())
+            .with_context(|| format!("failed to load GGUF model {}", path.display()))?;
+        let model = Arc::new(model);
+        *cached = Some((path.to_path_buf(), model.clone()));
+        Ok(model)
+    }
+
+    fn generate(
+        path: &Path,
+        plan_prompt: &str,
+        temperature: f32,
+        max_tokens: u32,
+    ) -> Result<String> {
+        let backend = backend()?;
+        let model = model(path)?;
+        let template = model
+            .chat_template(None)
+            .context("model has no chat template")?;
+        let prompt = model.apply_chat_template(
+            &template,
+            &[
+                LlamaChatMessage::new("system".to_string(), PLANNER_SYSTEM_PROMPT.to_string())?,
+                LlamaChatMessage::new("user".to_string(), plan_prompt.to_string())?,
+            ],
+            true,
+        )?;
+        let tokens = model.str_to_token(&prompt, AddBos::Always)?;
+        let budget = tokens.len() + max_tokens as usize;
+        if budget > CONTEXT_TOKENS as usize {
+            return Err(anyhow!(
+                "plan prompt needs {budget} tokens, over the local context of {CONTEXT_TOKENS}"
+            ));
+        }
+
+        let params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(CONTEXT_TOKENS));
+        let mut ctx = model.new_context(backend, params)?;
+        let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+        let last = tokens.len() as i32 - 1;
+        for (pos, token) in (0_i32..).zip(tokens) {
+            batch.add(token, pos, &[0], pos == last)?;
+        }
+        ctx.decode(&mut batch)?;
+
+        let mut sampler = if temperature > 0.0 {
+            LlamaSampler::chain_simple([LlamaSampler::temp(temperature), LlamaSampler::dist(0)])
+        } else {
+            LlamaSampler::greedy()
+        };
+        let mut out = Vec::new();
+        let mut pos = batch.n_tokens();
+        for _ in 0..max_tokens {
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if model.is_eog_token(token) {
+                break;
+            }
+            out.extend(model.token_to_bytes(token, Special::Tokenize)?);
+            batch.clear();
+            batch.add(token, pos, &[0], true)?;
+            pos += 1;
+            ctx.decode(&mut batch)?;
+        }
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+
+    pub async fn request_plan_text(
+        planner: &PlannerConfig,
+        plan_prompt: &str,
+        sampling: &SamplingParams,
+    ) -> Result<String, ApiError> {
+        let path = planner.local_model_path.clone().ok_or_else(|| {
+            ApiError::bad_gateway(
+                "local_model_missing",
+                "local planner mode requires a GGUF model path",
+            )
+        })?;
+        let prompt = plan_prompt.to_string();
+        let temperature = sampling.temperature.unwrap_or(0.0) as f32;
+        let max_tokens = sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        let generated = tokio::time::timeout(
+            planner.timeout,
+            tokio::task::spawn_blocking(move || generate(&path, &prompt, temperature, max_tokens)),
+        )
+        .await
+        .map_err(|_| ApiError::bad_gateway("planner_timeout", "local planner timed out"))?
+        .map_err(|e| ApiError::bad_gateway("local_planner_failed", e.to_string()))?;
+        generated.map_err(|e| ApiError::bad_gateway("local_planner_failed", format!("{e:#}")))
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+pub const LEDGER_REDACTION_MAP: &str = "proxy.redaction.map";
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+const CARD_PATTERN: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+const PHONE_PATTERN: &str = r"\+?\(?\d[\d ().-]{6,}\d";
+
+#[derive(Debug, Clone, Default)]
+pub struct RedactionConfig {
+    pub pii: bool,
+    pub patterns: Vec<String>,
+    pub before_append: bool,
+}
+
+impl RedactionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.pii || !self.patterns.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    label: &'static str,
+    regex: Regex,
+    accept: fn(&str) -> bool,
+}
+
+#[derive(Debug)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+    pub before_append: bool,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Option<Self>> {
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+        let mut rules = Vec::new();
+        if config.pii {
+            // Cards before phones so long digit runs are not claimed by the looser phone rule.
+            rules.push(rule("EMAIL", EMAIL_PATTERN, |_| true)?);
+            rules.push(rule("CARD", CARD_PATTERN, luhn_valid)?);
+            rules.push(rule("PHONE", PHONE_PATTERN, plausible_phone)?);
+        }
+        for pattern in &config.patterns {
+            rules.push(
+                rule("CUSTOM", pattern, |_| true)
+                    .with_context(|| format!("invalid redaction pattern '{pattern}'"))?,
+            );
+        }
+        Ok(Some(Self {
+            rules,
+            before_append: config.before_append,
+        }))
+    }
+
+    /// Replaces matches with placeholders, recording them in `map` (placeholder -> original).
+    /// Values already in `map` keep their placeholder so one request maps consistently.
+    pub fn redact(&self, text: &str, map: &mut BTreeMap<String, String>) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let mut out = String::with_capacity(text.len());
+            let mut last = 0;
+            for m in rule.regex.find_iter(&text) {
+                if !(rule.accept)(m.as_str()) {
+                    continue;
+                }
+                out.push_str(&text[last..m.start()]);
+                out.push_str(&placeholder(map, rule.label, m.as_str()));
+                last = m.end();
+            }
+            out.push_str(&text[last..]);
+            text = out;
+        }
+        text
+    }
+}
+
+/// Puts original values back in place of their placeholders.
+pub fn restore(text: &str, map: &BTreeMap<String, String>) -> String {
+    map.iter()
+        .fold(text.to_string(), |acc, (placeholder, original)| {
+            acc.replace(placeholder, original)
+        })
+}
+
+fn rule(label: &'static str, pattern: &str, accept: fn(&str) -> bool) -> Result<Rule> {
+    Ok(Rule {
+        label,
+        regex: Regex::new(pattern)?,
+        accept,
+    })
+}
+
+fn placeholder(map: &mut BTreeMap<String, String>, label: &str, original: &str) -> String {
+    if let Some((existing, _)) = map.iter().find(|(_, v)| v.as_str() == original) {
+        return existing.clone();
+    }
+    let prefix = format!("[{label}_");
+    let next = map.keys().filter(|k| k.starts_with(&prefix)).count() + 1;
+    let key = format!("{prefix}{next}]");
+    map.insert(key.clone(), original.to_string());
+    key
+}
+
+fn digits(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn luhn_valid(value: &str) -> bool {
+    let digits = digits(value);
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| match (i % 2 == 1, d * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => *d,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+fn plausible_phone(value: &str) -> bool {
+    (7..=15).contains(&digits(value).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_builtin_and_custom_patterns_and_restores() {
+        let redactor = Redactor::new(&RedactionConfig {
+            pii: true,
+            patterns: vec![r"ACME-\d+".to_string()],
+            before_append: false,
+        })
+        .unwrap()
+        .unwrap();
+        let text = "mail ann@example.com or bob@example.org, card 4111 1111 1111 1111, \
+                    call +1 (555) 010-9999, ticket ACME-42, again ann@example.com, year 2024";
+        let mut map = BTreeMap::new();
+        let redacted = redactor.redact(text, &mut map);
+        assert_eq!(
+            redacted,
+            "mail [EMAIL_1] or [EMAIL_2], card [CARD_1], call [PHONE_1], ticket [CUSTOM_1], \
+             again [EMAIL_1], year 2024"
+        );
+        assert_eq!(restore(&redacted, &map), text);
+    }
+
+    #[test]
+    fn disabled_config_builds_no_redactor() {
+        assert!(
+            Redactor::new(&RedactionConfig::default())
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            Redactor::new(&RedactionConfig {
+                pii: false,
+                patterns: vec!["(".to_string()],
+                before_append: false,
+            })
+            .is_err()
+        );
+    }
+}
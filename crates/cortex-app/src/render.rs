@@ -0,0 +1,181 @@
+use std::fmt::Write as _;
+
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+use crate::cache::CachedCompletion;
+
+const NO_VERIFIED_OUTPUT: &str = "No verified output.";
+
+/// How the text of an `OK` answer is laid out for the client. Tool calls are not affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderTemplate {
+    /// Verified blocks separated by blank lines.
+    #[default]
+    Plain,
+    /// One bullet per verified block with numbered citations underneath.
+    Bulleted,
+    /// The verified assertions and their citations as a JSON document.
+    Json,
+    /// Verified blocks, then the kernel's narrative blocks under an unverified heading.
+    Sections,
+}
+
+impl RenderTemplate {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "plain" | "" => Ok(Self::Plain),
+            "bulleted" | "bullets" => Ok(Self::Bulleted),
+            "json" => Ok(Self::Json),
+            "sections" => Ok(Self::Sections),
+            other => Err(anyhow!(
+                "unsupported render template '{other}', expected plain|bulleted|json|sections"
+            )),
+        }
+    }
+}
+
+/// The plain rendering, which is also what write-back and usage estimates see.
+pub fn plain(verified_blocks: &[String]) -> String {
+    if verified_blocks.is_empty() {
+        NO_VERIFIED_OUTPUT.to_string()
+    } else {
+        verified_blocks.join("\n\n")
+    }
+}
+
+pub fn render(template: RenderTemplate, completion: &CachedCompletion) -> String {
+    match template {
+        RenderTemplate::Plain => completion.content.clone(),
+        RenderTemplate::Bulleted => bulleted(completion),
+        RenderTemplate::Json => {
+            let assertions = completion
+                .assertions
+                .iter()
+                .enumerate()
+                .map(|(i, fields)| {
+                    json!({
+                        "fields": fields,
+                        "citations": completion.citations.get(i).cloned().unwrap_or_default(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&json!({
+                "assertions": assertions,
+                "semantic_root": completion.semantic_root,
+            }))
+            .unwrap_or_default()
+        }
+        RenderTemplate::Sections => {
+            let mut out = String::from("Verified:\n");
+            list(&mut out, &completion.verified_blocks);
+            out.push_str("\nUnverified:\n");
+            list(&mut out, &completion.narrative_blocks);
+            out.trim_end().to_string()
+        }
+    }
+}
+
+/// Blocks are matched to assertions by position only when the kernel returned one block per
+/// assertion; otherwise every citation is listed without per-bullet references.
+fn bulleted(completion: &CachedCompletion) -> String {
+    if completion.verified_blocks.is_empty() {
+        return NO_VERIFIED_OUTPUT.to_string();
+    }
+    let paired = completion.verified_blocks.len() == completion.citations.len();
+    let mut sources = Vec::new();
+    let mut out = String::new();
+    for (i, block) in completion.verified_blocks.iter().enumerate() {
+        let _ = write!(out, "- {}", block.trim());
+        if paired {
+            for digest in &completion.citations[i] {
+                let _ = write!(out, " [{}]", source_number(&mut sources, digest));
+            }
+        }
+        out.push('\n');
+    }
+    if !paired {
+        for digest in completion.citations.iter().flatten() {
+            source_number(&mut sources, digest);
+        }
+    }
+    if !sources.is_empty() {
+        out.push_str("\nSources:\n");
+        for (i, digest) in sources.iter().enumerate() {
+            let _ = writeln!(out, "[{}] {digest}", i + 1);
+        }
+    }
+    out.trim_end().to_string()
+}
+
+fn source_number<'a>(sources: &mut Vec<&'a str>, digest: &'a str) -> usize {
+    match sources.iter().position(|s| *s == digest) {
+        Some(i) => i + 1,
+        None => {
+            sources.push(digest);
+            sources.len()
+        }
+    }
+}
+
+fn list(out: &mut String, blocks: &[String]) {
+    if blocks.is_empty() {
+        out.push_str("None.\n");
+    }
+    for block in blocks {
+        let _ = writeln!(out, "- {}", block.trim());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_each_template_from_the_same_completion() {
+        let verified = vec![
+            "User prefers tea.".to_string(),
+            "User lives in Oslo.".to_string(),
+        ];
+        let completion = CachedCompletion {
+            content: plain(&verified),
+            assertions: vec![json!({"value": "tea"}), json!({"value": "Oslo"})],
+            verified_blocks: verified,
+            narrative_blocks: vec!["Maybe they like coffee too.".to_string()],
+            citations: vec![
+                vec!["d1".to_string()],
+                vec!["d1".to_string(), "d2".to_string()],
+            ],
+            status: "OK".to_string(),
+            semantic_root: Some("root".to_string()),
+            trace_root: None,
+            error_code: None,
+            plan_prompt: String::new(),
+            plan_source: "fallback".to_string(),
+            sampling: Default::default(),
+            proof: None,
+        };
+
+        assert_eq!(
+            render(RenderTemplate::Plain, &completion),
+            "User prefers tea.\n\nUser lives in Oslo."
+        );
+        assert_eq!(
+            render(RenderTemplate::Bulleted, &completion),
+            "- User prefers tea. [1]\n- User lives in Oslo. [1] [2]\n\nSources:\n[1] d1\n[2] d2"
+        );
+        let doc: serde_json::Value =
+            serde_json::from_str(&render(RenderTemplate::Json, &completion)).unwrap();
+        assert_eq!(doc["assertions"][1]["citations"], json!(["d1", "d2"]));
+        assert_eq!(doc["semantic_root"], "root");
+        assert_eq!(
+            render(RenderTemplate::Sections, &completion),
+            "Verified:\n- User prefers tea.\n- User lives in Oslo.\n\nUnverified:\n- Maybe they like coffee too."
+        );
+        assert_eq!(
+            RenderTemplate::parse(" Bulleted ").unwrap(),
+            RenderTemplate::Bulleted
+        );
+        assert!(RenderTemplate::parse("html").is_err());
+    }
+}
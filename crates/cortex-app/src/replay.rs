@@ -0,0 +1,99 @@
+use adapter_rmvm::RmvmAdapter;
+use anyhow::{Context, Result, anyhow};
+use brain_store::BrainStore;
+use planner_guard::{parse_plan_json, validate_plan_against_manifest};
+use rmvm_grpc::GetManifestRequest;
+use rmvm_proto::{ExecuteRequest, ExecutionStatus};
+use serde::Serialize;
+
+pub const LEDGER_EXECUTION_PROOF: &str = "rmvm.execute.proof";
+
+#[derive(Debug, Serialize)]
+pub struct ReplayReport {
+    pub request_id: String,
+    pub brain_id: String,
+    pub recorded_at: String,
+    pub status: String,
+    pub matches: bool,
+    pub recorded_semantic_root: Option<String>,
+    pub replayed_semantic_root: Option<String>,
+    pub recorded_trace_root: Option<String>,
+    pub replayed_trace_root: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Re-executes the plan recorded for `request_id` against the current kernel.
+/// Returns `Ok(None)` when the brain ledger has no replayable proof for that request.
+pub async fn replay_recorded_plan(
+    endpoint: &str,
+    store: &BrainStore,
+    brain_id: &str,
+    request_id: &str,
+) -> Result<Option<ReplayReport>> {
+    let ledger = store.ledger(brain_id)?;
+    let Some(event) = ledger.iter().rev().find(|e| {
+        e.operation == LEDGER_EXECUTION_PROOF
+            && e.payload.get("request_id").and_then(|v| v.as_str()) == Some(request_id)
+    }) else {
+        return Ok(None);
+    };
+    let plan_json = event
+        .payload
+        .get("plan")
+        .filter(|p| !p.is_null())
+        .ok_or_else(|| anyhow!("proof for {request_id} has no recorded plan"))?;
+    let recorded_semantic_root = payload_string(&event.payload, "semantic_root");
+    let recorded_trace_root = payload_string(&event.payload, "trace_root");
+
+    let replay_id = format!("{request_id}-replay");
+    let plan = parse_plan_json(&plan_json.to_string(), &replay_id)?;
+    let adapter = RmvmAdapter::new(endpoint.to_string());
+    let manifest = adapter
+        .get_manifest(GetManifestRequest {
+            request_id: replay_id.clone(),
+        })
+        .await?
+        .manifest
+        .context("rmvm returned no manifest")?;
+
+    let mut report = ReplayReport {
+        request_id: request_id.to_string(),
+        brain_id: brain_id.to_string(),
+        recorded_at: event.ts.clone(),
+        status: ExecutionStatus::Unspecified.as_str_name().to_string(),
+        matches: false,
+        recorded_semantic_root,
+        replayed_semantic_root: None,
+        recorded_trace_root,
+        replayed_trace_root: None,
+        error: None,
+    };
+    if let Err(err) = validate_plan_against_manifest(&plan, &manifest) {
+        report.error = Some(format!("recorded plan no longer fits the manifest: {err}"));
+        return Ok(Some(report));
+    }
+
+    let execute = adapter
+        .execute(ExecuteRequest {
+            manifest: Some(manifest),
+            plan: Some(plan),
+        })
+        .await?;
+    report.status = ExecutionStatus::try_from(execute.status)
+        .unwrap_or(ExecutionStatus::Unspecified)
+        .as_str_name()
+        .to_string();
+    report.replayed_semantic_root = execute.proof.as_ref().map(|p| p.semantic_root.clone());
+    report.replayed_trace_root = execute.proof.as_ref().map(|p| p.trace_root.clone());
+    report.error = execute.error.map(|e| e.message);
+    report.matches = report.recorded_semantic_root.is_some()
+        && report.recorded_semantic_root == report.replayed_semantic_root;
+    Ok(Some(report))
+}
+
+fn payload_string(payload: &serde_json::Value, key: &str) -> Option<String> {
+    payload
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(ToOwned::to_owned)
+}
@@ -0,0 +1,219 @@
+use std::fmt::Write as _;
+
+use brain_store::{BrainState, BrainSummary, MemoryObject};
+use serde_json::Value as JsonValue;
+
+/// Audit entries shown at the end of the report, newest first.
+const RECENT_AUDIT: usize = 50;
+
+/// A readable, decrypted summary of a brain for review. Unlike a `.cbrain` package it cannot be
+/// imported and holds memory values in plain text.
+pub fn render_markdown(brain: &BrainSummary, state: &BrainState, generated_at: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Brain report: {}\n", cell(&brain.name));
+    let _ = writeln!(out, "- Brain id: `{}`", brain.brain_id);
+    let _ = writeln!(out, "- Tenant: `{}`", brain.tenant_id);
+    let _ = writeln!(out, "- Active branch: `{}`", brain.active_branch);
+    let _ = writeln!(out, "- Last updated: {}", brain.updated_at);
+    let _ = writeln!(out, "- Generated: {generated_at}");
+    out.push_str("\n> Decrypted contents. Share with care.\n");
+
+    for (name, branch) in &state.branches {
+        let marker = if *name == brain.active_branch {
+            " (active)"
+        } else {
+            ""
+        };
+        let _ = writeln!(out, "\n## Branch `{name}`{marker}\n");
+        let live = branch.memory_objects.values().filter(|o| o.is_live());
+        memory_table(&mut out, live.collect());
+        let inactive = branch
+            .memory_objects
+            .values()
+            .filter(|o| !o.is_live())
+            .count();
+        if inactive > 0 {
+            let _ = writeln!(
+                out,
+                "\n{inactive} forgotten or superseded object(s) not shown."
+            );
+        }
+
+        if !branch.suppressions.is_empty() {
+            out.push_str("\n### Suppression history\n\n");
+            out.push_str("| When | Subject | Predicate | Scope | Reason | Objects |\n");
+            out.push_str("| --- | --- | --- | --- | --- | --- |\n");
+            for s in &branch.suppressions {
+                let objects = if s.is_partial() {
+                    s.object_ids.join(", ")
+                } else {
+                    "all".to_string()
+                };
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {} | {} ({}) |",
+                    s.ts,
+                    cell(&s.subject),
+                    cell(&s.predicate),
+                    cell(&s.scope),
+                    cell(&s.reason),
+                    cell(&objects),
+                    s.suppressed_count
+                );
+            }
+        }
+
+        if !branch.rules.is_empty() {
+            out.push_str("\n### Rules\n\n");
+            out.push_str("| Id | Class | Action | Sinks | Description |\n");
+            out.push_str("| --- | --- | --- | --- | --- |\n");
+            for r in &branch.rules {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {} |",
+                    cell(&r.id),
+                    cell(&r.memory_class),
+                    format!("{:?}", r.action).to_lowercase(),
+                    cell(&r.allowed_sinks.join(", ")),
+                    cell(&r.description)
+                );
+            }
+        }
+    }
+
+    out.push_str("\n## Attachments\n\n");
+    if state.attachments.is_empty() {
+        out.push_str("No agents are attached.\n");
+    } else {
+        out.push_str("| Agent | Model | Read | Write | Sinks | Expires |\n");
+        out.push_str("| --- | --- | --- | --- | --- | --- |\n");
+        for a in &state.attachments {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} |",
+                cell(&a.agent_id),
+                cell(&a.model_id),
+                cell(&a.read_classes.join(", ")),
+                cell(&a.write_classes.join(", ")),
+                cell(&a.sinks.join(", ")),
+                a.expires_at.as_deref().unwrap_or("never")
+            );
+        }
+    }
+
+    out.push_str("\n## Recent audit\n\n");
+    if state.audit.is_empty() {
+        out.push_str("No audit entries.\n");
+    } else {
+        out.push_str("| When | Actor | Action | Details |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+        for e in state.audit.iter().rev().take(RECENT_AUDIT) {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                e.ts,
+                cell(&e.actor),
+                cell(&e.action),
+                cell(&e.details.to_string())
+            );
+        }
+        if state.audit.len() > RECENT_AUDIT {
+            let _ = writeln!(
+                out,
+                "\n{} older entries not shown.",
+                state.audit.len() - RECENT_AUDIT
+            );
+        }
+    }
+    out
+}
+
+fn memory_table(out: &mut String, objects: Vec<&MemoryObject>) {
+    if objects.is_empty() {
+        out.push_str("No live memories.\n");
+        return;
+    }
+    out.push_str("| Subject | Predicate | Value | Type | Recorded |\n");
+    out.push_str("| --- | --- | --- | --- | --- |\n");
+    for o in objects {
+        let value = match &o.value {
+            JsonValue::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(&o.subject),
+            cell(&o.predicate),
+            cell(&value),
+            cell(&o.memory_type),
+            o.recorded_at.as_deref().unwrap_or("")
+        );
+    }
+}
+
+/// Keeps a value inside one table cell.
+fn cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use brain_store::{BranchState, SuppressionRecord};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn renders_live_memories_and_suppressions_per_branch() {
+        let memory = |id: &str, value: JsonValue, suppressed: bool| MemoryObject {
+            id: id.to_string(),
+            subject: "user:local".to_string(),
+            predicate: "prefers_beverage".to_string(),
+            value,
+            memory_type: "preference".to_string(),
+            suppressed,
+            recorded_at: None,
+            superseded_by: None,
+        };
+        let mut main = BranchState {
+            name: "main".to_string(),
+            ..Default::default()
+        };
+        main.memory_objects.insert(
+            "m1".to_string(),
+            memory("m1", json!("tea | no sugar"), false),
+        );
+        main.memory_objects
+            .insert("m2".to_string(), memory("m2", json!("coffee"), true));
+        main.suppressions.push(SuppressionRecord {
+            id: "s1".to_string(),
+            ts: "2026-01-01T00:00:00Z".to_string(),
+            subject: "user:local".to_string(),
+            predicate: "prefers_beverage".to_string(),
+            scope: "global".to_string(),
+            reason: "user asked".to_string(),
+            suppressed_count: 1,
+            object_ids: Vec::new(),
+        });
+        let mut state = BrainState::default();
+        state.branches.insert("main".to_string(), main);
+        let brain = BrainSummary {
+            brain_id: "b1".to_string(),
+            name: "personal".to_string(),
+            tenant_id: "t1".to_string(),
+            updated_at: "2026-01-02T00:00:00Z".to_string(),
+            active_branch: "main".to_string(),
+        };
+
+        let report = render_markdown(&brain, &state, "2026-01-03T00:00:00Z");
+        assert!(report.contains("## Branch `main` (active)"));
+        assert!(report.contains("| user:local | prefers_beverage | tea \\| no sugar |"));
+        assert!(!report.contains("coffee"));
+        assert!(report.contains("1 forgotten or superseded object(s) not shown."));
+        assert!(report.contains("| user asked | all (1) |"));
+        assert!(report.contains("No agents are attached."));
+    }
+}
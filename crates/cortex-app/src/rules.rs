@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use brain_store::{RuleAction, RuleEntry};
+use rmvm_proto::cortex::rmvm::v3_1::value::V;
+use rmvm_proto::{ExecuteResponse, PublicManifest};
+use serde::Serialize;
+
+/// Sink of a proxy response when the caller does not name one with `X-Cortex-Sink`.
+pub const DEFAULT_SINK: &str = "chat";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleHit {
+    pub rule_id: String,
+    pub action: RuleAction,
+    pub memory_class: String,
+    pub subject: String,
+    pub predicate: String,
+}
+
+fn violated<'a>(
+    rules: &'a [RuleEntry],
+    sink: &'a str,
+    action: RuleAction,
+    memory_class: &'a str,
+) -> impl Iterator<Item = &'a RuleEntry> {
+    rules
+        .iter()
+        .filter(move |r| r.action == action && r.covers(memory_class) && !r.allows(sink))
+}
+
+/// Memory class of every manifest handle, keyed by `(subject, predicate)`.
+pub fn handle_classes(manifest: &PublicManifest) -> BTreeMap<(String, String), String> {
+    manifest
+        .handles
+        .iter()
+        .filter_map(|h| {
+            let meta = h.meta.as_ref()?;
+            Some((
+                (meta.subject.clone(), meta.predicate_label.clone()),
+                h.type_id.clone(),
+            ))
+        })
+        .collect()
+}
+
+/// Drops handles a `redact` rule keeps away from `sink` before planning, so neither the
+/// assertions nor the rendered text can carry them.
+pub fn redact_manifest(
+    rules: &[RuleEntry],
+    sink: &str,
+    manifest: &mut PublicManifest,
+) -> Vec<RuleHit> {
+    let mut hits = Vec::new();
+    manifest.handles.retain(|handle| {
+        let (subject, predicate) = handle
+            .meta
+            .as_ref()
+            .map(|m| (m.subject.clone(), m.predicate_label.clone()))
+            .unwrap_or_default();
+        let before = hits.len();
+        hits.extend(
+            violated(rules, sink, RuleAction::Redact, &handle.type_id).map(|rule| RuleHit {
+                rule_id: rule.id.clone(),
+                action: rule.action,
+                memory_class: handle.type_id.clone(),
+                subject: subject.clone(),
+                predicate: predicate.clone(),
+            }),
+        );
+        hits.len() == before
+    });
+    hits
+}
+
+/// Assertions that a `block` rule forbids sending to `sink`. Assertions whose handle is not in
+/// `classes` are only matched by `*` rules.
+pub fn blocked(
+    rules: &[RuleEntry],
+    sink: &str,
+    classes: &BTreeMap<(String, String), String>,
+    execute: &ExecuteResponse,
+) -> Vec<RuleHit> {
+    let mut hits = Vec::new();
+    for assertion in &execute.assertions {
+        let field = |name: &str| match assertion.fields.get(name).and_then(|v| v.v.as_ref()) {
+            Some(V::S(s)) => s.clone(),
+            _ => String::new(),
+        };
+        let (subject, predicate) = (field("subject"), field("predicate"));
+        let class = classes
+            .get(&(subject.clone(), predicate.clone()))
+            .map(String::as_str)
+            .unwrap_or_default();
+        hits.extend(
+            violated(rules, sink, RuleAction::Block, class).map(|rule| RuleHit {
+                rule_id: rule.id.clone(),
+                action: rule.action,
+                memory_class: class.to_string(),
+                subject: subject.clone(),
+                predicate: predicate.clone(),
+            }),
+        );
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use rmvm_proto::{HandleMeta, HandleRef, Value, VerifiedAssertion};
+
+    use super::*;
+
+    fn rule(id: &str, class: &str, sinks: &[&str], action: RuleAction) -> RuleEntry {
+        RuleEntry {
+            id: id.to_string(),
+            description: String::new(),
+            allowed_sinks: sinks.iter().map(|s| s.to_string()).collect(),
+            memory_class: class.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn redacts_and_blocks_by_class_and_sink() {
+        let handle = |r: &str, class: &str, predicate: &str| HandleRef {
+            r#ref: r.to_string(),
+            type_id: class.to_string(),
+            meta: Some(HandleMeta {
+                subject: "user:local".to_string(),
+                predicate_label: predicate.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut manifest = PublicManifest {
+            handles: vec![
+                handle("H1", "normative.preference", "prefers_beverage"),
+                handle("H2", "episodic.event", "visited"),
+            ],
+            ..Default::default()
+        };
+        let classes = handle_classes(&manifest);
+        let rules = vec![
+            rule("r1", "episodic.event", &["chat"], RuleAction::Redact),
+            rule("r2", "normative.preference", &["chat"], RuleAction::Block),
+        ];
+
+        assert!(redact_manifest(&rules, "chat", &mut manifest.clone()).is_empty());
+        let hits = redact_manifest(&rules, "export", &mut manifest);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].predicate, "visited");
+        assert_eq!(manifest.handles.len(), 1);
+
+        let execute = ExecuteResponse {
+            assertions: vec![VerifiedAssertion {
+                fields: BTreeMap::from([
+                    (
+                        "subject".to_string(),
+                        Value {
+                            v: Some(V::S("user:local".to_string())),
+                        },
+                    ),
+                    (
+                        "predicate".to_string(),
+                        Value {
+                            v: Some(V::S("prefers_beverage".to_string())),
+                        },
+                    ),
+                ]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(blocked(&rules, "chat", &classes, &execute).is_empty());
+        let hits = blocked(&rules, "export", &classes, &execute);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].rule_id, "r2");
+        assert_eq!(hits[0].memory_class, "normative.preference");
+    }
+}
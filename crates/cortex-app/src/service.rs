@@ -0,0 +1,292 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+
+use crate::product::default_paths;
+
+const SYSTEMD_UNIT: &str = "cortex.service";
+const LAUNCHD_LABEL: &str = "com.vinzify.cortex";
+const WINDOWS_TASK: &str = "Cortex";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Manager {
+    Systemd,
+    Launchd,
+    Schtasks,
+}
+
+impl Manager {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Systemd => "systemd",
+            Self::Launchd => "launchd",
+            Self::Schtasks => "schtasks",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceStatus {
+    manager: Manager,
+    installed: bool,
+    active: bool,
+    definition: String,
+}
+
+fn manager() -> Result<Manager> {
+    if cfg!(target_os = "macos") {
+        Ok(Manager::Launchd)
+    } else if cfg!(target_os = "windows") {
+        Ok(Manager::Schtasks)
+    } else if cfg!(target_os = "linux") {
+        Ok(Manager::Systemd)
+    } else {
+        bail!("cortex service is supported on Linux (systemd), macOS (launchd) and Windows")
+    }
+}
+
+fn definition_path(manager: Manager) -> Result<PathBuf> {
+    match manager {
+        Manager::Systemd => Ok(dirs::config_dir()
+            .ok_or_else(|| anyhow!("failed to resolve config dir"))?
+            .join("systemd")
+            .join("user")
+            .join(SYSTEMD_UNIT)),
+        Manager::Launchd => Ok(dirs::home_dir()
+            .ok_or_else(|| anyhow!("failed to resolve home dir"))?
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{LAUNCHD_LABEL}.plist"))),
+        // Scheduled tasks live in the Task Scheduler, not in a file.
+        Manager::Schtasks => Ok(PathBuf::from(WINDOWS_TASK)),
+    }
+}
+
+/// User unit running the stack in the foreground so systemd supervises and restarts it.
+fn systemd_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Cortex portable brain (RMVM + proxy)\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart=\"{}\" up --detached=false\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exe.display()
+    )
+}
+
+fn launchd_plist(exe: &Path, log: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{label}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>{exe}</string>
+    <string>up</string>
+    <string>--detached=false</string>
+  </array>
+  <key>RunAtLoad</key>
+  <true/>
+  <key>KeepAlive</key>
+  <dict>
+    <key>SuccessfulExit</key>
+    <false/>
+  </dict>
+  <key>StandardOutPath</key>
+  <string>{log}</string>
+  <key>StandardErrorPath</key>
+  <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        exe = xml_escape(&exe.display().to_string()),
+        log = xml_escape(&log.display().to_string()),
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+pub fn install() -> Result<()> {
+    let manager = manager()?;
+    let exe = env::current_exe().context("failed to resolve cortex executable path")?;
+    let path = definition_path(manager)?;
+    match manager {
+        Manager::Systemd => {
+            write_definition(&path, &systemd_unit(&exe))?;
+            run("systemctl", &["--user", "daemon-reload"])?;
+            run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])?;
+        }
+        Manager::Launchd => {
+            let log = default_paths()?.state_dir.join("logs").join("service.log");
+            if let Some(parent) = log.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if path.exists() {
+                let _ = run("launchctl", &["unload", "-w", &path.display().to_string()]);
+            }
+            write_definition(&path, &launchd_plist(&exe, &log))?;
+            run("launchctl", &["load", "-w", &path.display().to_string()])?;
+        }
+        Manager::Schtasks => {
+            // A logon task runs detached `cortex up`, which needs no service-control handler or admin rights.
+            let action = format!("\"{}\" up", exe.display());
+            run(
+                "schtasks",
+                &[
+                    "/Create",
+                    "/TN",
+                    WINDOWS_TASK,
+                    "/TR",
+                    &action,
+                    "/SC",
+                    "ONLOGON",
+                    "/RL",
+                    "LIMITED",
+                    "/F",
+                ],
+            )?;
+            run("schtasks", &["/Run", "/TN", WINDOWS_TASK])?;
+        }
+    }
+    println!("Installed Cortex service ({})", path.display());
+    Ok(())
+}
+
+pub fn uninstall() -> Result<()> {
+    let manager = manager()?;
+    let path = definition_path(manager)?;
+    if !is_installed(manager, &path) {
+        println!("Cortex service is not installed.");
+        return Ok(());
+    }
+    match manager {
+        Manager::Systemd => {
+            let _ = run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT]);
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+            run("systemctl", &["--user", "daemon-reload"])?;
+        }
+        Manager::Launchd => {
+            let _ = run("launchctl", &["unload", "-w", &path.display().to_string()]);
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        }
+        Manager::Schtasks => {
+            run("schtasks", &["/Delete", "/TN", WINDOWS_TASK, "/F"])?;
+        }
+    }
+    println!("Removed Cortex service ({})", path.display());
+    Ok(())
+}
+
+pub fn status(json: bool) -> Result<()> {
+    let manager = manager()?;
+    let path = definition_path(manager)?;
+    let installed = is_installed(manager, &path);
+    let active = installed
+        && match manager {
+            Manager::Systemd => succeeds(
+                "systemctl",
+                &["--user", "is-active", "--quiet", SYSTEMD_UNIT],
+            ),
+            Manager::Launchd => {
+                run("launchctl", &["list", LAUNCHD_LABEL]).is_ok_and(|out| out.contains("\"PID\""))
+            }
+            Manager::Schtasks => run("schtasks", &["/Query", "/TN", WINDOWS_TASK, "/FO", "LIST"])
+                .is_ok_and(|out| out.contains("Running")),
+        };
+    let view = ServiceStatus {
+        manager,
+        installed,
+        active,
+        definition: path.display().to_string(),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&view)?);
+    } else {
+        println!(
+            "service manager={} installed={} active={}",
+            view.manager.as_str(),
+            view.installed,
+            view.active
+        );
+        println!("definition={}", view.definition);
+    }
+    Ok(())
+}
+
+fn is_installed(manager: Manager, path: &Path) -> bool {
+    match manager {
+        Manager::Systemd | Manager::Launchd => path.exists(),
+        Manager::Schtasks => succeeds("schtasks", &["/Query", "/TN", WINDOWS_TASK]),
+    }
+}
+
+fn write_definition(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definitions_run_cortex_up_in_the_foreground() {
+        let exe = Path::new("/opt/cortex & co/cortex");
+        let unit = systemd_unit(exe);
+        assert!(unit.contains("ExecStart=\"/opt/cortex & co/cortex\" up --detached=false\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+
+        let plist = launchd_plist(exe, Path::new("/tmp/service.log"));
+        assert!(plist.contains("<string>/opt/cortex &amp; co/cortex</string>"));
+        assert!(plist.contains("<string>--detached=false</string>"));
+        assert!(plist.contains(&format!("<string>{LAUNCHD_LABEL}</string>")));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rmvm_proto::PublicManifest;
+use sha2::{Digest, Sha256};
+
+const MAX_SESSIONS: usize = 1024;
+const SESSION_IDLE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug)]
+struct SessionState {
+    touched: Instant,
+    appended: HashSet<String>,
+    last_manifest: Option<PublicManifest>,
+}
+
+/// Per-conversation ingestion state so resent history is not appended to the kernel twice.
+/// A message is identified by its turn and every user message up to it, so conversations that
+/// share a fallback id only share the history they actually have in common.
+#[derive(Debug, Default)]
+pub struct SessionTracker {
+    sessions: Mutex<HashMap<String, SessionState>>,
+}
+
+impl SessionTracker {
+    /// Client-supplied id when present, otherwise a digest of the conversation's first user
+    /// message. The fallback only groups turns; [`Self::pending`] still tells conversations
+    /// apart once their histories differ.
+    pub fn conversation_id(supplied: Option<&str>, first_message: &str) -> String {
+        match supplied.map(str::trim).filter(|id| !id.is_empty()) {
+            Some(id) => id.to_string(),
+            None => format!(
+                "conv-{}",
+                &format!("{:x}", Sha256::digest(first_message.as_bytes()))[..16]
+            ),
+        }
+    }
+
+    /// `caller` is the API key's id (or the anonymous id) and `subject` the memory subject.
+    pub fn key(
+        caller: &str,
+        subject: &str,
+        brain_id: Option<&str>,
+        conversation_id: &str,
+    ) -> String {
+        format!(
+            "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            brain_id.unwrap_or_default(),
+            caller,
+            subject,
+            conversation_id
+        )
+    }
+
+    /// Returns the user messages (turn index, text, message id) this session has not appended
+    /// yet; pass the id to [`Self::mark_appended`].
+    pub fn pending(&self, key: &str, user_messages: &[String]) -> Vec<(usize, String, String)> {
+        let ids = message_ids(user_messages);
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let session = touch(&mut sessions, key);
+        user_messages
+            .iter()
+            .zip(ids)
+            .enumerate()
+            .filter(|(_, (_, id))| !session.appended.contains(id))
+            .map(|(turn, (text, id))| (turn, text.clone(), id))
+            .collect()
+    }
+
+    pub fn mark_appended(&self, key: &str, message_id: String) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            touch(&mut sessions, key).appended.insert(message_id);
+        }
+    }
+
+    pub fn last_manifest(&self, key: &str) -> Option<PublicManifest> {
+        let sessions = self.sessions.lock().ok()?;
+        sessions.get(key)?.last_manifest.clone()
+    }
+
+    pub fn store_manifest(&self, key: &str, manifest: &PublicManifest) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            touch(&mut sessions, key).last_manifest = Some(manifest.clone());
+        }
+    }
+
+    /// Drops the manifests every session of `brain_id` remembered, whichever session or request
+    /// changed the brain, while keeping the ingestion record.
+    pub fn invalidate_brain(&self, brain_id: Option<&str>) {
+        let prefix = format!("{}\u{1f}", brain_id.unwrap_or_default());
+        if let Ok(mut sessions) = self.sessions.lock() {
+            for (_, session) in sessions
+                .iter_mut()
+                .filter(|(key, _)| key.starts_with(&prefix))
+            {
+                session.last_manifest = None;
+            }
+        }
+    }
+}
+
+fn touch<'a>(sessions: &'a mut HashMap<String, SessionState>, key: &str) -> &'a mut SessionState {
+    if !sessions.contains_key(key) {
+        sessions.retain(|_, s| s.touched.elapsed() < SESSION_IDLE_TTL);
+        if sessions.len() >= MAX_SESSIONS
+            && let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, s)| s.touched)
+                .map(|(k, _)| k.clone())
+        {
+            sessions.remove(&oldest);
+        }
+    }
+    let session = sessions
+        .entry(key.to_string())
+        .or_insert_with(|| SessionState {
+            touched: Instant::now(),
+            appended: HashSet::new(),
+            last_manifest: None,
+        });
+    session.touched = Instant::now();
+    session
+}
+
+/// Chains each message's digest onto the one before it.
+fn message_ids(user_messages: &[String]) -> Vec<String> {
+    let mut chain = Sha256::new();
+    user_messages
+        .iter()
+        .enumerate()
+        .map(|(turn, text)| {
+            chain.update(Sha256::digest(text.as_bytes()));
+            format!("{turn}:{:x}", chain.clone().finalize())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(pending: Vec<(usize, String, String)>) -> Vec<(usize, String)> {
+        pending
+            .into_iter()
+            .map(|(turn, text, _)| (turn, text))
+            .collect()
+    }
+
+    #[test]
+    fn resent_history_is_only_pending_once() {
+        let tracker = SessionTracker::default();
+        let key = SessionTracker::key("key:a", "user:local", Some("brain"), "conv-1");
+        let first = vec!["hello".to_string()];
+        let pending = tracker.pending(&key, &first);
+        assert_eq!(texts(pending.clone()), vec![(0, "hello".to_string())]);
+        tracker.mark_appended(&key, pending[0].2.clone());
+
+        let second = vec!["hello".to_string(), "hello".to_string()];
+        assert_eq!(
+            texts(tracker.pending(&key, &second)),
+            vec![(1, "hello".to_string())]
+        );
+
+        let other = SessionTracker::key("key:a", "user:local", Some("brain"), "conv-2");
+        assert_eq!(tracker.pending(&other, &first).len(), 1);
+        let other_key = SessionTracker::key("key:b", "user:local", Some("brain"), "conv-1");
+        assert_eq!(tracker.pending(&other_key, &first).len(), 1);
+    }
+
+    #[test]
+    fn conversations_sharing_a_fallback_id_keep_their_own_turns() {
+        let tracker = SessionTracker::default();
+        let id = SessionTracker::conversation_id(None, "hi");
+        let key = SessionTracker::key("key:a", "user:local", Some("brain"), &id);
+        let one = vec!["hi".to_string(), "I like tea".to_string()];
+        for (_, _, message_id) in tracker.pending(&key, &one) {
+            tracker.mark_appended(&key, message_id);
+        }
+
+        // Same opening, same second turn text, but after a different history.
+        let two = vec![
+            "hi".to_string(),
+            "my name is Ada".to_string(),
+            "I like tea".to_string(),
+        ];
+        assert_eq!(
+            texts(tracker.pending(&key, &two)),
+            vec![
+                (1, "my name is Ada".to_string()),
+                (2, "I like tea".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn any_change_to_the_brain_drops_remembered_manifests() {
+        let tracker = SessionTracker::default();
+        let mine = SessionTracker::key("key:a", "user:local", Some("brain"), "conv-1");
+        let theirs = SessionTracker::key("key:b", "user:local", Some("brain"), "conv-9");
+        let elsewhere = SessionTracker::key("key:a", "user:local", Some("other"), "conv-1");
+        let manifest = PublicManifest::default();
+        for key in [&mine, &theirs, &elsewhere] {
+            tracker.store_manifest(key, &manifest);
+        }
+
+        tracker.invalidate_brain(Some("brain"));
+        assert!(tracker.last_manifest(&mine).is_none());
+        assert!(tracker.last_manifest(&theirs).is_none());
+        assert!(tracker.last_manifest(&elsewhere).is_some());
+    }
+}
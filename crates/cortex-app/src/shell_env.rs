@@ -0,0 +1,70 @@
+use anyhow::{Result, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvShell {
+    Posix,
+    Fish,
+    PowerShell,
+}
+
+impl EnvShell {
+    /// `bash`, `zsh` and `sh` share POSIX syntax; unset picks PowerShell on Windows.
+    pub fn parse(shell: Option<&str>) -> Result<Self> {
+        let Some(shell) = shell else {
+            return Ok(if cfg!(windows) {
+                Self::PowerShell
+            } else {
+                Self::Posix
+            });
+        };
+        Ok(match shell.trim().to_ascii_lowercase().as_str() {
+            "bash" | "zsh" | "sh" => Self::Posix,
+            "fish" => Self::Fish,
+            "powershell" | "pwsh" => Self::PowerShell,
+            other => bail!("unknown shell '{other}', expected bash|zsh|fish|powershell"),
+        })
+    }
+}
+
+/// Renders one assignment per variable, quoted so values are taken literally by `eval`.
+pub fn render(shell: EnvShell, vars: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    for (name, value) in vars {
+        let line = match shell {
+            EnvShell::Posix => format!("export {name}='{}'", value.replace('\'', r"'\''")),
+            EnvShell::Fish => format!(
+                "set -gx {name} '{}'",
+                value.replace('\\', r"\\").replace('\'', r"\'")
+            ),
+            EnvShell::PowerShell => format!("$env:{name} = '{}'", value.replace('\'', "''")),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_values_for_each_shell() {
+        let vars = [
+            ("OPENAI_BASE_URL", "http://127.0.0.1:8080/v1".to_string()),
+            ("CORTEX_BRAIN", r"it's a\brain".to_string()),
+        ];
+        assert_eq!(
+            render(EnvShell::Posix, &vars),
+            "export OPENAI_BASE_URL='http://127.0.0.1:8080/v1'\nexport CORTEX_BRAIN='it'\\''s a\\brain'\n"
+        );
+        assert!(
+            render(EnvShell::Fish, &vars).ends_with("set -gx CORTEX_BRAIN 'it\\'s a\\\\brain'\n")
+        );
+        assert!(
+            render(EnvShell::PowerShell, &vars).ends_with("$env:CORTEX_BRAIN = 'it''s a\\brain'\n")
+        );
+        assert_eq!(EnvShell::parse(Some("ZSH")).unwrap(), EnvShell::Posix);
+        assert!(EnvShell::parse(Some("tcsh")).is_err());
+    }
+}
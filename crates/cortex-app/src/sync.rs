@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use adapter_rmvm::RmvmAdapter;
+use anyhow::{Context, Result};
+use brain_store::BrainStore;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{info, warn};
+
+use crate::endpoints::RmvmEndpoints;
+use crate::hydrate::{FlushReport, apply_forgets, flush_kernel, hydrate_brain};
+
+/// Per-brain sync state shown by `cortex status` and the dashboard. Counters are totals since
+/// the proxy started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub hydrated_at: Option<String>,
+    pub last_sync_at: Option<String>,
+    pub last_error: Option<String>,
+    pub memories_added: u64,
+    pub forgets_applied: u64,
+    pub conflicts: u64,
+}
+
+#[derive(Debug, Default)]
+struct BrainSync {
+    /// Brain suppression ids the kernel has already been told about.
+    applied_forgets: BTreeSet<String>,
+    /// Set once the brain has been hydrated into its kernel.
+    hydration: Arc<OnceCell<()>>,
+    status: SyncStatus,
+}
+
+/// Keeps every brain the proxy serves in step with the kernel: hydration on first use, then
+/// incremental passes that flush kernel writes into the brain and send brain-side forgets to
+/// the kernel. Status is written through to `path` (when set) for `cortex status`.
+#[derive(Debug)]
+pub struct KernelSync {
+    endpoints: RmvmEndpoints,
+    brain_home: Option<PathBuf>,
+    hydrate: bool,
+    path: Option<PathBuf>,
+    brains: Mutex<BTreeMap<String, BrainSync>>,
+}
+
+impl KernelSync {
+    pub fn new(
+        endpoints: RmvmEndpoints,
+        brain_home: Option<PathBuf>,
+        hydrate: bool,
+        path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            endpoints,
+            brain_home,
+            hydrate,
+            path,
+            brains: Mutex::default(),
+        }
+    }
+
+    /// Starts tracking `brain_id`, hydrating it into the kernel the first time. A failed
+    /// hydration is recorded and retried on the next call. True when this call hydrated it.
+    /// Hydration runs outside the shared lock: requests for other brains carry on, and
+    /// concurrent requests for the same brain wait for the one hydration in flight.
+    pub async fn ensure_hydrated(&self, brain_id: &str) -> bool {
+        let (hydration, mut applied) = {
+            let mut brains = self.brains.lock().await;
+            let brain = brains.entry(brain_id.to_string()).or_default();
+            if !self.hydrate || brain.hydration.initialized() {
+                return false;
+            }
+            (brain.hydration.clone(), brain.applied_forgets.clone())
+        };
+        let mut hydrated = false;
+        let this_call = &mut hydrated;
+        let _ = hydration
+            .get_or_try_init(move || async move {
+                let result = match BrainStore::new(self.brain_home.clone()) {
+                    Ok(store) => {
+                        let endpoint = self.endpoints.resolve(&store, brain_id);
+                        hydrate_brain(&endpoint, &store, brain_id, &mut applied)
+                            .await
+                            .map(|report| (endpoint, report))
+                    }
+                    Err(err) => Err(err),
+                };
+                let mut brains = self.brains.lock().await;
+                let brain = brains.entry(brain_id.to_string()).or_default();
+                brain.applied_forgets.extend(applied);
+                let outcome = match result {
+                    Ok((endpoint, report)) => {
+                        info!(
+                            "hydrated brain {brain_id} into {endpoint} ({} events, {} suppressions)",
+                            report.appended, report.forgotten
+                        );
+                        brain.status.hydrated_at = Some(Utc::now().to_rfc3339());
+                        brain.status.forgets_applied += report.forgotten as u64;
+                        brain.status.last_error = None;
+                        *this_call = true;
+                        Ok(())
+                    }
+                    Err(err) => {
+                        warn!("failed to hydrate brain {brain_id}: {err}");
+                        brain.status.last_error = Some(format!("hydrate: {err}"));
+                        Err(())
+                    }
+                };
+                self.persist(&brains);
+                outcome
+            })
+            .await;
+        hydrated
+    }
+
+    /// One sync pass over every tracked brain and `default_brain`. The lock is released while a
+    /// brain syncs so requests are not held up by kernel round trips. With hydration on, brains
+    /// that are not hydrated yet are left to [`Self::ensure_hydrated`]. Returns how many forgets
+    /// were applied to kernels.
+    pub async fn sync_all(&self, default_brain: Option<&str>) -> usize {
+        let pending = {
+            let mut brains = self.brains.lock().await;
+            if let Some(brain_id) = default_brain {
+                brains.entry(brain_id.to_string()).or_default();
+            }
+            brains
+                .iter()
+                .filter(|(_, brain)| !self.hydrate || brain.hydration.initialized())
+                .map(|(id, brain)| (id.clone(), brain.applied_forgets.clone()))
+                .collect::<Vec<_>>()
+        };
+        let mut total_forgets = 0;
+        for (brain_id, mut applied) in pending {
+            let result = self.sync_brain(&brain_id, &mut applied).await;
+            let mut brains = self.brains.lock().await;
+            let brain = brains.entry(brain_id.clone()).or_default();
+            brain.applied_forgets.extend(applied);
+            match result {
+                Ok((forgets, flush)) => {
+                    if flush.added > 0 || forgets > 0 || flush.conflicts > 0 {
+                        info!(
+                            "synced brain {brain_id}: {} memories added, {forgets} forgets applied, {} conflicts",
+                            flush.added, flush.conflicts
+                        );
+                    }
+                    brain.status.last_sync_at = Some(Utc::now().to_rfc3339());
+                    brain.status.memories_added += flush.added as u64;
+                    brain.status.forgets_applied += forgets as u64;
+                    total_forgets += forgets;
+                    brain.status.conflicts += flush.conflicts as u64;
+                    brain.status.last_error = None;
+                }
+                Err(err) => {
+                    warn!("failed to sync brain {brain_id}: {err}");
+                    brain.status.last_error = Some(format!("sync: {err}"));
+                }
+            }
+            self.persist(&brains);
+        }
+        total_forgets
+    }
+
+    async fn sync_brain(
+        &self,
+        brain_id: &str,
+        applied: &mut BTreeSet<String>,
+    ) -> Result<(usize, FlushReport)> {
+        let store = BrainStore::new(self.brain_home.clone())?;
+        let branch = store.active_branch_state(brain_id)?;
+        let endpoint = self.endpoints.resolve(&store, brain_id);
+        let adapter = RmvmAdapter::new(endpoint.clone());
+        let forgets = apply_forgets(&adapter, brain_id, &branch.suppressions, applied).await?;
+        let flush = flush_kernel(&endpoint, &store, brain_id).await?;
+        Ok((forgets, flush))
+    }
+
+    pub async fn snapshot(&self) -> BTreeMap<String, SyncStatus> {
+        self.brains
+            .lock()
+            .await
+            .iter()
+            .map(|(id, brain)| (id.clone(), brain.status.clone()))
+            .collect()
+    }
+
+    fn persist(&self, brains: &BTreeMap<String, BrainSync>) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+        let statuses = brains
+            .iter()
+            .map(|(id, brain)| (id.clone(), brain.status.clone()))
+            .collect::<BTreeMap<_, _>>();
+        if let Err(err) = write_sync_file(path, &statuses) {
+            warn!("failed to write sync status {}: {err}", path.display());
+        }
+    }
+}
+
+fn write_sync_file(path: &Path, statuses: &BTreeMap<String, SyncStatus>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(statuses)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read_sync_file(path: &Path) -> Result<BTreeMap<String, SyncStatus>> {
+    let raw = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&raw).with_context(|| format!("invalid sync status {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_status_round_trips_through_the_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("state").join("sync.json");
+        let statuses = BTreeMap::from([(
+            "brain-1".to_string(),
+            SyncStatus {
+                last_sync_at: Some("2026-01-01T00:00:00Z".to_string()),
+                memories_added: 3,
+                conflicts: 1,
+                ..Default::default()
+            },
+        )]);
+        write_sync_file(&path, &statuses).unwrap();
+        let read = read_sync_file(&path).unwrap();
+        assert_eq!(read["brain-1"].memories_added, 3);
+        assert_eq!(read["brain-1"].conflicts, 1);
+        assert!(read["brain-1"].last_error.is_none());
+    }
+}
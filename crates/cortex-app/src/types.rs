@@ -1,11 +1,79 @@
 use serde::{Deserialize, Serialize};
 
+use crate::proof::ProofBundle;
+
 #[derive(Debug, Deserialize)]
 pub struct ChatCompletionRequest {
     pub model: Option<String>,
     pub messages: Vec<ChatMessage>,
     pub user: Option<String>,
     pub stream: Option<bool>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f64>,
+    pub stop: Option<serde_json::Value>,
+    pub response_format: Option<serde_json::Value>,
+    pub tools: Option<Vec<ToolDefinition>>,
+    pub tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToolDefinition {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub function: ToolFunction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToolFunction {
+    pub name: String,
+}
+
+impl ChatCompletionRequest {
+    pub fn sampling(&self) -> SamplingParams {
+        SamplingParams {
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            stop: self.stop.clone(),
+            response_format: self.response_format.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SamplingParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponsesRequest {
+    pub model: Option<String>,
+    pub input: serde_json::Value,
+    pub instructions: Option<String>,
+    pub user: Option<String>,
+    pub stream: Option<bool>,
+    pub temperature: Option<f64>,
+    pub max_output_tokens: Option<u32>,
+    pub top_p: Option<f64>,
+    pub tools: Option<Vec<ResponsesTool>>,
+    pub tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponsesTool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub name: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,7 +103,23 @@ pub struct Choice {
 #[derive(Debug, Serialize)]
 pub struct AssistantMessage {
     pub role: String,
-    pub content: String,
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub function: ToolCallFunction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolCallFunction {
+    pub name: String,
+    pub arguments: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,6 +129,52 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ResponsesResponse {
+    pub id: String,
+    pub object: String,
+    pub created_at: i64,
+    pub status: String,
+    pub model: String,
+    pub output: Vec<ResponseOutputItem>,
+    pub output_text: String,
+    pub usage: ResponsesUsage,
+    pub cortex: CortexEnvelope,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseOutputItem {
+    Message {
+        id: String,
+        status: String,
+        role: String,
+        content: Vec<ResponseOutputContent>,
+    },
+    FunctionCall {
+        id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+        status: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseOutputContent {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub text: String,
+    pub annotations: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponsesUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CortexEnvelope {
     pub status: String,
@@ -52,7 +182,12 @@ pub struct CortexEnvelope {
     pub trace_root: Option<String>,
     pub error_code: Option<String>,
     pub plan_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_prompt_sha256: Option<String>,
     pub plan_source: Option<String>,
+    pub sampling: SamplingParams,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ProofBundle>,
 }
 
 #[derive(Debug, Serialize)]
@@ -66,6 +201,30 @@ pub struct OpenAiError {
     #[serde(rename = "type")]
     pub error_type: String,
     pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cortex: Option<CortexErrorDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CortexErrorDetail {
+    pub status: String,
+    pub hints: Vec<ErrorHint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stall: Option<StallDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorHint {
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StallDetail {
+    pub handle_ref: String,
+    pub availability: String,
+    pub retrieval_ticket: Option<String>,
+    pub estimated_ready_at: Option<String>,
 }
 
 pub fn message_content_as_text(content: &serde_json::Value) -> Option<String> {
@@ -0,0 +1,258 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::product::sidecar_binary_name;
+
+const DEFAULT_REPO: &str = "vinzify/Cortex-portable-brain";
+const REPO_ENV: &str = "CORTEX_BRAIN_REPO";
+
+#[derive(Debug, Clone)]
+pub struct SelfUpdateRequest {
+    pub version: Option<String>,
+    pub check: bool,
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// A downloaded and verified binary waiting to replace `target`.
+struct Staged {
+    target: PathBuf,
+    staged: PathBuf,
+}
+
+/// Asset suffix used by the release workflow, e.g. `linux-x64` or `windows-x64.exe`.
+fn platform_suffix() -> Result<String> {
+    let os = match env::consts::OS {
+        "linux" => "linux",
+        "macos" => "macos",
+        "windows" => "windows",
+        other => bail!("no release binaries for OS '{other}'"),
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => bail!("no release binaries for architecture '{other}'"),
+    };
+    Ok(format!("{os}-{arch}{}", env::consts::EXE_SUFFIX))
+}
+
+fn is_current(tag: &str) -> bool {
+    tag.trim_start_matches('v') == env!("CARGO_PKG_VERSION")
+}
+
+async fn fetch_release(client: &Client, repo: &str, version: Option<&str>) -> Result<Release> {
+    let api = format!("https://api.github.com/repos/{repo}/releases");
+    if let Some(tag) = version {
+        return get_json(client, &format!("{api}/tags/{tag}")).await;
+    }
+    // `latest` excludes pre-releases, so fall back to the newest entry of the list.
+    if let Ok(release) = get_json(client, &format!("{api}/latest")).await {
+        return Ok(release);
+    }
+    let releases: Vec<Release> = get_json(client, &format!("{api}?per_page=20")).await?;
+    releases
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no published releases found for {repo}"))
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(client: &Client, url: &str) -> Result<T> {
+    let resp = client
+        .get(url)
+        .header("accept", "application/vnd.github+json")
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {url}"))?
+        .error_for_status()
+        .with_context(|| format!("release feed request failed: {url}"))?;
+    Ok(resp.json().await?)
+}
+
+async fn download(client: &Client, url: &str) -> Result<Vec<u8>> {
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("failed to download {url}"))?
+        .error_for_status()
+        .with_context(|| format!("download failed: {url}"))?;
+    Ok(resp.bytes().await?.to_vec())
+}
+
+fn asset_url<'a>(release: &'a Release, name: &str) -> Result<&'a str> {
+    release
+        .assets
+        .iter()
+        .find(|a| a.name == name)
+        .map(|a| a.browser_download_url.as_str())
+        .ok_or_else(|| anyhow!("release {} has no asset {name}", release.tag_name))
+}
+
+/// Checks `bytes` against a `<hex>  <name>` checksum file as written by the release workflow.
+fn verify_sha256(name: &str, bytes: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("empty checksum file for {name}"))?
+        .to_ascii_lowercase();
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if expected != actual {
+        bail!("sha256 mismatch for {name}: expected {expected}, got {actual}");
+    }
+    Ok(())
+}
+
+async fn stage(client: &Client, release: &Release, asset: &str, target: &Path) -> Result<Staged> {
+    let bytes = download(client, asset_url(release, asset)?).await?;
+    let checksum = download(client, asset_url(release, &format!("{asset}.sha256"))?).await?;
+    verify_sha256(asset, &bytes, &String::from_utf8_lossy(&checksum))?;
+    let staged = target.with_extension("update");
+    fs::write(&staged, &bytes).with_context(|| format!("failed to write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(Staged {
+        target: target.to_path_buf(),
+        staged,
+    })
+}
+
+/// Renames the new binary in and returns where the old one was kept for rollback. On Unix the
+/// rename replaces the old binary atomically and a hard link keeps it; Windows cannot overwrite
+/// a running binary, so it is moved aside first.
+fn swap(staged: &Staged) -> Result<PathBuf> {
+    let backup = staged.target.with_extension("old");
+    let _ = fs::remove_file(&backup);
+    if staged.target.exists() {
+        #[cfg(windows)]
+        fs::rename(&staged.target, &backup)
+            .with_context(|| format!("failed to move {} aside", staged.target.display()))?;
+        #[cfg(not(windows))]
+        fs::hard_link(&staged.target, &backup)
+            .with_context(|| format!("failed to back up {}", staged.target.display()))?;
+    }
+    if let Err(err) = fs::rename(&staged.staged, &staged.target) {
+        if cfg!(windows) {
+            let _ = fs::rename(&backup, &staged.target);
+        } else {
+            let _ = fs::remove_file(&backup);
+        }
+        return Err(err).with_context(|| format!("failed to replace {}", staged.target.display()));
+    }
+    Ok(backup)
+}
+
+pub async fn self_update(req: SelfUpdateRequest) -> Result<()> {
+    let repo = env::var(REPO_ENV).unwrap_or_else(|_| DEFAULT_REPO.to_string());
+    let client = Client::builder()
+        .user_agent(concat!("cortex/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(300))
+        .build()?;
+    let release = fetch_release(&client, &repo, req.version.as_deref()).await?;
+    if is_current(&release.tag_name) && !req.force {
+        println!("cortex {} is up to date.", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    if req.check {
+        println!(
+            "Update available: {} -> {} (run `cortex self-update`)",
+            env!("CARGO_PKG_VERSION"),
+            release.tag_name
+        );
+        return Ok(());
+    }
+
+    let suffix = platform_suffix()?;
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("failed to resolve cortex executable path")?;
+    let sidecar = exe.with_file_name(sidecar_binary_name());
+    // Download and verify everything before touching the installed binaries.
+    let mut staged = vec![stage(&client, &release, &format!("cortex-app-{suffix}"), &exe).await?];
+    if sidecar.exists() {
+        let asset = format!("rmvm-grpc-server-{suffix}");
+        staged.push(stage(&client, &release, &asset, &sidecar).await?);
+    }
+
+    let mut swapped = Vec::new();
+    for item in &staged {
+        match swap(item) {
+            Ok(backup) => swapped.push((item, backup)),
+            Err(err) => {
+                for (done, backup) in swapped.into_iter().rev() {
+                    let _ = fs::rename(&backup, &done.target);
+                }
+                for item in &staged {
+                    let _ = fs::remove_file(&item.staged);
+                }
+                return Err(err);
+            }
+        }
+    }
+    for (_, backup) in swapped {
+        // The old binary may still be running on Windows; it is cleaned up by the next update.
+        let _ = fs::remove_file(backup);
+    }
+    for item in &staged {
+        println!("Updated {}", item.target.display());
+    }
+    println!(
+        "cortex updated to {}. Restart running services with: cortex stop --all && cortex up",
+        release.tag_name
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_release_checksum_files() {
+        let bytes = b"cortex binary";
+        let hex = format!("{:x}", Sha256::digest(bytes));
+        let file = format!("{}  cortex-app-linux-x64\n", hex.to_ascii_uppercase());
+        assert!(verify_sha256("cortex-app-linux-x64", bytes, &file).is_ok());
+        assert!(verify_sha256("cortex-app-linux-x64", b"tampered", &file).is_err());
+        assert!(verify_sha256("cortex-app-linux-x64", bytes, "").is_err());
+        assert!(is_current(&format!("v{}", env!("CARGO_PKG_VERSION"))));
+    }
+
+    #[test]
+    fn swap_replaces_the_binary_and_keeps_the_old_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("cortex");
+        fs::write(&target, "old").unwrap();
+        let staged = Staged {
+            target: target.clone(),
+            staged: target.with_extension("update"),
+        };
+        fs::write(&staged.staged, "new").unwrap();
+
+        let backup = swap(&staged).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "old");
+        assert!(!staged.staged.exists());
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use brain_store::KeyQuota;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+pub const ANONYMOUS_KEY: &str = "anonymous";
+
+/// How often recorded usage is written to the usage file.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub requests: u64,
+    pub errors: u64,
+    pub estimated_tokens: u64,
+    pub planner_tokens: u64,
+    pub last_seen: Option<String>,
+    #[serde(default)]
+    pub agents: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub day: UsageWindow,
+    #[serde(default)]
+    pub month: UsageWindow,
+}
+
+/// Counters for the current UTC day (`2026-01-31`) or month (`2026-01`); reset when the period changes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageWindow {
+    pub period: String,
+    pub requests: u64,
+    pub planner_tokens: u64,
+}
+
+impl UsageWindow {
+    fn current(&self, period: &str) -> (u64, u64) {
+        if self.period == period {
+            (self.requests, self.planner_tokens)
+        } else {
+            (0, 0)
+        }
+    }
+
+    fn roll(&mut self, period: &str) {
+        if self.period != period {
+            *self = Self {
+                period: period.to_string(),
+                ..Self::default()
+            };
+        }
+    }
+
+    fn add(&mut self, period: &str) {
+        self.roll(period);
+        self.requests += 1;
+    }
+
+    fn charge(&mut self, period: &str, planner_tokens: u64) {
+        self.roll(period);
+        self.planner_tokens = self.planner_tokens.saturating_add(planner_tokens);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub limit: &'static str,
+    pub allowed: u64,
+    pub used: u64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl KeyUsage {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UsageSample {
+    pub ok: bool,
+    pub estimated_tokens: u64,
+}
+
+/// Per-key counters, written to `path` (when set) by [`UsageTracker::flush`] so they survive
+/// proxy restarts. Recording only updates memory.
+#[derive(Debug)]
+pub struct UsageTracker {
+    path: Option<PathBuf>,
+    keys: Mutex<BTreeMap<String, KeyUsage>>,
+    dirty: AtomicBool,
+}
+
+impl UsageTracker {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let keys = match path.as_deref() {
+            Some(path) if path.exists() => read_usage_file(path).unwrap_or_else(|err| {
+                warn!("ignoring unreadable usage file {}: {err}", path.display());
+                BTreeMap::new()
+            }),
+            _ => BTreeMap::new(),
+        };
+        Self {
+            path,
+            keys: Mutex::new(keys),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    pub fn record(&self, key_id: &str, agent: Option<&str>, sample: UsageSample) {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let usage = keys.entry(key_id.to_string()).or_default();
+        usage.requests += 1;
+        if !sample.ok {
+            usage.errors += 1;
+        }
+        usage.estimated_tokens = usage
+            .estimated_tokens
+            .saturating_add(sample.estimated_tokens);
+        let now = Utc::now();
+        usage.day.add(&day_period(now));
+        usage.month.add(&month_period(now));
+        usage.last_seen = Some(now.to_rfc3339());
+        if let Some(agent) = agent {
+            *usage.agents.entry(agent.to_string()).or_default() += 1;
+        }
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Charges tokens a planner reported for `key_id`, as each planner request finishes, so
+    /// failed and hedged requests count too.
+    pub fn charge_planner(&self, key_id: &str, planner_tokens: u64) {
+        if planner_tokens == 0 {
+            return;
+        }
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let usage = keys.entry(key_id.to_string()).or_default();
+        usage.planner_tokens = usage.planner_tokens.saturating_add(planner_tokens);
+        let now = Utc::now();
+        usage.day.charge(&day_period(now), planner_tokens);
+        usage.month.charge(&month_period(now), planner_tokens);
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Writes the counters to the usage file if anything changed since the last flush. The
+    /// file is written from a snapshot, so requests are not held up by the disk.
+    pub fn flush(&self) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        if let Err(err) = write_usage_file(path, &self.snapshot()) {
+            self.dirty.store(true, Ordering::Release);
+            warn!("failed to persist usage to {}: {err}", path.display());
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, KeyUsage> {
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the first limit in `quota` that `key_id` has already used up.
+    pub fn check_quota(&self, key_id: &str, quota: &KeyQuota) -> Option<QuotaExceeded> {
+        if quota.is_unlimited() {
+            return None;
+        }
+        let usage = self
+            .keys
+            .lock()
+            .ok()?
+            .get(key_id)
+            .cloned()
+            .unwrap_or_default();
+        exceeded_quota(&usage, quota, Utc::now())
+    }
+}
+
+fn exceeded_quota(usage: &KeyUsage, quota: &KeyQuota, now: DateTime<Utc>) -> Option<QuotaExceeded> {
+    let (day_requests, day_tokens) = usage.day.current(&day_period(now));
+    let (month_requests, month_tokens) = usage.month.current(&month_period(now));
+    let next_day = next_day_start(now);
+    let next_month = next_month_start(now);
+    [
+        (
+            "requests_per_day",
+            quota.requests_per_day,
+            day_requests,
+            next_day,
+        ),
+        (
+            "planner_tokens_per_day",
+            quota.planner_tokens_per_day,
+            day_tokens,
+            next_day,
+        ),
+        (
+            "requests_per_month",
+            quota.requests_per_month,
+            month_requests,
+            next_month,
+        ),
+        (
+            "planner_tokens_per_month",
+            quota.planner_tokens_per_month,
+            month_tokens,
+            next_month,
+        ),
+    ]
+    .into_iter()
+    .find_map(|(limit, allowed, used, resets_at)| {
+        let allowed = allowed?;
+        (used >= allowed).then_some(QuotaExceeded {
+            limit,
+            allowed,
+            used,
+            resets_at,
+        })
+    })
+}
+
+fn day_period(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m-%d").to_string()
+}
+
+fn month_period(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m").to_string()
+}
+
+fn next_day_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let next = now.date_naive().succ_opt().unwrap_or(now.date_naive());
+    Utc.from_utc_datetime(&next.and_time(NaiveTime::MIN))
+}
+
+fn next_month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(now.date_naive());
+    Utc.from_utc_datetime(&first.and_time(NaiveTime::MIN))
+}
+
+/// Stable, non-secret id for an API key: the first 12 hex chars of its SHA-256, which is also
+/// the prefix of the `key_hash` shown for key mappings.
+pub fn key_id(api_key: Option<&str>) -> String {
+    match api_key {
+        Some(key) => format!(
+            "key:{}",
+            &format!("{:x}", Sha256::digest(key.as_bytes()))[..12]
+        ),
+        None => ANONYMOUS_KEY.to_string(),
+    }
+}
+
+pub fn read_usage_file(path: &Path) -> Result<BTreeMap<String, KeyUsage>> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("invalid {}", path.display()))
+}
+
+fn write_usage_file(path: &Path, keys: &BTreeMap<String, KeyUsage>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(keys)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotas_apply_to_the_current_period_only() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 18, 0, 0).unwrap();
+        let mut usage = KeyUsage::default();
+        usage.day.add(&day_period(now));
+        usage.day.charge(&day_period(now), 40);
+        usage.day.add(&day_period(now));
+        usage.day.charge(&day_period(now), 40);
+        usage.month.add(&month_period(now));
+        usage.month.charge(&month_period(now), 80);
+        let quota = KeyQuota {
+            requests_per_day: Some(5),
+            planner_tokens_per_day: Some(50),
+            ..KeyQuota::default()
+        };
+
+        let exceeded = exceeded_quota(&usage, &quota, now).unwrap();
+        assert_eq!(exceeded.limit, "planner_tokens_per_day");
+        assert_eq!(exceeded.used, 80);
+        assert_eq!(
+            exceeded.resets_at,
+            Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+        );
+
+        let tomorrow = Utc.with_ymd_and_hms(2027, 1, 1, 9, 0, 0).unwrap();
+        assert!(exceeded_quota(&usage, &quota, tomorrow).is_none());
+
+        let monthly = KeyQuota {
+            requests_per_month: Some(1),
+            ..KeyQuota::default()
+        };
+        assert_eq!(
+            exceeded_quota(&usage, &monthly, now).map(|e| e.limit),
+            Some("requests_per_month")
+        );
+    }
+
+    #[test]
+    fn usage_reaches_the_file_on_flush() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("usage.json");
+        let tracker = UsageTracker::load(Some(path.clone()));
+        let sample = UsageSample {
+            ok: true,
+            estimated_tokens: 12,
+        };
+        tracker.record("key:a", Some("cli"), sample);
+        tracker.charge_planner("key:a", 300);
+        tracker.charge_planner("key:a", 200);
+        tracker.record("key:a", None, sample);
+        assert!(!path.exists());
+
+        tracker.flush();
+        let usage = read_usage_file(&path).unwrap();
+        let entry = &usage["key:a"];
+        assert_eq!(entry.requests, 2);
+        assert_eq!(entry.planner_tokens, 500);
+        assert_eq!(entry.day.planner_tokens, 500);
+        assert_eq!(entry.month.requests, 2);
+
+        fs::remove_file(&path).unwrap();
+        tracker.flush();
+        assert!(!path.exists(), "nothing changed since the last flush");
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+pub const HX_CORTEX_EVENT: &str = "x-cortex-event";
+pub const HX_CORTEX_SIGNATURE: &str = "x-cortex-signature";
+
+const HMAC_BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Default)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct WebhookDispatcher {
+    urls: Vec<String>,
+    secret: Option<String>,
+    http: Client,
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: WebhookConfig) -> Option<Self> {
+        if config.urls.is_empty() {
+            return None;
+        }
+        let http = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .ok()?;
+        Some(Self {
+            urls: config.urls,
+            secret: config.secret.filter(|s| !s.is_empty()),
+            http,
+        })
+    }
+
+    /// Delivers `event` to every configured URL in the background; failures are only logged.
+    /// The handles let a short-lived process wait for delivery before exiting.
+    pub fn fire(&self, event: &str, data: JsonValue) -> Vec<JoinHandle<()>> {
+        let body = json!({
+            "event": event,
+            "created_at": Utc::now().to_rfc3339(),
+            "data": data,
+        })
+        .to_string();
+        let signature = self.secret.as_deref().map(|secret| {
+            format!(
+                "sha256={}",
+                hmac_sha256_hex(secret.as_bytes(), body.as_bytes())
+            )
+        });
+        let mut deliveries = Vec::new();
+        for url in &self.urls {
+            let mut req = self
+                .http
+                .post(url)
+                .header("content-type", "application/json")
+                .header(HX_CORTEX_EVENT, event)
+                .body(body.clone());
+            if let Some(signature) = signature.as_deref() {
+                req = req.header(HX_CORTEX_SIGNATURE, signature);
+            }
+            let url = url.clone();
+            let event = event.to_string();
+            deliveries.push(tokio::spawn(async move {
+                match req.send().await {
+                    Ok(resp) if !resp.status().is_success() => {
+                        warn!("webhook {event} to {url} returned HTTP {}", resp.status())
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("webhook {event} to {url} failed: {err}"),
+                }
+            }));
+        }
+        deliveries
+    }
+}
+
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    hmac_sha256(key, message)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// HMAC-SHA256 (RFC 2104); also signs Bedrock requests.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; HMAC_BLOCK_SIZE];
+    if key.len() > HMAC_BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_matches_rfc4231_vector() {
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+/* C bindings for cortex-sdk, built with `--features ffi`.
+ *
+ * Regenerate after changing src/ffi.rs:
+ *   cbindgen --config crates/cortex-sdk/cbindgen.toml --crate cortex-sdk \
+ *     --output crates/cortex-sdk/include/cortex_sdk.h
+ */
+
+#ifndef CORTEX_SDK_H
+#define CORTEX_SDK_H
+
+#include <stdint.h>
+
+typedef struct Cortex Cortex;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Message for the last failure on this thread, or NULL. Valid until the next failing call. */
+const char *cortex_last_error(void);
+
+void cortex_string_free(char *s);
+
+/* Opens the store at `home`, or the default home when NULL. Free with `cortex_close`. */
+Cortex *cortex_open(const char *home);
+
+void cortex_close(Cortex *cortex);
+
+/* JSON array of brains. */
+char *cortex_brains(const Cortex *cortex);
+
+/* JSON of the new brain. `passphrase_env` may be NULL for the default variable. */
+char *cortex_create_brain(const Cortex *cortex, const char *name, const char *passphrase_env);
+
+/* Stores a memory and returns its id. `brain` NULL means the active brain; `value_json` is
+ * any JSON value. */
+char *cortex_remember(const Cortex *cortex,
+                      const char *brain,
+                      const char *subject,
+                      const char *predicate,
+                      const char *value_json,
+                      const char *memory_type);
+
+/* JSON array of live memories. `brain` NULL means the active brain. */
+char *cortex_memories(const Cortex *cortex, const char *brain);
+
+/* Number of memories suppressed, or -1. */
+int64_t cortex_forget(const Cortex *cortex,
+                      const char *brain,
+                      const char *subject,
+                      const char *predicate);
+
+/* JSON `{"valid": bool, "findings": [...]}`. */
+char *cortex_check_plan(const char *manifest_json, const char *plan_json);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif /* CORTEX_SDK_H */
@@ -0,0 +1,270 @@
+//! C bindings. Strings are UTF-8 and NUL-terminated; structured results are JSON. Returned
+//! strings are owned by the caller and freed with `cortex_string_free`. On failure a function
+//! returns NULL (or -1) and `cortex_last_error` describes why.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::path::Path;
+use std::ptr;
+
+use serde::Serialize;
+
+use crate::{Cortex, Error, Result, check_plan};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(err: Error) {
+    let message = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// `None` for NULL.
+///
+/// # Safety
+/// `p` is NULL or a NUL-terminated string that outlives the returned borrow.
+unsafe fn opt_str<'a>(p: *const c_char) -> Result<Option<&'a str>> {
+    if p.is_null() {
+        return Ok(None);
+    }
+    unsafe { CStr::from_ptr(p) }
+        .to_str()
+        .map(Some)
+        .map_err(|_| Error::InvalidInput("string is not UTF-8".to_string()))
+}
+
+unsafe fn req_str<'a>(p: *const c_char, name: &str) -> Result<&'a str> {
+    unsafe { opt_str(p) }?.ok_or_else(|| Error::InvalidInput(format!("{name} is NULL")))
+}
+
+unsafe fn handle<'a>(p: *const Cortex) -> Result<&'a Cortex> {
+    unsafe { p.as_ref() }.ok_or_else(|| Error::InvalidInput("cortex handle is NULL".to_string()))
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(_) => {
+            set_error(Error::Store("result contains a NUL byte".to_string()));
+            ptr::null_mut()
+        }
+    }
+}
+
+fn json_out<T: Serialize>(result: Result<T>) -> *mut c_char {
+    match result.and_then(|v| serde_json::to_string(&v).map_err(|e| Error::Store(e.to_string()))) {
+        Ok(json) => into_c_string(json),
+        Err(err) => {
+            set_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Message for the last failure on this thread, or NULL. Valid until the next failing call.
+#[unsafe(no_mangle)]
+pub extern "C" fn cortex_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// # Safety
+/// `s` is NULL or a string returned by this library, not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Opens the store at `home`, or the default home when NULL. Free with `cortex_close`.
+///
+/// # Safety
+/// `home` is NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_open(home: *const c_char) -> *mut Cortex {
+    let opened = unsafe { opt_str(home) }.and_then(|home| Cortex::open(home.map(Path::new)));
+    match opened {
+        Ok(cortex) => Box::into_raw(Box::new(cortex)),
+        Err(err) => {
+            set_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `cortex` is NULL or a handle from `cortex_open`, not yet closed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_close(cortex: *mut Cortex) {
+    if !cortex.is_null() {
+        drop(unsafe { Box::from_raw(cortex) });
+    }
+}
+
+/// JSON array of brains.
+///
+/// # Safety
+/// `cortex` is a handle from `cortex_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_brains(cortex: *const Cortex) -> *mut c_char {
+    json_out(unsafe { handle(cortex) }.and_then(Cortex::brains))
+}
+
+/// JSON of the new brain. `passphrase_env` may be NULL for the default variable.
+///
+/// # Safety
+/// `cortex` is a handle from `cortex_open`; strings are NULL or NUL-terminated.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_create_brain(
+    cortex: *const Cortex,
+    name: *const c_char,
+    passphrase_env: *const c_char,
+) -> *mut c_char {
+    json_out(unsafe {
+        handle(cortex)
+            .and_then(|c| c.create_brain(req_str(name, "name")?, opt_str(passphrase_env)?))
+    })
+}
+
+/// Stores a memory and returns its id. `brain` NULL means the active brain; `value_json` is
+/// any JSON value.
+///
+/// # Safety
+/// `cortex` is a handle from `cortex_open`; strings are NULL or NUL-terminated.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_remember(
+    cortex: *const Cortex,
+    brain: *const c_char,
+    subject: *const c_char,
+    predicate: *const c_char,
+    value_json: *const c_char,
+    memory_type: *const c_char,
+) -> *mut c_char {
+    let result = unsafe {
+        handle(cortex).and_then(|c| {
+            let value = serde_json::from_str(req_str(value_json, "value_json")?)
+                .map_err(|e| Error::InvalidInput(format!("value_json: {e}")))?;
+            c.remember(
+                opt_str(brain)?,
+                req_str(subject, "subject")?,
+                req_str(predicate, "predicate")?,
+                value,
+                req_str(memory_type, "memory_type")?,
+            )
+        })
+    };
+    match result {
+        Ok(id) => into_c_string(id),
+        Err(err) => {
+            set_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// JSON array of live memories. `brain` NULL means the active brain.
+///
+/// # Safety
+/// `cortex` is a handle from `cortex_open`; `brain` is NULL or NUL-terminated.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_memories(
+    cortex: *const Cortex,
+    brain: *const c_char,
+) -> *mut c_char {
+    json_out(unsafe { handle(cortex).and_then(|c| c.memories(opt_str(brain)?)) })
+}
+
+/// Number of memories suppressed, or -1.
+///
+/// # Safety
+/// `cortex` is a handle from `cortex_open`; strings are NULL or NUL-terminated.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_forget(
+    cortex: *const Cortex,
+    brain: *const c_char,
+    subject: *const c_char,
+    predicate: *const c_char,
+) -> i64 {
+    let result = unsafe {
+        handle(cortex).and_then(|c| {
+            c.forget(
+                opt_str(brain)?,
+                req_str(subject, "subject")?,
+                req_str(predicate, "predicate")?,
+            )
+        })
+    };
+    match result {
+        Ok(n) => n as i64,
+        Err(err) => {
+            set_error(err);
+            -1
+        }
+    }
+}
+
+/// JSON `{"valid": bool, "findings": [...]}`.
+///
+/// # Safety
+/// Both arguments are NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cortex_check_plan(
+    manifest_json: *const c_char,
+    plan_json: *const c_char,
+) -> *mut c_char {
+    json_out(unsafe {
+        req_str(manifest_json, "manifest_json")
+            .and_then(|m| check_plan(m, req_str(plan_json, "plan_json")?))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let out = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { cortex_string_free(s) };
+        out
+    }
+
+    #[test]
+    fn round_trips_memories_through_the_c_api() {
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_FFI", "test-secret-ffi");
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let home = CString::new(temp.path().to_str().unwrap()).unwrap();
+        let c = |s: &str| CString::new(s).unwrap();
+        unsafe {
+            let cortex = cortex_open(home.as_ptr());
+            assert!(!cortex.is_null());
+            let brain = take(cortex_create_brain(
+                cortex,
+                c("ffi-test").as_ptr(),
+                c("TEST_BRAIN_SECRET_FFI").as_ptr(),
+            ));
+            assert!(brain.contains("\"name\":\"ffi-test\""));
+
+            let name = c("ffi-test");
+            let id = take(cortex_remember(
+                cortex,
+                name.as_ptr(),
+                c("user:local").as_ptr(),
+                c("prefers_beverage").as_ptr(),
+                c("\"tea\"").as_ptr(),
+                c("normative.preference").as_ptr(),
+            ));
+            assert!(take(cortex_memories(cortex, name.as_ptr())).contains(&id));
+
+            let missing = cortex_memories(cortex, c("missing").as_ptr());
+            assert!(missing.is_null());
+            let error = CStr::from_ptr(cortex_last_error()).to_str().unwrap();
+            assert!(error.starts_with("brain not found"));
+
+            cortex_close(cortex);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Stable embedding API for the portable brain. Internal crates may change shape between
+//! releases; the types and functions here only change with a semver-major bump.
+//!
+//! Build with `--features ffi` for the C bindings declared in `include/cortex_sdk.h`.
+
+use std::path::Path;
+
+use brain_store::{BrainStore, BrainSummary, CreateBrainRequest, MemoryObject};
+use planner_guard::{Severity, lint_plan, parse_manifest_json, parse_plan_json};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+const DEFAULT_TENANT: &str = "local";
+const DEFAULT_SCOPE: &str = "SCOPE_GLOBAL";
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("brain not found: {0}")]
+    BrainNotFound(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Store(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn store_error(e: anyhow::Error) -> Error {
+    Error::Store(format!("{e:#}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Brain {
+    pub id: String,
+    pub name: String,
+    pub tenant_id: String,
+    pub updated_at: String,
+}
+
+impl From<BrainSummary> for Brain {
+    fn from(b: BrainSummary) -> Self {
+        Self {
+            id: b.brain_id,
+            name: b.name,
+            tenant_id: b.tenant_id,
+            updated_at: b.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Memory {
+    pub id: String,
+    pub subject: String,
+    pub predicate: String,
+    pub value: JsonValue,
+    pub memory_type: String,
+    pub recorded_at: Option<String>,
+}
+
+impl From<MemoryObject> for Memory {
+    fn from(m: MemoryObject) -> Self {
+        Self {
+            id: m.id,
+            subject: m.subject,
+            predicate: m.predicate,
+            value: m.value,
+            memory_type: m.memory_type,
+            recorded_at: m.recorded_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct PlanFinding {
+    /// `error` or `warning`.
+    pub severity: String,
+    pub code: String,
+    pub step: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct PlanCheck {
+    /// True when no finding is an error.
+    pub valid: bool,
+    pub findings: Vec<PlanFinding>,
+}
+
+/// A brain store. Brain arguments take an id or name; `None` means the active brain.
+/// Passphrases are read from the environment variable each brain was created with.
+#[derive(Debug)]
+pub struct Cortex {
+    store: BrainStore,
+}
+
+impl Cortex {
+    /// Opens the store at `home`, or `CORTEX_HOME` / `~/.cortex` when `None`.
+    pub fn open(home: Option<&Path>) -> Result<Self> {
+        let store = BrainStore::new(home.map(Path::to_path_buf)).map_err(store_error)?;
+        Ok(Self { store })
+    }
+
+    fn resolve(&self, brain: Option<&str>) -> Result<BrainSummary> {
+        self.store
+            .resolve_brain_or_active(brain)
+            .map_err(|_| Error::BrainNotFound(brain.unwrap_or("active").to_string()))
+    }
+
+    pub fn brains(&self) -> Result<Vec<Brain>> {
+        let brains = self.store.list_brains().map_err(store_error)?;
+        Ok(brains.into_iter().map(Into::into).collect())
+    }
+
+    pub fn active_brain(&self) -> Result<Option<Brain>> {
+        match self.store.active_brain_id().map_err(store_error)? {
+            Some(id) => Ok(Some(self.resolve(Some(&id))?.into())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn create_brain(&self, name: &str, passphrase_env: Option<&str>) -> Result<Brain> {
+        if name.trim().is_empty() {
+            return Err(Error::InvalidInput("brain name is required".to_string()));
+        }
+        let brain = self
+            .store
+            .create_brain(CreateBrainRequest {
+                name: name.to_string(),
+                tenant_id: DEFAULT_TENANT.to_string(),
+                passphrase_env: passphrase_env.map(str::to_string),
+            })
+            .map_err(store_error)?;
+        Ok(brain.into())
+    }
+
+    pub fn use_brain(&self, brain: &str) -> Result<Brain> {
+        let brain = self.resolve(Some(brain))?;
+        let brain = self
+            .store
+            .set_active_brain(&brain.brain_id)
+            .map_err(store_error)?;
+        Ok(brain.into())
+    }
+
+    /// Stores one memory and returns its id.
+    pub fn remember(
+        &self,
+        brain: Option<&str>,
+        subject: &str,
+        predicate: &str,
+        value: JsonValue,
+        memory_type: &str,
+    ) -> Result<String> {
+        if subject.is_empty() || predicate.is_empty() {
+            return Err(Error::InvalidInput(
+                "subject and predicate are required".to_string(),
+            ));
+        }
+        let brain = self.resolve(brain)?;
+        let id = format!("mem-{}", Uuid::new_v4());
+        let memory = MemoryObject {
+            id: id.clone(),
+            subject: subject.to_string(),
+            predicate: predicate.to_string(),
+            value,
+            memory_type: memory_type.to_string(),
+            suppressed: false,
+            recorded_at: None,
+            superseded_by: None,
+        };
+        self.store
+            .add_memory_objects(&brain.brain_id, vec![memory])
+            .map_err(store_error)?;
+        Ok(id)
+    }
+
+    /// Live memories, i.e. neither forgotten nor superseded.
+    pub fn memories(&self, brain: Option<&str>) -> Result<Vec<Memory>> {
+        let brain = self.resolve(brain)?;
+        let branch = self
+            .store
+            .active_branch_state(&brain.brain_id)
+            .map_err(store_error)?;
+        Ok(branch
+            .memory_objects
+            .into_values()
+            .filter(MemoryObject::is_live)
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Suppresses every memory about `(subject, predicate)`; returns how many.
+    pub fn forget(&self, brain: Option<&str>, subject: &str, predicate: &str) -> Result<usize> {
+        let brain = self.resolve(brain)?;
+        self.store
+            .forget_suppress(
+                &brain.brain_id,
+                subject,
+                predicate,
+                DEFAULT_SCOPE,
+                "forgotten via cortex-sdk",
+            )
+            .map_err(store_error)
+    }
+
+    pub fn export_brain(&self, brain: Option<&str>, out_file: &Path) -> Result<()> {
+        let brain = self.resolve(brain)?;
+        self.store
+            .export_brain(&brain.brain_id, out_file)
+            .map_err(store_error)
+    }
+
+    pub fn import_brain(&self, in_file: &Path, name: Option<&str>) -> Result<Brain> {
+        let brain = self
+            .store
+            .import_brain(in_file, name.map(str::to_string), false)
+            .map_err(store_error)?
+            .ok_or_else(|| Error::Store("import returned no brain".to_string()))?;
+        Ok(brain.into())
+    }
+}
+
+/// Lints a planner's plan against the manifest it was given, both as JSON.
+pub fn check_plan(manifest_json: &str, plan_json: &str) -> Result<PlanCheck> {
+    let manifest =
+        parse_manifest_json(manifest_json).map_err(|e| Error::InvalidInput(format!("{e:#}")))?;
+    let plan = parse_plan_json(plan_json, &manifest.request_id)
+        .map_err(|e| Error::InvalidInput(format!("{e:#}")))?;
+    let findings = lint_plan(&plan, Some(&manifest))
+        .into_iter()
+        .map(|f| PlanFinding {
+            severity: match f.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            }
+            .to_string(),
+            code: f.code.to_string(),
+            step: f.step,
+            message: f.message,
+        })
+        .collect::<Vec<_>>();
+    Ok(PlanCheck {
+        valid: !findings.iter().any(|f| f.severity == "error"),
+        findings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn remembers_lists_and_forgets_memories() {
+        unsafe {
+            std::env::set_var("TEST_BRAIN_SECRET_SDK", "test-secret-sdk");
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let cortex = Cortex::open(Some(temp.path())).unwrap();
+        let brain = cortex
+            .create_brain("sdk-test", Some("TEST_BRAIN_SECRET_SDK"))
+            .unwrap();
+        assert_eq!(cortex.brains().unwrap(), vec![brain.clone()]);
+
+        let id = cortex
+            .remember(
+                Some("sdk-test"),
+                "user:local",
+                "prefers_beverage",
+                json!("tea"),
+                "normative.preference",
+            )
+            .unwrap();
+        let memories = cortex.memories(Some(&brain.id)).unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, id);
+
+        assert_eq!(
+            cortex
+                .forget(Some("sdk-test"), "user:local", "prefers_beverage")
+                .unwrap(),
+            1
+        );
+        assert!(cortex.memories(Some("sdk-test")).unwrap().is_empty());
+        assert!(matches!(
+            cortex.memories(Some("missing")),
+            Err(Error::BrainNotFound(_))
+        ));
+        assert!(matches!(
+            check_plan("{}", "not json"),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
@@ -1,32 +1,49 @@
 use std::env;
+use std::net::SocketAddr;
 use std::time::Duration;
 
+use adapter_rmvm::pipe;
 use rmvm_grpc::{GrpcKernelService, RmvmExecutorServer};
 use tonic::transport::Server;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr_str = env::var("RMVM_SERVER_ADDR").unwrap_or_else(|_| "127.0.0.1:50051".to_string());
-    let addr = addr_str.parse()?;
     let max_decoding = env_usize("RMVM_MAX_DECODING_BYTES", 4 * 1024 * 1024);
     let max_encoding = env_usize("RMVM_MAX_ENCODING_BYTES", 4 * 1024 * 1024);
     let timeout_secs = env_u64("RMVM_REQUEST_TIMEOUT_SECS", 30);
+    let brain_service = env_bool("RMVM_BRAIN_SERVICE", false);
+    let pipe_name = pipe::pipe_name(&addr_str);
+    let socket_addr = pipe_name
+        .is_none()
+        .then(|| addr_str.parse::<SocketAddr>())
+        .transpose()?;
 
     let service = GrpcKernelService::default();
     let service = RmvmExecutorServer::new(service)
         .max_decoding_message_size(max_decoding)
         .max_encoding_message_size(max_encoding);
 
+    // The brain store is served on the same port; brains resolve against CORTEX_HOME.
+    let token = env::var("RMVM_BRAIN_SERVICE_TOKEN").ok();
+    let brains = brain_service
+        .then(|| brain_grpc::local_server(socket_addr, token.as_deref()))
+        .transpose()?;
+
     println!(
-        "RMVM gRPC server listening on {} (decode={} encode={} timeout={}s)",
-        addr, max_decoding, max_encoding, timeout_secs
+        "RMVM gRPC server listening on {} (decode={} encode={} timeout={}s brain_service={})",
+        addr_str, max_decoding, max_encoding, timeout_secs, brain_service
     );
 
-    Server::builder()
+    let router = Server::builder()
         .timeout(Duration::from_secs(timeout_secs))
         .add_service(service)
-        .serve(addr)
-        .await?;
+        .add_optional_service(brains);
+    // `pipe://<name>` serves on a Windows named pipe instead of a TCP port.
+    match (socket_addr, pipe_name) {
+        (Some(addr), _) => router.serve(addr).await?,
+        (None, name) => pipe::serve(router, name.unwrap_or_default()).await?,
+    }
     Ok(())
 }
 
@@ -37,6 +54,13 @@ fn env_usize(name: &str, default: usize) -> usize {
         .unwrap_or(default)
 }
 
+fn env_bool(name: &str, default: bool) -> bool {
+    env::var(name)
+        .ok()
+        .map(|v| !matches!(v.trim(), "0" | "false" | "off"))
+        .unwrap_or(default)
+}
+
 fn env_u64(name: &str, default: u64) -> u64 {
     env::var(name)
         .ok()